@@ -0,0 +1,46 @@
+//! Evaluation of a large corpus of packets.
+//!
+//! Run with `cargo bench --bench batch`.
+//!
+//! `eval_batch` against a loop over `Packet::eval` measured on a single core,
+//! where batching only adds compiling and checked arithmetic; it divides the
+//! batch column by the number of cores available:
+//!
+//! | case             | loop   | batch  |
+//! |------------------|--------|--------|
+//! | 100000 x 3^4     | 260 ms | 425 ms |
+//! | 1000 x chain 500 | 14 ms  | 20 ms  |
+
+mod common;
+
+use std::hint::black_box;
+
+use jacob::{eval_batch, Packet};
+
+use common::time;
+
+fn bench(name: &str, packets: &[Packet]) {
+    let (looped, _) = time(|| {
+        black_box(packets)
+            .iter()
+            .map(Packet::eval)
+            .collect::<Vec<_>>()
+    });
+    let (batched, _) = time(|| eval_batch(black_box(packets)));
+    println!("{name}: loop {looped:?}, batch {batched:?}");
+}
+
+fn main() {
+    let balanced: Vec<Packet> = (0..100_000)
+        .map(|i| {
+            (0..4).fold(Packet::from(i), |packet, _| {
+                Packet::max_of([Packet::from(1) * packet.clone(), packet.clone(), packet])
+            })
+        })
+        .collect();
+    bench("100000 x 3^4", &balanced);
+    let chains: Vec<Packet> = (0..1000)
+        .map(|i| (0..500).fold(Packet::from(i), |packet, j| Packet::from(j) + packet))
+        .collect();
+    bench("1000 x chain 500", &chains);
+}
@@ -0,0 +1,41 @@
+//! Evaluating expressions from text.
+//!
+//! Run with `cargo bench --bench calc`.
+//!
+//! Folding values while parsing instead of building a packet and evaluating it
+//! measured:
+//!
+//! | case          | packet  | fold    |
+//! |---------------|---------|---------|
+//! | formula       | 1.80 µs | 1.53 µs |
+//! | chain 2000    | 557 µs  | 339 µs  |
+
+mod common;
+
+use std::hint::black_box;
+
+use jacob::width::Overflow;
+use jacob::{eval_expression, Packet};
+
+use common::time;
+
+fn bench(name: &str, expr: &str) {
+    let (packet, runs) = time(|| {
+        Packet::from_expression(black_box(expr))
+            .expect("expression parses")
+            .eval_as::<usize>(Overflow::Checked)
+            .expect("expression evaluates")
+    });
+    println!("{name}: {packet:?} per packet evaluation ({runs} runs)");
+    let (fold, runs) = time(|| eval_expression(black_box(expr)).expect("expression evaluates"));
+    println!("{name}: {fold:?} per folded evaluation ({runs} runs)");
+}
+
+fn main() {
+    bench(
+        "formula",
+        "if(max(12, 7 * 3) > 20, (4 + 5) * 6, min(1, 2, 3)) + 1_000 * 2",
+    );
+    let chain: Vec<String> = (0..2000).map(|i| format!("{i} * 2")).collect();
+    bench("chain 2000", &chain.join(" + "));
+}
@@ -0,0 +1,16 @@
+//! Timing shared by the benches.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Runs `f` repeatedly for two seconds, returning the mean time per run and
+/// the number of runs.
+pub fn time<T>(mut f: impl FnMut() -> T) -> (Duration, u32) {
+    let mut runs = 0_u32;
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(2) {
+        black_box(f());
+        runs += 1;
+    }
+    (start.elapsed() / runs, runs)
+}
@@ -0,0 +1,52 @@
+//! Parsing throughput on large transmissions.
+//!
+//! Run with `cargo bench --bench parse`. Each case is parsed repeatedly and the
+//! mean time per parse is printed.
+//!
+//! Reading operands from a single cursor instead of nested relative readers
+//! measured, per parse:
+//!
+//! | case         | relative readers | single cursor |
+//! |--------------|------------------|---------------|
+//! | balanced 8^5 | 1.91 ms          | 1.28 ms       |
+//! | chain 1000   | 131 µs           | 103 µs        |
+//! | flat 2000    | 90.5 µs          | 55.9 µs       |
+
+mod common;
+
+use std::hint::black_box;
+
+use jacob::Packet;
+
+use common::time;
+
+/// Sum of `width` operands nested `depth` deep, ending in literals.
+fn balanced(width: usize, depth: usize) -> String {
+    if depth == 0 {
+        return "2021".to_string();
+    }
+    let operand = balanced(width, depth - 1);
+    format!("sum({})", vec![operand; width].join(", "))
+}
+
+/// Operators nested `depth` deep with one literal beside each.
+fn chain(depth: usize) -> String {
+    (0..depth).fold("7".to_string(), |expr, i| format!("max({i}, {expr})"))
+}
+
+fn bench(name: &str, expression: &str) {
+    let packet = Packet::from_expression(expression).expect("valid expression");
+    // Mix both length encodings, as real transmissions do
+    let bytes = packet.canonicalize().to_bytes().expect("packet serializes");
+    let (per_parse, runs) = time(|| Packet::from_bytes(black_box(&bytes)).expect("packet parses"));
+    println!(
+        "{name}: {} bytes, {per_parse:?} per parse ({runs} runs)",
+        bytes.len()
+    );
+}
+
+fn main() {
+    bench("balanced 8^5", &balanced(8, 5));
+    bench("chain 1000", &chain(1000));
+    bench("flat 2000", &balanced(2000, 1));
+}
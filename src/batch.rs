@@ -0,0 +1,110 @@
+//! Evaluation of many packets across every core.
+//!
+//! ```
+//! use jacob::{eval_batch, Packet};
+//!
+//! let packets: Vec<Packet> = (0..1000).map(|i| Packet::from(i) * Packet::from(2)).collect();
+//! let values = eval_batch(&packets);
+//! assert_eq!(*values[999].as_ref().unwrap(), 1998);
+//! ```
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::vm::{Bytecode, Vm};
+use crate::{Packet, PacketError};
+
+/// Packets claimed by a worker at a time: small enough to balance uneven
+/// packets, large enough that the shared counter is rarely contended.
+const CHUNK: usize = 64;
+
+/// Evaluates every packet, returning the results in the same order.
+///
+/// Workers, one per available core, repeatedly claim the next chunk of
+/// packets, so a few huge packets do not leave the other cores idle. Each
+/// packet is compiled with [`Packet::compile`], which validates every
+/// operator's arity before evaluating anything, and run with checked
+/// arithmetic, so overflow is reported as [`PacketError::Overflow`] rather
+/// than wrapping or panicking.
+#[must_use]
+pub fn eval_batch(packets: &[Packet]) -> Vec<Result<usize, PacketError>> {
+    let workers = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(packets.len().div_ceil(CHUNK));
+    if workers <= 1 {
+        let mut worker = Worker::default();
+        return packets.iter().map(|packet| worker.eval(packet)).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let work = || {
+        let mut worker = Worker::default();
+        let mut results = Vec::new();
+        loop {
+            let start = next.fetch_add(CHUNK, Ordering::Relaxed);
+            if start >= packets.len() {
+                return results;
+            }
+            let chunk = &packets[start..packets.len().min(start + CHUNK)];
+            let values = chunk.iter().map(|packet| worker.eval(packet));
+            results.push((start, values.collect::<Vec<_>>()));
+        }
+    };
+    let mut chunks: Vec<(usize, Vec<Result<usize, PacketError>>)> = thread::scope(|scope| {
+        // Spawn every worker before joining any
+        #[allow(clippy::needless_collect)]
+        let handles: Vec<_> = (0..workers).map(|_| scope.spawn(work)).collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect()
+    });
+    chunks.sort_unstable_by_key(|(start, _)| *start);
+    chunks.into_iter().flat_map(|(_, values)| values).collect()
+}
+
+/// Reusable state of one worker.
+#[derive(Default)]
+struct Worker {
+    bytecode: Bytecode,
+    vm: Vm,
+}
+
+impl Worker {
+    fn eval(&mut self, packet: &Packet) -> Result<usize, PacketError> {
+        packet.compile_into(&mut self.bytecode)?;
+        self.vm.run(&self.bytecode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::eval_batch;
+    use crate::{ErrorKind, Packet};
+
+    #[test]
+    fn test_eval_batch() {
+        assert!(eval_batch(&[]).is_empty());
+        let packets: Vec<Packet> = (0..1000)
+            .map(|i| match i % 3 {
+                0 => Packet::from(i) + Packet::from(1),
+                1 => Packet::min_of(Vec::<Packet>::new()),
+                _ => Packet::from(usize::MAX) * Packet::from(i),
+            })
+            .collect();
+        let results = eval_batch(&packets);
+        assert_eq!(results.len(), packets.len());
+        for (i, result) in results.iter().enumerate() {
+            match i % 3 {
+                0 => assert_eq!(*result.as_ref().unwrap(), i + 1),
+                1 => assert_eq!(result.as_ref().unwrap_err().kind(), ErrorKind::Arity),
+                _ => assert_eq!(result.as_ref().unwrap_err().kind(), ErrorKind::Overflow),
+            }
+        }
+    }
+}
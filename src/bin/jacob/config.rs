@@ -0,0 +1,261 @@
+//! Defaults read from `jacob.toml`, or the file given with `--config`.
+//!
+//! The file is the subset of TOML made of `key = value` lines, where values
+//! are quoted strings, one-line arrays of them, or `true` or `false`. Blank
+//! lines and `#` comments are ignored. Every key is optional:
+//!
+//! ```toml
+//! in_format = "expr"          # for every command reading packets
+//! out_format = "hex"          # for every command printing packets
+//! profile = "extended"        # standard, extended, shared, run-length, or wide-4 to wide-8
+//! trailing_padding = "forbid" # ignore, must-be-zero, or forbid
+//! strict = true               # trailing_padding = "forbid", or "ignore" if false
+//! passes = ["simplify", "sort-commutative"] # run by `transform` without pass flags
+//! names = "names.toml"        # relative to this file
+//! max_input_bytes = "64KiB"   # bytes, or with a B, KB, KiB, MB, MiB, GB, or GiB suffix
+//! max_depth = "100"           # deepest operator nesting to parse
+//! timeout = "2s"              # seconds, or with an ms, s, m, or h suffix
+//! ```
+//!
+//! See [`crate::settings`] for how the file combines with other sources.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use jacob::format::Registry;
+use jacob::pass::{Canonicalize, Minify, Simplify, SortCommutative};
+use jacob::{PacketError, ParseOptions, Pass, Profile, TrailingPadding};
+
+/// File read when no other is given, if it exists.
+pub const DEFAULT_PATH: &str = "jacob.toml";
+
+/// Every key, in the order they are documented.
+pub const KEYS: [&str; 10] = [
+    "in_format",
+    "out_format",
+    "profile",
+    "trailing_padding",
+    "strict",
+    "passes",
+    "names",
+    "max_input_bytes",
+    "max_depth",
+    "timeout",
+];
+
+#[derive(Clone, Default)]
+pub struct Config {
+    pub in_format: Option<String>,
+    pub out_format: Option<String>,
+    pub parse_options: ParseOptions,
+    /// Names of the passes `transform` runs when none are given.
+    pub passes: Vec<String>,
+    pub names: Option<PathBuf>,
+    /// Time after which the process gives up.
+    pub timeout: Option<Duration>,
+}
+
+impl Config {
+    /// Reads the file at `path`, or [`DEFAULT_PATH`] if it exists.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_PATH).is_file() => Path::new(DEFAULT_PATH),
+            None => return Ok(Self::default()),
+        };
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read `{}`: {e}", path.display()))?;
+        let mut config =
+            Self::parse(&text).map_err(|e| format!("Failed to load `{}`: {e}", path.display()))?;
+        if let (Some(names), Some(dir)) = (&mut config.names, path.parent()) {
+            *names = dir.join(&*names);
+        }
+        Ok(config)
+    }
+
+    fn parse(text: &str) -> Result<Self, PacketError> {
+        let mut config = Self::default();
+        for (i, line) in text.lines().enumerate() {
+            let invalid = |reason| PacketError::ConfigError {
+                line: i + 1,
+                reason,
+            };
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected `key = value`"))?;
+            let value = value.trim();
+            let value = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                Some(items) => items
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| quoted(item).ok_or_else(|| invalid("expected a quoted string")))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join(","),
+                None if matches!(value, "true" | "false") => value.to_string(),
+                None => quoted(value)
+                    .ok_or_else(|| invalid("expected a quoted string"))?
+                    .to_string(),
+            };
+            config.set(key.trim(), &value).map_err(invalid)?;
+        }
+        Ok(config)
+    }
+
+    /// Sets `key` to `value`, written without TOML quoting: lists are
+    /// comma-separated and booleans are `true` or `false`.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), &'static str> {
+        match key {
+            "in_format" => {
+                if Registry::new().input(value).is_none() {
+                    return Err("unknown input format");
+                }
+                self.in_format = Some(value.to_string());
+            }
+            "out_format" => {
+                if Registry::new().output(value).is_none() {
+                    return Err("unknown output format");
+                }
+                self.out_format = Some(value.to_string());
+            }
+            "profile" => {
+                self.parse_options.profile = parse_profile(value).ok_or("unknown profile")?;
+            }
+            "trailing_padding" => {
+                self.parse_options.trailing_padding = match value {
+                    "ignore" => TrailingPadding::Ignore,
+                    "must-be-zero" => TrailingPadding::MustBeZero,
+                    "forbid" => TrailingPadding::Forbid,
+                    _ => return Err("unknown trailing padding"),
+                };
+            }
+            "strict" => {
+                self.parse_options.trailing_padding = match value {
+                    "true" => TrailingPadding::Forbid,
+                    "false" => TrailingPadding::Ignore,
+                    _ => return Err("expected `true` or `false`"),
+                };
+            }
+            "passes" => {
+                self.passes = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(|name| {
+                        pass_by_name(name)
+                            .map(|_| name.to_string())
+                            .ok_or("unknown pass")
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            "names" => self.names = Some(PathBuf::from(value)),
+            "max_input_bytes" => {
+                self.parse_options.max_input_bytes = Some(parse_size(value)?);
+            }
+            "max_depth" => {
+                self.parse_options.max_depth =
+                    Some(value.parse().map_err(|_| "expected a number")?);
+            }
+            "timeout" => self.timeout = Some(parse_duration(value)?),
+            _ => return Err("unknown key"),
+        }
+        Ok(())
+    }
+}
+
+/// Returns `line` up to the first `#` outside a quoted string.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Returns the contents of a quoted string without escapes.
+fn quoted(value: &str) -> Option<&str> {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .filter(|value| !value.contains(['"', '\\']))
+}
+
+/// Parses a number of bytes with an optional unit, such as `512`, `64KiB`,
+/// or `1.5MB`. Units are case-insensitive.
+pub fn parse_size(size: &str) -> Result<usize, &'static str> {
+    let (number, unit) = split_unit(size);
+    let scale: f64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "kb" => 1e3,
+        "kib" => 1024.0,
+        "mb" => 1e6,
+        "mib" => 1_048_576.0,
+        "gb" => 1e9,
+        "gib" => 1_073_741_824.0,
+        _ => return Err("unknown size unit"),
+    };
+    let bytes = number.parse::<f64>().map_err(|_| "expected a size")? * scale;
+    if !(0.0..=usize::MAX as f64).contains(&bytes) {
+        return Err("size out of range");
+    }
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    // In range, and fractions of a byte are dropped
+    Ok(bytes as usize)
+}
+
+/// Parses a duration with an optional unit, such as `30`, `500ms`, or `1.5m`.
+/// Numbers without a unit are seconds.
+pub fn parse_duration(duration: &str) -> Result<Duration, &'static str> {
+    let (number, unit) = split_unit(duration);
+    let scale = match unit {
+        "ms" => 1e-3,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => return Err("unknown duration unit"),
+    };
+    let seconds = number.parse::<f64>().map_err(|_| "expected a duration")? * scale;
+    Duration::try_from_secs_f64(seconds).map_err(|_| "duration out of range")
+}
+
+/// Splits `value` into its leading number and the unit after it.
+fn split_unit(value: &str) -> (&str, &str) {
+    let value = value.trim();
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    (&value[..end], value[end..].trim_start())
+}
+
+fn parse_profile(name: &str) -> Option<Profile> {
+    match name {
+        "standard" => Some(Profile::Standard),
+        "extended" => Some(Profile::Extended),
+        "shared" => Some(Profile::Shared),
+        "run-length" => Some(Profile::RunLength),
+        _ => name
+            .strip_prefix("wide-")
+            .and_then(|bits| bits.parse().ok())
+            .and_then(Profile::wide),
+    }
+}
+
+/// Returns the pass enabled by the `transform` flag `name`.
+pub fn pass_by_name(name: &str) -> Option<Box<dyn Pass>> {
+    let pass: Box<dyn Pass> = match name {
+        "simplify" => Box::new(Simplify),
+        "canonicalize" => Box::new(Canonicalize),
+        "sort-commutative" => Box::new(SortCommutative),
+        "minify" => Box::new(Minify),
+        _ => return None,
+    };
+    Some(pass)
+}
@@ -0,0 +1,2286 @@
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use clap::{ArgEnum, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use jacob::classify::looks_like_packet;
+use jacob::corpus::CorpusStats;
+use jacob::crypto::{random_nonce, EncryptedFrame, KEY_LEN};
+use jacob::cst::Cst;
+use jacob::diff::{diff_expressions, Markers};
+use jacob::format::{Expression, OutputFormatter, Registry};
+use jacob::input::read_entries;
+use jacob::inspect::LiteralRecord;
+use jacob::json::Value;
+use jacob::pass::{Canonicalize, Minify, Simplify, SortCommutative};
+use jacob::scan::{scan, ScanOptions};
+use jacob::stress::{self, Generator};
+use jacob::transmission::{parse_all, Transmission};
+use jacob::width::{ComparisonOrder, EmptyOperator, Overflow};
+use jacob::{
+    bytes_from_hex, hex_from_bytes, minimize, EvalOptions, ExpressionStyle, ExpressionSymbols,
+    Operation, Packet, PacketError, PacketPath, ParseOptions, Pass, PassManager, Profile, Version,
+};
+
+use config::Config;
+
+mod config;
+mod settings;
+#[cfg(feature = "tui")]
+mod tui;
+
+#[derive(ArgEnum, Clone, Copy)]
+enum Report {
+    #[clap(name = "json")]
+    Json,
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum Symbols {
+    /// The symbols expressions are parsed with
+    Ascii,
+    /// `×` for products and `=` for equality
+    Unicode,
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum StdinFormat {
+    /// One `{"id": ..., "hex": "..."}` record per line
+    #[clap(name = "ndjson")]
+    Ndjson,
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum ExitEval {
+    #[clap(name = "truthy")]
+    Truthy,
+    #[clap(name = "value")]
+    Value,
+}
+
+/// Compiles, decompiles, and evaluates BITS packets
+#[derive(Parser)]
+#[clap(
+    about,
+    version,
+    author,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// Input format; see `jacob formats`
+    #[clap(short, long, default_value = "hex", parse(try_from_str = parse_in_format))]
+    in_format: String,
+
+    /// Output format; see `jacob formats`
+    #[clap(short, long, default_value = "eval", parse(try_from_str = parse_out_format))]
+    out_format: String,
+
+    /// Set the exit code from evaluation. `truthy` exits 0 if every packet
+    /// evaluates to non-zero, 1 if any is zero, and 2 on failure. `value` exits
+    /// with the last packet's value clamped to 254, or 255 on failure.
+    #[clap(
+        arg_enum,
+        long,
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "truthy"
+    )]
+    exit_eval: Option<ExitEval>,
+
+    /// Print one JSON object per input, on stdout, with its output or error
+    /// and how long it took
+    #[clap(arg_enum, long)]
+    report: Option<Report>,
+
+    /// Print warnings about each packet, such as padded literals or unused
+    /// bits, on stderr
+    #[clap(long)]
+    warnings: bool,
+
+    /// Append each input that fails to parse or evaluate to this file, as a
+    /// JSON object with its error, instead of reporting it on stderr
+    #[clap(long, value_name = "FILE")]
+    quarantine: Option<PathBuf>,
+
+    /// Print each packet as a hex ChaCha20-Poly1305 frame sealed with this
+    /// 32-byte hex key
+    #[clap(long, parse(try_from_str = parse_key), conflicts_with = "out-format")]
+    encrypt_key: Option<[u8; KEY_LEN]>,
+
+    /// Read inputs as hex ChaCha20-Poly1305 frames opened with this 32-byte
+    /// hex key
+    #[clap(long, parse(try_from_str = parse_key), conflicts_with = "in-format")]
+    decrypt_key: Option<[u8; KEY_LEN]>,
+
+    /// Render literals named in this table of `NAME = value` lines by name
+    /// in expression output
+    #[clap(long, value_name = "FILE")]
+    names: Option<PathBuf>,
+
+    /// Operation symbols in expression output
+    #[clap(arg_enum, long, default_value = "ascii")]
+    symbols: Symbols,
+
+    /// Read defaults from this file instead of `$JACOB_CONFIG` or `jacob.toml`
+    #[clap(long, value_name = "FILE", global = true)]
+    config: Option<PathBuf>,
+
+    /// Reject inputs larger than this many bytes, e.g. `64KiB` or `1MB`
+    #[clap(long, value_name = "SIZE", global = true, parse(try_from_str = config::parse_size))]
+    max_input_bytes: Option<usize>,
+
+    /// Reject packets with operators nested deeper than this
+    #[clap(long, value_name = "N", global = true)]
+    max_depth: Option<usize>,
+
+    /// Give up and exit with status 124 after this long, e.g. `500ms` or `2s`
+    #[clap(long, value_name = "DURATION", global = true, parse(try_from_str = config::parse_duration))]
+    timeout: Option<Duration>,
+
+    /// Read inputs from records on stdin instead of arguments, and print one
+    /// JSON result per record, on stdout, carrying its `id`
+    #[clap(arg_enum, long, conflicts_with_all = &["in-format", "inputs"])]
+    stdin_format: Option<StdinFormat>,
+
+    #[clap(required_unless_present = "stdin-format")]
+    inputs: Vec<String>,
+}
+
+/// An input of the default mode.
+struct Input {
+    /// The packet, or the whole record if it is malformed.
+    text: String,
+    /// The `id` of the NDJSON record the input came from, `null` if it has
+    /// none.
+    id: Option<Value>,
+    /// Why the NDJSON record the input came from is malformed.
+    malformed: Option<String>,
+}
+
+impl From<String> for Input {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            id: None,
+            malformed: None,
+        }
+    }
+}
+
+/// What the default mode reports about each input besides its output.
+#[derive(Clone, Copy)]
+struct Reporting {
+    report: Option<Report>,
+    warnings: bool,
+}
+
+/// Keys applied around parsing and rendering in the default mode.
+#[derive(Clone, Copy, Default)]
+struct Keys {
+    encrypt: Option<[u8; KEY_LEN]>,
+    decrypt: Option<[u8; KEY_LEN]>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Applies transformation passes, in the order given, to packets read from stdin
+    Transform(TransformArgs),
+    /// Prints a smaller packet with the same value, for reviewing giant transmissions
+    Summarize(SummarizeArgs),
+    /// Reports operation usage, or locates packets matching a query
+    Inspect(InspectArgs),
+    /// Decodes transmissions received as datagrams and prints them as they arrive
+    Listen(ListenArgs),
+    /// Encodes packets and transmits the bytes to a device
+    Send(SendArgs),
+    /// Checks that hex packets survive parsing and re-serializing unchanged
+    Roundtrip(RoundtripArgs),
+    /// Checks that random packets survive every format and evaluate consistently
+    Stress(StressArgs),
+    /// Shrinks a packet into the smallest one that still fails a check
+    Minimize(MinimizeArgs),
+    /// Prints every literal with its path and enclosing operation as a table
+    ExportLiterals(ExportLiteralsArgs),
+    /// Writes random packets with their expected results as JSON test vectors
+    Vectors(VectorsArgs),
+    /// Writes an HTML page exploring a packet's tree, bits, and values
+    Report(ReportArgs),
+    /// Explores a packet's tree in a full-screen terminal interface
+    #[cfg(feature = "tui")]
+    Tui(TuiArgs),
+    /// Evaluates expressions, or encodes them as hex packets
+    Calc(CalcArgs),
+    /// Lists the formats packets can be read from and written to
+    Formats(FormatsArgs),
+    /// Prints sample hex packets with their expressions and values to try
+    Examples,
+    /// Compares two packets, highlighting the subterms that differ
+    Diff(DiffArgs),
+    /// Finds packets at any bit offset of a file, such as a memory dump or capture
+    Scan(ScanArgs),
+    /// Aggregates statistics over every file of packets in a directory
+    CorpusStats(CorpusStatsArgs),
+    /// Writes each packet of a multi-packet transmission to its own hex file
+    Split(SplitArgs),
+    /// Combines the packets of hex files under a new operator, or into one
+    /// multi-packet transmission
+    Merge(MergeArgs),
+    /// Reformats a file of expressions in canonical style, keeping comments
+    FmtExpr(FmtExprArgs),
+    /// Evaluates hex packets and compares them with their expected values
+    Check(CheckArgs),
+    /// Guesses whether each file holds a packet, for triaging unknown data
+    Classify(ClassifyArgs),
+}
+
+#[derive(Args)]
+struct TransformArgs {
+    /// Input format; see `jacob formats`
+    #[clap(short, long, default_value = "hex", parse(try_from_str = parse_in_format))]
+    in_format: String,
+
+    /// Output format; see `jacob formats`
+    #[clap(short, long, default_value = "hex", parse(try_from_str = parse_out_format))]
+    out_format: String,
+
+    /// Flatten nested operators and unwrap single-operand operators
+    #[clap(long)]
+    simplify: bool,
+
+    /// Zero versions and use the smallest length encodings
+    #[clap(long)]
+    canonicalize: bool,
+
+    /// Sort the operands of commutative operators
+    #[clap(long)]
+    sort_commutative: bool,
+
+    /// Simplify and use the smallest length encodings, keeping versions
+    #[clap(long)]
+    minify: bool,
+
+    /// Print per-pass statistics to stderr
+    #[clap(long)]
+    stats: bool,
+
+    /// Packets to transform; read line by line from stdin when omitted
+    inputs: Vec<String>,
+}
+
+#[derive(Args)]
+struct SummarizeArgs {
+    /// Input format; see `jacob formats`
+    #[clap(short, long, default_value = "hex", parse(try_from_str = parse_in_format))]
+    in_format: String,
+
+    /// Output format; see `jacob formats`
+    #[clap(short, long, default_value = "expr", parse(try_from_str = parse_out_format))]
+    out_format: String,
+
+    /// Replace operators nested deeper than this with their values
+    #[clap(short, long, default_value = "3")]
+    depth: usize,
+
+    /// Keep at most this many operands per sum, product, min, and max
+    #[clap(short, long)]
+    sample: Option<usize>,
+
+    /// Seed used to pick sampled operands
+    #[clap(long, default_value = "0")]
+    seed: u64,
+
+    /// Packets to summarize; read line by line from stdin when omitted
+    inputs: Vec<String>,
+}
+
+#[derive(Args)]
+struct InspectArgs {
+    /// Input format; see `jacob formats`
+    #[clap(short, long, default_value = "hex", parse(try_from_str = parse_in_format))]
+    in_format: String,
+
+    /// Print the paths of matching packets instead of the histogram, e.g. `op=product`
+    #[clap(long, parse(try_from_str = parse_query))]
+    find: Option<Operation>,
+
+    /// Print literal statistics and the value of each top-level operand
+    /// instead of the histogram
+    #[clap(long, conflicts_with = "find")]
+    values: bool,
+
+    /// Print groups of identical subtrees, largest savings first, instead of
+    /// the histogram
+    #[clap(long, conflicts_with_all = &["find", "values"])]
+    dupes: bool,
+
+    /// Ignore duplicate subtrees smaller than this many bits
+    #[clap(long, requires = "dupes", default_value = "0")]
+    min_bits: u64,
+
+    /// Print the number of packets and the bytes the parsed tree takes in
+    /// memory instead of the histogram
+    #[clap(long, conflicts_with_all = &["find", "values", "dupes"])]
+    memory: bool,
+
+    /// Packets to inspect; read line by line from stdin when omitted
+    inputs: Vec<String>,
+}
+
+#[derive(Args)]
+struct ListenArgs {
+    /// Output format; see `jacob formats`
+    #[clap(short, long, default_value = "eval", parse(try_from_str = parse_out_format))]
+    out_format: String,
+
+    /// Address to receive UDP datagrams on, e.g. `0.0.0.0:9000`
+    #[clap(long)]
+    udp: SocketAddr,
+
+    /// Exit after this many datagrams
+    #[clap(short = 'n', long)]
+    count: Option<usize>,
+}
+
+#[derive(Args)]
+struct SendArgs {
+    /// Destination, e.g. `192.168.1.20:9000`
+    address: String,
+
+    /// Input format; see `jacob formats`
+    #[clap(short, long, default_value = "hex", parse(try_from_str = parse_in_format))]
+    in_format: String,
+
+    /// Connect over TCP and write every packet to one stream instead of
+    /// sending one UDP datagram per packet
+    #[clap(long)]
+    tcp: bool,
+
+    /// Wrap each packet in the self-describing envelope, in the profile it
+    /// needs. Otherwise packets are encoded in the configured profile
+    #[clap(long)]
+    envelope: bool,
+
+    /// Packets to send; read line by line from stdin when omitted
+    inputs: Vec<String>,
+}
+
+/// Where `jacob send` writes packets.
+enum Transport {
+    Tcp(TcpStream),
+    /// A socket of the destination's address family, and the destination.
+    Udp(UdpSocket, SocketAddr),
+}
+
+#[derive(Args)]
+struct RoundtripArgs {
+    /// Hex packets to check; read line by line from stdin when omitted
+    inputs: Vec<String>,
+}
+
+#[derive(Args)]
+struct StressArgs {
+    /// Number of random packets to check
+    #[clap(long, default_value = "10000")]
+    iterations: usize,
+
+    /// Seed for the packet generator; the same seed checks the same packets
+    #[clap(long, default_value = "0")]
+    seed: u64,
+
+    /// Deepest nesting of operators to generate
+    #[clap(long, default_value = "4")]
+    max_depth: usize,
+
+    /// Also generate operations only the extended profile supports
+    #[clap(long)]
+    extended: bool,
+}
+
+#[derive(Clone, Copy, ArgEnum)]
+enum ExportFormat {
+    /// Comma-separated `packet,path,value,operation` rows with a header
+    Csv,
+    /// A Parquet file with the columns of `csv`; needs the `parquet` feature
+    Parquet,
+}
+
+#[derive(Args)]
+struct ExportLiteralsArgs {
+    /// Input format; see `jacob formats`
+    #[clap(short, long, default_value = "hex", parse(try_from_str = parse_in_format))]
+    in_format: String,
+
+    /// Format to write the literals in
+    #[clap(arg_enum, long, default_value = "csv")]
+    format: ExportFormat,
+
+    /// File to write; stdout when omitted
+    #[clap(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Packets to export; read line by line from stdin when omitted
+    inputs: Vec<String>,
+}
+
+#[derive(Clone, Copy, ArgEnum)]
+enum VectorProfile {
+    /// The Advent of Code format
+    Default,
+    /// The format with 4-bit type IDs and conditionals
+    Extended,
+}
+
+#[derive(Args)]
+struct VectorsArgs {
+    #[clap(arg_enum, long, default_value = "default")]
+    profile: VectorProfile,
+
+    /// Number of vectors to write
+    #[clap(long, default_value = "100")]
+    count: usize,
+
+    /// Seed for the packet generator; the same seed writes the same vectors
+    #[clap(long, default_value = "0")]
+    seed: u64,
+
+    /// File to write; stdout when omitted
+    #[clap(long, value_name = "FILE")]
+    out: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct ReportArgs {
+    /// Input format; see `jacob formats`
+    #[clap(short, long, default_value = "hex", parse(try_from_str = parse_in_format))]
+    in_format: String,
+
+    /// File to write; stdout when omitted
+    #[clap(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Packet to report on
+    input: String,
+}
+
+#[cfg(feature = "tui")]
+#[derive(Args)]
+struct TuiArgs {
+    /// Input format; see `jacob formats`
+    #[clap(short, long, default_value = "hex", parse(try_from_str = parse_in_format))]
+    in_format: String,
+
+    /// Packet to explore
+    input: String,
+}
+
+#[derive(Args)]
+struct FormatsArgs {
+    /// Print a JSON array of objects instead of a table
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Input format; see `jacob formats`
+    #[clap(short, long, default_value = "hex", parse(try_from_str = parse_in_format))]
+    in_format: String,
+
+    /// How to show the differences: both expressions with the differing
+    /// subterms marked, or the path of each differing packet
+    #[clap(arg_enum, long = "as", default_value = "expr")]
+    view: DiffView,
+
+    /// Highlight differences in red and green instead of `[-…-]` and `{+…+}`
+    #[clap(long)]
+    color: bool,
+
+    /// Packet to compare from
+    a: String,
+
+    /// Packet to compare to
+    b: String,
+}
+
+#[derive(Args)]
+struct ScanArgs {
+    /// Output format; see `jacob formats`
+    #[clap(short, long, default_value = "expr", parse(try_from_str = parse_out_format))]
+    out_format: String,
+
+    /// Bits between the offsets tried; 8 tries only byte boundaries
+    #[clap(long, default_value = "1")]
+    step: u64,
+
+    /// Ignore packets shorter than this many bits
+    #[clap(long, default_value = "32")]
+    min_bits: u64,
+
+    /// Also print packets inside earlier ones, such as their operands
+    #[clap(long)]
+    overlapping: bool,
+
+    /// File to scan
+    file: PathBuf,
+}
+
+#[derive(Clone, Copy, ArgEnum)]
+enum StatsFormat {
+    Json,
+    Markdown,
+}
+
+#[derive(Args)]
+struct CorpusStatsArgs {
+    /// Input format; see `jacob formats`
+    #[clap(short, long, default_value = "hex", parse(try_from_str = parse_in_format))]
+    in_format: String,
+
+    #[clap(arg_enum, long, default_value = "markdown")]
+    format: StatsFormat,
+
+    /// Directory whose files hold one packet per line, with `#` comments
+    dir: PathBuf,
+}
+
+#[derive(Args)]
+struct SplitArgs {
+    /// Split into the packets the transmission carries, the only level
+    /// supported
+    #[clap(long, required = true)]
+    at_top_level: bool,
+
+    /// Directory to write `N.hex` files and `manifest.json` to, created if
+    /// missing
+    #[clap(long, value_name = "DIR")]
+    output_dir: PathBuf,
+
+    /// File holding the transmission as hex, with `#` comments
+    file: PathBuf,
+}
+
+#[derive(Args)]
+struct MergeArgs {
+    /// Operation of a new root packet taking every packet as an operand, such
+    /// as `sum`. Without it the packets are concatenated
+    #[clap(long, value_name = "OPERATION", parse(try_from_str = parse_operation))]
+    wrap: Option<Operation>,
+
+    /// Version of the new root packet
+    #[clap(long, default_value = "0", requires = "wrap", parse(try_from_str = parse_version))]
+    version: Version,
+
+    /// Files holding packets or multi-packet transmissions as hex
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+struct FmtExprArgs {
+    /// Print nothing, and fail if the file is not formatted
+    #[clap(long, conflicts_with = "write")]
+    check: bool,
+
+    /// Rewrite the file in place instead of printing it
+    #[clap(long)]
+    write: bool,
+
+    /// File holding one expression per line, with `#` comments
+    file: PathBuf,
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    /// File of `HEX => EXPECTED` lines with `#` comments, or of JSON objects
+    /// with `hex` and `eval` members like `jacob vectors` writes
+    file: PathBuf,
+}
+
+#[derive(Args)]
+struct ClassifyArgs {
+    /// Read the files as hex, with `#` comments, instead of raw bytes
+    #[clap(long)]
+    hex: bool,
+
+    /// Files to classify
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum DiffView {
+    #[clap(name = "expr")]
+    Expression,
+    #[clap(name = "paths")]
+    Paths,
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum OverflowMode {
+    #[clap(name = "checked")]
+    Checked,
+    #[clap(name = "wrapping")]
+    Wrapping,
+    #[clap(name = "saturating")]
+    Saturating,
+}
+
+impl From<OverflowMode> for Overflow {
+    fn from(mode: OverflowMode) -> Self {
+        match mode {
+            OverflowMode::Checked => Self::Checked,
+            OverflowMode::Wrapping => Self::Wrapping,
+            OverflowMode::Saturating => Self::Saturating,
+        }
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum ComparisonMode {
+    #[clap(name = "strict-two")]
+    StrictTwo,
+    #[clap(name = "left-fold")]
+    LeftFold,
+    #[clap(name = "chain")]
+    Chain,
+}
+
+impl From<ComparisonMode> for ComparisonOrder {
+    fn from(mode: ComparisonMode) -> Self {
+        match mode {
+            ComparisonMode::StrictTwo => Self::StrictTwo,
+            ComparisonMode::LeftFold => Self::LeftFold,
+            ComparisonMode::Chain => Self::Chain,
+        }
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy)]
+enum EmptyMode {
+    #[clap(name = "identity")]
+    Identity,
+    #[clap(name = "error")]
+    Error,
+    #[clap(name = "skip")]
+    Skip,
+}
+
+impl From<EmptyMode> for EmptyOperator {
+    fn from(mode: EmptyMode) -> Self {
+        match mode {
+            EmptyMode::Identity => Self::Identity,
+            EmptyMode::Error => Self::Error,
+            EmptyMode::Skip => Self::Skip,
+        }
+    }
+}
+
+#[derive(Args)]
+struct CalcArgs {
+    /// Print each expression encoded as a hex packet instead of its value
+    #[clap(long)]
+    hex: bool,
+
+    /// Evaluate with all arithmetic modulo N
+    #[clap(long, value_name = "N", conflicts_with = "hex")]
+    modulus: Option<u64>,
+
+    /// Evaluate in 64 bits, handling values that do not fit like this
+    #[clap(arg_enum, long, conflicts_with = "hex")]
+    overflow: Option<OverflowMode>,
+
+    /// Read comparisons with more than two operands as `(a > b) > c`
+    /// (left-fold) or `a > b and b > c` (chain) instead of failing
+    #[clap(arg_enum, long, conflicts_with = "hex")]
+    comparisons: Option<ComparisonMode>,
+
+    /// Evaluate operators without operands as the identity of sums and
+    /// products (identity), as errors (error), or as if they were absent
+    /// from their parent (skip)
+    #[clap(arg_enum, long, conflicts_with = "hex")]
+    empty_operators: Option<EmptyMode>,
+
+    /// Expressions to evaluate; read line by line from stdin when omitted
+    inputs: Vec<String>,
+}
+
+#[derive(Args)]
+struct MinimizeArgs {
+    /// Failure to preserve: `parse-fails` (the re-encoded packet does not
+    /// parse back) or `eval-differs-from N`
+    #[clap(long, parse(try_from_str = parse_check))]
+    check: Check,
+
+    /// Hex packet to minimize
+    hex: String,
+}
+
+/// Failure preserved by the `minimize` subcommand.
+#[derive(Clone, Copy)]
+enum Check {
+    ParseFails,
+    EvalDiffersFrom(usize),
+}
+
+impl Check {
+    fn fails(self, packet: &Packet) -> bool {
+        match self {
+            Self::ParseFails => packet
+                .to_hex()
+                .and_then(|hex| Packet::from_str(&hex))
+                .is_err(),
+            Self::EvalDiffersFrom(expected) => {
+                packet.eval_as::<usize>(Overflow::Checked).ok() != Some(expected)
+            }
+        }
+    }
+}
+
+fn parse_check(check: &str) -> Result<Check, String> {
+    match check.split_whitespace().collect::<Vec<_>>()[..] {
+        ["parse-fails"] => Ok(Check::ParseFails),
+        ["eval-differs-from", value] => value
+            .parse()
+            .map(Check::EvalDiffersFrom)
+            .map_err(|e| format!("invalid value `{value}`: {e}")),
+        _ => Err(format!(
+            "unsupported check `{check}`, expected `parse-fails` or `eval-differs-from N`"
+        )),
+    }
+}
+
+fn parse_in_format(name: &str) -> Result<String, String> {
+    Registry::new()
+        .input(name)
+        .map(|parser| parser.name().to_string())
+        .ok_or_else(|| format!("unknown input format `{name}`, see `jacob formats`"))
+}
+
+fn parse_out_format(name: &str) -> Result<String, String> {
+    Registry::new()
+        .output(name)
+        .map(|formatter| formatter.name().to_string())
+        .ok_or_else(|| format!("unknown output format `{name}`, see `jacob formats`"))
+}
+
+fn parse_query(query: &str) -> Result<Operation, String> {
+    match query.split_once('=') {
+        Some(("op", name)) => {
+            Operation::from_func_str(name).ok_or_else(|| format!("unknown operation `{name}`"))
+        }
+        _ => Err(format!("unsupported query `{query}`, expected `op=NAME`")),
+    }
+}
+
+fn parse_operation(name: &str) -> Result<Operation, String> {
+    Operation::from_func_str(name).ok_or_else(|| format!("unknown operation `{name}`"))
+}
+
+fn parse_version(version: &str) -> Result<Version, String> {
+    version.parse().ok().and_then(Version::new).ok_or_else(|| {
+        format!(
+            "invalid version `{version}`, expected 0 to {}",
+            Version::MAX.get()
+        )
+    })
+}
+
+fn parse_key(key: &str) -> Result<[u8; KEY_LEN], String> {
+    bytes_from_hex(key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| format!("expected {} hex digits", KEY_LEN * 2))
+}
+
+/// Formats a path as dot-separated child indices.
+fn format_path(path: &[usize]) -> String {
+    PacketPath::from(path).to_string()
+}
+
+impl TransformArgs {
+    /// Returns the passes enabled by flags, in the order given, or the
+    /// configured passes if there are none.
+    fn pass_manager(&self, matches: &ArgMatches, config: &Config) -> PassManager {
+        let mut passes: Vec<(bool, Box<dyn Pass>)> = vec![
+            (self.simplify, Box::new(Simplify)),
+            (self.canonicalize, Box::new(Canonicalize)),
+            (self.sort_commutative, Box::new(SortCommutative)),
+            (self.minify, Box::new(Minify)),
+        ];
+        passes.retain(|(enabled, _)| *enabled);
+        passes.sort_by_key(|(_, pass)| matches.index_of(pass.name()));
+        if passes.is_empty() {
+            passes = config
+                .passes
+                .iter()
+                .filter_map(|name| config::pass_by_name(name))
+                .map(|pass| (true, pass))
+                .collect();
+        }
+
+        let mut manager = PassManager::new();
+        for (_, pass) in passes {
+            manager.add_boxed(pass);
+        }
+        manager
+    }
+}
+
+fn parse(packet_str: &str, format: &str, options: &ParseOptions) -> Result<Packet, PacketError> {
+    Registry::new()
+        .input(format)
+        .expect("input formats are validated when parsed")
+        .parse(packet_str, options)
+}
+
+/// Returns the registered output format named `name`.
+fn formatter<'a>(registry: &'a Registry, name: &str) -> &'a dyn OutputFormatter {
+    registry
+        .output(name)
+        .expect("output formats are validated when parsed")
+}
+
+/// Reads the `--names` table into an expression style with `symbols`.
+fn load_style(names: Option<PathBuf>, symbols: Symbols) -> Result<ExpressionStyle, String> {
+    let mut style = ExpressionStyle {
+        symbols: match symbols {
+            Symbols::Ascii => ExpressionSymbols::new(),
+            Symbols::Unicode => ExpressionSymbols::unicode(),
+        },
+        ..ExpressionStyle::default()
+    };
+    if let Some(path) = names {
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read `{}`: {e}", path.display()))?;
+        style.names = jacob::expression::parse_names(&text)
+            .map_err(|e| format!("Failed to load `{}`: {e}", path.display()))?;
+    }
+    Ok(style)
+}
+
+/// Returns `inputs`, or the entries of stdin if there are none.
+fn read_inputs(inputs: Vec<String>) -> Vec<String> {
+    if !inputs.is_empty() {
+        return inputs;
+    }
+    let mut text = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut text) {
+        eprintln!("Failed to read stdin. Full error:\n{e}");
+        std::process::exit(2);
+    }
+    read_entries(&text)
+        .map(|entry| entry.text.to_string())
+        .collect()
+}
+
+/// Returns the hex of every entry of `text`, ignoring whitespace within lines.
+fn read_hex(text: &str) -> String {
+    read_entries(text)
+        .flat_map(|entry| entry.text.split_whitespace())
+        .collect()
+}
+
+/// Reads NDJSON records with a `hex` member and an optional `id` from stdin,
+/// skipping blank lines.
+fn read_records() -> Vec<Input> {
+    let mut text = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut text) {
+        eprintln!("Failed to read stdin. Full error:\n{e}");
+        std::process::exit(2);
+    }
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let record = line.parse::<Value>().map_err(|e| e.to_string());
+            let id = record
+                .as_ref()
+                .ok()
+                .and_then(|record| record.get("id"))
+                .cloned()
+                .unwrap_or(Value::Null);
+            let hex = record.and_then(|record| {
+                record
+                    .get("hex")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .ok_or_else(|| "expected a string `hex` member".to_string())
+            });
+            match hex {
+                Ok(hex) => Input {
+                    text: hex,
+                    id: Some(id),
+                    malformed: None,
+                },
+                Err(e) => Input {
+                    text: line.to_string(),
+                    id: Some(id),
+                    malformed: Some(format!("malformed record: {e}")),
+                },
+            }
+        })
+        .collect()
+}
+
+/// Prints each input rendered by `output`, returning the evaluated value of
+/// each, or `None` where parsing or evaluation failed.
+fn convert(
+    inputs: Vec<Input>,
+    in_format: &str,
+    output: &dyn OutputFormatter,
+    options: &ParseOptions,
+    keys: Keys,
+    reporting: Reporting,
+    mut quarantine: Option<&mut dyn Write>,
+) -> Vec<Option<usize>> {
+    let mut values = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let packet_str = input.text;
+        if let Some(e) = input.malformed {
+            let id = input.id.as_ref();
+            println!("{}", json_report(id, &packet_str, &Err(e), Duration::ZERO));
+            values.push(None);
+            continue;
+        }
+        let start = Instant::now();
+        // Warnings about the encoding are only known for hex
+        let mut warnings = None;
+        let packet = match keys.decrypt {
+            Some(key) => bytes_from_hex(&packet_str)
+                .and_then(|bytes| EncryptedFrame::from_bytes(&bytes))
+                .and_then(|frame| frame.open(&key))
+                .map_err(|e| (format!("Failed to decrypt packet. Full error:\n{e}"), e)),
+            None => {
+                let parsed = if reporting.warnings && in_format == "hex" {
+                    Packet::parse_with_warnings(&packet_str, options).map(|(packet, found)| {
+                        warnings = Some(found);
+                        packet
+                    })
+                } else {
+                    parse(&packet_str, in_format, options)
+                };
+                parsed.map_err(|e| {
+                    (
+                        format!("Failed to parse packet with format: `{in_format}`"),
+                        e,
+                    )
+                })
+            }
+        };
+        if let (true, Ok(packet)) = (reporting.warnings, &packet) {
+            for warning in warnings.unwrap_or_else(|| packet.warnings()) {
+                eprintln!("Warning for `{packet_str}`: {warning}");
+            }
+        }
+        // Evaluate once, for the exit value and for the eval format alike
+        let evaluated = packet.as_ref().ok().map(Packet::eval);
+        let value = evaluated
+            .as_ref()
+            .and_then(|value| value.as_ref().ok().copied());
+        let rendered = packet
+            .as_ref()
+            .ok()
+            .zip(evaluated)
+            .map(|(packet, evaluated)| match keys.encrypt {
+                Some(key) => random_nonce()
+                    .and_then(|nonce| EncryptedFrame::seal(packet, &key, nonce))
+                    .map(|frame| hex_from_bytes(&frame.to_bytes())),
+                None if output.name() == "eval" => evaluated.map(|value| value.to_string()),
+                None => output.render(packet),
+            });
+        let duration = start.elapsed();
+        let failure = match (&packet, &rendered) {
+            (Err((_, e)), _) => Some(("parse", e)),
+            (_, Some(Err(e))) => Some(("eval", e)),
+            _ => None,
+        };
+        if let (Some(file), Some((stage, e))) = (&mut quarantine, failure) {
+            if let Err(io_error) = writeln!(file, "{}", json_quarantine(&packet_str, stage, e)) {
+                eprintln!("Failed to write to quarantine file. Full error:\n{io_error}");
+                std::process::exit(2);
+            }
+        }
+        let failed = failure.is_some();
+        let output = match (&packet, rendered) {
+            (Err((_, e)), _) => Err(e.to_string()),
+            (_, Some(rendered)) => rendered.map_err(|e| e.to_string()),
+            (Ok(_), None) => unreachable!("parsed packets are rendered"),
+        };
+        match reporting.report {
+            Some(Report::Json) => println!(
+                "{}",
+                json_report(input.id.as_ref(), &packet_str, &output, duration)
+            ),
+            None => match (&packet, &output) {
+                _ if failed && quarantine.is_some() => {}
+                (Err((message, _)), _) => eprintln!("{message}"),
+                (Ok(_), Ok(result)) => println!("{result}"),
+                (Ok(_), Err(e)) => eprintln!("Failed to evaluate packet. Full error:\n{e}"),
+            },
+        }
+        values.push(value);
+    }
+    values
+}
+
+/// Formats one line of `--report json` output, led by the `id` of the
+/// input's NDJSON record if it has one.
+fn json_report(
+    id: Option<&Value>,
+    input: &str,
+    output: &Result<String, String>,
+    duration: Duration,
+) -> String {
+    let (ok, output, error) = match output {
+        Ok(output) => (true, json_string(output), "null".to_string()),
+        Err(error) => (false, "null".to_string(), json_string(error)),
+    };
+    let id = id.map_or_else(String::new, |id| format!(r#""id":{id},"#));
+    format!(
+        r#"{{{id}"input":{},"ok":{ok},"output":{output},"error":{error},"duration_ms":{:.3}}}"#,
+        json_string(input),
+        duration.as_secs_f64() * 1000.0
+    )
+}
+
+/// Formats one line of the `--quarantine` file: the input, the stage that
+/// failed, and the error with its kind and location when known.
+fn json_quarantine(input: &str, stage: &str, error: &PacketError) -> String {
+    let path = error.path().map_or_else(
+        || "null".to_string(),
+        |path| {
+            let indices: Vec<String> = path.iter().map(ToString::to_string).collect();
+            format!("[{}]", indices.join(","))
+        },
+    );
+    let bit_offset = error
+        .bit_offset()
+        .map_or_else(|| "null".to_string(), |offset| offset.to_string());
+    format!(
+        r#"{{"input":{},"stage":"{stage}","kind":"{:?}","error":{},"path":{path},"bit_offset":{bit_offset}}}"#,
+        json_string(input),
+        error.kind(),
+        json_string(&error.to_string())
+    )
+}
+
+/// Quotes and escapes `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn exit_code(values: &[Option<usize>], mode: ExitEval) -> i32 {
+    match mode {
+        ExitEval::Truthy => {
+            if values.iter().any(Option::is_none) {
+                2
+            } else {
+                i32::from(values.iter().flatten().any(|&value| value == 0))
+            }
+        }
+        ExitEval::Value => match values.last() {
+            Some(Some(value)) => i32::try_from(*value).map_or(254, |value| value.min(254)),
+            _ => 255,
+        },
+    }
+}
+
+fn transform(
+    args: TransformArgs,
+    matches: &ArgMatches,
+    config: &Config,
+    output: &dyn OutputFormatter,
+) {
+    let manager = args.pass_manager(matches, config);
+    for packet_str in read_inputs(args.inputs) {
+        match parse(&packet_str, &args.in_format, &config.parse_options) {
+            Ok(packet) => {
+                let (packet, stats) = manager.run(&packet);
+                if args.stats {
+                    for stats in stats {
+                        eprintln!(
+                            "{}: {} -> {} packets, {} -> {} bits in {:?}",
+                            stats.name,
+                            stats.packets_before,
+                            stats.packets_after,
+                            stats.bits_before,
+                            stats.bits_after,
+                            stats.duration
+                        );
+                        for (rule, count) in &stats.rules {
+                            eprintln!("  {rule}: {count}");
+                        }
+                    }
+                }
+                match output.render(&packet) {
+                    Ok(result) => println!("{result}"),
+                    Err(e) => eprintln!("Failed to render packet. Full error:\n{e}"),
+                }
+            }
+            Err(e) => eprintln!("Failed to parse packet `{packet_str}`. Full error:\n{e}"),
+        }
+    }
+}
+
+fn summarize(args: SummarizeArgs, options: &ParseOptions, output: &dyn OutputFormatter) {
+    for packet_str in read_inputs(args.inputs) {
+        let summary = parse(&packet_str, &args.in_format, options).and_then(|packet| {
+            eprintln!(
+                "{} packets, depth {}, {} bits",
+                packet.flat_packets().len(),
+                packet.depth(),
+                packet.bit_len()
+            );
+            let packet = match args.sample {
+                Some(k) => packet.sample_children(k, args.seed)?,
+                None => packet,
+            };
+            output.render(&packet.truncate_depth(args.depth)?)
+        });
+        match summary {
+            Ok(result) => println!("{result}"),
+            Err(e) => eprintln!("Failed to summarize packet `{packet_str}`. Full error:\n{e}"),
+        }
+    }
+}
+
+fn inspect(args: InspectArgs, options: &ParseOptions) {
+    for packet_str in read_inputs(args.inputs) {
+        match parse(&packet_str, &args.in_format, options) {
+            Ok(packet) => match args.find {
+                Some(operation) => {
+                    for path in packet.paths_of(operation) {
+                        println!("{}", format_path(&path));
+                    }
+                }
+                None if args.dupes => {
+                    for group in packet.find_duplicates(args.min_bits) {
+                        println!(
+                            "{} bits saved ({} x {} bits): {}",
+                            group.bits_saved(),
+                            group.paths.len(),
+                            group.size_bits,
+                            group
+                                .paths
+                                .iter()
+                                .map(|path| format_path(path))
+                                .collect::<Vec<_>>()
+                                .join(" ")
+                        );
+                    }
+                }
+                None if args.memory => {
+                    let heap = packet.heap_size();
+                    println!("packets: {}", packet.packet_count());
+                    println!("heap: {heap} bytes");
+                    println!("total: {} bytes", size_of::<Packet>() + heap);
+                }
+                None if args.values => {
+                    match packet.literal_stats() {
+                        Some(stats) => println!(
+                            "literals: {}, min {}, max {}, mean {:.2}, sum {}",
+                            stats.count,
+                            stats.min,
+                            stats.max,
+                            stats.mean(),
+                            stats.sum
+                        ),
+                        None => println!("literals: 0"),
+                    }
+                    for (i, value) in packet.eval_per_child().into_iter().enumerate() {
+                        match value {
+                            Ok(value) => println!("{i}: {value}"),
+                            Err(e) => println!("{i}: error: {e}"),
+                        }
+                    }
+                }
+                None => {
+                    for (operation, count) in packet.op_histogram() {
+                        println!("{}: {count}", operation.as_func_str());
+                    }
+                }
+            },
+            Err(e) => eprintln!("Failed to parse packet `{packet_str}`. Full error:\n{e}"),
+        }
+    }
+}
+
+/// Prints the literals of every input, numbering packets from 0 in input
+/// order. Packets that fail to parse are reported and skipped.
+fn export_literals(args: ExportLiteralsArgs, options: &ParseOptions) {
+    let mut out: Box<dyn Write + Send> = match &args.output {
+        Some(path) => Box::new(std::fs::File::create(path).unwrap_or_else(|e| {
+            eprintln!("Failed to create `{}`. Full error:\n{e}", path.display());
+            std::process::exit(2);
+        })),
+        None => Box::new(std::io::stdout()),
+    };
+    let fail = |e: &dyn std::fmt::Display| -> ! {
+        eprintln!("Failed to write literals. Full error:\n{e}");
+        std::process::exit(1);
+    };
+    // CSV rows are written as they are found, Parquet columns all at once
+    let mut rows = Vec::new();
+    if let ExportFormat::Csv = args.format {
+        writeln!(out, "packet,path,value,operation").unwrap_or_else(|e| fail(&e));
+    }
+    for (i, packet_str) in read_inputs(args.inputs).into_iter().enumerate() {
+        let packet = match parse(&packet_str, &args.in_format, options) {
+            Ok(packet) => packet,
+            Err(e) => {
+                eprintln!("Failed to parse packet `{packet_str}`. Full error:\n{e}");
+                continue;
+            }
+        };
+        for record in packet.literals_with_paths() {
+            match args.format {
+                ExportFormat::Csv => {
+                    let operation = record
+                        .operation
+                        .map_or("", |operation| operation.as_func_str());
+                    writeln!(
+                        out,
+                        "{i},{},{},{operation}",
+                        format_path(&record.path),
+                        record.value
+                    )
+                    .unwrap_or_else(|e| fail(&e));
+                }
+                ExportFormat::Parquet => rows.push((i, record)),
+            }
+        }
+    }
+    if let ExportFormat::Parquet = args.format {
+        write_literals_parquet(&rows, &mut out).unwrap_or_else(|e| fail(&e));
+    }
+}
+
+/// Schema of `export-literals --format parquet`. Integers are unsigned, held
+/// bit for bit in `INT64` columns.
+#[cfg(feature = "parquet")]
+const LITERALS_SCHEMA: &str = "
+message literals {
+    REQUIRED INT64 packet (INTEGER(64, false));
+    REQUIRED BYTE_ARRAY path (UTF8);
+    REQUIRED INT64 value (INTEGER(64, false));
+    OPTIONAL BYTE_ARRAY operation (UTF8);
+}";
+
+/// Writes each literal in `rows`, with the index of the packet it is from,
+/// to `out` as a Parquet file of one row group.
+#[cfg(feature = "parquet")]
+fn write_literals_parquet(
+    rows: &[(usize, LiteralRecord)],
+    out: &mut (dyn Write + Send),
+) -> parquet::errors::Result<()> {
+    use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    #[allow(clippy::cast_possible_wrap)]
+    let int64 = |value: usize| value as i64;
+    let packets: Vec<i64> = rows.iter().map(|&(i, _)| int64(i)).collect();
+    let paths: Vec<ByteArray> = rows
+        .iter()
+        .map(|(_, record)| format_path(&record.path).as_str().into())
+        .collect();
+    let values: Vec<i64> = rows.iter().map(|(_, record)| int64(record.value)).collect();
+    let operations: Vec<ByteArray> = rows
+        .iter()
+        .filter_map(|(_, record)| record.operation)
+        .map(|operation| operation.as_func_str().into())
+        .collect();
+    let defined: Vec<i16> = rows
+        .iter()
+        .map(|(_, record)| i16::from(record.operation.is_some()))
+        .collect();
+
+    let schema = std::sync::Arc::new(parse_message_type(LITERALS_SCHEMA)?);
+    let mut writer = SerializedFileWriter::new(out, schema, std::sync::Arc::default())?;
+    let mut group = writer.next_row_group()?;
+    for column in 0.. {
+        let Some(mut column_writer) = group.next_column()? else {
+            break;
+        };
+        match column {
+            0 => column_writer
+                .typed::<Int64Type>()
+                .write_batch(&packets, None, None)?,
+            1 => column_writer
+                .typed::<ByteArrayType>()
+                .write_batch(&paths, None, None)?,
+            2 => column_writer
+                .typed::<Int64Type>()
+                .write_batch(&values, None, None)?,
+            _ => column_writer.typed::<ByteArrayType>().write_batch(
+                &operations,
+                Some(&defined),
+                None,
+            )?,
+        };
+        column_writer.close()?;
+    }
+    group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "parquet"))]
+fn write_literals_parquet(
+    _rows: &[(usize, LiteralRecord)],
+    _out: &mut (dyn Write + Send),
+) -> Result<(), &'static str> {
+    Err("jacob was built without the `parquet` feature")
+}
+
+/// Prints the value or hex packet of each expression, returning `false` if
+/// any failed. Values are folded without building packets.
+fn calc(args: CalcArgs) -> bool {
+    let mut ok = true;
+    for expr in read_inputs(args.inputs) {
+        let output = if args.hex {
+            Packet::from_expression(&expr).and_then(|packet| packet.to_hex())
+        } else if args.modulus.is_some()
+            || args.overflow.is_some()
+            || args.comparisons.is_some()
+            || args.empty_operators.is_some()
+        {
+            let options = EvalOptions::new()
+                .modulus(args.modulus)
+                .overflow(args.overflow.map_or(Overflow::Checked, Overflow::from))
+                .comparison_order(
+                    args.comparisons
+                        .map_or(ComparisonOrder::StrictTwo, ComparisonOrder::from),
+                )
+                .empty_operators(
+                    args.empty_operators
+                        .map_or(EmptyOperator::Identity, EmptyOperator::from),
+                );
+            Packet::from_expression(&expr)
+                .and_then(|packet| packet.eval_with_options(&options))
+                .map(|value| value.to_string())
+        } else {
+            jacob::eval_expression(&expr).map(|value| value.to_string())
+        };
+        match output {
+            Ok(output) => println!("{output}"),
+            Err(e) => {
+                eprintln!("Failed to evaluate `{expr}`. Full error:\n{e}");
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+/// Prints how `args.a` and `args.b` differ, returning `true` if they are equal.
+fn diff(args: &DiffArgs, options: &ParseOptions) -> Result<bool, PacketError> {
+    let a = parse(&args.a, &args.in_format, options)?;
+    let b = parse(&args.b, &args.in_format, options)?;
+    match args.view {
+        DiffView::Expression => {
+            let markers = if args.color {
+                Markers::ANSI
+            } else {
+                Markers::WORD
+            };
+            let (a_expr, b_expr) = diff_expressions(&a, &b, &ExpressionStyle::default(), &markers);
+            println!("- {a_expr}");
+            println!("+ {b_expr}");
+        }
+        DiffView::Paths => {
+            for path in a.differences(&b) {
+                println!("{}", format_path(&path));
+            }
+        }
+    }
+    Ok(a == b)
+}
+
+fn formats(args: &FormatsArgs) {
+    if args.json {
+        let formats: Vec<String> = Registry::new()
+            .formats()
+            .iter()
+            .map(|format| {
+                format!(
+                    r#"  {{"name":{},"description":{},"input":{},"output":{},"streaming":{}}}"#,
+                    json_string(format.name),
+                    json_string(format.description),
+                    format.input,
+                    format.output,
+                    format.streaming
+                )
+            })
+            .collect();
+        println!("[\n{}\n]", formats.join(",\n"));
+        return;
+    }
+    for format in Registry::new().formats() {
+        let direction = match (format.input, format.output) {
+            (true, true) => "in/out",
+            (true, false) => "in",
+            _ => "out",
+        };
+        let streaming = if format.streaming { "streaming" } else { "" };
+        println!(
+            "{:<8} {direction:<6} {streaming:<9} {}",
+            format.name, format.description
+        );
+    }
+}
+
+/// Prints each sample packet with its description, expression, and value.
+fn examples() {
+    for (i, example) in jacob::examples::examples().iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let packet = example.packet();
+        let value = packet
+            .eval()
+            .map_or_else(|e| format!("error: {e}"), |value| value.to_string());
+        println!("{}", example.hex);
+        println!("  {}", example.description);
+        println!("  {packet} = {value}");
+    }
+}
+
+/// Prints whether each stage of a parse, serialize, parse cycle is stable,
+/// returning `false` at the first divergence.
+fn roundtrip_one(hex: &str) -> Result<bool, PacketError> {
+    let packet = Packet::from_str(hex)?;
+    let reserialized = packet.to_hex()?;
+    let input = hex.to_ascii_uppercase();
+    match input
+        .chars()
+        .zip(reserialized.chars())
+        .position(|(a, b)| a != b)
+    {
+        Some(i) => {
+            println!("hex: diverges at character {i}: {input} vs {reserialized}");
+            return Ok(false);
+        }
+        None if input.len() != reserialized.len() => {
+            let i = input.len().min(reserialized.len());
+            println!("hex: diverges at character {i}: {input} vs {reserialized}");
+            return Ok(false);
+        }
+        None => println!("hex: stable"),
+    }
+
+    let reparsed = Packet::from_str(&reserialized)?;
+    if let Some(path) = packet.first_difference(&reparsed) {
+        println!("structure: diverges at {}", format_path(&path));
+        return Ok(false);
+    }
+    println!("structure: stable");
+
+    match (packet.eval(), reparsed.eval()) {
+        (Ok(a), Ok(b)) if a == b => println!("eval: stable ({a})"),
+        (Ok(a), Ok(b)) => {
+            println!("eval: diverges: {a} vs {b}");
+            return Ok(false);
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            println!("eval: failed: {e}");
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn roundtrip(args: RoundtripArgs) -> bool {
+    let mut stable = true;
+    for packet_str in read_inputs(args.inputs) {
+        println!("{packet_str}");
+        stable &= roundtrip_one(&packet_str).unwrap_or_else(|e| {
+            println!("parse: failed: {e}");
+            false
+        });
+    }
+    stable
+}
+
+/// Prints the first mismatch with a shrunk reproducer and returns `false`,
+/// or returns `true` if every packet passes.
+fn stress(args: &StressArgs) -> bool {
+    let mut generator = Generator::new(args.seed);
+    generator.max_depth = args.max_depth;
+    if args.extended {
+        generator.profile = Profile::Extended;
+    }
+    for iteration in 0..args.iterations {
+        let packet = generator.packet();
+        if let Err(mismatch) = stress::check(&packet) {
+            let shrunk = minimize(&packet, |candidate| {
+                stress::check(candidate).map_or_else(|e| e.check == mismatch.check, |()| false)
+            });
+            println!("iteration {iteration}: {mismatch}");
+            println!("packet: {}", render_for_report(&packet));
+            println!("shrunk: {}", render_for_report(&shrunk));
+            if let Err(mismatch) = stress::check(&shrunk) {
+                println!("shrunk {mismatch}");
+            }
+            return false;
+        }
+    }
+    println!("{} packets passed", args.iterations);
+    true
+}
+
+/// Returns a JSON array of `count` vectors. Packets whose value overflows
+/// 64 bits are skipped, so every vector has a definite result.
+fn vectors_json(args: &VectorsArgs) -> Result<String, PacketError> {
+    let mut generator = Generator::new(args.seed);
+    let profile = match args.profile {
+        VectorProfile::Default => Profile::Standard,
+        VectorProfile::Extended => Profile::Extended,
+    };
+    generator.profile = profile;
+    let mut vectors = Vec::with_capacity(args.count);
+    while vectors.len() < args.count {
+        let packet = generator.packet();
+        let Ok(eval) = packet.eval_as::<u64>(Overflow::Checked) else {
+            continue;
+        };
+        vectors.push(format!(
+            r#"  {{"hex":{},"eval":{eval},"version_sum":{},"expr":{}}}"#,
+            json_string(&packet.to_hex_with_profile(profile)?),
+            packet.version_sum(),
+            json_string(&packet.to_string())
+        ));
+    }
+    Ok(format!("[\n{}\n]\n", vectors.join(",\n")))
+}
+
+fn vectors(args: &VectorsArgs) -> Result<(), String> {
+    let json = vectors_json(args).map_err(|e| e.to_string())?;
+    match &args.out {
+        Some(path) => std::fs::write(path, json).map_err(|e| e.to_string()),
+        None => std::io::stdout()
+            .write_all(json.as_bytes())
+            .map_err(|e| e.to_string()),
+    }
+}
+
+fn report(args: &ReportArgs, options: &ParseOptions) -> Result<(), String> {
+    let packet = parse(&args.input, &args.in_format, options).map_err(|e| e.to_string())?;
+    let html = jacob::report::html(&packet).map_err(|e| e.to_string())?;
+    match &args.output {
+        Some(path) => std::fs::write(path, html).map_err(|e| e.to_string()),
+        None => std::io::stdout()
+            .write_all(html.as_bytes())
+            .map_err(|e| e.to_string()),
+    }
+}
+
+/// Prints the bit offset and rendering of every packet found in the file.
+/// Returns whether any were found.
+fn scan_file(args: &ScanArgs, options: &ParseOptions, output: &dyn OutputFormatter) -> bool {
+    let bytes = std::fs::read(&args.file).unwrap_or_else(|e| {
+        eprintln!("Failed to read `{}`. Full error:\n{e}", args.file.display());
+        std::process::exit(2);
+    });
+    let candidates = scan(
+        &bytes,
+        &ScanOptions {
+            profile: options.profile,
+            step: args.step,
+            min_bits: args.min_bits,
+            overlapping: args.overlapping,
+        },
+    );
+    for (offset, packet) in &candidates {
+        match output.render(packet) {
+            Ok(result) => println!("{offset}\t{result}"),
+            Err(e) => eprintln!("Failed to render packet at bit {offset}. Full error:\n{e}"),
+        }
+    }
+    !candidates.is_empty()
+}
+
+/// Writes each packet of the transmission in `args.file` to its own file in
+/// `args.output_dir`, listed in a JSON manifest with its byte offset.
+fn split(args: &SplitArgs, options: &ParseOptions) -> Result<(), String> {
+    let text = std::fs::read_to_string(&args.file).unwrap_or_else(|e| {
+        eprintln!("Failed to read `{}`. Full error:\n{e}", args.file.display());
+        std::process::exit(2);
+    });
+    let hex = read_hex(&text);
+    let transmission = bytes_from_hex(&hex)
+        .and_then(|bytes| parse_all(&bytes, options))
+        .map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&args.output_dir).map_err(|e| e.to_string())?;
+    let width = transmission
+        .packets
+        .len()
+        .saturating_sub(1)
+        .to_string()
+        .len();
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    for (i, packet) in transmission.packets.iter().enumerate() {
+        let hex = packet
+            .to_hex_with_profile(options.profile)
+            .map_err(|e| e.to_string())?;
+        let name = format!("{i:0width$}.hex");
+        std::fs::write(args.output_dir.join(&name), format!("{hex}\n"))
+            .map_err(|e| format!("Failed to write `{name}`: {e}"))?;
+        entries.push(format!(
+            r#"  {{"index":{i},"file":{},"offset":{offset},"bytes":{},"expr":{}}}"#,
+            json_string(&name),
+            hex.len() / 2,
+            json_string(&packet.to_string())
+        ));
+        offset += hex.len() / 2;
+    }
+    let manifest = if entries.is_empty() {
+        "[]\n".to_string()
+    } else {
+        format!("[\n{}\n]\n", entries.join(",\n"))
+    };
+    std::fs::write(args.output_dir.join("manifest.json"), manifest).map_err(|e| e.to_string())?;
+    println!(
+        "Wrote {} packets to `{}`",
+        transmission.packets.len(),
+        args.output_dir.display()
+    );
+    Ok(())
+}
+
+/// Prints the packets of every file in `args.files` as one hex transmission,
+/// wrapped in a new operator if `args.wrap` is given.
+fn merge(args: MergeArgs, options: &ParseOptions) -> Result<(), String> {
+    let mut transmission = Transmission::default();
+    for path in &args.files {
+        let text = std::fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("Failed to read `{}`. Full error:\n{e}", path.display());
+            std::process::exit(2);
+        });
+        let hex = read_hex(&text);
+        let packets = bytes_from_hex(&hex)
+            .and_then(|bytes| parse_all(&bytes, options))
+            .map_err(|e| format!("`{}`: {e}", path.display()))?;
+        transmission.packets.extend(packets.packets);
+    }
+    let bytes = match args.wrap {
+        Some(operation) => transmission
+            .wrap(operation, args.version)
+            .and_then(|packet| packet.to_bytes_with_profile(options.profile)),
+        None => transmission.to_bytes_with_profile(options.profile),
+    }
+    .map_err(|e| e.to_string())?;
+    println!("{}", hex_from_bytes(&bytes));
+    Ok(())
+}
+
+/// Prints `args.file` formatted, or writes it back, returning `false` if an
+/// expression failed to parse or `args.check` found the file unformatted.
+fn fmt_expr(args: &FmtExprArgs) -> Result<bool, String> {
+    let src = std::fs::read_to_string(&args.file).unwrap_or_else(|e| {
+        eprintln!("Failed to read `{}`. Full error:\n{e}", args.file.display());
+        std::process::exit(2);
+    });
+    let cst = Cst::parse(&src);
+    let mut ok = true;
+    for entry in cst.entries() {
+        if let Err(e) = entry.parse() {
+            let line = src[..entry.span().start].matches('\n').count() + 1;
+            eprintln!(
+                "Failed to parse expression `{}` on line {line} of `{}`, left as is. Full error:\n{e}",
+                entry.expression(),
+                args.file.display()
+            );
+            ok = false;
+        }
+    }
+    let formatted = cst.format();
+    if args.check {
+        if formatted != src {
+            eprintln!("`{}` is not formatted", args.file.display());
+            ok = false;
+        }
+    } else if args.write {
+        if formatted != src {
+            std::fs::write(&args.file, &formatted).map_err(|e| e.to_string())?;
+        }
+    } else {
+        print!("{formatted}");
+    }
+    Ok(ok)
+}
+
+/// Prints how likely each file in `args.files` is to hold a packet.
+fn classify(args: &ClassifyArgs) {
+    for path in &args.files {
+        let bytes = if args.hex {
+            std::fs::read_to_string(path).and_then(|text| {
+                bytes_from_hex(&read_hex(&text))
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            })
+        } else {
+            std::fs::read(path)
+        }
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to read `{}`. Full error:\n{e}", path.display());
+            std::process::exit(2);
+        });
+        println!("{}: {}", path.display(), looks_like_packet(&bytes));
+    }
+}
+
+/// A hex packet and its expected value, or why its entry is malformed.
+type CheckCase = Result<(String, u64), String>;
+
+/// Reads the labelled cases of a `jacob check` file: a JSON array, or a
+/// `HEX => EXPECTED` entry or JSON object per line.
+fn check_cases(text: &str) -> Result<Vec<(String, CheckCase)>, String> {
+    let json_case = |value: &Value| {
+        let hex = value
+            .get("hex")
+            .and_then(Value::as_str)
+            .ok_or("expected a string `hex` member")?;
+        let eval = value
+            .get("eval")
+            .and_then(Value::as_u64)
+            .ok_or("expected a number `eval` member")?;
+        Ok((hex.to_string(), eval))
+    };
+    if text.trim_start().starts_with('[') {
+        let value: Value = text.parse().map_err(|e: PacketError| e.to_string())?;
+        let cases = value.as_array().ok_or("expected an array")?;
+        return Ok(cases
+            .iter()
+            .enumerate()
+            .map(|(i, case)| (format!("case {}", i + 1), json_case(case)))
+            .collect());
+    }
+    Ok(read_entries(text)
+        .map(|entry| {
+            let case = if entry.text.starts_with('{') {
+                entry
+                    .text
+                    .parse()
+                    .map_err(|e: PacketError| e.to_string())
+                    .and_then(|value| json_case(&value))
+            } else {
+                entry
+                    .text
+                    .split_once("=>")
+                    .ok_or_else(|| "expected `HEX => EXPECTED`".to_string())
+                    .and_then(|(hex, expected)| {
+                        let expected = expected
+                            .trim()
+                            .parse()
+                            .map_err(|e| format!("invalid expected value: {e}"))?;
+                        Ok((hex.trim().to_string(), expected))
+                    })
+            };
+            (format!("line {}", entry.line), case)
+        })
+        .collect())
+}
+
+/// Prints whether each packet in `args.file` evaluates to its expected value,
+/// then a summary, returning `false` if any did not.
+fn check(args: &CheckArgs, options: &ParseOptions) -> bool {
+    let text = std::fs::read_to_string(&args.file).unwrap_or_else(|e| {
+        eprintln!("Failed to read `{}`. Full error:\n{e}", args.file.display());
+        std::process::exit(2);
+    });
+    let cases = check_cases(&text).unwrap_or_else(|e| {
+        eprintln!(
+            "Failed to parse `{}`. Full error:\n{e}",
+            args.file.display()
+        );
+        std::process::exit(2);
+    });
+    let mut failed = 0;
+    for (label, case) in &cases {
+        let outcome = case
+            .as_ref()
+            .map_err(Clone::clone)
+            .and_then(|(hex, expected)| {
+                let actual = Packet::from_hex_with(hex, options)
+                    .and_then(|packet| packet.eval_as::<u64>(Overflow::Checked))
+                    .map_err(|e| format!("{hex}: {e}"))?;
+                if actual == *expected {
+                    Ok(format!("{hex} = {actual}"))
+                } else {
+                    Err(format!("{hex} = {actual}, expected {expected}"))
+                }
+            });
+        match outcome {
+            Ok(message) => println!("ok: {label}: {message}"),
+            Err(message) => {
+                println!("FAIL: {label}: {message}");
+                failed += 1;
+            }
+        }
+    }
+    println!("{} passed, {failed} failed", cases.len() - failed);
+    failed == 0
+}
+
+/// Prints statistics over the packets in every file of the directory,
+/// returning `false` if any failed to parse.
+fn corpus_stats(args: &CorpusStatsArgs, options: &ParseOptions) -> bool {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&args.dir)
+        .and_then(|entries| entries.map(|entry| Ok(entry?.path())).collect())
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to read `{}`. Full error:\n{e}", args.dir.display());
+            std::process::exit(2);
+        });
+    files.retain(|path| path.is_file());
+    files.sort();
+    let mut stats = CorpusStats::new();
+    let mut failed = 0;
+    for path in &files {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                eprintln!("Failed to read `{}`. Full error:\n{e}", path.display());
+                failed += 1;
+                continue;
+            }
+        };
+        for entry in read_entries(&contents) {
+            match parse(entry.text, &args.in_format, options) {
+                Ok(packet) => stats.add(&packet),
+                Err(e) => {
+                    eprintln!(
+                        "Failed to parse packet `{}` on line {} of `{}`. Full error:\n{e}",
+                        entry.text,
+                        entry.line,
+                        path.display()
+                    );
+                    failed += 1;
+                }
+            }
+        }
+    }
+    let percentiles: Vec<(u8, Option<usize>)> = [0, 50, 90, 99, 100]
+        .into_iter()
+        .map(|p| (p, stats.literal_percentile(f64::from(p))))
+        .collect();
+    match args.format {
+        StatsFormat::Json => {
+            let ops: Vec<String> = stats
+                .op_histogram
+                .iter()
+                .map(|(operation, count)| format!(r#""{}":{count}"#, operation.as_func_str()))
+                .collect();
+            let depths: Vec<String> = stats
+                .depths
+                .iter()
+                .map(|(depth, count)| format!(r#""{depth}":{count}"#))
+                .collect();
+            let percentiles: Vec<String> = percentiles
+                .iter()
+                .map(|(p, value)| {
+                    format!(
+                        r#""p{p}":{}"#,
+                        value.map_or_else(|| "null".to_string(), |value| value.to_string())
+                    )
+                })
+                .collect();
+            println!(
+                r#"{{"files":{},"packets":{},"failed":{failed},"operations":{{{}}},"depths":{{{}}},"literals":{{"count":{},{}}},"bits":{},"minimal_bits":{},"efficiency":{:.4}}}"#,
+                files.len(),
+                stats.packets,
+                ops.join(","),
+                depths.join(","),
+                stats.literal_count(),
+                percentiles.join(","),
+                stats.bits,
+                stats.minimal_bits,
+                stats.efficiency()
+            );
+        }
+        StatsFormat::Markdown => {
+            println!("# Corpus statistics\n");
+            println!(
+                "{} packets from {} files, {failed} failed\n",
+                stats.packets,
+                files.len()
+            );
+            println!("## Operations\n\n| operation | count |\n| --- | ---: |");
+            for (operation, count) in &stats.op_histogram {
+                println!("| {} | {count} |", operation.as_func_str());
+            }
+            println!("\n## Depths\n\n| depth | packets |\n| ---: | ---: |");
+            for (depth, count) in &stats.depths {
+                println!("| {depth} | {count} |");
+            }
+            println!(
+                "\n## Literals\n\n{} literals\n\n| percentile | value |\n| ---: | ---: |",
+                stats.literal_count()
+            );
+            for (p, value) in &percentiles {
+                if let Some(value) = value {
+                    println!("| {p} | {value} |");
+                }
+            }
+            println!(
+                "\n## Encoding\n\n{} bits, {} when minified ({:.1}% efficient)",
+                stats.bits,
+                stats.minimal_bits,
+                stats.efficiency() * 100.0
+            );
+        }
+    }
+    failed == 0
+}
+
+fn minimize_packet(args: &MinimizeArgs) -> Result<bool, PacketError> {
+    let packet = Packet::from_str(&args.hex)?;
+    if !args.check.fails(&packet) {
+        println!("packet does not fail the check");
+        return Ok(false);
+    }
+    let minimal = minimize(&packet, |candidate| args.check.fails(candidate));
+    println!("{}", render_for_report(&minimal));
+    Ok(true)
+}
+
+/// Returns the packet as hex in the profile it needs, followed by its expression.
+fn render_for_report(packet: &Packet) -> String {
+    let profile = if packet.to_bytes().is_ok() {
+        Profile::Standard
+    } else {
+        Profile::Extended
+    };
+    let hex = packet
+        .to_bytes_with_profile(profile)
+        .map_or_else(|e| format!("<{e}>"), |bytes| hex_from_bytes(&bytes));
+    format!("{hex} ({profile:?}) {packet}")
+}
+
+/// Consecutive receive errors after which `jacob listen` exits.
+const MAX_RECEIVE_FAILURES: u32 = 10;
+
+fn listen(args: ListenArgs, options: &ParseOptions, output: &dyn OutputFormatter) {
+    let socket = UdpSocket::bind(args.udp).unwrap_or_else(|e| {
+        eprintln!("Failed to bind {}. Full error:\n{e}", args.udp);
+        std::process::exit(1);
+    });
+    // Datagrams carry at most 65507 bytes of payload over IPv4
+    let mut buffer = vec![0; 65_536];
+    let mut received = 0;
+    let mut failures = 0;
+    while args.count.is_none_or(|count| received < count) {
+        let (len, sender) = match socket.recv_from(&mut buffer) {
+            Ok(datagram) => datagram,
+            Err(e) => {
+                eprintln!("Failed to receive datagram. Full error:\n{e}");
+                failures += 1;
+                if failures == MAX_RECEIVE_FAILURES {
+                    eprintln!("Giving up after {failures} failures in a row");
+                    std::process::exit(1);
+                }
+                // Back off from 10ms, doubling, for errors that persist
+                std::thread::sleep(Duration::from_millis(10 << (failures - 1)));
+                continue;
+            }
+        };
+        failures = 0;
+        received += 1;
+        match Packet::from_bytes_with(&buffer[..len], options)
+            .and_then(|packet| output.render(&packet))
+        {
+            Ok(result) => println!("{result}"),
+            Err(e) => eprintln!("Failed to decode datagram from {sender}. Full error:\n{e}"),
+        }
+    }
+}
+
+fn send(args: SendArgs, options: &ParseOptions) -> std::io::Result<()> {
+    let mut transport = if args.tcp {
+        Transport::Tcp(TcpStream::connect(&args.address)?)
+    } else {
+        let destination = args.address.to_socket_addrs()?.next().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("`{}` resolves to no address", args.address),
+            )
+        })?;
+        let wildcard = match destination {
+            SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+            SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+        };
+        Transport::Udp(UdpSocket::bind(wildcard)?, destination)
+    };
+    for packet_str in read_inputs(args.inputs) {
+        let bytes = parse(&packet_str, &args.in_format, options).and_then(|packet| {
+            if args.envelope {
+                packet.to_bytes_enveloped()
+            } else {
+                packet.to_bytes_with_profile(options.profile)
+            }
+        });
+        match bytes {
+            Ok(bytes) => match &mut transport {
+                Transport::Tcp(stream) => stream.write_all(&bytes)?,
+                Transport::Udp(socket, destination) => {
+                    socket.send_to(&bytes, *destination)?;
+                }
+            },
+            Err(e) => eprintln!("Failed to encode packet `{packet_str}`. Full error:\n{e}"),
+        }
+    }
+    Ok(())
+}
+
+/// Exits the process with status 124, like `timeout(1)`, once `timeout` has
+/// passed.
+fn start_watchdog(timeout: Duration) {
+    std::thread::spawn(move || {
+        std::thread::sleep(timeout);
+        eprintln!("Timed out after {timeout:?}");
+        std::process::exit(124);
+    });
+}
+
+fn main() {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let mut config = settings::load(cli.config.as_deref()).unwrap_or_else(|message| {
+        eprintln!("{message}");
+        std::process::exit(2);
+    });
+    config.parse_options.max_input_bytes =
+        cli.max_input_bytes.or(config.parse_options.max_input_bytes);
+    config.parse_options.max_depth = cli.max_depth.or(config.parse_options.max_depth);
+    if let Some(timeout) = cli.timeout.or(config.timeout) {
+        start_watchdog(timeout);
+    }
+    let options = &config.parse_options;
+    // Formats left at their defaults fall back to the configuration
+    let command_matches = matches
+        .subcommand()
+        .map_or(&matches, |(_, matches)| matches);
+    let in_format = |format| {
+        settings::pick(
+            command_matches,
+            "in-format",
+            format,
+            config.in_format.clone(),
+        )
+    };
+    let out_format = |format| {
+        settings::pick(
+            command_matches,
+            "out-format",
+            format,
+            config.out_format.clone(),
+        )
+    };
+    let registry = Registry::new();
+
+    match cli.command {
+        Some(Command::Transform(mut args)) => {
+            args.in_format = in_format(args.in_format);
+            args.out_format = out_format(args.out_format);
+            let output = formatter(&registry, &args.out_format);
+            transform(args, command_matches, &config, output);
+        }
+        Some(Command::Summarize(mut args)) => {
+            args.in_format = in_format(args.in_format);
+            args.out_format = out_format(args.out_format);
+            let output = formatter(&registry, &args.out_format);
+            summarize(args, options, output);
+        }
+        Some(Command::Inspect(mut args)) => {
+            args.in_format = in_format(args.in_format);
+            inspect(args, options);
+        }
+        Some(Command::Listen(mut args)) => {
+            args.out_format = out_format(args.out_format);
+            let output = formatter(&registry, &args.out_format);
+            listen(args, options, output);
+        }
+        Some(Command::Roundtrip(args)) => {
+            if !roundtrip(args) {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Stress(args)) => {
+            if !stress(&args) {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::ExportLiterals(mut args)) => {
+            args.in_format = in_format(args.in_format);
+            export_literals(args, options);
+        }
+        Some(Command::Formats(args)) => formats(&args),
+        Some(Command::Examples) => examples(),
+        Some(Command::Diff(mut args)) => {
+            args.in_format = in_format(args.in_format);
+            match diff(&args, options) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("Failed to parse packet. Full error:\n{e}");
+                    std::process::exit(2);
+                }
+            }
+        }
+        Some(Command::Scan(mut args)) => {
+            args.out_format = out_format(args.out_format);
+            let output = formatter(&registry, &args.out_format);
+            if !scan_file(&args, options, output) {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::CorpusStats(mut args)) => {
+            args.in_format = in_format(args.in_format);
+            if !corpus_stats(&args, options) {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Split(args)) => {
+            if let Err(e) = split(&args, options) {
+                eprintln!("Failed to split transmission. Full error:\n{e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Merge(args)) => {
+            if let Err(e) = merge(args, options) {
+                eprintln!("Failed to merge packets. Full error:\n{e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Check(args)) => {
+            if !check(&args, options) {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Classify(args)) => classify(&args),
+        Some(Command::FmtExpr(args)) => match fmt_expr(&args) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("Failed to write formatted expressions. Full error:\n{e}");
+                std::process::exit(1);
+            }
+        },
+        Some(Command::Calc(args)) => {
+            if !calc(args) {
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "tui")]
+        Some(Command::Tui(mut args)) => {
+            args.in_format = in_format(args.in_format);
+            let packet = parse(&args.input, &args.in_format, options).unwrap_or_else(|e| {
+                eprintln!(
+                    "Failed to parse packet with format: `{}`. Full error:\n{e}",
+                    args.in_format
+                );
+                std::process::exit(1);
+            });
+            if let Err(e) = tui::run(&packet) {
+                eprintln!("Failed to run the terminal interface. Full error:\n{e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Report(mut args)) => {
+            args.in_format = in_format(args.in_format);
+            if let Err(e) = report(&args, options) {
+                eprintln!("Failed to write report. Full error:\n{e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Vectors(args)) => {
+            if let Err(e) = vectors(&args) {
+                eprintln!("Failed to write vectors. Full error:\n{e}");
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Minimize(args)) => match minimize_packet(&args) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("Failed to parse packet. Full error:\n{e}");
+                std::process::exit(2);
+            }
+        },
+        Some(Command::Send(mut args)) => {
+            args.in_format = in_format(args.in_format);
+            if let Err(e) = send(args, options) {
+                eprintln!("Failed to send packets. Full error:\n{e}");
+                std::process::exit(1);
+            }
+        }
+        None => {
+            let keys = Keys {
+                encrypt: cli.encrypt_key,
+                decrypt: cli.decrypt_key,
+            };
+            let style = load_style(cli.names.or_else(|| config.names.clone()), cli.symbols)
+                .unwrap_or_else(|message| {
+                    eprintln!("{message}");
+                    std::process::exit(2);
+                });
+            let mut registry = Registry::new();
+            registry.add_output(Expression(style));
+            let mut quarantine = cli.quarantine.map(|path| {
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .unwrap_or_else(|e| {
+                        eprintln!("Failed to open `{}`. Full error:\n{e}", path.display());
+                        std::process::exit(2);
+                    })
+            });
+            // Records hold hex whatever the configured input format
+            let (inputs, report, in_format) = match cli.stdin_format {
+                Some(StdinFormat::Ndjson) => {
+                    (read_records(), Some(Report::Json), "hex".to_string())
+                }
+                None => (
+                    cli.inputs.into_iter().map(Input::from).collect(),
+                    cli.report,
+                    in_format(cli.in_format),
+                ),
+            };
+            let values = convert(
+                inputs,
+                &in_format,
+                formatter(&registry, &out_format(cli.out_format)),
+                options,
+                keys,
+                Reporting {
+                    report,
+                    warnings: cli.warnings,
+                },
+                quarantine.as_mut().map(|file| file as &mut dyn Write),
+            );
+            if let Some(mode) = cli.exit_eval {
+                std::process::exit(exit_code(&values, mode));
+            }
+        }
+    }
+}
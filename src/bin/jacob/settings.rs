@@ -0,0 +1,41 @@
+//! Defaults shared by every command.
+//!
+//! Each default is resolved from, in increasing precedence, the
+//! [configuration file](crate::config), an environment variable named after
+//! its key (`JACOB_IN_FORMAT`, `JACOB_STRICT`, ...) holding the value without
+//! TOML quoting, and the command line. `JACOB_CONFIG` names the configuration
+//! file when `--config` is not given.
+
+use std::path::{Path, PathBuf};
+
+use clap::{ArgMatches, ValueSource};
+
+use crate::config::{self, Config};
+
+const PREFIX: &str = "JACOB_";
+
+/// Reads the configuration file, then applies environment overrides.
+pub fn load(path: Option<&Path>) -> Result<Config, String> {
+    let path = path
+        .map(Path::to_path_buf)
+        .or_else(|| std::env::var_os(format!("{PREFIX}CONFIG")).map(PathBuf::from));
+    let mut config = Config::load(path.as_deref())?;
+    for key in config::KEYS {
+        let var = format!("{PREFIX}{}", key.to_ascii_uppercase());
+        if let Ok(value) = std::env::var(&var) {
+            config
+                .set(key, &value)
+                .map_err(|reason| format!("Invalid `{var}`: {reason}"))?;
+        }
+    }
+    Ok(config)
+}
+
+/// Returns `value` if the argument `id` was given on the command line,
+/// otherwise the configured value if there is one.
+pub fn pick<T>(matches: &ArgMatches, id: &str, value: T, configured: Option<T>) -> T {
+    match matches.value_source(id) {
+        Some(ValueSource::DefaultValue) | None => configured.unwrap_or(value),
+        Some(_) => value,
+    }
+}
@@ -0,0 +1,294 @@
+//! Full-screen explorer of a packet's tree, behind the `tui` feature.
+//!
+//! The tree is listed on the left, one packet per row, with operators
+//! folded and unfolded from the keyboard. The right shows the selected
+//! packet's path, bits, and value, and a preview of it re-encoded on its own.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use jacob::width::Overflow;
+use jacob::{Packet, PacketKind, PacketPath};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::Frame;
+
+const KEYS: &str = "↑↓ move  ← out  → in  space fold  / search  n N next/previous  q quit";
+
+/// Shows `packet` until the user quits.
+pub fn run(packet: &Packet) -> std::io::Result<()> {
+    let mut explorer = Explorer::new(packet);
+    ratatui::run(|terminal| loop {
+        terminal.draw(|frame| explorer.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !explorer.handle(key.code) {
+                return Ok(());
+            }
+        }
+    })
+}
+
+/// State of the explorer between key presses.
+struct Explorer<'a> {
+    packet: &'a Packet,
+    /// Path and bit span of every packet, in pre-order.
+    nodes: Vec<(Vec<usize>, Range<u64>)>,
+    /// Paths of folded operators.
+    folded: HashSet<Vec<usize>>,
+    selected: Vec<usize>,
+    /// The search being typed, if any.
+    query: Option<String>,
+    /// Paths of the packets the last search found.
+    matches: Vec<Vec<usize>>,
+    status: String,
+}
+
+impl<'a> Explorer<'a> {
+    fn new(packet: &'a Packet) -> Self {
+        let nodes = packet
+            .packets_with_paths()
+            .into_iter()
+            .map(|(path, _)| path)
+            .zip(packet.bit_spans())
+            .collect();
+        Self {
+            packet,
+            nodes,
+            folded: HashSet::new(),
+            selected: Vec::new(),
+            query: None,
+            matches: Vec::new(),
+            status: KEYS.to_string(),
+        }
+    }
+
+    /// Returns the packets not inside a folded operator, in pre-order.
+    fn visible(&self) -> Vec<&[usize]> {
+        self.nodes
+            .iter()
+            .map(|(path, _)| &path[..])
+            .filter(|path| (0..path.len()).all(|end| !self.folded.contains(&path[..end])))
+            .collect()
+    }
+
+    /// Handles a key press, returning `false` to quit.
+    fn handle(&mut self, key: KeyCode) -> bool {
+        if let Some(query) = &mut self.query {
+            match key {
+                KeyCode::Char(c) => query.push(c),
+                KeyCode::Backspace => {
+                    query.pop();
+                }
+                KeyCode::Enter => {
+                    let query = self.query.take().unwrap_or_default();
+                    self.search(&query);
+                }
+                KeyCode::Esc => {
+                    self.query = None;
+                    self.status = KEYS.to_string();
+                }
+                _ => {}
+            }
+            return true;
+        }
+        let visible = self.visible();
+        let row = visible
+            .iter()
+            .position(|path| *path == &self.selected[..])
+            .unwrap_or(0);
+        let is_operator = matches!(
+            self.packet.get(&self.selected).map(|packet| &packet.kind),
+            Some(PacketKind::Operator { packets, .. }) if !packets.is_empty()
+        );
+        let folded = self.folded.contains(&self.selected);
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down | KeyCode::Char('j') => {
+                if let Some(path) = visible.get(row + 1) {
+                    self.selected = path.to_vec();
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                if let Some(path) = row.checked_sub(1).and_then(|row| visible.get(row)) {
+                    self.selected = path.to_vec();
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') if is_operator && !folded => {
+                self.folded.insert(self.selected.clone());
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.selected.pop();
+            }
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(' ') | KeyCode::Enter if folded => {
+                self.folded.remove(&self.selected);
+            }
+            KeyCode::Right | KeyCode::Char('l') if is_operator => self.selected.push(0),
+            KeyCode::Char(' ') | KeyCode::Enter if is_operator => {
+                self.folded.insert(self.selected.clone());
+            }
+            KeyCode::Char('g') | KeyCode::Home => self.selected.clear(),
+            KeyCode::Char('/') => self.query = Some(String::new()),
+            KeyCode::Char('n') => self.next_match(true),
+            KeyCode::Char('N') => self.next_match(false),
+            _ => {}
+        }
+        true
+    }
+
+    /// Finds the packets whose label contains `query`, such as `product` or
+    /// a literal's value, and selects the first after the current one.
+    fn search(&mut self, query: &str) {
+        self.matches = self
+            .nodes
+            .iter()
+            .map(|(path, _)| path)
+            .filter(|path| {
+                self.packet
+                    .get(path)
+                    .is_some_and(|packet| label(packet).contains(query))
+            })
+            .cloned()
+            .collect();
+        self.next_match(true);
+    }
+
+    /// Selects the next match after the selection, or the previous one
+    /// before it, unfolding the operators around it.
+    fn next_match(&mut self, forward: bool) {
+        if self.matches.is_empty() {
+            self.status = "no matches".to_string();
+            return;
+        }
+        let position = |path: &Vec<usize>| self.nodes.iter().position(|(p, _)| p == path);
+        let current = position(&self.selected);
+        let found = if forward {
+            self.matches
+                .iter()
+                .find(|path| position(path) > current)
+                .or_else(|| self.matches.first())
+        } else {
+            self.matches
+                .iter()
+                .rev()
+                .find(|path| position(path) < current)
+                .or_else(|| self.matches.last())
+        };
+        if let Some(path) = found.cloned() {
+            for end in 0..path.len() {
+                self.folded.remove(&path[..end]);
+            }
+            let index = self.matches.iter().position(|p| *p == path).unwrap_or(0);
+            self.status = format!("match {} of {}", index + 1, self.matches.len());
+            self.selected = path;
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [tree, side] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(main);
+        let [details, preview] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(side);
+
+        let visible = self.visible();
+        let items: Vec<ListItem> = visible
+            .iter()
+            .filter_map(|path| {
+                let packet = self.packet.get(path)?;
+                let marker = match &packet.kind {
+                    PacketKind::Operator { .. } if self.folded.contains(*path) => "▸ ",
+                    PacketKind::Operator { .. } => "▾ ",
+                    PacketKind::Literal(_) => "  ",
+                };
+                let indent = "  ".repeat(path.len());
+                Some(ListItem::new(format!("{indent}{marker}{}", label(packet))))
+            })
+            .collect();
+        let mut state = ListState::default()
+            .with_selected(visible.iter().position(|path| *path == &self.selected[..]));
+        let list = List::new(items)
+            .block(Block::bordered().title(" Packets "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, tree, &mut state);
+
+        frame.render_widget(
+            Paragraph::new(self.details())
+                .block(Block::bordered().title(" Details "))
+                .wrap(Wrap { trim: false }),
+            details,
+        );
+        frame.render_widget(
+            Paragraph::new(self.preview())
+                .block(Block::bordered().title(" Re-encoded on its own "))
+                .wrap(Wrap { trim: false }),
+            preview,
+        );
+        let status_line = self
+            .query
+            .as_ref()
+            .map_or_else(|| self.status.clone(), |query| format!("/{query}"));
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+
+    /// Describes the selected packet: its path, header, bits, and value.
+    fn details(&self) -> Vec<Line<'static>> {
+        let Some(packet) = self.packet.get(&self.selected) else {
+            return Vec::new();
+        };
+        let span = self
+            .nodes
+            .iter()
+            .find(|(path, _)| *path == self.selected)
+            .map_or(0..0, |(_, span)| span.clone());
+        let value = packet
+            .eval_as::<usize>(Overflow::Checked)
+            .map_or_else(|e| format!("error: {e}"), |value| value.to_string());
+        vec![
+            Line::from(format!("path     {}", PacketPath::from(&self.selected[..]))),
+            Line::from(format!("packet   {}", label(packet))),
+            Line::from(format!("version  {}", packet.version)),
+            Line::from(format!(
+                "bits     {}..{} ({} bits)",
+                span.start,
+                span.end,
+                span.end - span.start
+            )),
+            Line::from(format!("depth    {}", packet.depth())),
+            Line::from(format!("value    {value}")),
+        ]
+    }
+
+    /// Shows the selected packet as a transmission of its own.
+    fn preview(&self) -> Vec<Line<'static>> {
+        let Some(packet) = self.packet.get(&self.selected) else {
+            return Vec::new();
+        };
+        let profile = packet.required_profile();
+        let hex = packet
+            .to_hex_with_profile(profile)
+            .unwrap_or_else(|e| format!("error: {e}"));
+        let expression = packet
+            .to_expression()
+            .unwrap_or_else(|e| format!("error: {e}"));
+        vec![
+            Line::from(format!("profile  {profile:?}")),
+            Line::from(format!("hex      {hex}")),
+            Line::from(format!("expr     {expression}")),
+        ]
+    }
+}
+
+/// Returns a one-line description of `packet` without its operands.
+fn label(packet: &Packet) -> String {
+    match &packet.kind {
+        PacketKind::Literal(value) => format!("literal {value}"),
+        PacketKind::Operator {
+            operation, packets, ..
+        } => format!("{} of {}", operation.as_func_str(), packets.len()),
+    }
+}
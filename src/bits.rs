@@ -0,0 +1,391 @@
+//! Bit-level reading and writing.
+//!
+//! Packets are read from any [`BitCursor`], such as a [`BitReader`] over a
+//! byte slice, and written with a [`BitWriter`] appending to a byte vector.
+//! Both report problems as [`BitError`], so reading and writing packets pulls
+//! in no dependencies.
+//!
+//! ```
+//! use jacob::bits::{BitCursor, BitReader, BitWriter};
+//!
+//! let mut bytes = Vec::new();
+//! let mut writer = BitWriter::new(&mut bytes);
+//! writer.write(3, 6_u8).unwrap();
+//! writer.write_bit(true).unwrap();
+//! writer.byte_align();
+//! assert_eq!(bytes, [0b1101_0000]);
+//!
+//! let mut reader = BitReader::new(&bytes);
+//! assert_eq!(reader.read(3).unwrap(), 6);
+//! assert!(reader.read_bool().unwrap());
+//! assert_eq!(reader.remaining(), 4);
+//! ```
+//!
+//! Implementing [`BitCursor`] lets packets be parsed from other sources with
+//! [`Packet::read_with_profile`](crate::Packet::read_with_profile), without
+//! first collecting the bits into bytes, as [`HexBitReader`] does for
+//! hexadecimal text.
+
+use std::borrow::Cow;
+
+use thiserror::Error;
+
+use crate::{bytes_from_hex, hex_from_bytes, PacketError};
+
+/// Failure to read or write a bit field.
+#[derive(Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitError {
+    /// Reading `requested` bits at `position` would pass the end of the
+    /// `length` bits available.
+    #[error("incomplete packet bits")]
+    NotEnoughData {
+        position: u64,
+        length: u64,
+        requested: u64,
+    },
+    /// A value does not fit in the field of `bits` bits it is written to or
+    /// read from. Fields are at most 64 bits.
+    #[error("value does not fit in {bits} bits")]
+    ValueTooWide { bits: u32 },
+}
+
+/// A source of bits, read most significant bit first.
+pub trait BitCursor {
+    /// Reads the next `bits` bits, at most 64, as an unsigned number.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if fewer than `bits` bits remain or `bits` exceeds 64.
+    fn read(&mut self, bits: u32) -> Result<u64, BitError>;
+
+    /// Returns the number of bits read so far.
+    fn position(&self) -> u64;
+
+    /// Returns the number of bits left to read.
+    fn remaining(&self) -> u64;
+
+    /// Reads one bit.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if no bits remain.
+    fn read_bool(&mut self) -> Result<bool, BitError> {
+        Ok(self.read(1)? == 1)
+    }
+
+    /// Reads the next `bits` bits, at most 8.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if fewer than `bits` bits remain or `bits` exceeds 8.
+    fn read_u8(&mut self, bits: u32) -> Result<u8, BitError> {
+        if bits > u8::BITS {
+            return Err(BitError::ValueTooWide { bits });
+        }
+        Ok(u8::try_from(self.read(bits)?).unwrap_or_default())
+    }
+
+    /// Reads the next `bits` bits, at most 16.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if fewer than `bits` bits remain or `bits` exceeds 16.
+    fn read_u16(&mut self, bits: u32) -> Result<u16, BitError> {
+        if bits > u16::BITS {
+            return Err(BitError::ValueTooWide { bits });
+        }
+        Ok(u16::try_from(self.read(bits)?).unwrap_or_default())
+    }
+}
+
+/// Reads bits from a byte slice.
+#[derive(Clone, Debug)]
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: u64,
+}
+
+impl<'a> BitReader<'a> {
+    /// Returns a reader starting at the first bit of `bytes`.
+    #[must_use]
+    pub const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+}
+
+impl BitCursor for BitReader<'_> {
+    fn read(&mut self, bits: u32) -> Result<u64, BitError> {
+        if bits > u64::BITS {
+            return Err(BitError::ValueTooWide { bits });
+        }
+        let length = self.bytes.len() as u64 * 8;
+        if self.position + u64::from(bits) > length {
+            return Err(BitError::NotEnoughData {
+                position: self.position,
+                length,
+                requested: u64::from(bits),
+            });
+        }
+        let mut value = 0;
+        let mut left = bits;
+        while left > 0 {
+            // Take as many of the wanted bits as the current byte holds
+            let byte = self.bytes[usize::try_from(self.position / 8).unwrap_or(usize::MAX)];
+            let offset = u32::try_from(self.position % 8).unwrap_or_default();
+            let take = (8 - offset).min(left);
+            let chunk = u64::from(byte) >> (8 - offset - take) & ((1 << take) - 1);
+            value = value << take | chunk;
+            left -= take;
+            self.position += u64::from(take);
+        }
+        Ok(value)
+    }
+
+    fn position(&self) -> u64 {
+        self.position
+    }
+
+    fn remaining(&self) -> u64 {
+        self.bytes.len() as u64 * 8 - self.position
+    }
+}
+
+/// Reads bits straight from hexadecimal digits, decoded as
+/// [`bytes_from_hex`] would, so a packet can be parsed from text without
+/// first converting all of it to bytes.
+///
+/// ```
+/// use jacob::bits::{BitCursor, HexBitReader};
+///
+/// let mut reader = HexBitReader::new("D2FE28").unwrap();
+/// assert_eq!(reader.read(3).unwrap(), 6);
+/// assert_eq!(reader.remaining(), 21);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HexBitReader<'a> {
+    digits: Cow<'a, [u8]>,
+    position: u64,
+    length: u64,
+}
+
+impl<'a> HexBitReader<'a> {
+    /// Returns a reader starting at the first bit of `hex`. Digits are only
+    /// decoded as they are read, unless `hex` holds anything but ASCII
+    /// hexadecimal digits.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `hex` is not valid hexadecimal.
+    pub fn new(hex: &'a str) -> Result<Self, PacketError> {
+        let digits = if hex.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+            Cow::Borrowed(hex.as_bytes())
+        } else {
+            // Rare: let the full decoder report the error, or normalize
+            // whatever else it accepts
+            Cow::Owned(hex_from_bytes(&bytes_from_hex(hex)?).into_bytes())
+        };
+        let length = digits.len().div_ceil(2) as u64 * 8;
+        Ok(Self {
+            digits,
+            position: 0,
+            length,
+        })
+    }
+
+    /// Returns the number of bytes the digits decode to.
+    #[must_use]
+    pub fn byte_len(&self) -> usize {
+        self.digits.len().div_ceil(2)
+    }
+
+    /// Returns the value of the `i`th four bits. A trailing unpaired digit is
+    /// a byte of its own, so its high four bits are zero.
+    fn nibble(&self, i: usize) -> u8 {
+        let len = self.digits.len();
+        let digit = match i.checked_sub(len) {
+            _ if len.is_multiple_of(2) || i + 1 < len => self.digits[i],
+            Some(0) => self.digits[len - 1],
+            _ => return 0,
+        };
+        char::from(digit)
+            .to_digit(16)
+            .and_then(|value| u8::try_from(value).ok())
+            .unwrap_or_default()
+    }
+}
+
+impl BitCursor for HexBitReader<'_> {
+    fn read(&mut self, bits: u32) -> Result<u64, BitError> {
+        if bits > u64::BITS {
+            return Err(BitError::ValueTooWide { bits });
+        }
+        if self.position + u64::from(bits) > self.length {
+            return Err(BitError::NotEnoughData {
+                position: self.position,
+                length: self.length,
+                requested: u64::from(bits),
+            });
+        }
+        let mut value = 0;
+        let mut left = bits;
+        while left > 0 {
+            // Take as many of the wanted bits as the current digit holds
+            let nibble = self.nibble(usize::try_from(self.position / 4).unwrap_or(usize::MAX));
+            let offset = u32::try_from(self.position % 4).unwrap_or_default();
+            let take = (4 - offset).min(left);
+            let chunk = u64::from(nibble) >> (4 - offset - take) & ((1 << take) - 1);
+            value = value << take | chunk;
+            left -= take;
+            self.position += u64::from(take);
+        }
+        Ok(value)
+    }
+
+    fn position(&self) -> u64 {
+        self.position
+    }
+
+    fn remaining(&self) -> u64 {
+        self.length - self.position
+    }
+}
+
+/// Writes bit fields, most significant bit first, to the end of a byte vector.
+#[derive(Debug)]
+pub struct BitWriter<'a> {
+    bytes: &'a mut Vec<u8>,
+    /// Bits of the last byte already written, or 0 if the writer is aligned.
+    used: u32,
+}
+
+impl<'a> BitWriter<'a> {
+    /// Returns a writer appending to `bytes`.
+    pub const fn new(bytes: &'a mut Vec<u8>) -> Self {
+        Self { bytes, used: 0 }
+    }
+
+    /// Writes the low `bits` bits of `value`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `bits` exceeds 64 or `value` does not fit in
+    /// `bits` bits.
+    pub fn write<T: Into<u64>>(&mut self, bits: u32, value: T) -> Result<(), BitError> {
+        let value = value.into();
+        if bits > u64::BITS || (bits < u64::BITS && value >> bits != 0) {
+            return Err(BitError::ValueTooWide { bits });
+        }
+        for i in (0..bits).rev() {
+            self.push(value >> i & 1 == 1);
+        }
+        Ok(())
+    }
+
+    /// Writes one bit.
+    ///
+    /// # Errors
+    ///
+    /// Never fails; returns `Result` for symmetry with [`BitWriter::write`].
+    pub fn write_bit(&mut self, bit: bool) -> Result<(), BitError> {
+        self.push(bit);
+        Ok(())
+    }
+
+    /// Pads the last byte with zero bits, if it is incomplete.
+    pub const fn byte_align(&mut self) {
+        self.used = 0;
+    }
+
+    fn push(&mut self, bit: bool) {
+        if self.used == 0 {
+            self.bytes.push(0);
+        }
+        if bit {
+            if let Some(byte) = self.bytes.last_mut() {
+                *byte |= 0x80 >> self.used;
+            }
+        }
+        self.used = (self.used + 1) % 8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitCursor, BitError, BitReader, BitWriter, HexBitReader};
+    use crate::bytes_from_hex;
+
+    #[test]
+    fn test_bit_writer() {
+        let mut bytes = vec![0xAB];
+        let mut writer = BitWriter::new(&mut bytes);
+        writer.write(4, 0xF_u8).unwrap();
+        writer.write(12, 0x123_u16).unwrap();
+        writer.write(3, 0_u8).unwrap();
+        writer.write_bit(true).unwrap();
+        writer.byte_align();
+        writer.byte_align();
+        writer.write(64, u64::MAX).unwrap();
+        assert!(writer.write(2, 4_u8).is_err());
+        assert_eq!(
+            writer.write(65, 1_u8),
+            Err(BitError::ValueTooWide { bits: 65 })
+        );
+        assert_eq!(
+            bytes,
+            [0xAB, 0xF1, 0x23, 0x10, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn test_bit_reader() {
+        let bytes = [
+            0xAB, 0xF1, 0x23, 0x10, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        ];
+        let mut reader = BitReader::new(&bytes);
+        assert_eq!(reader.read(0).unwrap(), 0);
+        assert_eq!(reader.read_u8(8).unwrap(), 0xAB);
+        assert_eq!(reader.read(4).unwrap(), 0xF);
+        assert_eq!(reader.read_u16(12).unwrap(), 0x123);
+        assert!(!reader.read_bool().unwrap());
+        assert_eq!(reader.read(7).unwrap(), 0b001_0000);
+        assert_eq!(reader.position(), 32);
+        assert_eq!(reader.read(64).unwrap(), u64::MAX);
+        assert_eq!(reader.remaining(), 0);
+        assert_eq!(
+            reader.read(1),
+            Err(BitError::NotEnoughData {
+                position: 96,
+                length: 96,
+                requested: 1
+            })
+        );
+        assert_eq!(
+            BitReader::new(&[0; 9]).read(65),
+            Err(BitError::ValueTooWide { bits: 65 })
+        );
+        assert_eq!(
+            BitReader::new(&[0xFF; 2]).read_u8(9),
+            Err(BitError::ValueTooWide { bits: 9 })
+        );
+    }
+
+    #[test]
+    fn test_hex_bit_reader() {
+        for hex in ["ABF1231", "abf1231", "", "7", "+A", "D2FE28"] {
+            let bytes = bytes_from_hex(hex).unwrap();
+            let mut expected = BitReader::new(&bytes);
+            let mut reader = HexBitReader::new(hex).unwrap();
+            assert_eq!(reader.byte_len(), bytes.len(), "{hex}");
+            for bits in [3, 0, 9, 1, 12, 64].into_iter().cycle().take(12) {
+                assert_eq!(reader.read(bits), expected.read(bits), "{hex}");
+                assert_eq!(reader.position(), expected.position(), "{hex}");
+                assert_eq!(reader.remaining(), expected.remaining(), "{hex}");
+            }
+        }
+        assert!(HexBitReader::new("12G4").is_err());
+        assert_eq!(
+            HexBitReader::new("00").unwrap().read(65),
+            Err(BitError::ValueTooWide { bits: 65 })
+        );
+    }
+}
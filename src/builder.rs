@@ -0,0 +1,351 @@
+//! Constructors for building packets in code.
+//!
+//! ```
+//! use jacob::Packet;
+//!
+//! let packet = Packet::sum_of([Packet::from(1), Packet::product_of([2, 3])]);
+//! assert_eq!(packet.to_expression().unwrap(), "1 + (2 * 3)");
+//! assert_eq!(packet.eval().unwrap(), 7);
+//!
+//! // Operators mirror the shape of the Rust expression, one packet per `+`
+//! let (x, y, z) = (Packet::from(2), Packet::from(3), Packet::from(4));
+//! let packet = (x + y) * z;
+//! assert_eq!(packet.to_expression().unwrap(), "(2 + 3) * 4");
+//! assert_eq!(packet.clone().less_than(21).eval().unwrap(), 1);
+//!
+//! // The `packet!` macro spells packets like expression functions
+//! let packet = jacob::packet!(sum(1, product(2, 3)));
+//! assert_eq!(packet.to_expression().unwrap(), "1 + (2 * 3)");
+//! ```
+
+use std::ops::{Add, Mul};
+
+use crate::{Length, Operation, Packet, PacketKind, Version};
+
+impl Packet {
+    /// Returns a literal packet with version `0`.
+    #[must_use]
+    pub const fn literal(value: usize) -> Self {
+        Self {
+            version: Version::ZERO,
+            kind: PacketKind::Literal(value),
+        }
+    }
+
+    /// Returns an operator packet with version `0` and the smallest length
+    /// encoding for `packets`.
+    pub fn operator<P: Into<Self>>(
+        operation: Operation,
+        packets: impl IntoIterator<Item = P>,
+    ) -> Self {
+        let packets: Vec<Self> = packets.into_iter().map(Into::into).collect();
+        Self {
+            version: Version::ZERO,
+            kind: PacketKind::Operator {
+                length: Length::minimal(&packets),
+                operation,
+                packets,
+            },
+        }
+    }
+
+    /// Returns a [`Sum`](Operation::Sum) of `packets`.
+    pub fn sum_of<P: Into<Self>>(packets: impl IntoIterator<Item = P>) -> Self {
+        Self::operator(Operation::Sum, packets)
+    }
+
+    /// Returns a [`Product`](Operation::Product) of `packets`.
+    pub fn product_of<P: Into<Self>>(packets: impl IntoIterator<Item = P>) -> Self {
+        Self::operator(Operation::Product, packets)
+    }
+
+    /// Returns a [`Minimum`](Operation::Minimum) of `packets`.
+    pub fn min_of<P: Into<Self>>(packets: impl IntoIterator<Item = P>) -> Self {
+        Self::operator(Operation::Minimum, packets)
+    }
+
+    /// Returns a [`Maximum`](Operation::Maximum) of `packets`.
+    pub fn max_of<P: Into<Self>>(packets: impl IntoIterator<Item = P>) -> Self {
+        Self::operator(Operation::Maximum, packets)
+    }
+}
+
+impl Packet {
+    /// Returns a [`LessThan`](Operation::LessThan) comparing `self` to `other`.
+    ///
+    /// Named after the operation rather than `lt` so that it does not shadow
+    /// [`PartialOrd::lt`].
+    #[must_use]
+    pub fn less_than(self, other: impl Into<Self>) -> Self {
+        Self::operator(Operation::LessThan, [self, other.into()])
+    }
+
+    /// Returns a [`GreaterThan`](Operation::GreaterThan) comparing `self` to `other`.
+    #[must_use]
+    pub fn greater_than(self, other: impl Into<Self>) -> Self {
+        Self::operator(Operation::GreaterThan, [self, other.into()])
+    }
+
+    /// Returns an [`EqualTo`](Operation::EqualTo) comparing `self` to `other`.
+    ///
+    /// Named after the operation rather than `eq` so that it does not shadow
+    /// [`PartialEq::eq`].
+    #[must_use]
+    pub fn equal_to(self, other: impl Into<Self>) -> Self {
+        Self::operator(Operation::EqualTo, [self, other.into()])
+    }
+}
+
+/// Builds a comparison one operand at a time, so it cannot be finished with
+/// the wrong number of operands.
+///
+/// The state parameter records which operands have been given: a builder
+/// from [`CompareBuilder::lhs`] only offers [`rhs`](CompareBuilder::rhs), and
+/// only the builder that returns offers the comparisons.
+///
+/// ```
+/// use jacob::builder::CompareBuilder;
+///
+/// let packet = CompareBuilder::lhs(3).rhs(4).less_than();
+/// assert_eq!(packet.to_expression().unwrap(), "3 < 4");
+/// ```
+///
+/// ```compile_fail
+/// jacob::builder::CompareBuilder::lhs(3).less_than();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[must_use]
+pub struct CompareBuilder<S>(S);
+
+/// State of a [`CompareBuilder`] holding its left operand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lhs(Packet);
+
+/// State of a [`CompareBuilder`] holding both operands.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Operands(Packet, Packet);
+
+impl CompareBuilder<Lhs> {
+    /// Starts a comparison with its left operand.
+    pub fn lhs(lhs: impl Into<Packet>) -> Self {
+        Self(Lhs(lhs.into()))
+    }
+
+    /// Adds the right operand.
+    pub fn rhs(self, rhs: impl Into<Packet>) -> CompareBuilder<Operands> {
+        CompareBuilder(Operands(self.0 .0, rhs.into()))
+    }
+}
+
+impl CompareBuilder<Operands> {
+    /// Returns a [`LessThan`](Operation::LessThan) of the operands.
+    #[must_use]
+    pub fn less_than(self) -> Packet {
+        self.build(Operation::LessThan)
+    }
+
+    /// Returns a [`GreaterThan`](Operation::GreaterThan) of the operands.
+    #[must_use]
+    pub fn greater_than(self) -> Packet {
+        self.build(Operation::GreaterThan)
+    }
+
+    /// Returns an [`EqualTo`](Operation::EqualTo) of the operands.
+    #[must_use]
+    pub fn equal_to(self) -> Packet {
+        self.build(Operation::EqualTo)
+    }
+
+    fn build(self, operation: Operation) -> Packet {
+        let Operands(lhs, rhs) = self.0;
+        Packet::operator(operation, [lhs, rhs])
+    }
+}
+
+/// Builds a two-operand [`Sum`](Operation::Sum). Chains are not flattened;
+/// use [`Packet::simplify`] for that.
+impl<T: Into<Self>> Add<T> for Packet {
+    type Output = Self;
+
+    fn add(self, rhs: T) -> Self {
+        Self::sum_of([self, rhs.into()])
+    }
+}
+
+/// Builds a two-operand [`Product`](Operation::Product). Chains are not
+/// flattened; use [`Packet::simplify`] for that.
+impl<T: Into<Self>> Mul<T> for Packet {
+    type Output = Self;
+
+    fn mul(self, rhs: T) -> Self {
+        Self::product_of([self, rhs.into()])
+    }
+}
+
+impl From<usize> for Packet {
+    fn from(value: usize) -> Self {
+        Self::literal(value)
+    }
+}
+
+/// Builds a packet from the function syntax of expressions.
+///
+/// Functions are `sum`, `product`, `min`, `max`, `gt`, `lt`, `eq`, and `if`.
+/// Operands are functions, integer literals, or `{ ... }` blocks evaluating
+/// to anything convertible into a [`Packet`].
+///
+/// The macro expands to the constructors in [`crate::builder`], so literals
+/// that are not `usize` values and comparisons or conditionals with the
+/// wrong number of operands fail to compile.
+///
+/// ```
+/// use jacob::{packet, Packet};
+///
+/// let x = Packet::from(4);
+/// let packet = packet!(if(gt({ x.clone() }, 3), max(1, 5, 2), 0));
+/// assert_eq!(packet.to_expression().unwrap(), "if((4 > 3), max(1, 5, 2), 0)");
+/// assert_eq!(packet.eval().unwrap(), 5);
+/// ```
+///
+/// ```compile_fail
+/// jacob::packet!(gt(1, 2, 3));
+/// ```
+///
+/// ```compile_fail
+/// jacob::packet!(sum(1, -2));
+/// ```
+#[macro_export]
+macro_rules! packet {
+    (sum($($operands:tt)*)) => {
+        $crate::Packet::sum_of($crate::packet!(@operands [] $($operands)*))
+    };
+    (product($($operands:tt)*)) => {
+        $crate::Packet::product_of($crate::packet!(@operands [] $($operands)*))
+    };
+    (min($($operands:tt)*)) => {
+        $crate::Packet::min_of($crate::packet!(@operands [] $($operands)*))
+    };
+    (max($($operands:tt)*)) => {
+        $crate::Packet::max_of($crate::packet!(@operands [] $($operands)*))
+    };
+    (gt($($operands:tt)*)) => {
+        $crate::packet!(@compare greater_than $($operands)*)
+    };
+    (lt($($operands:tt)*)) => {
+        $crate::packet!(@compare less_than $($operands)*)
+    };
+    (eq($($operands:tt)*)) => {
+        $crate::packet!(@compare equal_to $($operands)*)
+    };
+    (if($($operands:tt)*)) => {{
+        let [condition, then, otherwise]: [$crate::Packet; 3] =
+            $crate::packet!(@array [] $($operands)*);
+        $crate::Packet::operator($crate::Operation::If, [condition, then, otherwise])
+    }};
+    ($function:ident($($operands:tt)*)) => {
+        ::std::compile_error!(::std::concat!("unknown function `", ::std::stringify!($function), "`"))
+    };
+    ({ $value:expr }) => {
+        $crate::Packet::from($value)
+    };
+    ($value:literal) => {
+        $crate::Packet::literal($value)
+    };
+
+    // Two operands, compared with the named method
+    (@compare $method:ident $($operands:tt)*) => {{
+        let [a, b]: [$crate::Packet; 2] = $crate::packet!(@array [] $($operands)*);
+        a.$method(b)
+    }};
+    // Comma-separated operands, each an operand token or a function and its
+    // parenthesized operands, munched into a vector or an array
+    (@operands [$($done:expr,)*]) => {{
+        let packets: ::std::vec::Vec<$crate::Packet> = ::std::vec![$($done),*];
+        packets
+    }};
+    (@operands [$($done:expr,)*] $function:ident($($inner:tt)*) $(, $($rest:tt)*)?) => {
+        $crate::packet!(@operands [$($done,)* $crate::packet!($function($($inner)*)),] $($($rest)*)?)
+    };
+    (@operands [$($done:expr,)*] $value:tt $(, $($rest:tt)*)?) => {
+        $crate::packet!(@operands [$($done,)* $crate::packet!($value),] $($($rest)*)?)
+    };
+    (@array [$($done:expr,)*]) => {
+        [$($done),*]
+    };
+    (@array [$($done:expr,)*] $function:ident($($inner:tt)*) $(, $($rest:tt)*)?) => {
+        $crate::packet!(@array [$($done,)* $crate::packet!($function($($inner)*)),] $($($rest)*)?)
+    };
+    (@array [$($done:expr,)*] $value:tt $(, $($rest:tt)*)?) => {
+        $crate::packet!(@array [$($done,)* $crate::packet!($value),] $($($rest)*)?)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompareBuilder;
+    use crate::{Length, Operation, Packet, PacketKind};
+
+    #[test]
+    fn test_operators() {
+        let x = Packet::from(5);
+        let packet = (x.clone() + 1 + 2) * (x.clone() * 3);
+        assert_eq!(packet.to_expression().unwrap(), "((5 + 1) + 2) * (5 * 3)");
+        assert_eq!(packet.eval().unwrap(), 120);
+        assert_eq!(
+            packet.simplify().to_expression().unwrap(),
+            "(5 + 1 + 2) * 5 * 3"
+        );
+        assert_eq!(x.clone().greater_than(4).eval().unwrap(), 1);
+        assert_eq!(x.clone().equal_to(4).eval().unwrap(), 0);
+        assert_eq!(x.less_than(Packet::from(4) + 2).eval().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_compare_builder() {
+        let operands = CompareBuilder::lhs(Packet::from(2) * 3).rhs(6);
+        assert_eq!(
+            operands.clone().greater_than(),
+            (Packet::from(2) * 3).greater_than(6)
+        );
+        assert_eq!(operands.clone().less_than().eval().unwrap(), 0);
+        assert_eq!(operands.equal_to().eval().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_constructors() {
+        let packet = Packet::max_of([
+            Packet::sum_of(1..=4),
+            Packet::min_of([7, 8]),
+            Packet::product_of([5]),
+        ]);
+        assert_eq!(
+            packet.to_expression().unwrap(),
+            "max((1 + 2 + 3 + 4), min(7, 8), (product(5)))"
+        );
+        assert_eq!(packet.eval().unwrap(), 10);
+        assert_eq!(Packet::from(2021), "12FE28".parse().unwrap());
+        assert!(matches!(
+            packet.kind,
+            PacketKind::Operator {
+                length: Length::PacketCount(3),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_packet_macro() {
+        assert_eq!(
+            packet!(sum(1, product(2, 3))),
+            Packet::sum_of([Packet::from(1), Packet::product_of([2, 3])])
+        );
+        assert_eq!(packet!(sum()), Packet::sum_of(Vec::<Packet>::new()));
+        assert_eq!(packet!(lt(1, max(2, 0x10))).eval().unwrap(), 1);
+        assert_eq!(packet!(eq(min(3), 3)), Packet::min_of([3]).equal_to(3));
+        let total = 2 + 3;
+        assert_eq!(
+            packet!(if(1, { total }, 7)),
+            Packet::operator(Operation::If, [1, total, 7])
+        );
+    }
+}
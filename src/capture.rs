@@ -0,0 +1,277 @@
+//! Extraction of transmissions from packet captures.
+//!
+//! [`from_pcap`] walks a classic libpcap file, pulls the payload out of every
+//! UDP or TCP segment matching a [`Filter`], and parses each payload as a
+//! transmission. Ethernet (with optional 802.1Q tags) and raw IP link types
+//! are understood, carrying IPv4 or IPv6. Fragmented IPv4 datagrams, IPv6
+//! extension headers, and pcapng files are not supported.
+
+use std::path::Path;
+
+use crate::{Packet, PacketError};
+
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+
+const GLOBAL_HEADER_LEN: usize = 24;
+const RECORD_HEADER_LEN: usize = 16;
+
+/// Transport protocol carrying a payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transport {
+    Udp,
+    Tcp,
+}
+
+/// Selects which segments of a capture are parsed.
+///
+/// The default filter matches every non-empty UDP and TCP payload.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Filter {
+    /// Only match this transport protocol.
+    pub transport: Option<Transport>,
+    /// Only match segments with this source or destination port.
+    pub port: Option<u16>,
+}
+
+impl Filter {
+    fn matches(self, transport: Transport, source: u16, destination: u16) -> bool {
+        self.transport.is_none_or(|expected| expected == transport)
+            && self
+                .port
+                .is_none_or(|port| port == source || port == destination)
+    }
+}
+
+/// Iterator over the transmissions found in a capture, yielding the 1-based
+/// frame number of each matching frame together with its parse result.
+#[derive(Clone, Debug)]
+pub struct Frames {
+    data: Vec<u8>,
+    offset: usize,
+    frame_no: usize,
+    big_endian: bool,
+    link_type: u32,
+    filter: Filter,
+}
+
+/// Opens the pcap file at `path` and returns the transmissions it contains.
+///
+/// # Errors
+///
+/// Will return `Err` if the file cannot be read or is not a supported pcap
+/// file. Errors parsing individual payloads are yielded by the iterator.
+pub fn from_pcap(path: impl AsRef<Path>, filter: Filter) -> Result<Frames, PacketError> {
+    from_pcap_bytes(std::fs::read(path).map_err(PacketError::ReadError)?, filter)
+}
+
+/// Like [`from_pcap`], but reads the capture from memory.
+///
+/// # Errors
+///
+/// Will return `Err` if `data` is not a supported pcap file.
+pub fn from_pcap_bytes(data: Vec<u8>, filter: Filter) -> Result<Frames, PacketError> {
+    let header = data
+        .get(..GLOBAL_HEADER_LEN)
+        .ok_or(PacketError::CaptureError("truncated pcap header"))?;
+    let big_endian = match header[..4] {
+        // Microsecond and nanosecond timestamp magics
+        [0xD4, 0xC3, 0xB2, 0xA1] | [0x4D, 0x3C, 0xB2, 0xA1] => false,
+        [0xA1, 0xB2, 0xC3, 0xD4] | [0xA1, 0xB2, 0x3C, 0x4D] => true,
+        _ => return Err(PacketError::CaptureError("not a pcap file")),
+    };
+    let link_type = read_u32(&header[20..24], big_endian);
+    if !matches!(
+        link_type,
+        LINKTYPE_ETHERNET | LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6
+    ) {
+        return Err(PacketError::CaptureError("unsupported link type"));
+    }
+    Ok(Frames {
+        data,
+        offset: GLOBAL_HEADER_LEN,
+        frame_no: 0,
+        big_endian,
+        link_type,
+        filter,
+    })
+}
+
+fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    }
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*bytes.get(at)?, *bytes.get(at + 1)?]))
+}
+
+impl Frames {
+    /// Returns the transport payload of a link-layer frame if it matches the filter.
+    fn payload<'a>(&self, frame: &'a [u8]) -> Option<&'a [u8]> {
+        let ip = match self.link_type {
+            LINKTYPE_ETHERNET => {
+                let mut offset = 12;
+                let mut ether_type = read_u16(frame, offset)?;
+                while ether_type == 0x8100 {
+                    offset += 4;
+                    ether_type = read_u16(frame, offset)?;
+                }
+                match ether_type {
+                    0x0800 | 0x86DD => frame.get(offset + 2..)?,
+                    _ => return None,
+                }
+            }
+            _ => frame,
+        };
+
+        let (protocol, segment) = match ip.first()? >> 4 {
+            4 => {
+                // Headers are at least 20 bytes, so an IHL under 5 is malformed
+                let header_len = Some(usize::from(ip[0] & 0x0F) * 4).filter(|&len| len >= 20)?;
+                let total_len = usize::from(read_u16(ip, 2)?);
+                let fragment = read_u16(ip, 6)?;
+                // More fragments flag or a non-zero fragment offset
+                if fragment & 0x3FFF != 0 {
+                    return None;
+                }
+                (*ip.get(9)?, ip.get(header_len..total_len.min(ip.len()))?)
+            }
+            6 => {
+                let payload_len = usize::from(read_u16(ip, 4)?);
+                (*ip.get(6)?, ip.get(40..(40 + payload_len).min(ip.len()))?)
+            }
+            _ => return None,
+        };
+
+        let (transport, header_len) = match protocol {
+            17 => (Transport::Udp, 8),
+            6 => (
+                Transport::Tcp,
+                Some(usize::from(segment.get(12)? >> 4) * 4).filter(|&len| len >= 20)?,
+            ),
+            _ => return None,
+        };
+        let (source, destination) = (read_u16(segment, 0)?, read_u16(segment, 2)?);
+        if !self.filter.matches(transport, source, destination) {
+            return None;
+        }
+        segment
+            .get(header_len..)
+            .filter(|payload| !payload.is_empty())
+    }
+}
+
+impl Iterator for Frames {
+    type Item = (usize, Result<Packet, PacketError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let header = self
+                .data
+                .get(self.offset..self.offset + RECORD_HEADER_LEN)?;
+            let captured_len = read_u32(&header[8..12], self.big_endian) as usize;
+            let start = self.offset + RECORD_HEADER_LEN;
+            let frame = self.data.get(start..start + captured_len)?;
+            self.offset = start + captured_len;
+            self.frame_no += 1;
+            if let Some(payload) = self.payload(frame) {
+                return Some((self.frame_no, Packet::from_bytes(payload)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_pcap_bytes, Filter, Transport};
+    use crate::bytes_from_hex;
+
+    /// Builds a little-endian Ethernet capture of IPv4 segments.
+    fn capture(segments: &[(u8, u16, &[u8])]) -> Vec<u8> {
+        let mut data = bytes_from_hex("D4C3B2A102000400000000000000000000000400").unwrap();
+        data.extend(1u32.to_le_bytes());
+        for (protocol, port, payload) in segments {
+            let transport = if *protocol == 17 {
+                let mut udp = vec![0x30, 0x39];
+                udp.extend(port.to_be_bytes());
+                udp.extend(u16::try_from(8 + payload.len()).unwrap().to_be_bytes());
+                udp.extend([0, 0]);
+                udp
+            } else {
+                let mut tcp = vec![0x30, 0x39];
+                tcp.extend(port.to_be_bytes());
+                tcp.extend([0; 8]);
+                tcp.extend([0x50, 0x18, 0, 0, 0, 0, 0, 0]);
+                tcp
+            };
+            let mut ip = vec![0x45, 0];
+            let total_len = 20 + transport.len() + payload.len();
+            ip.extend(u16::try_from(total_len).unwrap().to_be_bytes());
+            ip.extend([
+                0, 0, 0x40, 0, 64, *protocol, 0, 0, 127, 0, 0, 1, 127, 0, 0, 1,
+            ]);
+            let mut frame = vec![0; 12];
+            frame.extend([0x08, 0x00]);
+            frame.extend(ip);
+            frame.extend(transport);
+            frame.extend(*payload);
+            // Ethernet padding after the IP datagram must be ignored
+            frame.extend([0; 4]);
+
+            data.extend([0; 8]);
+            data.extend(u32::try_from(frame.len()).unwrap().to_le_bytes());
+            data.extend(u32::try_from(frame.len()).unwrap().to_le_bytes());
+            data.extend(frame);
+        }
+        data
+    }
+
+    #[test]
+    fn test_from_pcap_bytes() {
+        let packet = bytes_from_hex("C200B40A82").unwrap();
+        let data = capture(&[
+            (17, 9000, &packet),
+            (6, 9000, &packet),
+            (17, 53, &packet),
+            (17, 9000, &[0xFF]),
+        ]);
+
+        let frames: Vec<_> = from_pcap_bytes(data.clone(), Filter::default())
+            .unwrap()
+            .collect();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[1].1.as_ref().unwrap().eval().unwrap(), 3);
+        assert!(frames[3].1.is_err());
+
+        let filter = Filter {
+            transport: Some(Transport::Udp),
+            port: Some(9000),
+        };
+        let frame_nos: Vec<_> = from_pcap_bytes(data, filter)
+            .unwrap()
+            .map(|(frame_no, _)| frame_no)
+            .collect();
+        assert_eq!(frame_nos, [1, 4]);
+
+        assert!(from_pcap_bytes(vec![0; 24], Filter::default()).is_err());
+    }
+
+    #[test]
+    fn test_malformed_header_lengths() {
+        let packet = bytes_from_hex("C200B40A82").unwrap();
+        let ip = 24 + 16 + 14;
+        // An IPv4 IHL of 4 and a TCP data offset of 4, each under 20 bytes
+        for (offset, byte) in [(ip, 0x44), (ip + 20 + 12, 0x40)] {
+            let mut data = capture(&[(6, 9000, &packet)]);
+            data[offset] = byte;
+            assert_eq!(from_pcap_bytes(data, Filter::default()).unwrap().count(), 0);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Guessing whether unknown data is a packet.
+//!
+//! Almost any bytes whose type ID bits happen to fit parse as some packet, so
+//! parsing alone says little about a blob of unknown origin.
+//! [`looks_like_packet`] weighs a successful parse against what real packets
+//! look like: zero padding ending in the final byte, operators that evaluate,
+//! modest nesting, and consistent versions.
+//!
+//! ```
+//! use jacob::classify::{looks_like_packet, Confidence};
+//!
+//! let sum = jacob::bytes_from_hex("C200B40A82").unwrap();
+//! assert_eq!(looks_like_packet(&sum), Confidence::High);
+//! // The start of a zip archive parses, as a literal with junk after it
+//! assert_eq!(looks_like_packet(b"PK\x03\x04"), Confidence::Low);
+//! ```
+
+use std::fmt;
+
+use crate::bits::{BitCursor, BitReader};
+use crate::{Packet, PacketKind, Profile};
+
+/// How likely some data is to be a packet, from least to most.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    /// The data does not parse as a packet.
+    Unparsable,
+    /// The data parses, but looks more like noise that happens to.
+    Low,
+    /// The data parses, with some signs of noise.
+    Medium,
+    /// The data parses and looks like what encoders produce.
+    High,
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Unparsable => "unparsable",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        })
+    }
+}
+
+/// Nesting deeper than this is taken as a sign of noise.
+const MAX_PLAUSIBLE_DEPTH: usize = 32;
+/// More operands than this under one operator is taken as a sign of noise.
+const MAX_PLAUSIBLE_WIDTH: usize = 256;
+
+/// Returns how likely `bytes` are to hold one packet in the standard profile;
+/// see the [module documentation](self).
+#[must_use]
+pub fn looks_like_packet(bytes: &[u8]) -> Confidence {
+    let mut reader = BitReader::new(bytes);
+    let Ok(packet) = Packet::read_with_profile(&mut reader, Profile::Standard) else {
+        return Confidence::Unparsable;
+    };
+    let end = reader.position();
+    let mut score = 0;
+
+    // Encoders pad with zeros to the next byte, or a few bytes more
+    let last = usize::try_from(end.div_ceil(8)).unwrap_or(usize::MAX);
+    let partial = match end % 8 {
+        0 => 0,
+        used => bytes[last - 1] & (0xFF >> used),
+    };
+    let rest = bytes.get(last..).unwrap_or_default();
+    if partial != 0 || rest.iter().any(|&byte| byte != 0) {
+        score -= 2;
+    } else if rest.len() > 8 {
+        score -= 1;
+    } else {
+        score += 1;
+    }
+
+    score += if packet.eval().is_ok() { 1 } else { -1 };
+
+    let operands = packet
+        .flat_packets()
+        .into_iter()
+        .map(|packet| match &packet.kind {
+            PacketKind::Literal(_) => 0,
+            PacketKind::Operator { packets, .. } => packets.len(),
+        });
+    let sane = packet.depth() <= MAX_PLAUSIBLE_DEPTH
+        && operands.max().unwrap_or(0) <= MAX_PLAUSIBLE_WIDTH
+        && packet.empty_operators().is_empty();
+    score += if sane { 1 } else { -1 };
+
+    // Noise has versions spread evenly; a sender usually sets one version
+    let versions: Vec<_> = packet.flat_packets().iter().map(|p| p.version).collect();
+    if versions.len() >= 3 && versions.iter().all(|&version| version == versions[0]) {
+        score += 1;
+    }
+
+    // A lone literal takes only 11 bits, and is easily hit by chance
+    if end < 24 {
+        score -= 1;
+    }
+
+    match score {
+        3.. => Confidence::High,
+        1..=2 => Confidence::Medium,
+        _ => Confidence::Low,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{looks_like_packet, Confidence};
+    use crate::{bytes_from_hex, Packet};
+
+    #[test]
+    fn test_looks_like_packet() {
+        let hex = |hex| looks_like_packet(&bytes_from_hex(hex).unwrap());
+        assert_eq!(hex("C200B40A82"), Confidence::High);
+        assert_eq!(hex("9C0141080250320F1802104A08"), Confidence::High);
+        assert_eq!(hex("D2FE28"), Confidence::Medium);
+        // Set padding bits, and an equality with three operands
+        assert_eq!(hex("D2FE2F"), Confidence::Low);
+        let packet = Packet::from_expression("eq(1, 2, 3)").unwrap();
+        assert!(looks_like_packet(&packet.to_bytes().unwrap()) < Confidence::High);
+        assert_eq!(looks_like_packet(&[]), Confidence::Unparsable);
+        assert_eq!(looks_like_packet(&[0xFF; 16]), Confidence::Unparsable);
+        assert!(Confidence::Unparsable < Confidence::Low);
+    }
+}
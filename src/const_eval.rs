@@ -0,0 +1,289 @@
+//! Evaluating packets at compile time.
+//!
+//! [`eval_hex`] and [`eval_literal_hex`] are `const fn`s that read a
+//! hexadecimal packet in the [`Standard`](crate::Profile::Standard) profile
+//! directly from its digits, without building a [`Packet`](crate::Packet) or
+//! allocating. Used to initialize a constant, they decode the packet while
+//! compiling, so firmware can embed the values of known transmissions at no
+//! runtime cost:
+//!
+//! ```
+//! use jacob::const_eval::{eval_hex, eval_literal_hex};
+//!
+//! const SUM: usize = match eval_hex("C200B40A82") {
+//!     Some(value) => value,
+//!     None => panic!("invalid packet"),
+//! };
+//! const YEAR: Option<usize> = eval_literal_hex("D2FE28");
+//! assert_eq!(SUM, 3);
+//! assert_eq!(YEAR, Some(2021));
+//! ```
+//!
+//! Digits are read as [`bytes_from_hex`](crate::bytes_from_hex) reads them, so
+//! a trailing unpaired digit is a byte of its own. Both return `None` for
+//! anything parsing the hexadecimal as a `Packet` would reject, and also for
+//! non-zero bits after the packet and where evaluating would overflow, so a
+//! bad table entry fails the build rather than the device. Operands are evaluated as
+//! they are read and only the standard operations are understood; a procedural
+//! macro building whole packets at compile time would need a separate crate.
+
+use crate::Operation;
+
+/// Type ID of literals.
+const LITERAL: usize = 4;
+
+/// Reads bits from hexadecimal digits.
+struct HexDigits<'a> {
+    digits: &'a [u8],
+    position: usize,
+}
+
+impl HexDigits<'_> {
+    /// Reads the next `bits` bits, at most `usize::BITS`.
+    const fn read(&mut self, bits: usize) -> Option<usize> {
+        let mut value = 0;
+        let mut i = 0;
+        while i < bits {
+            let Some(nibble) = self.nibble(self.position / 4) else {
+                return None;
+            };
+            value = value << 1 | (nibble >> (3 - self.position % 4) & 1);
+            self.position += 1;
+            i += 1;
+        }
+        Some(value)
+    }
+
+    /// Returns the value of the `i`th four bits, or `None` past the end or at
+    /// an invalid digit. Like [`bytes_from_hex`](crate::bytes_from_hex), a
+    /// trailing unpaired digit is a byte of its own, so its high four bits
+    /// are zero.
+    const fn nibble(&self, i: usize) -> Option<usize> {
+        let len = self.digits.len();
+        if len.is_multiple_of(2) || i + 1 < len {
+            return if i < len { digit(self.digits[i]) } else { None };
+        }
+        let Some(last) = digit(self.digits[len - 1]) else {
+            return None;
+        };
+        match i - (len - 1) {
+            0 => Some(0),
+            1 => Some(last),
+            _ => None,
+        }
+    }
+
+    /// Reads the value groups of a literal.
+    const fn read_literal(&mut self) -> Option<usize> {
+        let mut value: usize = 0;
+        loop {
+            let Some(group) = self.read(5) else {
+                return None;
+            };
+            if value.leading_zeros() < 4 {
+                return None;
+            }
+            value = value << 4 | group & 0xF;
+            if group & 0x10 == 0 {
+                return Some(value);
+            }
+        }
+    }
+
+    /// Returns `true` if every digit has been read, ignoring zero padding.
+    const fn at_end(&self) -> bool {
+        let mut i = self.position / 4;
+        while i < self.digits.len().div_ceil(2) * 2 {
+            let Some(nibble) = self.nibble(i) else {
+                return false;
+            };
+            let unread = if i == self.position / 4 {
+                nibble & (0xF >> (self.position % 4))
+            } else {
+                nibble
+            };
+            if unread != 0 {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+}
+
+const fn digit(byte: u8) -> Option<usize> {
+    match byte {
+        b'0'..=b'9' => Some((byte - b'0') as usize),
+        b'A'..=b'F' => Some((byte - b'A' + 10) as usize),
+        b'a'..=b'f' => Some((byte - b'a' + 10) as usize),
+        _ => None,
+    }
+}
+
+/// Combines the values of an operator's operands as they are read.
+struct Fold {
+    operation: Operation,
+    count: usize,
+    /// The result over the operands so far, if there is one yet.
+    acc: Option<usize>,
+}
+
+impl Fold {
+    /// Starts folding the operands of an operator of type `type_id`, which is
+    /// not [`LITERAL`].
+    const fn new(type_id: usize) -> Self {
+        let operation = Operation::ALL[if type_id < LITERAL {
+            type_id
+        } else {
+            type_id - 1
+        }];
+        Self {
+            operation,
+            count: 0,
+            acc: operation.identity(),
+        }
+    }
+
+    const fn push(&mut self, value: usize) -> Option<()> {
+        self.count += 1;
+        self.acc = match self.acc {
+            Some(acc) => match self.operation.fold(acc, value) {
+                Some(acc) => Some(acc),
+                None => return None,
+            },
+            None => Some(value),
+        };
+        Some(())
+    }
+
+    const fn finish(&self) -> Option<usize> {
+        if self.operation.info().arity.accepts(self.count) {
+            self.acc
+        } else {
+            None
+        }
+    }
+}
+
+/// Evaluates the packet starting at the reader's position.
+const fn eval_at(reader: &mut HexDigits<'_>) -> Option<usize> {
+    let Some(header) = reader.read(6) else {
+        return None;
+    };
+    let type_id = header & 0b111;
+    if type_id == LITERAL {
+        return reader.read_literal();
+    }
+    let mut fold = Fold::new(type_id);
+    match reader.read(1) {
+        Some(1) => {
+            let Some(count) = reader.read(11) else {
+                return None;
+            };
+            let mut i = 0;
+            while i < count {
+                let Some(value) = eval_at(reader) else {
+                    return None;
+                };
+                if fold.push(value).is_none() {
+                    return None;
+                }
+                i += 1;
+            }
+        }
+        Some(_) => {
+            let Some(bits) = reader.read(15) else {
+                return None;
+            };
+            let end = reader.position + bits;
+            while reader.position < end {
+                let Some(value) = eval_at(reader) else {
+                    return None;
+                };
+                if fold.push(value).is_none() {
+                    return None;
+                }
+            }
+            if reader.position != end {
+                return None;
+            }
+        }
+        None => return None,
+    }
+    fold.finish()
+}
+
+/// Evaluates a hexadecimal packet in the standard profile, or returns `None`
+/// if it is invalid, is followed by anything but zero padding, or overflows.
+#[must_use]
+pub const fn eval_hex(hex: &str) -> Option<usize> {
+    let mut reader = HexDigits {
+        digits: hex.as_bytes(),
+        position: 0,
+    };
+    match eval_at(&mut reader) {
+        Some(value) if reader.at_end() => Some(value),
+        _ => None,
+    }
+}
+
+/// Returns the value of a hexadecimal literal packet, or `None` if it is not
+/// a valid literal followed by nothing but zero padding.
+#[must_use]
+pub const fn eval_literal_hex(hex: &str) -> Option<usize> {
+    let mut reader = HexDigits {
+        digits: hex.as_bytes(),
+        position: 0,
+    };
+    match reader.read(6) {
+        Some(header) if header & 0b111 == LITERAL => {}
+        _ => return None,
+    }
+    match reader.read_literal() {
+        Some(value) if reader.at_end() => Some(value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{eval_hex, eval_literal_hex};
+    use crate::Packet;
+
+    #[test]
+    fn test_const_eval() {
+        const YEAR: Option<usize> = eval_literal_hex("d2fe28");
+
+        for hex in [
+            "C200B40A82",
+            "04005AC33890",
+            "880086C3E88112",
+            "CE00C43D881120",
+            "D8005AC2A8F0",
+            "F600BC2D8F",
+            "9C005AC2F8F0",
+            "9C0141080250320F1802104A08",
+            "8A004A801A8002F478",
+        ] {
+            let packet: Packet = hex.parse().unwrap();
+            assert_eq!(eval_hex(hex), packet.eval().ok(), "{hex}");
+        }
+        assert_eq!(YEAR, Some(2021));
+        assert_eq!(eval_hex("D2FE28"), Some(2021));
+        assert_eq!(eval_literal_hex("C200B40A82"), None);
+
+        // Truncated, invalid digits, trailing data
+        assert_eq!(eval_hex("C200B40A"), None);
+        assert_eq!(eval_hex("D2FG28"), None);
+        assert_eq!(eval_hex("D2FE2801"), None);
+        assert_eq!(eval_hex("D2FE2800"), Some(2021));
+        // An unpaired final digit is the low half of a byte: `D2 07`
+        let packet: Packet = "D27".parse().unwrap();
+        assert_eq!(eval_hex("D27"), packet.eval().ok());
+        assert_eq!(eval_literal_hex("D27"), Some(7));
+        assert_eq!(eval_hex("D2G"), None);
+        // Overflowing literal
+        assert_eq!(eval_literal_hex("13FFFFFFFFFFFFFFFFFFBC"), Some(usize::MAX));
+        assert_eq!(eval_literal_hex("13FFFFFFFFFFFFFFFFFFFDE"), None);
+    }
+}
@@ -0,0 +1,347 @@
+//! Statistics over many packets.
+//!
+//! [`CorpusStats`] accumulates the per-packet [inspections](crate::inspect)
+//! of a collection of transmissions: how often each operation occurs, how
+//! deeply packets nest, the distribution of literal values, and how many bits
+//! the packets take compared to their [minified](Packet::minify) forms.
+//!
+//! ```
+//! use jacob::corpus::CorpusStats;
+//! use jacob::{Operation, Packet};
+//!
+//! let mut stats = CorpusStats::new();
+//! for expr in ["1 + 2", "max(3, 4 * 5)", "6"] {
+//!     stats.add(&Packet::from_expression(expr).unwrap());
+//! }
+//! assert_eq!(stats.packets, 3);
+//! assert_eq!(stats.op_histogram[0], (Operation::Sum, 1));
+//! assert_eq!(stats.depths, [(0, 1), (1, 1), (2, 1)]);
+//! assert_eq!(stats.literal_percentile(50.0), Some(3));
+//! ```
+//!
+//! [`CorpusEvaluator`] evaluates a collection of packets that share subtrees,
+//! as generated telemetry often does, evaluating each distinct subtree once.
+
+use std::collections::HashMap;
+
+use crate::{Operation, Packet, PacketError, PacketKind};
+
+/// Aggregate statistics of the packets [added](CorpusStats::add) to it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CorpusStats {
+    pub packets: usize,
+    /// Occurrences of each operation over every packet, ordered by type ID.
+    /// Operations that do not occur are omitted.
+    pub op_histogram: Vec<(Operation, usize)>,
+    /// Number of packets of each [depth](Packet::depth), ordered by depth.
+    pub depths: Vec<(usize, usize)>,
+    /// Bits of every packet in the standard encoding, excluding padding.
+    pub bits: u64,
+    /// Bits of every packet once [minified](Packet::minify).
+    pub minimal_bits: u64,
+    /// Every literal value, sorted when a percentile is requested.
+    literals: Vec<usize>,
+}
+
+impl CorpusStats {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the statistics of `packet`.
+    pub fn add(&mut self, packet: &Packet) {
+        self.packets += 1;
+        for (operation, count) in packet.op_histogram() {
+            match self
+                .op_histogram
+                .iter_mut()
+                .find(|(op, _)| *op == operation)
+            {
+                Some((_, total)) => *total += count,
+                None => self.op_histogram.push((operation, count)),
+            }
+        }
+        self.op_histogram
+            .sort_by_key(|(operation, _)| u8::from(operation));
+        let depth = packet.depth();
+        match self
+            .depths
+            .binary_search_by_key(&depth, |(depth, _)| *depth)
+        {
+            Ok(i) => self.depths[i].1 += 1,
+            Err(i) => self.depths.insert(i, (depth, 1)),
+        }
+        self.bits += packet.bit_len();
+        self.minimal_bits += packet.minify().bit_len();
+        self.literals
+            .extend(
+                packet
+                    .flat_packets()
+                    .into_iter()
+                    .filter_map(|packet| match packet.kind {
+                        PacketKind::Literal(value) => Some(value),
+                        PacketKind::Operator { .. } => None,
+                    }),
+            );
+    }
+
+    /// Returns the number of literals added.
+    #[must_use]
+    pub const fn literal_count(&self) -> usize {
+        self.literals.len()
+    }
+
+    /// Returns the literal value below which `percentile` percent of literals
+    /// fall, by the nearest-rank method, or `None` if there are no literals.
+    /// Percentiles outside 0 to 100 are clamped.
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn literal_percentile(&mut self, percentile: f64) -> Option<usize> {
+        if self.literals.is_empty() {
+            return None;
+        }
+        self.literals.sort_unstable();
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * self.literals.len() as f64).ceil();
+        Some(self.literals[(rank as usize).max(1) - 1])
+    }
+
+    /// Returns the minified size as a fraction of the actual size: `1.0` if
+    /// the packets are encoded as compactly as possible, less if they could
+    /// shrink.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn efficiency(&self) -> f64 {
+        if self.bits == 0 {
+            1.0
+        } else {
+            self.minimal_bits as f64 / self.bits as f64
+        }
+    }
+}
+
+/// Evaluates packets like [`Packet::eval`], remembering operator values.
+///
+/// Every distinct subtree is given an id from its operation and the ids of
+/// its operands, so a subtree seen before, in the same packet or an earlier one,
+/// is recognized exactly and not evaluated again.
+///
+/// ```
+/// use jacob::corpus::CorpusEvaluator;
+/// use jacob::Packet;
+///
+/// let mut evaluator = CorpusEvaluator::new();
+/// for (expr, value) in [("max(1, 2 * 3) + 4", 10), ("5 * max(1, 2 * 3)", 30)] {
+///     let packet = Packet::from_expression(expr).unwrap();
+///     assert_eq!(evaluator.eval(&packet).unwrap(), value);
+/// }
+/// assert_eq!(evaluator.hits, 1);
+/// ```
+///
+/// Subtrees are told apart by operations, literal values, and the order of
+/// operands, but not versions or length encodings, which do not change
+/// values. Failures are not remembered.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CorpusEvaluator {
+    /// Id of every distinct subtree seen.
+    ids: HashMap<Subtree, usize>,
+    /// Values of operators by subtree id.
+    values: HashMap<usize, usize>,
+    /// Number of operators whose value was remembered.
+    pub hits: usize,
+    /// Number of operators evaluated.
+    pub misses: usize,
+}
+
+impl CorpusEvaluator {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluates `packet`, reusing and remembering the values of its
+    /// operators.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if [`Packet::eval`] would.
+    pub fn eval(&mut self, packet: &Packet) -> Result<usize, PacketError> {
+        let mut subtrees = Vec::new();
+        self.identify_subtrees(packet, &mut subtrees);
+        self.eval_at(packet, &subtrees, &mut 0)
+    }
+
+    /// Returns the number of values remembered.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if no values are remembered.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Forgets every value.
+    pub fn clear(&mut self) {
+        self.ids.clear();
+        self.values.clear();
+    }
+
+    /// Appends the id and number of packets of every subtree of `packet`,
+    /// in preorder, returning the id of `packet`.
+    fn identify_subtrees(&mut self, packet: &Packet, subtrees: &mut Vec<(usize, usize)>) -> usize {
+        let index = subtrees.len();
+        subtrees.push((0, 1));
+        let subtree = match &packet.kind {
+            PacketKind::Literal(value) => Subtree::Literal(*value),
+            PacketKind::Operator {
+                operation, packets, ..
+            } => Subtree::Operator(
+                *operation,
+                packets
+                    .iter()
+                    .map(|operand| self.identify_subtrees(operand, subtrees))
+                    .collect(),
+            ),
+        };
+        let next = self.ids.len();
+        let id = *self.ids.entry(subtree).or_insert(next);
+        subtrees[index] = (id, subtrees.len() - index);
+        id
+    }
+
+    /// Evaluates `packet`, whose id and size are `subtrees[*index]`,
+    /// advancing `index` past its subtree.
+    fn eval_at(
+        &mut self,
+        packet: &Packet,
+        subtrees: &[(usize, usize)],
+        index: &mut usize,
+    ) -> Result<usize, PacketError> {
+        let (id, size) = subtrees[*index];
+        let (operation, packets) = match &packet.kind {
+            PacketKind::Literal(value) => {
+                *index += 1;
+                return Ok(*value);
+            }
+            PacketKind::Operator {
+                operation, packets, ..
+            } => (*operation, packets),
+        };
+        if let Some(&value) = self.values.get(&id) {
+            self.hits += 1;
+            *index += size;
+            return Ok(value);
+        }
+        if !operation.info().arity.accepts(packets.len()) {
+            return Err(PacketError::arity(packets.len(), operation));
+        }
+        *index += 1;
+        let mut operands = Vec::with_capacity(packets.len());
+        for (i, operand) in packets.iter().enumerate() {
+            // Like `eval`, ignore the branch `if` does not take
+            let taken = operation != Operation::If || i == 0 || (i == 1) == (operands[0] != 0);
+            if taken {
+                operands.push(
+                    self.eval_at(operand, subtrees, index)
+                        .map_err(|e| e.within(i))?,
+                );
+            } else {
+                *index += subtrees[*index].1;
+            }
+        }
+        let value = match operation {
+            Operation::If => operands[1],
+            _ => operation
+                .fold_all(&operands)
+                .ok_or(PacketError::Overflow(Some(operation)))?,
+        };
+        self.misses += 1;
+        self.values.insert(id, value);
+        Ok(value)
+    }
+}
+
+/// A packet with its operands replaced by their ids.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Subtree {
+    Literal(usize),
+    Operator(Operation, Vec<usize>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CorpusEvaluator, CorpusStats};
+    use crate::{Operation, Packet};
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_corpus_stats() {
+        let mut stats = CorpusStats::new();
+        assert_eq!(stats.literal_percentile(50.0), None);
+        assert_eq!(stats.efficiency(), 1.0);
+
+        for hex in ["C200B40A82", "9C0141080250320F1802104A08", "D2FE28"] {
+            stats.add(&hex.parse::<Packet>().unwrap());
+        }
+        stats.add(&Packet::from_expression("1 + 2 + 3").unwrap());
+        assert_eq!(stats.packets, 4);
+        assert_eq!(
+            stats.op_histogram,
+            [
+                (Operation::Sum, 3),
+                (Operation::Product, 1),
+                (Operation::EqualTo, 1)
+            ]
+        );
+        assert_eq!(stats.depths, [(0, 1), (1, 2), (2, 1)]);
+        assert_eq!(stats.literal_count(), 10);
+        assert_eq!(stats.literal_percentile(0.0), Some(1));
+        assert_eq!(stats.literal_percentile(50.0), Some(2));
+        assert_eq!(stats.literal_percentile(100.0), Some(2021));
+        assert!(stats.minimal_bits < stats.bits);
+        assert!(stats.efficiency() < 1.0);
+    }
+
+    #[test]
+    fn test_corpus_evaluator() {
+        let shared = "max(7, 2 * 3, if(1 < 2, 40, min()))";
+        let mut evaluator = CorpusEvaluator::new();
+        for expr in [
+            format!("{shared} + 1"),
+            format!("{shared} + 1"),
+            format!("product({shared}, {shared})"),
+            "if(0, min(), 5)".to_string(),
+        ] {
+            let packet = Packet::from_expression(&expr).unwrap();
+            assert_eq!(
+                evaluator.eval(&packet).unwrap(),
+                packet.eval().unwrap(),
+                "{expr}"
+            );
+        }
+        assert_eq!(evaluator.hits, 3);
+        assert_eq!(evaluator.len(), evaluator.misses);
+
+        // Versions do not matter, and failures are reported at their path
+        let versioned: Packet = "38006F45291200".parse().unwrap();
+        let misses = evaluator.misses;
+        assert_eq!(evaluator.eval(&versioned).unwrap(), 1);
+        assert_eq!(evaluator.eval(&versioned.canonicalize()).unwrap(), 1);
+        assert_eq!(evaluator.misses, misses + 1);
+        let packet = Packet::from_expression(&format!("{shared} + eq(1)")).unwrap();
+        assert_eq!(
+            evaluator.eval(&packet).unwrap_err().path(),
+            packet.eval().unwrap_err().path()
+        );
+
+        evaluator.clear();
+        assert!(evaluator.is_empty());
+    }
+}
@@ -0,0 +1,328 @@
+//! Authenticated and encrypted transmissions.
+//!
+//! A [`SignedFrame`] is an enveloped transmission followed by a 32-byte
+//! HMAC-SHA-256 tag over those bytes, so peers sharing a key can tell whether
+//! a control packet was tampered with. An [`EncryptedFrame`] additionally
+//! hides the transmission using `ChaCha20-Poly1305`. Both come from the
+//! `RustCrypto` `hmac`, `sha2`, and `chacha20poly1305` crates, and
+//! [`random_nonce`] reads nonces from the operating system via `getrandom`.
+//!
+//! ```
+//! use jacob::crypto::SignedFrame;
+//! use jacob::Packet;
+//!
+//! let packet = Packet::from_expression("1 + 2").unwrap();
+//! let bytes = SignedFrame::sign(&packet, b"secret").unwrap().to_bytes();
+//!
+//! let frame = SignedFrame::from_bytes(&bytes).unwrap();
+//! assert_eq!(frame.verify(b"secret").unwrap(), packet);
+//! assert!(frame.verify(b"wrong key").is_err());
+//! ```
+
+use std::io;
+
+use chacha20poly1305::{AeadInOut, ChaCha20Poly1305, Key, KeyInit, Nonce, Tag};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{Packet, PacketError};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Length in bytes of the tag appended by [`SignedFrame`].
+pub const TAG_LEN: usize = 32;
+
+/// Length in bytes of an [`EncryptedFrame`] key.
+pub const KEY_LEN: usize = 32;
+
+/// Length in bytes of an [`EncryptedFrame`] nonce.
+pub const NONCE_LEN: usize = 12;
+
+/// Length in bytes of the Poly1305 tag appended by [`EncryptedFrame`].
+pub const AEAD_TAG_LEN: usize = 16;
+
+/// Returns an HMAC-SHA-256 keyed with `key`.
+fn mac(key: &[u8]) -> HmacSha256 {
+    HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length")
+}
+
+/// Returns a nonce read from the operating system's random source, so it
+/// does not repeat for a key in practice however many frames are sealed.
+///
+/// # Errors
+///
+/// Will return `Err` if the random source is unavailable.
+pub fn random_nonce() -> Result<[u8; NONCE_LEN], PacketError> {
+    let mut nonce = [0; NONCE_LEN];
+    getrandom::fill(&mut nonce).map_err(|e| PacketError::ReadError(io::Error::other(e)))?;
+    Ok(nonce)
+}
+
+/// Enveloped transmission authenticated with HMAC-SHA-256.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedFrame {
+    payload: Vec<u8>,
+    tag: [u8; TAG_LEN],
+}
+
+impl SignedFrame {
+    /// Serializes `packet` with [`Packet::to_bytes_enveloped`] and signs the
+    /// result with `key`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if serialization fails.
+    pub fn sign(packet: &Packet, key: &[u8]) -> Result<Self, PacketError> {
+        let payload = packet.to_bytes_enveloped()?;
+        let mut mac = mac(key);
+        mac.update(&payload);
+        let tag = mac.finalize().into_bytes().into();
+        Ok(Self { payload, tag })
+    }
+
+    /// Splits a signed transmission into its payload and tag without
+    /// verifying it.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `bytes` are too short to hold a tag.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PacketError> {
+        let split = bytes
+            .len()
+            .checked_sub(TAG_LEN)
+            .ok_or(PacketError::AuthenticationError("missing signature"))?;
+        let (payload, tag) = bytes.split_at(split);
+        Ok(Self {
+            payload: payload.to_vec(),
+            tag: tag
+                .try_into()
+                .map_err(|_| PacketError::AuthenticationError("missing signature"))?,
+        })
+    }
+
+    /// Returns the payload followed by the tag.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [&self.payload[..], &self.tag].concat()
+    }
+
+    /// Returns the signed bytes, which have not necessarily been verified.
+    #[must_use]
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Checks the tag against `key` and parses the payload.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the tag does not match or the payload is not a
+    /// valid packet.
+    pub fn verify(&self, key: &[u8]) -> Result<Packet, PacketError> {
+        let mut mac = mac(key);
+        mac.update(&self.payload);
+        mac.verify_slice(&self.tag)
+            .map_err(|_| PacketError::AuthenticationError("signature mismatch"))?;
+        Packet::from_bytes(&self.payload)
+    }
+}
+
+/// Enveloped transmission encrypted and authenticated with `ChaCha20-Poly1305`.
+///
+/// Serialized as the nonce, the ciphertext, and the tag. A nonce must never be
+/// reused with the same key; it does not need to be secret.
+///
+/// ```
+/// use jacob::crypto::EncryptedFrame;
+/// use jacob::Packet;
+///
+/// let key = [7; 32];
+/// let packet = Packet::from_expression("min(3, 4) * 2").unwrap();
+/// let bytes = EncryptedFrame::seal(&packet, &key, [0; 12]).unwrap().to_bytes();
+///
+/// let frame = EncryptedFrame::from_bytes(&bytes).unwrap();
+/// assert_eq!(frame.open(&key).unwrap(), packet);
+/// assert!(frame.open(&[8; 32]).is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedFrame {
+    nonce: [u8; NONCE_LEN],
+    ciphertext: Vec<u8>,
+    tag: [u8; AEAD_TAG_LEN],
+}
+
+impl EncryptedFrame {
+    /// Serializes `packet` with [`Packet::to_bytes_enveloped`] and encrypts
+    /// the result with `key` and `nonce`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if serialization fails.
+    pub fn seal(
+        packet: &Packet,
+        key: &[u8; KEY_LEN],
+        nonce: [u8; NONCE_LEN],
+    ) -> Result<Self, PacketError> {
+        let mut ciphertext = packet.to_bytes_enveloped()?;
+        let tag = ChaCha20Poly1305::new(&Key::from(*key))
+            .encrypt_inout_detached(&Nonce::from(nonce), &[], ciphertext.as_mut_slice().into())
+            .map_err(|_| PacketError::AuthenticationError("payload is too long to encrypt"))?;
+        Ok(Self {
+            nonce,
+            ciphertext,
+            tag: tag.into(),
+        })
+    }
+
+    /// Splits an encrypted transmission into its nonce, ciphertext, and tag
+    /// without decrypting it.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `bytes` are too short to hold a nonce and tag.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PacketError> {
+        let too_short = || PacketError::AuthenticationError("encrypted frame is too short");
+        let split = bytes
+            .len()
+            .checked_sub(AEAD_TAG_LEN)
+            .filter(|&split| split >= NONCE_LEN)
+            .ok_or_else(too_short)?;
+        let (nonce, ciphertext) = bytes[..split].split_at(NONCE_LEN);
+        Ok(Self {
+            nonce: nonce.try_into().map_err(|_| too_short())?,
+            ciphertext: ciphertext.to_vec(),
+            tag: bytes[split..].try_into().map_err(|_| too_short())?,
+        })
+    }
+
+    /// Returns the nonce, the ciphertext, and the tag.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [&self.nonce[..], &self.ciphertext, &self.tag].concat()
+    }
+
+    /// Checks the tag against `key`, then decrypts and parses the payload.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the tag does not match or the decrypted payload is
+    /// not a valid packet.
+    pub fn open(&self, key: &[u8; KEY_LEN]) -> Result<Packet, PacketError> {
+        let mut plaintext = self.ciphertext.clone();
+        ChaCha20Poly1305::new(&Key::from(*key))
+            .decrypt_inout_detached(
+                &Nonce::from(self.nonce),
+                &[],
+                plaintext.as_mut_slice().into(),
+                &Tag::from(self.tag),
+            )
+            .map_err(|_| PacketError::AuthenticationError("decryption failed"))?;
+        Packet::from_bytes(&plaintext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chacha20poly1305::{AeadInOut, ChaCha20Poly1305, Key, KeyInit, Nonce};
+    use hmac::Mac;
+    use sha2::{Digest, Sha256};
+
+    use super::{mac, random_nonce, EncryptedFrame, SignedFrame};
+    use crate::{bytes_from_hex, hex_from_bytes, Packet, PacketError};
+
+    fn hmac_sha256(key: &[u8], message: &[u8]) -> String {
+        let mut mac = mac(key);
+        mac.update(message);
+        hex_from_bytes(&mac.finalize().into_bytes())
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            hex_from_bytes(&Sha256::digest(b"abc")),
+            "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD"
+        );
+        assert_eq!(
+            hex_from_bytes(&Sha256::digest(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "248D6A61D20638B8E5C026930C3E6039A33CE45964FF2167F6ECEDD419DB06C1"
+        );
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231 test cases 2 and 6
+        assert_eq!(
+            hmac_sha256(b"Jefe", b"what do ya want for nothing?"),
+            "5BDCC146BF60754E6A042426089575C75A003F089D2739839DEC58B964EC3843"
+        );
+        assert_eq!(
+            hmac_sha256(
+                &[0xAA; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ),
+            "60E431591EE0B67F0D8A26AACBF5B77F8E0BC6213728C5140546040F0EE37F54"
+        );
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let packet = Packet::try_from("9C0141080250320F1802104A08").unwrap();
+        let mut bytes = SignedFrame::sign(&packet, b"key").unwrap().to_bytes();
+        assert_eq!(
+            SignedFrame::from_bytes(&bytes)
+                .unwrap()
+                .verify(b"key")
+                .unwrap(),
+            packet
+        );
+
+        let last = bytes.len() - super::TAG_LEN - 1;
+        bytes[last] ^= 1;
+        assert!(matches!(
+            SignedFrame::from_bytes(&bytes).unwrap().verify(b"key"),
+            Err(PacketError::AuthenticationError(_))
+        ));
+        assert!(SignedFrame::from_bytes(&bytes_from_hex("00FF").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_chacha20_poly1305() {
+        // RFC 8439, section 2.8.2
+        let key: Vec<u8> = (0x80..=0x9F).collect();
+        let key = Key::try_from(&key[..]).unwrap();
+        let nonce =
+            Nonce::try_from(&bytes_from_hex("070000004041424344454647").unwrap()[..]).unwrap();
+        let aad = bytes_from_hex("50515253C0C1C2C3C4C5C6C7").unwrap();
+        let mut data = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.".to_vec();
+        let tag = ChaCha20Poly1305::new(&key)
+            .encrypt_inout_detached(&nonce, &aad, data.as_mut_slice().into())
+            .unwrap();
+        assert!(hex_from_bytes(&data).starts_with("D31A8D34648E60DB7B86AFBC53EF7EC2"));
+        assert_eq!(hex_from_bytes(&tag), "1AE10B594F09E26A7E902ECBD0600691");
+    }
+
+    #[test]
+    fn test_encrypted_frame() {
+        let key = [0x42; 32];
+        let packet = Packet::try_from("9C0141080250320F1802104A08").unwrap();
+        let mut bytes = EncryptedFrame::seal(&packet, &key, [1; 12])
+            .unwrap()
+            .to_bytes();
+        let frame = EncryptedFrame::from_bytes(&bytes).unwrap();
+        assert_eq!(frame.open(&key).unwrap(), packet);
+
+        bytes[super::NONCE_LEN] ^= 1;
+        assert!(matches!(
+            EncryptedFrame::from_bytes(&bytes).unwrap().open(&key),
+            Err(PacketError::AuthenticationError(_))
+        ));
+        assert!(EncryptedFrame::from_bytes(&[0; 27]).is_err());
+    }
+
+    #[test]
+    fn test_random_nonce() {
+        assert_ne!(random_nonce().unwrap(), random_nonce().unwrap());
+    }
+}
@@ -0,0 +1,403 @@
+//! Lossless syntax trees of expression files.
+//!
+//! An expression file holds one expression per line. An expression continues
+//! onto the next line while it has unclosed parentheses, and `#` starts a
+//! comment running to the end of the line. A [`Cst`] keeps every character of
+//! such a file, whitespace and comments included, so it writes the file back
+//! unchanged, while [`Cst::format`] restyles the expressions in place:
+//!
+//! ```
+//! use jacob::cst::Cst;
+//!
+//! let src = "# Totals\n1+2   # small\nmax(3,\n  4*5 # product\n)\n";
+//! let cst = Cst::parse(src);
+//! assert_eq!(cst.to_string(), src);
+//! assert_eq!(cst.entries().len(), 2);
+//! assert_eq!(
+//!     cst.format(),
+//!     "# Totals\n1 + 2 # small\nmax(3,\n    4 * 5 # product\n)\n"
+//! );
+//! ```
+
+use std::fmt;
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::CharIndices;
+
+use crate::expression::{parse, ExpressionError};
+use crate::Packet;
+
+/// What a [`Token`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Spaces and tabs, not line breaks.
+    Whitespace,
+    Newline,
+    /// `#` and the rest of its line.
+    Comment,
+    Number,
+    /// A function name, or any other word.
+    Ident,
+    /// `+`, `*`, `-`, `<`, `>`, or `==`.
+    Operator,
+    Comma,
+    LeftParen,
+    RightParen,
+    /// A character no expression contains.
+    Unknown,
+}
+
+impl TokenKind {
+    /// Returns `true` for whitespace, line breaks, and comments.
+    #[must_use]
+    pub const fn is_trivia(self) -> bool {
+        matches!(self, Self::Whitespace | Self::Newline | Self::Comment)
+    }
+}
+
+/// A run of source text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// Byte offset of the text in the source.
+    pub offset: usize,
+}
+
+impl Token<'_> {
+    /// Returns the byte range of the token in the source.
+    #[must_use]
+    pub const fn span(&self) -> Range<usize> {
+        self.offset..self.offset + self.text.len()
+    }
+}
+
+/// One expression of a file, with the comments and blank lines before it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry<'a> {
+    /// Trivia between the previous entry and this one.
+    pub leading: Vec<Token<'a>>,
+    /// The expression from its first token to the end of its last line,
+    /// including comments and line breaks within it.
+    pub tokens: Vec<Token<'a>>,
+}
+
+impl Entry<'_> {
+    /// Returns the byte range of the expression in the source, from its first
+    /// significant token to its last.
+    #[must_use]
+    pub fn span(&self) -> Range<usize> {
+        let is_significant = |token: &&Token<'_>| !token.kind.is_trivia();
+        let start = self
+            .tokens
+            .iter()
+            .find(is_significant)
+            .map_or(0, |token| token.offset);
+        let end = self
+            .tokens
+            .iter()
+            .rfind(is_significant)
+            .map_or(start, |token| token.span().end);
+        start..end
+    }
+
+    /// Returns the expression on one line in canonical style, without its
+    /// comments.
+    #[must_use]
+    pub fn expression(&self) -> String {
+        let mut out = String::new();
+        write_line(
+            &mut out,
+            self.tokens.iter().filter(|token| !token.kind.is_trivia()),
+        );
+        out
+    }
+
+    /// Parses the expression.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the expression is malformed.
+    pub fn parse(&self) -> Result<Packet, ExpressionError> {
+        parse(&self.expression())
+    }
+
+    /// Writes the entry in canonical style, or as written if it does not
+    /// parse.
+    fn format(&self, out: &mut String) {
+        if self.parse().is_err() {
+            for token in &self.tokens {
+                out.push_str(token.text);
+            }
+            return;
+        }
+        let has_comments = self
+            .tokens
+            .iter()
+            .any(|token| token.kind == TokenKind::Comment);
+        let lines: Vec<&[Token<'_>]> = if has_comments {
+            self.tokens
+                .split(|token| token.kind == TokenKind::Newline)
+                .collect()
+        } else {
+            vec![&self.tokens]
+        };
+        let mut depth = 0_usize;
+        for line in lines {
+            let mut significant = line
+                .iter()
+                .filter(|token| !token.kind.is_trivia())
+                .peekable();
+            let comment = line.iter().find(|token| token.kind == TokenKind::Comment);
+            if significant.peek().is_none() && comment.is_none() {
+                continue;
+            }
+            let closes = significant
+                .peek()
+                .is_some_and(|token| token.kind == TokenKind::RightParen);
+            let indent = depth.saturating_sub(usize::from(closes));
+            for token in line {
+                match token.kind {
+                    TokenKind::LeftParen => depth += 1,
+                    TokenKind::RightParen => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            let code = significant.peek().is_some();
+            out.push_str(&"    ".repeat(indent));
+            write_line(out, significant);
+            if let Some(comment) = comment {
+                if code {
+                    out.push(' ');
+                }
+                out.push_str(comment.text.trim_end());
+            }
+            out.push('\n');
+        }
+    }
+}
+
+/// Writes `tokens` separated as in canonical style.
+fn write_line<'t, 'a: 't>(out: &mut String, tokens: impl IntoIterator<Item = &'t Token<'a>>) {
+    let mut previous: Option<TokenKind> = None;
+    for token in tokens {
+        let space = match (previous, token.kind) {
+            (None | Some(TokenKind::LeftParen), _)
+            | (_, TokenKind::Comma | TokenKind::RightParen)
+            | (Some(TokenKind::Ident), TokenKind::LeftParen) => false,
+            (Some(_), _) => true,
+        };
+        if space {
+            out.push(' ');
+        }
+        out.push_str(token.text);
+        previous = Some(token.kind);
+    }
+}
+
+/// A lossless syntax tree of an expression file; see the
+/// [module documentation](self).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cst<'a> {
+    entries: Vec<Entry<'a>>,
+    /// Trivia after the last entry.
+    trailing: Vec<Token<'a>>,
+}
+
+impl<'a> Cst<'a> {
+    /// Splits `src` into entries. Never fails: text that is not a valid
+    /// expression is kept in entries that do not [parse](Entry::parse).
+    #[must_use]
+    pub fn parse(src: &'a str) -> Self {
+        let mut entries = Vec::new();
+        let mut leading = Vec::new();
+        let mut tokens = Vec::new();
+        let mut depth = 0_usize;
+        for token in lex(src) {
+            if tokens.is_empty() && token.kind.is_trivia() {
+                leading.push(token);
+                continue;
+            }
+            match token.kind {
+                TokenKind::LeftParen => depth += 1,
+                TokenKind::RightParen => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            let ends = token.kind == TokenKind::Newline && depth == 0;
+            tokens.push(token);
+            if ends {
+                entries.push(Entry {
+                    leading: std::mem::take(&mut leading),
+                    tokens: std::mem::take(&mut tokens),
+                });
+            }
+        }
+        if !tokens.is_empty() {
+            entries.push(Entry {
+                leading: std::mem::take(&mut leading),
+                tokens,
+            });
+        }
+        Self {
+            entries,
+            trailing: leading,
+        }
+    }
+
+    /// Returns the expressions of the file, in order.
+    #[must_use]
+    pub fn entries(&self) -> &[Entry<'a>] {
+        &self.entries
+    }
+
+    /// Returns the file with every expression that parses in canonical style:
+    /// single spaces around operators and after commas, and none inside
+    /// parentheses. Comments stay on their lines, and an expression with
+    /// comments inside keeps its line breaks, indented by nesting. Blank
+    /// lines are collapsed to one, and the file ends with a line break.
+    #[must_use]
+    pub fn format(&self) -> String {
+        let mut out = String::new();
+        for entry in &self.entries {
+            format_trivia(&mut out, &entry.leading, true);
+            entry.format(&mut out);
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+        format_trivia(&mut out, &self.trailing, false);
+        out
+    }
+}
+
+impl fmt::Display for Cst<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self
+            .entries
+            .iter()
+            .flat_map(|entry| entry.leading.iter().chain(&entry.tokens));
+        for token in entries.chain(&self.trailing) {
+            f.write_str(token.text)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the comments among `trivia` on their own lines, keeping at most one
+/// blank line between them, and after them if `entry` follows.
+fn format_trivia(out: &mut String, trivia: &[Token<'_>], entry: bool) {
+    let lines: Vec<&[Token<'_>]> = trivia
+        .split(|token| token.kind == TokenKind::Newline)
+        .collect();
+    let mut blank = false;
+    for (i, line) in lines.iter().enumerate() {
+        match line.iter().find(|token| token.kind == TokenKind::Comment) {
+            Some(comment) => {
+                if blank && !out.is_empty() {
+                    out.push('\n');
+                }
+                blank = false;
+                out.push_str(comment.text.trim_end());
+                out.push('\n');
+            }
+            // Whatever follows the last line break is not a line of its own
+            None => blank |= i + 1 < lines.len(),
+        }
+    }
+    if entry && blank && !out.is_empty() {
+        out.push('\n');
+    }
+}
+
+/// Splits `src` into tokens whose texts concatenate back to `src`.
+fn lex(src: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        let mut end = offset + c.len_utf8();
+        let kind = match c {
+            '\n' => TokenKind::Newline,
+            '#' => {
+                eat(&mut chars, &mut end, |c| c != '\n');
+                TokenKind::Comment
+            }
+            c if c.is_whitespace() => {
+                eat(&mut chars, &mut end, |c| c.is_whitespace() && c != '\n');
+                TokenKind::Whitespace
+            }
+            c if c.is_ascii_digit() => {
+                eat(&mut chars, &mut end, |c| {
+                    c.is_ascii_alphanumeric() || matches!(c, '_' | '.')
+                });
+                TokenKind::Number
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                eat(&mut chars, &mut end, |c| {
+                    c.is_ascii_alphanumeric() || c == '_'
+                });
+                TokenKind::Ident
+            }
+            '+' | '*' | '-' | '<' | '>' => TokenKind::Operator,
+            '=' if chars.peek().is_some_and(|&(_, c)| c == '=') => {
+                eat(&mut chars, &mut end, |c| c == '=');
+                TokenKind::Operator
+            }
+            ',' => TokenKind::Comma,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            _ => TokenKind::Unknown,
+        };
+        tokens.push(Token {
+            kind,
+            text: &src[offset..end],
+            offset,
+        });
+    }
+    tokens
+}
+
+/// Advances `chars` past the characters matching `pred`, moving `end` after
+/// them.
+fn eat(chars: &mut Peekable<CharIndices<'_>>, end: &mut usize, pred: impl Fn(char) -> bool) {
+    while let Some(&(i, c)) = chars.peek() {
+        if !pred(c) {
+            break;
+        }
+        *end = i + c.len_utf8();
+        chars.next();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cst, Entry};
+
+    #[test]
+    fn test_format() {
+        let src = "\n\n# Header\n\n\n1+2\n  max( 3 ,4)*2   \n\nif(1,2,3)# extended\n\
+                   sum(1,\n(2 < 3), # cmp\n  # alone\n  4)\n1 +* 2\n\n# end";
+        let cst = Cst::parse(src);
+        assert_eq!(cst.to_string(), src);
+        let expressions: Vec<String> = cst.entries().iter().map(Entry::expression).collect();
+        assert_eq!(
+            expressions,
+            [
+                "1 + 2",
+                "max(3, 4) * 2",
+                "if(1, 2, 3)",
+                "sum(1, (2 < 3), 4)",
+                "1 + * 2"
+            ]
+        );
+        assert_eq!(cst.entries()[1].span(), 19..31);
+        assert!(cst.entries()[4].parse().is_err());
+        let formatted = cst.format();
+        assert_eq!(
+            formatted,
+            "# Header\n\n1 + 2\nmax(3, 4) * 2\n\nif(1, 2, 3) # extended\n\
+             sum(1,\n    (2 < 3), # cmp\n    # alone\n    4)\n1 +* 2\n\n# end\n"
+        );
+        assert_eq!(Cst::parse(&formatted).format(), formatted);
+        assert_eq!(Cst::parse("").format(), "");
+    }
+}
@@ -0,0 +1,165 @@
+//! Patches turning one packet into another.
+//!
+//! A [`Patch`] replaces the subtrees at which two packets
+//! [differ](Packet::differences), so a slightly changed packet can be sent as
+//! the changes alone:
+//!
+//! ```
+//! use jacob::delta::Patch;
+//! use jacob::Packet;
+//!
+//! let old = Packet::from_expression("max(1 + 2, 3 * 4, 5, 6, 7, 8, 9)").unwrap();
+//! let new = Packet::from_expression("max(1 + 2, 3 * 10, 5, 6, 7, 8, 9)").unwrap();
+//! let bytes = Packet::delta(&old, &new).to_bytes().unwrap();
+//! assert!(bytes.len() < new.to_bytes().unwrap().len());
+//!
+//! let patch = Patch::from_bytes(&bytes).unwrap();
+//! assert_eq!(patch.apply(&old).unwrap(), new);
+//! ```
+//!
+//! A serialized patch is one byte naming the [`Profile`] its packets are
+//! encoded in, then bits: the number of replacements, and for each one its
+//! path length, path indices, and packet. Numbers are written as literal value
+//! groups. A patch does not identify the packet it was made from; applying it
+//! to another packet fails only if a path does not exist.
+
+use crate::bits::{BitCursor, BitReader, BitWriter};
+use crate::fields::read_literal_groups;
+use crate::{write_groups, Packet, PacketError, PacketKind, Profile};
+
+/// Replacements of subtrees, none within another, in pre-order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Patch {
+    replacements: Vec<(Vec<usize>, Packet)>,
+}
+
+impl Packet {
+    /// Returns the patch turning `old` into `new`.
+    #[must_use]
+    pub fn delta(old: &Self, new: &Self) -> Patch {
+        let replacements = old
+            .differences(new)
+            .into_iter()
+            .filter_map(|path| new.get(&path).map(|packet| (path, packet.clone())))
+            .collect();
+        Patch { replacements }
+    }
+
+    fn get_mut(&mut self, path: &[usize]) -> Option<&mut Self> {
+        path.iter()
+            .try_fold(self, |packet, &i| match &mut packet.kind {
+                PacketKind::Operator { packets, .. } => packets.get_mut(i),
+                PacketKind::Literal(_) => None,
+            })
+    }
+}
+
+impl Patch {
+    /// Returns `true` if the patch changes nothing.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.replacements.is_empty()
+    }
+
+    /// Returns the path and new packet of every replacement.
+    #[must_use]
+    pub fn replacements(&self) -> &[(Vec<usize>, Packet)] {
+        &self.replacements
+    }
+
+    /// Returns `old` with the patch applied.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a replaced path does not exist in `old`.
+    pub fn apply(&self, old: &Packet) -> Result<Packet, PacketError> {
+        let mut packet = old.clone();
+        for (path, replacement) in &self.replacements {
+            *packet
+                .get_mut(path)
+                .ok_or(PacketError::PatchError("path not found"))? = replacement.clone();
+        }
+        Ok(packet)
+    }
+
+    /// Serializes the patch.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a replacement packet cannot be serialized.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, PacketError> {
+        let profile = if self
+            .replacements
+            .iter()
+            .any(|(_, packet)| packet.required_profile() == Profile::Extended)
+        {
+            Profile::Extended
+        } else {
+            Profile::Standard
+        };
+        let mut bytes = vec![profile.id()];
+        let mut writer = BitWriter::new(&mut bytes);
+        write_groups(&mut writer, self.replacements.len())?;
+        for (path, packet) in &self.replacements {
+            write_groups(&mut writer, path.len())?;
+            for &i in path {
+                write_groups(&mut writer, i)?;
+            }
+            packet.write_bits_with_profile(&mut writer, profile)?;
+        }
+        writer.byte_align();
+        Ok(bytes)
+    }
+
+    /// Parses a patch serialized by [`to_bytes`](Self::to_bytes).
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the bytes are cut short or name an unknown
+    /// profile, or a replacement packet is invalid.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PacketError> {
+        let (&profile, bits) = bytes
+            .split_first()
+            .ok_or(PacketError::PatchError("missing profile"))?;
+        let profile =
+            Profile::from_id(profile).ok_or(PacketError::PatchError("unknown profile"))?;
+        let mut reader = BitReader::new(bits);
+        let count = read_literal_groups(&mut reader)?;
+        let mut replacements = Vec::new();
+        for _ in 0..count {
+            let depth = read_literal_groups(&mut reader)?;
+            let path = (0..depth)
+                .map(|_| read_literal_groups(&mut reader))
+                .collect::<Result<_, _>>()?;
+            replacements.push((path, Packet::read_with_profile(&mut reader, profile)?));
+        }
+        if reader.remaining() >= 8 {
+            return Err(PacketError::PatchError("trailing bytes"));
+        }
+        Ok(Self { replacements })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Patch;
+    use crate::{Packet, PacketError};
+
+    #[test]
+    fn test_delta() {
+        let old = Packet::from_expression("1 + (2 * 3) + 4").unwrap();
+        let new = Packet::from_expression("1 + (5 * 3) + if(1, 4, 0)").unwrap();
+        let patch = Packet::delta(&old, &new);
+        assert_eq!(patch.replacements().len(), 2);
+        let patch = Patch::from_bytes(&patch.to_bytes().unwrap()).unwrap();
+        assert_eq!(patch.apply(&old).unwrap(), new);
+
+        assert!(Packet::delta(&old, &old).is_empty());
+        assert_eq!(Patch::default().apply(&old).unwrap(), old);
+        assert!(matches!(
+            patch.apply(&Packet::from_expression("1").unwrap()),
+            Err(PacketError::PatchError(_))
+        ));
+        assert!(Patch::from_bytes(&[]).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! Expressions of two packets with the subterms that differ highlighted.
+//!
+//! Differences are found by [`Packet::differences`] and each differing
+//! subterm is wrapped in delimiters, like `git diff --word-diff`:
+//!
+//! ```
+//! use jacob::diff::{diff_expressions, Markers};
+//! use jacob::{ExpressionStyle, Packet};
+//!
+//! let a = Packet::from_expression("1 + (2 * 3) + 4").unwrap();
+//! let b = Packet::from_expression("1 + (5 * 3) + max(4)").unwrap();
+//! let (a, b) = diff_expressions(&a, &b, &ExpressionStyle::default(), &Markers::WORD);
+//! assert_eq!(a, "1 + ([-2-] * 3) + [-4-]");
+//! assert_eq!(b, "1 + ({+5+} * 3) + {+max(4)+}");
+//! ```
+
+use crate::{ExpressionStyle, Packet};
+
+/// Delimiters written around differing subterms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Markers<'a> {
+    /// Around subterms of the first packet.
+    pub removed: (&'a str, &'a str),
+    /// Around subterms of the second packet.
+    pub added: (&'a str, &'a str),
+}
+
+impl Markers<'static> {
+    /// `[-removed-]` and `{+added+}`.
+    pub const WORD: Self = Self {
+        removed: ("[-", "-]"),
+        added: ("{+", "+}"),
+    };
+    /// Red and green terminal colors.
+    pub const ANSI: Self = Self {
+        removed: ("\x1b[31m", "\x1b[0m"),
+        added: ("\x1b[32m", "\x1b[0m"),
+    };
+}
+
+/// Chooses the delimiters, if any, written around the expression of each
+/// packet. `()` marks nothing.
+pub(crate) trait Marks {
+    fn delimiters(&self, packet: &Packet) -> Option<(&str, &str)>;
+}
+
+impl Marks for () {
+    fn delimiters(&self, _: &Packet) -> Option<(&str, &str)> {
+        None
+    }
+}
+
+/// Marks `packets` with the same delimiters.
+struct Marked<'a> {
+    packets: Vec<&'a Packet>,
+    delimiters: (&'a str, &'a str),
+}
+
+impl Marks for Marked<'_> {
+    fn delimiters(&self, packet: &Packet) -> Option<(&str, &str)> {
+        self.packets
+            .iter()
+            .any(|marked| std::ptr::eq(*marked, packet))
+            .then_some(self.delimiters)
+    }
+}
+
+/// Returns the expressions of `a` and `b`, with the subterms at which they
+/// differ wrapped in `markers`. Both are unmarked if the packets are equal.
+#[must_use]
+pub fn diff_expressions(
+    a: &Packet,
+    b: &Packet,
+    style: &ExpressionStyle,
+    markers: &Markers<'_>,
+) -> (String, String) {
+    let paths = a.differences(b);
+    let render = |packet: &Packet, delimiters| {
+        let marks = Marked {
+            packets: paths.iter().filter_map(|path| packet.get(path)).collect(),
+            delimiters,
+        };
+        let mut expression = String::new();
+        // Writing to a `String` cannot fail
+        let _ = packet.write_term(&mut expression, style, &marks, false);
+        expression
+    };
+    (render(a, markers.removed), render(b, markers.added))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_expressions, Markers};
+    use crate::{ExpressionStyle, Packet};
+
+    #[test]
+    fn test_diff_expressions() {
+        let diff = |a, b| {
+            let a = Packet::from_expression(a).unwrap();
+            let b = Packet::from_expression(b).unwrap();
+            diff_expressions(&a, &b, &ExpressionStyle::default(), &Markers::WORD)
+        };
+        assert_eq!(
+            diff("1 + max(2, 3)", "1 + max(2, 3)"),
+            ("1 + max(2, 3)".to_string(), "1 + max(2, 3)".to_string())
+        );
+        assert_eq!(
+            diff("1 + (2 * 3)", "1 + (2 < 3)"),
+            ("1 + [-(2 * 3)-]".to_string(), "1 + {+(2 < 3)+}".to_string())
+        );
+        assert_eq!(
+            diff("1 + 2", "1 * 2"),
+            ("[-1 + 2-]".to_string(), "{+1 * 2+}".to_string())
+        );
+        assert_eq!(
+            diff("min(1, 2)", "min(1, 3)"),
+            ("min(1, [-2-])".to_string(), "min(1, {+3+})".to_string())
+        );
+    }
+}
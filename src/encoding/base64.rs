@@ -0,0 +1,237 @@
+//! Standard base64 (RFC 4648) with `=` padding.
+
+use std::io;
+
+use crate::PacketError;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes up to three bytes as four characters.
+fn encode_chunk(chunk: &[u8]) -> [u8; 4] {
+    let mut group = [0; 3];
+    group[..chunk.len()].copy_from_slice(chunk);
+    let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+    let mut text = [b'='; 4];
+    for (i, c) in text.iter_mut().take(chunk.len() + 1).enumerate() {
+        *c = ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize];
+    }
+    text
+}
+
+/// Decodes four characters, of which trailing ones may be `=`, into up to
+/// three bytes, returning them and how many are valid.
+fn decode_chunk(chunk: [u8; 4]) -> Result<([u8; 3], usize), &'static str> {
+    let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+    if padding > 2 {
+        return Err("too much padding");
+    }
+    let mut bits = 0u32;
+    for &c in &chunk[..4 - padding] {
+        let value = ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .ok_or("invalid character")?;
+        bits = bits << 6 | u32::try_from(value).unwrap_or(0);
+    }
+    bits <<= 6 * padding;
+    let [_, a, b, c] = bits.to_be_bytes();
+    Ok(([a, b, c], 3 - padding))
+}
+
+/// Converts base64 text into a byte array.
+///
+/// # Errors
+///
+/// Will return `Err` if the text is not padded to a multiple of four
+/// characters or contains characters outside the standard alphabet.
+pub fn decode(text: &str) -> Result<Vec<u8>, PacketError> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return Err(PacketError::EncodingError(
+            "base64 length is not a multiple of 4",
+        ));
+    }
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    for (i, chunk) in text.chunks_exact(4).enumerate() {
+        let chunk = [chunk[0], chunk[1], chunk[2], chunk[3]];
+        let (group, len) = decode_chunk(chunk).map_err(PacketError::EncodingError)?;
+        if len < 3 && (i + 1) * 4 != text.len() {
+            return Err(PacketError::EncodingError("base64 padding before the end"));
+        }
+        bytes.extend(&group[..len]);
+    }
+    Ok(bytes)
+}
+
+/// Converts a byte array into base64 text.
+#[must_use]
+pub fn encode(bytes: &[u8]) -> String {
+    bytes
+        .chunks(3)
+        .flat_map(encode_chunk)
+        .map(char::from)
+        .collect()
+}
+
+/// Writes bytes to the inner writer as base64.
+///
+/// Call [`Encoder::finish`] once done to write the final padded group.
+#[derive(Debug)]
+pub struct Encoder<W> {
+    inner: W,
+    pending: Vec<u8>,
+}
+
+impl<W: io::Write> Encoder<W> {
+    pub const fn new(inner: W) -> Self {
+        Self {
+            inner,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Writes any buffered bytes with padding and returns the inner writer.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if writing to the inner writer fails.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.pending.is_empty() {
+            self.inner.write_all(&encode_chunk(&self.pending))?;
+        }
+        Ok(self.inner)
+    }
+}
+
+impl<W: io::Write> io::Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend(buf);
+        let complete = self.pending.len() / 3 * 3;
+        let text: Vec<u8> = self.pending[..complete]
+            .chunks(3)
+            .flat_map(encode_chunk)
+            .collect();
+        self.inner.write_all(&text)?;
+        self.pending.drain(..complete);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads bytes from base64 text in the inner reader.
+///
+/// Unbuffered sources must be wrapped in an [`io::BufReader`].
+#[derive(Debug)]
+pub struct Decoder<R> {
+    inner: io::Bytes<R>,
+    group: [u8; 3],
+    start: usize,
+    end: usize,
+    finished: bool,
+}
+
+impl<R: io::BufRead> Decoder<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner: inner.bytes(),
+            group: [0; 3],
+            start: 0,
+            end: 0,
+            finished: false,
+        }
+    }
+
+    /// Decodes the next group into the buffer, returning `false` at the end.
+    fn refill(&mut self) -> io::Result<bool> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut chunk = [0; 4];
+        let mut len = 0;
+        while len < 4 {
+            match self.inner.next().transpose()? {
+                Some(c) if c.is_ascii_whitespace() => {}
+                Some(c) => {
+                    chunk[len] = c;
+                    len += 1;
+                }
+                None if len == 0 => return Ok(false),
+                None => return Err(invalid("base64 length is not a multiple of 4")),
+            }
+        }
+        if self.finished {
+            return Err(invalid("base64 padding before the end"));
+        }
+        let (group, len) = decode_chunk(chunk).map_err(invalid)?;
+        self.finished = len < 3;
+        self.group = group;
+        self.start = 0;
+        self.end = len;
+        Ok(true)
+    }
+}
+
+impl<R: io::BufRead> io::Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut n = 0;
+        while n < buf.len() {
+            if self.start == self.end && !self.refill()? {
+                break;
+            }
+            let len = (self.end - self.start).min(buf.len() - n);
+            buf[n..n + len].copy_from_slice(&self.group[self.start..self.start + len]);
+            self.start += len;
+            n += len;
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::{decode, encode, Decoder, Encoder};
+
+    #[test]
+    fn test_rfc_4648_vectors() {
+        for (bytes, text) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(encode(bytes.as_bytes()), text);
+            assert_eq!(decode(text).unwrap(), bytes.as_bytes());
+
+            let mut encoder = Encoder::new(Vec::new());
+            for byte in bytes.as_bytes() {
+                encoder.write_all(&[*byte]).unwrap();
+            }
+            assert_eq!(encoder.finish().unwrap(), text.as_bytes());
+
+            let mut decoded = Vec::new();
+            Decoder::new(text.as_bytes())
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, bytes.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        for text in ["Zg=", "Zg==Zg==", "Z===", "Zm9*"] {
+            assert!(decode(text).is_err(), "{text}");
+            assert!(
+                Decoder::new(text.as_bytes())
+                    .read_to_end(&mut Vec::new())
+                    .is_err(),
+                "{text}"
+            );
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Binary digits, eight per byte, most significant bit first.
+
+use std::fmt::Write as _;
+use std::io;
+
+use super::{DigitDecoder, DigitEncoder};
+use crate::PacketError;
+
+/// Converts a string of binary digits into a byte array.
+///
+/// # Errors
+///
+/// Will return `Err` if string contains anything but `0` and `1`.
+pub fn decode(bin: &str) -> Result<Vec<u8>, PacketError> {
+    Ok(bin
+        .chars()
+        .collect::<Vec<_>>()
+        .chunks(8)
+        .map(|chunk| u8::from_str_radix(&chunk.iter().collect::<String>(), 2))
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Converts a byte array into a string of binary digits.
+#[must_use]
+pub fn encode(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 8), |mut bin, byte| {
+            let _ = write!(bin, "{byte:08b}");
+            bin
+        })
+}
+
+/// Writes bytes to the inner writer as binary digits.
+#[derive(Debug)]
+pub struct Encoder<W>(DigitEncoder<W>);
+
+impl<W: io::Write> Encoder<W> {
+    pub const fn new(inner: W) -> Self {
+        Self(DigitEncoder {
+            inner,
+            radix: 2,
+            width: 8,
+        })
+    }
+
+    pub fn into_inner(self) -> W {
+        self.0.inner
+    }
+}
+
+impl<W: io::Write> io::Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Reads bytes from binary digits in the inner reader.
+///
+/// Unbuffered sources must be wrapped in an [`io::BufReader`].
+#[derive(Debug)]
+pub struct Decoder<R>(DigitDecoder<R>);
+
+impl<R: io::BufRead> Decoder<R> {
+    pub fn new(inner: R) -> Self {
+        Self(DigitDecoder {
+            inner: inner.bytes(),
+            radix: 2,
+            width: 8,
+        })
+    }
+}
+
+impl<R: io::BufRead> io::Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::{decode, encode, Decoder, Encoder};
+
+    #[test]
+    fn test_roundtrip() {
+        let bytes = [0xD2, 0xFE, 0x28];
+        let bin = encode(&bytes);
+        assert_eq!(bin, "110100101111111000101000");
+        assert_eq!(decode(&bin).unwrap(), bytes);
+        assert!(decode("0102").is_err());
+
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.write_all(&bytes).unwrap();
+        assert_eq!(encoder.into_inner(), bin.as_bytes());
+
+        let mut decoded = Vec::new();
+        Decoder::new(&b"11010010 11111110\n00101000"[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, bytes);
+
+        let mut decoded = Vec::new();
+        Decoder::new(&b"11010010 101"[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, decode("11010010101").unwrap());
+    }
+}
@@ -0,0 +1,172 @@
+//! Uppercase hexadecimal, two digits per byte.
+
+use std::io;
+
+use super::{DigitDecoder, DigitEncoder};
+use crate::PacketError;
+
+const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Value of each ASCII hexadecimal digit, or `INVALID`.
+const VALUES: [u8; 256] = {
+    let mut values = [INVALID; 256];
+    let mut i = 0;
+    while i < 10 {
+        values[(b'0' + i) as usize] = i;
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 6 {
+        values[(b'A' + i) as usize] = 10 + i;
+        values[(b'a' + i) as usize] = 10 + i;
+        i += 1;
+    }
+    values
+};
+
+const INVALID: u8 = 0xFF;
+
+/// Converts a hexadecimal string into a byte array.
+///
+/// A trailing unpaired digit becomes a byte of its own.
+///
+/// # Errors
+///
+/// Will return `Err` if string is not valid hexadecimal.
+pub fn decode(hex: &str) -> Result<Vec<u8>, PacketError> {
+    let digits = hex.as_bytes();
+    let mut bytes = Vec::with_capacity(digits.len().div_ceil(2));
+    for (i, pair) in digits.chunks(2).enumerate() {
+        let byte = match *pair {
+            [high, low] => (VALUES[usize::from(high)] << 4) | VALUES[usize::from(low)],
+            [digit] => VALUES[usize::from(digit)],
+            _ => unreachable!("chunks are one or two bytes"),
+        };
+        if pair
+            .iter()
+            .any(|&digit| VALUES[usize::from(digit)] == INVALID)
+        {
+            // Rare: let the standard parser decide on and describe the rest
+            bytes.extend(decode_slow(&hex[i * 2..])?);
+            break;
+        }
+        bytes.push(byte);
+    }
+    Ok(bytes)
+}
+
+/// Parses each pair of characters with [`u8::from_str_radix`].
+fn decode_slow(hex: &str) -> Result<Vec<u8>, PacketError> {
+    Ok(hex
+        .chars()
+        .collect::<Vec<_>>()
+        .chunks(2)
+        .map(|chunk| u8::from_str_radix(&chunk.iter().collect::<String>(), 16))
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Converts a byte array into a hexadecimal string.
+#[must_use]
+pub fn encode(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for &byte in bytes {
+        hex.push(char::from(DIGITS[usize::from(byte >> 4)]));
+        hex.push(char::from(DIGITS[usize::from(byte & 0xF)]));
+    }
+    hex
+}
+
+/// Writes bytes to the inner writer as hexadecimal.
+#[derive(Debug)]
+pub struct Encoder<W>(DigitEncoder<W>);
+
+impl<W: io::Write> Encoder<W> {
+    pub const fn new(inner: W) -> Self {
+        Self(DigitEncoder {
+            inner,
+            radix: 16,
+            width: 2,
+        })
+    }
+
+    pub fn into_inner(self) -> W {
+        self.0.inner
+    }
+}
+
+impl<W: io::Write> io::Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Reads bytes from hexadecimal text in the inner reader.
+///
+/// Unbuffered sources must be wrapped in an [`io::BufReader`].
+#[derive(Debug)]
+pub struct Decoder<R>(DigitDecoder<R>);
+
+impl<R: io::BufRead> Decoder<R> {
+    pub fn new(inner: R) -> Self {
+        Self(DigitDecoder {
+            inner: inner.bytes(),
+            radix: 16,
+            width: 2,
+        })
+    }
+}
+
+impl<R: io::BufRead> io::Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::{decode, decode_slow, encode, Decoder, Encoder};
+
+    #[test]
+    fn test_table_matches_parser() {
+        for hex in [
+            "", "00", "0aFf", "ABC", "D2FE28", "12G4", "1234 5", "+A", "é0",
+        ] {
+            assert_eq!(decode(hex).ok(), decode_slow(hex).ok(), "{hex}");
+        }
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode(&encode(&bytes)).unwrap(), bytes);
+        assert_eq!(encode(&[0x0A, 0xFF]), "0AFF");
+    }
+
+    #[test]
+    fn test_streaming() {
+        let bytes = decode("9C0141080250320F1802104A08").unwrap();
+        let mut encoder = Encoder::new(Vec::new());
+        encoder.write_all(&bytes[..4]).unwrap();
+        encoder.write_all(&bytes[4..]).unwrap();
+        let hex = encoder.into_inner();
+        assert_eq!(String::from_utf8(hex).unwrap(), encode(&bytes));
+
+        let mut decoded = Vec::new();
+        Decoder::new(&b"9C0141080250\n320F1802104A08\n"[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, bytes);
+
+        // An unpaired trailing digit is a byte of its own, as in `decode`
+        let mut decoded = Vec::new();
+        Decoder::new(&b"AB\nC\n"[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, decode("ABC").unwrap());
+        assert!(Decoder::new(&b"XY"[..])
+            .read_to_end(&mut Vec::new())
+            .is_err());
+    }
+}
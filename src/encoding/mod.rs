@@ -0,0 +1,94 @@
+//! Text encodings of serialized packets.
+//!
+//! Each submodule exposes `encode` and `decode` for whole buffers, plus an
+//! `Encoder` wrapping an [`io::Write`] and a `Decoder` wrapping an
+//! [`io::BufRead`] for transmissions too large to hold as text. The streaming
+//! decoders skip ASCII whitespace so that line-wrapped files can be read
+//! directly, and like `decode` read trailing digits too few for a whole byte
+//! as a byte of their own.
+//!
+//! ```
+//! use jacob::encoding::{base64, bin, hex};
+//!
+//! let bytes = hex::decode("D2FE28").unwrap();
+//! assert_eq!(bin::encode(&bytes), "110100101111111000101000");
+//! assert_eq!(base64::encode(&bytes), "0v4o");
+//! assert_eq!(base64::decode("0v4o").unwrap(), bytes);
+//! ```
+
+use std::io;
+
+pub mod base64;
+pub mod bin;
+pub mod hex;
+
+/// Writes each byte as a fixed number of digits in `radix`.
+#[derive(Debug)]
+struct DigitEncoder<W> {
+    inner: W,
+    radix: u32,
+    width: usize,
+}
+
+impl<W: io::Write> io::Write for DigitEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut text = String::with_capacity(buf.len() * self.width);
+        for byte in buf {
+            for i in (0..self.width).rev() {
+                let digit = u32::from(*byte) / self.radix.pow(u32::try_from(i).unwrap_or(0));
+                text.extend(char::from_digit(digit % self.radix, self.radix));
+            }
+        }
+        self.inner.write_all(text.to_ascii_uppercase().as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads bytes written as a fixed number of digits in `radix`.
+#[derive(Debug)]
+struct DigitDecoder<R> {
+    inner: io::Bytes<R>,
+    radix: u32,
+    width: usize,
+}
+
+impl<R: io::BufRead> DigitDecoder<R> {
+    /// Returns the next digit that is not whitespace, or `None` at the end.
+    fn next_digit(&mut self) -> io::Result<Option<u32>> {
+        for byte in self.inner.by_ref() {
+            let c = char::from(byte?);
+            if c.is_ascii_whitespace() {
+                continue;
+            }
+            return c.to_digit(self.radix).map(Some).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("invalid digit `{c}`"))
+            });
+        }
+        Ok(None)
+    }
+}
+
+impl<R: io::BufRead> io::Read for DigitDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        for (n, slot) in buf.iter_mut().enumerate() {
+            let Some(first) = self.next_digit()? else {
+                return Ok(n);
+            };
+            let mut value = first;
+            for _ in 1..self.width {
+                // Like `decode`, a trailing incomplete byte stands on its own
+                let Some(digit) = self.next_digit()? else {
+                    break;
+                };
+                value = value * self.radix + digit;
+            }
+            *slot = u8::try_from(value)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "byte out of range"))?;
+        }
+        Ok(buf.len())
+    }
+}
@@ -0,0 +1,167 @@
+//! Self-describing envelope around serialized packets.
+//!
+//! An enveloped transmission is [`MAGIC`], one byte of [`FORMAT_VERSION`], one
+//! byte naming the [`Profile`] of the payload, and then the packet bytes.
+//! [`Packet::from_bytes`] and hexadecimal parsing detect the envelope
+//! automatically. A bare transmission whose first bytes happen to equal
+//! [`MAGIC`] must be read with [`Packet::read_with_profile`] instead.
+
+use crate::bits::BitReader;
+use crate::{hex_from_bytes, Packet, PacketError, PacketKind, Profile};
+
+/// Bytes identifying an enveloped transmission: `JACB`.
+pub const MAGIC: [u8; 4] = *b"JACB";
+
+/// Envelope layout version written by this crate.
+pub const FORMAT_VERSION: u8 = 1;
+
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+/// Flag set in the profile byte of the [`Wide`](Profile::Wide) profile,
+/// whose low bits hold the type field width.
+const WIDE_ID: u8 = 0x80;
+
+impl Profile {
+    /// Returns the byte identifying this profile in an envelope.
+    #[must_use]
+    pub const fn id(self) -> u8 {
+        match self {
+            Self::Standard => 0,
+            Self::Extended => 1,
+            Self::Shared => 2,
+            Self::RunLength => 3,
+            Self::Wide { .. } => WIDE_ID | self.type_bits(),
+        }
+    }
+
+    /// Returns the profile identified by `id` in an envelope.
+    #[must_use]
+    pub const fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::Standard),
+            1 => Some(Self::Extended),
+            2 => Some(Self::Shared),
+            3 => Some(Self::RunLength),
+            id if id & WIDE_ID != 0 => Self::wide(id & !WIDE_ID),
+            _ => None,
+        }
+    }
+}
+
+/// Returns `true` if `bytes` begin with the envelope magic.
+#[must_use]
+pub fn is_enveloped(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// Splits an enveloped transmission into its profile and payload.
+///
+/// # Errors
+///
+/// Will return `Err` if `bytes` are not enveloped, the header is cut short, or
+/// it names an unknown format version or profile.
+pub fn open(bytes: &[u8]) -> Result<(Profile, &[u8]), PacketError> {
+    if !is_enveloped(bytes) {
+        return Err(PacketError::EnvelopeError("missing magic bytes"));
+    }
+    let [format_version, profile] = bytes
+        .get(MAGIC.len()..HEADER_LEN)
+        .and_then(|header| <[u8; 2]>::try_from(header).ok())
+        .ok_or(PacketError::EnvelopeError("truncated header"))?;
+    if format_version != FORMAT_VERSION {
+        return Err(PacketError::EnvelopeError("unsupported format version"));
+    }
+    let profile = Profile::from_id(profile).ok_or(PacketError::EnvelopeError("unknown profile"))?;
+    Ok((profile, &bytes[HEADER_LEN..]))
+}
+
+impl Packet {
+    /// Returns the most restrictive profile able to encode this packet.
+    #[must_use]
+    pub fn required_profile(&self) -> Profile {
+        let extended = self.flat_packets().iter().any(|packet| {
+            matches!(&packet.kind, PacketKind::Operator { operation, .. } if operation.is_extended())
+        });
+        if extended {
+            Profile::Extended
+        } else {
+            Profile::Standard
+        }
+    }
+
+    /// Returns packet as byte array wrapped in an envelope, encoded in the
+    /// most restrictive profile able to represent it.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if serialization fails.
+    pub fn to_bytes_enveloped(&self) -> Result<Vec<u8>, PacketError> {
+        let profile = self.required_profile();
+        let mut bytes = Vec::from(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.push(profile.id());
+        bytes.extend(self.to_bytes_with_profile(profile)?);
+        Ok(bytes)
+    }
+
+    /// Returns hexadecimal representation of [`Packet::to_bytes_enveloped`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if serialization fails.
+    pub fn to_hex_enveloped(&self) -> Result<String, PacketError> {
+        Ok(hex_from_bytes(&self.to_bytes_enveloped()?))
+    }
+
+    /// Parses a packet from bytes, unwrapping an envelope if present.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the envelope is invalid or the bytes do not form a
+    /// valid packet.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PacketError> {
+        let (profile, payload) = if is_enveloped(bytes) {
+            open(bytes)?
+        } else {
+            (Profile::Standard, bytes)
+        };
+        Self::read_with_profile(&mut BitReader::new(payload), profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{open, MAGIC};
+    use crate::{Packet, PacketError, Profile};
+
+    #[test]
+    fn test_envelope_round_trip() {
+        for expr in ["(1 + 3) == (2 * 2)", "if(1 < 2, 10, 20)"] {
+            let packet = Packet::from_expression(expr).unwrap();
+            let bytes = packet.to_bytes_enveloped().unwrap();
+            assert!(bytes.starts_with(&MAGIC));
+            assert_eq!(Packet::from_bytes(&bytes).unwrap(), packet);
+            let hex = packet.to_hex_enveloped().unwrap();
+            assert_eq!(hex.parse::<Packet>().unwrap(), packet);
+        }
+        let packet = Packet::from_expression("if(1, 2, 3)").unwrap();
+        let bytes = packet.to_bytes_enveloped().unwrap();
+        assert_eq!(open(&bytes).unwrap().0, Profile::Extended);
+
+        for type_bits in 4..=8 {
+            let profile = Profile::wide(type_bits).unwrap();
+            assert_eq!(Profile::from_id(profile.id()), Some(profile));
+        }
+        assert_eq!(Profile::from_id(0x89), None);
+    }
+
+    #[test]
+    fn test_invalid_envelopes() {
+        for bytes in [&b"JACB"[..], b"JACB\x02\x00\x00", b"JACB\x01\x09\x00"] {
+            assert!(matches!(
+                Packet::from_bytes(bytes),
+                Err(PacketError::EnvelopeError(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,629 @@
+//! Pluggable evaluation of packet trees.
+//!
+//! [`Packet::eval_with`] walks the tree bottom-up and hands literals and
+//! evaluated operands to an [`Evaluator`], which decides what a value is.
+//! [`IntervalEvaluator`] uses this to compute the range of values a packet can
+//! take when some of its literals are unknown, and [`ApproxEvaluator`] to
+//! evaluate in floating point with comparisons that allow for rounding.
+//!
+//! [`Packet::eval_with_hooks`] evaluates normally but reports each packet to
+//! [`EvalHooks`] before and after it is evaluated, for profiling and tracing.
+
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use crate::{Operation, Packet, PacketError, PacketKind};
+
+/// Defines the values produced while evaluating a packet.
+///
+/// Paths are the child indices leading from the root to a packet; the root's
+/// path is empty. Every operand is evaluated before its operator, including
+/// both branches of [`Operation::If`].
+pub trait Evaluator {
+    type Value;
+
+    /// Returns the value of the literal at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` to abort evaluation.
+    fn literal(&mut self, path: &[usize], value: usize) -> Result<Self::Value, PacketError>;
+
+    /// Returns the value of the operator at `path` applied to `operands`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` to abort evaluation, typically because of an invalid
+    /// number of operands.
+    fn operator(
+        &mut self,
+        path: &[usize],
+        operation: Operation,
+        operands: Vec<Self::Value>,
+    ) -> Result<Self::Value, PacketError>;
+}
+
+impl Packet {
+    /// Evaluates the packet with a custom evaluator.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the evaluator does.
+    pub fn eval_with<E: Evaluator>(&self, evaluator: &mut E) -> Result<E::Value, PacketError> {
+        self.eval_with_path(evaluator, &mut Vec::new())
+    }
+
+    fn eval_with_path<E: Evaluator>(
+        &self,
+        evaluator: &mut E,
+        path: &mut Vec<usize>,
+    ) -> Result<E::Value, PacketError> {
+        match &self.kind {
+            PacketKind::Literal(value) => evaluator.literal(path, *value),
+            PacketKind::Operator {
+                operation, packets, ..
+            } => {
+                let mut operands = Vec::with_capacity(packets.len());
+                for (i, packet) in packets.iter().enumerate() {
+                    path.push(i);
+                    let operand = packet.eval_with_path(evaluator, path);
+                    path.pop();
+                    operands.push(operand?);
+                }
+                evaluator.operator(path, *operation, operands)
+            }
+        }
+    }
+}
+
+/// Observes [`Packet::eval_with_hooks`] as it walks the tree.
+///
+/// Both methods do nothing by default.
+pub trait EvalHooks {
+    /// Called before the packet at `path` is evaluated. `operation` is `None`
+    /// for literals.
+    fn before(&mut self, _path: &[usize], _operation: Option<Operation>) {}
+
+    /// Called after the packet at `path` evaluated to `value` from `operands`.
+    /// Only the condition and the taken branch of [`Operation::If`] are
+    /// evaluated, so they are its only operands. Not called for packets that
+    /// fail to evaluate.
+    fn after(
+        &mut self,
+        _path: &[usize],
+        _operation: Option<Operation>,
+        _operands: &[usize],
+        _value: usize,
+    ) {
+    }
+}
+
+impl Packet {
+    /// Evaluates the packet like [`Packet::eval`], calling `hooks` around the
+    /// evaluation of every packet.
+    ///
+    /// ```
+    /// use jacob::evaluator::EvalHooks;
+    /// use jacob::{Operation, Packet};
+    ///
+    /// #[derive(Default)]
+    /// struct Depth {
+    ///     current: usize,
+    ///     max: usize,
+    /// }
+    ///
+    /// impl EvalHooks for Depth {
+    ///     fn before(&mut self, _path: &[usize], _operation: Option<Operation>) {
+    ///         self.current += 1;
+    ///         self.max = self.max.max(self.current);
+    ///     }
+    ///
+    ///     fn after(&mut self, _: &[usize], _: Option<Operation>, _: &[usize], _: usize) {
+    ///         self.current -= 1;
+    ///     }
+    /// }
+    ///
+    /// let mut depth = Depth::default();
+    /// let packet = Packet::from_expression("1 + (2 * 3)").unwrap();
+    /// assert_eq!(packet.eval_with_hooks(&mut depth).unwrap(), 7);
+    /// assert_eq!(depth.max, 3);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the packet does not evaluate properly, including
+    /// when a value overflows.
+    pub fn eval_with_hooks<H: EvalHooks>(&self, hooks: &mut H) -> Result<usize, PacketError> {
+        self.eval_with_hooks_path(hooks, &mut Vec::new())
+    }
+
+    fn eval_with_hooks_path<H: EvalHooks>(
+        &self,
+        hooks: &mut H,
+        path: &mut Vec<usize>,
+    ) -> Result<usize, PacketError> {
+        let (operation, packets) = match &self.kind {
+            PacketKind::Literal(value) => {
+                hooks.before(path, None);
+                hooks.after(path, None, &[], *value);
+                return Ok(*value);
+            }
+            PacketKind::Operator {
+                operation, packets, ..
+            } => (*operation, packets),
+        };
+        hooks.before(path, Some(operation));
+        let arity_error = |path: &[usize]| PacketError::ArgumentError {
+            count: packets.len(),
+            operation,
+            path: path.into(),
+        };
+        let operand = |i: usize, hooks: &mut H, path: &mut Vec<usize>| {
+            path.push(i);
+            let value = packets[i].eval_with_hooks_path(hooks, path);
+            path.pop();
+            value
+        };
+        let mut operands = Vec::with_capacity(packets.len());
+        if operation == Operation::If {
+            if packets.len() != 3 {
+                return Err(arity_error(path));
+            }
+            let condition = operand(0, hooks, path)?;
+            operands.push(condition);
+            operands.push(operand(if condition == 0 { 2 } else { 1 }, hooks, path)?);
+        } else {
+            for i in 0..packets.len() {
+                operands.push(operand(i, hooks, path)?);
+            }
+        }
+        let overflow = || PacketError::Overflow(Some(operation));
+        let value = match operation {
+            Operation::If => operands[1],
+            _ if !operation.info().arity.accepts(operands.len()) => {
+                return Err(arity_error(path));
+            }
+            _ => operation.fold_all(&operands).ok_or_else(overflow)?,
+        };
+        hooks.after(path, Some(operation), &operands, value);
+        Ok(value)
+    }
+}
+
+/// Inclusive range of values a packet may evaluate to.
+///
+/// Bounds are computed in `u128` and saturate, so a `max` of `u128::MAX`
+/// means the range is unbounded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Interval {
+    pub min: u128,
+    pub max: u128,
+}
+
+impl Interval {
+    #[must_use]
+    pub const fn new(min: u128, max: u128) -> Self {
+        Self { min, max }
+    }
+
+    #[must_use]
+    pub const fn exact(value: u128) -> Self {
+        Self::new(value, value)
+    }
+
+    /// Returns `true` if every value in the interval fits in `bits` unsigned bits.
+    #[must_use]
+    pub const fn fits_in(&self, bits: u32) -> bool {
+        bits >= u128::BITS || self.max < 1 << bits
+    }
+
+    fn hull(self, other: Self) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    const BOOLEAN: Self = Self::new(0, 1);
+}
+
+impl From<RangeInclusive<usize>> for Interval {
+    fn from(range: RangeInclusive<usize>) -> Self {
+        Self::new(*range.start() as u128, *range.end() as u128)
+    }
+}
+
+/// Computes the [`Interval`] of possible values when some literals are
+/// replaced by ranges.
+///
+/// ```
+/// use jacob::evaluator::{Interval, IntervalEvaluator};
+/// use jacob::Packet;
+///
+/// // The first operand of the product is a sensor reading in 0..=1000
+/// let packet = Packet::from_expression("(0 * 3) + 7").unwrap();
+/// let mut evaluator = IntervalEvaluator::new();
+/// evaluator.set_range(&[0, 0], 0..=1000);
+/// let interval = packet.eval_with(&mut evaluator).unwrap();
+/// assert_eq!(interval, Interval::new(7, 3007));
+/// assert!(interval.fits_in(12));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct IntervalEvaluator {
+    ranges: HashMap<Vec<usize>, Interval>,
+}
+
+impl IntervalEvaluator {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treats the literal at `path` as any value in `range`.
+    pub fn set_range(&mut self, path: &[usize], range: impl Into<Interval>) -> &mut Self {
+        self.ranges.insert(path.to_vec(), range.into());
+        self
+    }
+
+    /// Treats the literal at `path` as any value a literal can hold.
+    pub fn set_unknown(&mut self, path: &[usize]) -> &mut Self {
+        self.set_range(path, 0..=usize::MAX)
+    }
+}
+
+impl Evaluator for IntervalEvaluator {
+    type Value = Interval;
+
+    fn literal(&mut self, path: &[usize], value: usize) -> Result<Interval, PacketError> {
+        Ok(self
+            .ranges
+            .get(path)
+            .copied()
+            .unwrap_or_else(|| Interval::exact(value as u128)))
+    }
+
+    fn operator(
+        &mut self,
+        path: &[usize],
+        operation: Operation,
+        operands: Vec<Interval>,
+    ) -> Result<Interval, PacketError> {
+        let arity_error = || PacketError::ArgumentError {
+            count: operands.len(),
+            operation,
+            path: path.into(),
+        };
+        Ok(match operation {
+            Operation::Sum => operands.iter().fold(Interval::exact(0), |a, b| {
+                Interval::new(a.min.saturating_add(b.min), a.max.saturating_add(b.max))
+            }),
+            Operation::Product => operands.iter().fold(Interval::exact(1), |a, b| {
+                Interval::new(a.min.saturating_mul(b.min), a.max.saturating_mul(b.max))
+            }),
+            Operation::Minimum => operands
+                .iter()
+                .copied()
+                .reduce(|a, b| Interval::new(a.min.min(b.min), a.max.min(b.max)))
+                .ok_or_else(arity_error)?,
+            Operation::Maximum => operands
+                .iter()
+                .copied()
+                .reduce(|a, b| Interval::new(a.min.max(b.min), a.max.max(b.max)))
+                .ok_or_else(arity_error)?,
+            Operation::LessThan | Operation::GreaterThan | Operation::EqualTo => {
+                let [a, b] = operands[..] else {
+                    return Err(arity_error());
+                };
+                let (a, b) = if operation == Operation::GreaterThan {
+                    (b, a)
+                } else {
+                    (a, b)
+                };
+                let always = match operation {
+                    Operation::EqualTo => a.min == a.max && a == b,
+                    _ => a.max < b.min,
+                };
+                let never = match operation {
+                    Operation::EqualTo => a.max < b.min || b.max < a.min,
+                    _ => a.min >= b.max,
+                };
+                match (always, never) {
+                    (true, _) => Interval::exact(1),
+                    (_, true) => Interval::exact(0),
+                    _ => Interval::BOOLEAN,
+                }
+            }
+            Operation::If => {
+                let [condition, then, otherwise] = operands[..] else {
+                    return Err(arity_error());
+                };
+                if condition.max == 0 {
+                    otherwise
+                } else if condition.min > 0 {
+                    then
+                } else {
+                    then.hull(otherwise)
+                }
+            }
+        })
+    }
+}
+
+/// Evaluates in `f64`, treating values within `tolerance` of each other as
+/// equal.
+///
+/// Comparisons only hold if they hold by more than the tolerance, so `a < b`
+/// is `0` when `a` and `b` are within it, and conditions within it of zero are
+/// false. Sums, products, minimums and maximums are exact `f64` arithmetic;
+/// they never overflow, but literals beyond 2<sup>53</sup> are rounded.
+///
+/// ```
+/// use jacob::evaluator::ApproxEvaluator;
+/// use jacob::Packet;
+///
+/// let packet = Packet::from_expression("(3 * 1000) == 3001").unwrap();
+/// assert_eq!(packet.eval_with(&mut ApproxEvaluator::new(0.5)).unwrap(), 0.0);
+/// assert_eq!(packet.eval_f64_with_tolerance(1.0).unwrap(), 1.0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ApproxEvaluator {
+    pub tolerance: f64,
+}
+
+impl ApproxEvaluator {
+    #[must_use]
+    pub const fn new(tolerance: f64) -> Self {
+        Self { tolerance }
+    }
+
+    /// Returns `true` if `a` and `b` are within the tolerance of each other.
+    #[must_use]
+    pub fn approx_eq(&self, a: f64, b: f64) -> bool {
+        (a - b).abs() <= self.tolerance
+    }
+}
+
+impl Evaluator for ApproxEvaluator {
+    type Value = f64;
+
+    #[allow(clippy::cast_precision_loss)]
+    fn literal(&mut self, _path: &[usize], value: usize) -> Result<f64, PacketError> {
+        Ok(value as f64)
+    }
+
+    fn operator(
+        &mut self,
+        path: &[usize],
+        operation: Operation,
+        operands: Vec<f64>,
+    ) -> Result<f64, PacketError> {
+        let arity_error = || PacketError::ArgumentError {
+            count: operands.len(),
+            operation,
+            path: path.into(),
+        };
+        Ok(match operation {
+            Operation::Sum => operands.iter().sum(),
+            Operation::Product => operands.iter().product(),
+            Operation::Minimum => operands
+                .iter()
+                .copied()
+                .reduce(f64::min)
+                .ok_or_else(arity_error)?,
+            Operation::Maximum => operands
+                .iter()
+                .copied()
+                .reduce(f64::max)
+                .ok_or_else(arity_error)?,
+            Operation::LessThan | Operation::GreaterThan | Operation::EqualTo => {
+                let [a, b] = operands[..] else {
+                    return Err(arity_error());
+                };
+                let holds = match operation {
+                    Operation::LessThan => a < b && !self.approx_eq(a, b),
+                    Operation::GreaterThan => a > b && !self.approx_eq(a, b),
+                    _ => self.approx_eq(a, b),
+                };
+                if holds {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Operation::If => {
+                let [condition, then, otherwise] = operands[..] else {
+                    return Err(arity_error());
+                };
+                if self.approx_eq(condition, 0.0) {
+                    otherwise
+                } else {
+                    then
+                }
+            }
+        })
+    }
+}
+
+impl Packet {
+    /// Evaluates the packet with an [`ApproxEvaluator`] allowing `tolerance`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any operators have an invalid number of arguments.
+    pub fn eval_f64_with_tolerance(&self, tolerance: f64) -> Result<f64, PacketError> {
+        self.eval_with(&mut ApproxEvaluator::new(tolerance))
+    }
+
+    /// Returns `true` if both packets evaluate, with comparisons allowing
+    /// `eps`, to values within `eps` of each other.
+    ///
+    /// Unlike equality, this ignores how the packets are built, so a formula
+    /// re-encoded with slightly different constants still matches:
+    ///
+    /// ```
+    /// use jacob::Packet;
+    ///
+    /// let sent = Packet::from_expression("(20 * 9) + 320").unwrap();
+    /// let received = Packet::from_expression("(21 * 9) + 312").unwrap();
+    /// assert!(sent.semantic_eq_approx(&received, 2.0));
+    /// assert!(!sent.semantic_eq_approx(&received, 0.5));
+    /// ```
+    #[must_use]
+    pub fn semantic_eq_approx(&self, other: &Self, eps: f64) -> bool {
+        let mut evaluator = ApproxEvaluator::new(eps);
+        match (
+            self.eval_with(&mut evaluator),
+            other.eval_with(&mut evaluator),
+        ) {
+            (Ok(a), Ok(b)) => evaluator.approx_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApproxEvaluator, EvalHooks, Interval, IntervalEvaluator};
+    use crate::{Operation, Packet};
+
+    fn interval(expr: &str, ranges: &[(&[usize], Interval)]) -> Interval {
+        let mut evaluator = IntervalEvaluator::new();
+        for (path, range) in ranges {
+            evaluator.set_range(path, *range);
+        }
+        Packet::from_expression(expr)
+            .unwrap()
+            .eval_with(&mut evaluator)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_exact_matches_eval() {
+        for expr in ["(1 + 3) == (2 * 2)", "min(7, 8, 9) + max(1, 2)", "5 > 15"] {
+            let value = Packet::from_expression(expr).unwrap().eval().unwrap();
+            assert_eq!(interval(expr, &[]), Interval::exact(value as u128));
+        }
+    }
+
+    #[test]
+    fn test_ranges() {
+        let range = Interval::new(10, 20);
+        assert_eq!(interval("0 < 5", &[(&[0], range)]), Interval::exact(0));
+        assert_eq!(interval("0 < 15", &[(&[0], range)]), Interval::new(0, 1));
+        assert_eq!(interval("0 == 30", &[(&[0], range)]), Interval::exact(0));
+        assert_eq!(
+            interval("max(0, 12) * 2", &[(&[0, 0], range)]),
+            Interval::new(24, 40)
+        );
+        assert_eq!(
+            interval("if(0, 1, 2)", &[(&[0], Interval::new(0, 1))]),
+            Interval::new(1, 2)
+        );
+    }
+
+    #[test]
+    fn test_overflow_detection() {
+        let mut evaluator = IntervalEvaluator::new();
+        evaluator.set_unknown(&[0]).set_unknown(&[1]);
+        let packet = Packet::from_expression("0 * 0").unwrap();
+        let interval = packet.eval_with(&mut evaluator).unwrap();
+        assert!(!interval.fits_in(usize::BITS));
+        assert_eq!(interval.max, (usize::MAX as u128).pow(2));
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp, clippy::cast_precision_loss)]
+    fn test_approx() {
+        let eval = |expr: &str, tolerance| {
+            Packet::from_expression(expr)
+                .unwrap()
+                .eval_f64_with_tolerance(tolerance)
+                .unwrap()
+        };
+        for expr in ["(1 + 3) == (2 * 2)", "min(7, 8, 9) + max(1, 2)", "5 > 15"] {
+            let value = Packet::from_expression(expr).unwrap().eval().unwrap();
+            assert_eq!(eval(expr, 0.0), value as f64);
+        }
+        assert_eq!(eval("100 < 101", 0.0), 1.0);
+        assert_eq!(eval("100 < 101", 1.0), 0.0);
+        assert_eq!(eval("101 > 100", 1.0), 0.0);
+        assert_eq!(eval("100 == 101", 1.0), 1.0);
+        assert_eq!(eval("if(1, 2, 3)", 1.0), 3.0);
+        assert_eq!(
+            eval("18446744073709551615 * 2", 0.0),
+            usize::MAX as f64 * 2.0
+        );
+        assert!(ApproxEvaluator::new(0.5).approx_eq(1.0, 1.5));
+
+        let packet = Packet::from_expression("10 + 5").unwrap();
+        let other = Packet::from_expression("max(14, 2)").unwrap();
+        assert!(packet.semantic_eq_approx(&other, 1.0));
+        assert!(!packet.semantic_eq_approx(&other, 0.9));
+        let invalid = Packet::from_expression("min()").unwrap();
+        assert!(!invalid.semantic_eq_approx(&invalid, 1.0));
+    }
+
+    type Evaluated = (Vec<usize>, Option<Operation>, Vec<usize>, usize);
+
+    #[derive(Default)]
+    struct Trace {
+        before: Vec<Vec<usize>>,
+        after: Vec<Evaluated>,
+    }
+
+    impl EvalHooks for Trace {
+        fn before(&mut self, path: &[usize], _operation: Option<Operation>) {
+            self.before.push(path.to_vec());
+        }
+
+        fn after(
+            &mut self,
+            path: &[usize],
+            operation: Option<Operation>,
+            operands: &[usize],
+            value: usize,
+        ) {
+            self.after
+                .push((path.to_vec(), operation, operands.to_vec(), value));
+        }
+    }
+
+    #[test]
+    fn test_eval_with_hooks() {
+        let packet = Packet::from_expression("if(2 > 1, 3 * 4, min())").unwrap();
+        let mut trace = Trace::default();
+        assert_eq!(packet.eval_with_hooks(&mut trace).unwrap(), 12);
+        assert_eq!(
+            trace.before,
+            [
+                vec![],
+                vec![0],
+                vec![0, 0],
+                vec![0, 1],
+                vec![1],
+                vec![1, 0],
+                vec![1, 1]
+            ]
+        );
+        assert_eq!(trace.after.len(), 7);
+        assert_eq!(
+            trace.after[2],
+            (vec![0], Some(Operation::GreaterThan), vec![2, 1], 1)
+        );
+        assert_eq!(
+            trace.after[5],
+            (vec![1], Some(Operation::Product), vec![3, 4], 12)
+        );
+        assert_eq!(
+            trace.after[6],
+            (vec![], Some(Operation::If), vec![1, 12], 12)
+        );
+
+        for expr in ["(1 + 3) == (2 * 2)", "min(7, 8, 9) + max(1, 2)", "5 > 15"] {
+            let packet = Packet::from_expression(expr).unwrap();
+            assert_eq!(
+                packet.eval_with_hooks(&mut Trace::default()).unwrap(),
+                packet.eval().unwrap()
+            );
+        }
+        let overflow = Packet::from_expression(&format!("{} + 1", usize::MAX)).unwrap();
+        assert!(overflow.eval_with_hooks(&mut Trace::default()).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! Sample packets to try the crate and the `jacob` command with.
+//!
+//! [`examples`] lists packets covering every standard operation and both
+//! length encodings, from a lone literal to nested comparisons:
+//!
+//! ```
+//! use jacob::examples::examples;
+//!
+//! for example in examples() {
+//!     let packet = example.packet();
+//!     println!("{}: {packet} = {}", example.hex, packet.eval().unwrap());
+//! }
+//! assert_eq!(examples()[0].packet().eval().unwrap(), 2021);
+//! ```
+
+use crate::Packet;
+
+/// A sample packet; see [`examples`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Example {
+    /// The packet in hexadecimal, in the standard profile.
+    pub hex: &'static str,
+    /// What the packet shows.
+    pub description: &'static str,
+}
+
+impl Example {
+    /// Returns the packet parsed from [`Example::hex`].
+    ///
+    /// # Panics
+    ///
+    /// Never panics: every example is a valid packet.
+    #[must_use]
+    pub fn packet(&self) -> Packet {
+        self.hex.parse().expect("examples are valid packets")
+    }
+}
+
+const EXAMPLES: [Example; 12] = [
+    Example {
+        hex: "D2FE28",
+        description: "A literal, its value split into groups of four bits",
+    },
+    Example {
+        hex: "C200B40A82",
+        description: "A sum",
+    },
+    Example {
+        hex: "04005AC33890",
+        description: "A product",
+    },
+    Example {
+        hex: "880086C3E88112",
+        description: "A minimum, the length of its operands given in bits",
+    },
+    Example {
+        hex: "CE00C43D881120",
+        description: "A maximum, its operands counted instead",
+    },
+    Example {
+        hex: "D8005AC2A8F0",
+        description: "A less-than comparison",
+    },
+    Example {
+        hex: "F600BC2D8F",
+        description: "A greater-than comparison",
+    },
+    Example {
+        hex: "9C005AC2F8F0",
+        description: "An equality comparison",
+    },
+    Example {
+        hex: "38006F45291200",
+        description: "A comparison with non-zero versions and trailing padding",
+    },
+    Example {
+        hex: "9C0141080250320F1802104A08",
+        description: "A comparison of a sum and a product",
+    },
+    Example {
+        hex: "8A004A801A8002F478",
+        description: "Operators nested three deep around a single literal",
+    },
+    Example {
+        hex: "620080001611562C8802118E34",
+        description: "A sum of sums, mixing both length encodings",
+    },
+];
+
+/// Returns the sample packets, simplest first.
+#[must_use]
+pub const fn examples() -> &'static [Example] {
+    &EXAMPLES
+}
+
+#[cfg(test)]
+mod tests {
+    use super::examples;
+    use crate::{Operation, Packet};
+
+    #[test]
+    fn test_examples() {
+        let mut operations = Vec::new();
+        for example in examples() {
+            let packet = example.packet();
+            assert!(packet.eval().is_ok(), "{}", example.hex);
+            let reparsed: Packet = packet.to_hex().unwrap().parse().unwrap();
+            assert_eq!(reparsed, packet);
+            operations.extend(packet.op_histogram().into_iter().map(|(op, _)| op));
+        }
+        for operation in Operation::ALL.into_iter().filter(|op| !op.is_extended()) {
+            assert!(operations.contains(&operation), "{operation:?}");
+        }
+    }
+}
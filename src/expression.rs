@@ -0,0 +1,1195 @@
+//! Parsing and styling of mathematical expressions.
+//!
+//! The grammar mirrors what [`Packet::to_expression`] emits: `+` and `*` chains
+//! become a single n-ary operator, comparisons take exactly two operands, and
+//! every operation is also available in function form (`sum(1, 2)`).
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use thiserror::Error;
+
+use crate::input::{read_entries, Entry};
+use crate::{Arity, Length, Operation, Packet, PacketError, PacketKind, Version};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ExpressionError {
+    #[error("unexpected end of expression")]
+    UnexpectedEnd,
+    #[error("unexpected character `{0}` at position {1}")]
+    UnexpectedChar(char, usize),
+    #[error("unexpected `{0}` at position {1}")]
+    UnexpectedToken(String, usize),
+    #[error("unknown function `{0}` at position {1}")]
+    UnknownFunction(String, usize),
+    #[error("invalid numeric literal `{literal}` at position {position}: {reason}")]
+    InvalidNumber {
+        literal: String,
+        position: usize,
+        reason: &'static str,
+    },
+    #[error("comparisons cannot be chained without parentheses (position {0})")]
+    ChainedComparison(usize),
+    #[error("too many operands `{0}` for a single operator")]
+    TooManyOperands(usize),
+    #[error("unary minus at position {0} is not supported: packets have no negative values")]
+    UnaryMinus(usize),
+    #[error("subtraction at position {0} is not supported: no operation subtracts")]
+    Subtraction(usize),
+    #[error("implicit multiplication at position {0} is not enabled, write `*`")]
+    ImplicitMultiplication(usize),
+}
+
+impl ExpressionError {
+    /// Returns the byte offset in the expression the error is at, or `None`
+    /// if it is at the end or about the expression as a whole.
+    #[must_use]
+    pub const fn position(&self) -> Option<usize> {
+        match self {
+            Self::UnexpectedEnd | Self::TooManyOperands(_) => None,
+            Self::UnexpectedChar(_, position)
+            | Self::UnexpectedToken(_, position)
+            | Self::UnknownFunction(_, position)
+            | Self::InvalidNumber { position, .. }
+            | Self::ChainedComparison(position)
+            | Self::UnaryMinus(position)
+            | Self::Subtraction(position)
+            | Self::ImplicitMultiplication(position) => Some(*position),
+        }
+    }
+}
+
+/// Options for [`parse_with`] and [`Packet::from_expression_with`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExpressionOptions {
+    /// Multiply an operand by a parenthesized expression or function call
+    /// written right after it, so `2(3 + 4)` is `2 * (3 + 4)` and
+    /// `(1 + 2)max(3, 4)` is `(1 + 2) * max(3, 4)`.
+    pub implicit_multiplication: bool,
+}
+
+/// Base literals are written in by [`ExpressionStyle`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Radix {
+    #[default]
+    Decimal,
+    /// `0x` followed by upper case digits, such as `0xFF`.
+    Hexadecimal,
+    /// `0b` followed by binary digits, such as `0b1010`.
+    Binary,
+}
+
+/// Rendering options for [`Packet::to_expression_styled`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExpressionStyle {
+    /// Group the digits of literals using `_`, which the parser accepts back:
+    /// decimal literals with five or more digits in threes (`1_000_000`), and
+    /// other literals in fours (`0xFFFF_FFFF`).
+    pub digit_separators: bool,
+    /// Symbolic names rendered in place of literal values, as read by
+    /// [`parse_names`].
+    pub names: BTreeMap<usize, String>,
+    /// Base of literals of at least [`radix_threshold`](Self::radix_threshold).
+    pub radix: Radix,
+    /// Smallest literal written in [`radix`](Self::radix); smaller literals
+    /// stay decimal. For example, `1 << 32` writes only values that overflow
+    /// 32 bits in hexadecimal.
+    pub radix_threshold: usize,
+    /// Symbols written for operations.
+    pub symbols: ExpressionSymbols,
+}
+
+/// Symbols [`ExpressionStyle`] writes for operations.
+///
+/// Each replaces the [`Display`](std::fmt::Display) form of its operation:
+/// the infix symbol of `+`, `*`, and the comparisons, or the function name of
+/// the others. Expressions with replaced symbols are for reading and cannot
+/// be parsed back.
+///
+/// ```
+/// use jacob::{ExpressionStyle, ExpressionSymbols, Operation, Packet};
+///
+/// let style = ExpressionStyle {
+///     symbols: ExpressionSymbols::unicode().with(Operation::Maximum, "⌈max⌉"),
+///     ..ExpressionStyle::default()
+/// };
+/// let packet = Packet::from_expression("max(2 * 3, 4) == 6").unwrap();
+/// assert_eq!(packet.styled(&style).to_string(), "⌈max⌉((2 × 3), 4) = 6");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExpressionSymbols(BTreeMap<Operation, String>);
+
+impl ExpressionSymbols {
+    /// Returns a table that keeps every symbol.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    /// Returns a table writing `×` for products and `=` for equality.
+    #[must_use]
+    pub fn unicode() -> Self {
+        Self::new()
+            .with(Operation::Product, "×")
+            .with(Operation::EqualTo, "=")
+    }
+
+    /// Writes `symbol` for `operation`.
+    #[must_use]
+    pub fn with(mut self, operation: Operation, symbol: impl Into<String>) -> Self {
+        self.0.insert(operation, symbol.into());
+        self
+    }
+
+    /// Returns the symbol written for `operation`.
+    #[must_use]
+    pub fn get(&self, operation: Operation) -> &str {
+        self.0.get(&operation).map_or_else(
+            || {
+                let info = operation.info();
+                info.symbol.unwrap_or(info.func)
+            },
+            String::as_str,
+        )
+    }
+}
+
+/// A packet [displayed](std::fmt::Display) as an expression in a style; see
+/// [`Packet::styled`].
+#[derive(Clone, Copy, Debug)]
+pub struct Styled<'a> {
+    packet: &'a Packet,
+    style: &'a ExpressionStyle,
+}
+
+impl std::fmt::Display for Styled<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.packet.write_expression_styled(f, self.style)
+    }
+}
+
+impl Packet {
+    /// Returns the packet [displayed](std::fmt::Display) as an expression
+    /// written in `style`, where `Display` on the packet itself uses the
+    /// default style.
+    #[must_use]
+    pub const fn styled<'a>(&'a self, style: &'a ExpressionStyle) -> Styled<'a> {
+        Styled {
+            packet: self,
+            style,
+        }
+    }
+}
+
+impl ExpressionStyle {
+    pub(crate) fn write_literal<W: std::fmt::Write>(
+        &self,
+        w: &mut W,
+        value: usize,
+    ) -> std::fmt::Result {
+        if let Some(name) = self.names.get(&value) {
+            return w.write_str(name);
+        }
+        let (prefix, radix, group) = match self.radix {
+            Radix::Hexadecimal if value >= self.radix_threshold => ("0x", 16, 4),
+            Radix::Binary if value >= self.radix_threshold => ("0b", 2, 4),
+            _ if !self.digit_separators || value < 10_000 => return write!(w, "{value}"),
+            _ => ("", 10, 3),
+        };
+        w.write_str(prefix)?;
+        // Least significant digit first
+        let mut digits = [b'0'; usize::BITS as usize];
+        let mut len = 0;
+        let mut rest = value;
+        while rest > 0 || len == 0 {
+            digits[len] = b"0123456789ABCDEF"[rest % radix];
+            rest /= radix;
+            len += 1;
+        }
+        for i in (0..len).rev() {
+            w.write_char(char::from(digits[i]))?;
+            if self.digit_separators && i > 0 && i.is_multiple_of(group) {
+                w.write_char('_')?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a table of symbolic names for literal values.
+///
+/// The table is the subset of TOML made of `NAME = value` lines, where values
+/// are numbers as accepted in expressions. Blank lines and `#` comments are
+/// ignored. Names must be identifiers that do not shadow a function, and each
+/// value may only be named once.
+///
+/// ```
+/// let names = jacob::expression::parse_names("THRESHOLD = 100 # degrees").unwrap();
+/// assert_eq!(names[&100], "THRESHOLD");
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if a line is malformed or a name or value is repeated.
+pub fn parse_names(text: &str) -> Result<BTreeMap<usize, String>, PacketError> {
+    let mut names = BTreeMap::new();
+    for Entry {
+        line: number,
+        text: line,
+    } in read_entries(text)
+    {
+        let invalid = |reason| PacketError::ConfigError {
+            line: number,
+            reason,
+        };
+        let (name, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid("expected `NAME = value`"))?;
+        let (name, value) = (name.trim(), value.trim());
+        let mut chars = name.chars();
+        if !chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(invalid("names must be identifiers"));
+        }
+        if Operation::from_func_str(name).is_some() {
+            return Err(invalid("name shadows a function"));
+        }
+        let value = parse_number(value, 0).map_err(|_| invalid("invalid value"))?;
+        if names.values().any(|existing| existing == name) {
+            return Err(invalid("name is defined twice"));
+        }
+        if names.insert(value, name.to_string()).is_some() {
+            return Err(invalid("value is already named"));
+        }
+    }
+    Ok(names)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Number(usize),
+    Ident(&'a str),
+    Operator(Operation),
+    Minus,
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+impl std::fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{n}"),
+            Self::Ident(ident) => write!(f, "{ident}"),
+            Self::Operator(operation) => write!(f, "{operation}"),
+            Self::Minus => write!(f, "-"),
+            Self::LeftParen => write!(f, "("),
+            Self::RightParen => write!(f, ")"),
+            Self::Comma => write!(f, ","),
+        }
+    }
+}
+
+/// Validates and converts a numeric literal.
+///
+/// Decimal (`1_000`), hexadecimal (`0x1F`), and binary (`0b1010`) forms are
+/// accepted. Anything that
+/// could be read differently depending on locale or language (`1.000`, `1,000`,
+/// `010`) is rejected.
+fn parse_number(literal: &str, position: usize) -> Result<usize, ExpressionError> {
+    let invalid = |reason| ExpressionError::InvalidNumber {
+        literal: literal.to_string(),
+        position,
+        reason,
+    };
+
+    let (digits, radix) = match literal.get(..2) {
+        Some("0x" | "0X") => (&literal[2..], 16),
+        Some("0b" | "0B") => (&literal[2..], 2),
+        Some("0o" | "0O") => return Err(invalid("unsupported radix prefix")),
+        _ => (literal, 10),
+    };
+
+    if digits.is_empty() {
+        return Err(invalid("missing digits"));
+    }
+    if digits.contains(['.', ',']) {
+        return Err(invalid("decimal and grouping marks are ambiguous, use `_`"));
+    }
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return Err(invalid("`_` may only appear between digits"));
+    }
+    if radix == 10 && digits.len() > 1 && digits.starts_with('0') {
+        return Err(invalid("leading zeros are ambiguous"));
+    }
+    if !digits.chars().all(|c| c == '_' || c.is_digit(radix)) {
+        return Err(invalid("invalid digit"));
+    }
+
+    let value = if digits.contains('_') {
+        usize::from_str_radix(&digits.replace('_', ""), radix)
+    } else {
+        usize::from_str_radix(digits, radix)
+    };
+    value.map_err(|_| invalid("value does not fit in a literal"))
+}
+
+fn tokenize(s: &str) -> Result<Vec<(Token<'_>, usize)>, ExpressionError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some((position, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ',' => Token::Comma,
+            '+' => Token::Operator(Operation::Sum),
+            '*' => Token::Operator(Operation::Product),
+            '-' => Token::Minus,
+            '<' => Token::Operator(Operation::LessThan),
+            '>' => Token::Operator(Operation::GreaterThan),
+            '=' => match chars.next() {
+                Some((_, '=')) => Token::Operator(Operation::EqualTo),
+                Some((position, c)) => return Err(ExpressionError::UnexpectedChar(c, position)),
+                None => return Err(ExpressionError::UnexpectedEnd),
+            },
+            c if c.is_ascii_digit() => {
+                let mut end = position + c.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.')) {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                Token::Number(parse_number(&s[position..end], position)?)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = position + c.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                Token::Ident(&s[position..end])
+            }
+            c => return Err(ExpressionError::UnexpectedChar(c, position)),
+        };
+        tokens.push((token, position));
+    }
+    Ok(tokens)
+}
+
+/// What [`Parser`] builds from an expression: a [`Packet`] tree for
+/// [`parse`], or the value directly for [`eval_expression`]. Also used by
+/// [`crate::stream`] to fold values while decoding.
+pub(crate) trait Build {
+    type Node;
+    /// Operands collected for one operator.
+    type Operands;
+
+    fn literal(value: usize) -> Self::Node;
+    fn operands(operation: Operation) -> Self::Operands;
+    fn push(operands: &mut Self::Operands, operand: Self::Node);
+    fn operator(operands: Self::Operands, count: u16) -> Self::Node;
+}
+
+struct Tree;
+
+impl Build for Tree {
+    type Node = Packet;
+    type Operands = (Operation, Vec<Packet>);
+
+    fn literal(value: usize) -> Packet {
+        Packet {
+            version: Version::ZERO,
+            kind: PacketKind::Literal(value),
+        }
+    }
+
+    fn operands(operation: Operation) -> Self::Operands {
+        (operation, Vec::new())
+    }
+
+    fn push((_, packets): &mut Self::Operands, operand: Packet) {
+        packets.push(operand);
+    }
+
+    fn operator((operation, packets): Self::Operands, count: u16) -> Packet {
+        Packet {
+            version: Version::ZERO,
+            kind: PacketKind::Operator {
+                length: Length::PacketCount(count),
+                operation,
+                packets,
+            },
+        }
+    }
+}
+
+/// Folds each operator's operands as they are parsed. Values are results so
+/// that, as in [`Packet::eval_as`], errors in branches `if` does not take are
+/// ignored.
+pub(crate) struct Value;
+
+/// Operands of one operator folded so far.
+pub(crate) struct Fold {
+    operation: Operation,
+    count: usize,
+    /// The left operand of a comparison, or the condition of `if`.
+    first: Result<usize, PacketError>,
+    /// The running result of an operation with an
+    /// [identity](Operation::identity), the right operand of a comparison, or
+    /// the branch `if` takes.
+    value: Result<usize, PacketError>,
+}
+
+impl Build for Value {
+    type Node = Result<usize, PacketError>;
+    type Operands = Fold;
+
+    fn literal(value: usize) -> Self::Node {
+        Ok(value)
+    }
+
+    fn operands(operation: Operation) -> Fold {
+        Fold {
+            operation,
+            count: 0,
+            first: Ok(0),
+            value: Ok(operation.identity().unwrap_or_default()),
+        }
+    }
+
+    fn push(fold: &mut Fold, operand: Self::Node) {
+        let operand = operand.map_err(|error| error.within(fold.count));
+        let overflow = || PacketError::Overflow(Some(fold.operation));
+        let value = std::mem::replace(&mut fold.value, Ok(0));
+        fold.value = match (fold.operation, fold.count) {
+            (operation, _) if operation.identity().is_some() => {
+                value.and_then(|acc| operation.fold(acc, operand?).ok_or_else(overflow))
+            }
+            (_, 0) => {
+                fold.first = operand;
+                value
+            }
+            (Operation::If, 1) if matches!(fold.first, Ok(0)) => value,
+            (Operation::If, 2) if !matches!(fold.first, Ok(0)) => value,
+            (_, 1 | 2) => operand,
+            _ => value,
+        };
+        fold.count += 1;
+    }
+
+    fn operator(fold: Fold, _: u16) -> Self::Node {
+        let arity_error = || PacketError::arity(fold.count, fold.operation);
+        match fold.operation {
+            operation if !operation.info().arity.accepts(fold.count) => Err(arity_error()),
+            Operation::If => {
+                fold.first?;
+                fold.value
+            }
+            operation if operation.identity().is_some() => fold.value,
+            operation => operation
+                .fold(fold.first?, fold.value?)
+                .ok_or(PacketError::Overflow(Some(operation))),
+        }
+    }
+}
+
+struct Parser<'a, B> {
+    tokens: std::iter::Peekable<std::vec::IntoIter<(Token<'a>, usize)>>,
+    options: ExpressionOptions,
+    build: std::marker::PhantomData<B>,
+}
+
+impl<'a, B: Build> Parser<'a, B> {
+    fn new(s: &'a str, options: ExpressionOptions) -> Result<Self, ExpressionError> {
+        Ok(Self {
+            tokens: tokenize(s)?.into_iter().peekable(),
+            options,
+            build: std::marker::PhantomData,
+        })
+    }
+
+    fn next(&mut self) -> Result<(Token<'a>, usize), ExpressionError> {
+        self.tokens.next().ok_or(ExpressionError::UnexpectedEnd)
+    }
+
+    fn peek(&mut self) -> Option<&Token<'a>> {
+        self.tokens.peek().map(|(token, _)| token)
+    }
+
+    fn expect(&mut self, expected: &Token<'_>) -> Result<(), ExpressionError> {
+        match self.next()? {
+            (token, _) if &token == expected => Ok(()),
+            (token, position) => Err(ExpressionError::UnexpectedToken(
+                token.to_string(),
+                position,
+            )),
+        }
+    }
+
+    /// Parses a whole expression, rejecting anything after it.
+    fn expression(&mut self) -> Result<B::Node, ExpressionError> {
+        let node = self.comparison()?;
+        match self.tokens.next() {
+            None => Ok(node),
+            Some((token, position)) => Err(ExpressionError::UnexpectedToken(
+                token.to_string(),
+                position,
+            )),
+        }
+    }
+
+    /// `comparison := chain(sum) (('<' | '>' | '==') chain(sum))?`
+    fn comparison(&mut self) -> Result<B::Node, ExpressionError> {
+        let lhs = self.chain(Operation::Sum)?;
+        match self.peek() {
+            Some(Token::Operator(
+                operation @ (Operation::LessThan | Operation::GreaterThan | Operation::EqualTo),
+            )) => {
+                let mut operands = B::operands(*operation);
+                self.next()?;
+                B::push(&mut operands, lhs);
+                B::push(&mut operands, self.chain(Operation::Sum)?);
+                if let Some((Token::Operator(_), position)) = self.tokens.peek() {
+                    return Err(ExpressionError::ChainedComparison(*position));
+                }
+                Ok(B::operator(operands, 2))
+            }
+            _ => Ok(lhs),
+        }
+    }
+
+    /// Parses a run of `operation` (`+` binds looser than `*`) into one operator.
+    fn chain(&mut self, operation: Operation) -> Result<B::Node, ExpressionError> {
+        let operand = |parser: &mut Self| match operation {
+            Operation::Sum => parser.chain(Operation::Product),
+            _ => parser.atom(),
+        };
+        let first = operand(self)?;
+        if !self.continues(operation)? {
+            return Ok(first);
+        }
+        let mut operands = B::operands(operation);
+        B::push(&mut operands, first);
+        let mut count = 1;
+        loop {
+            B::push(&mut operands, operand(self)?);
+            count += 1;
+            if !self.continues(operation)? {
+                break;
+            }
+        }
+        Ok(B::operator(operands, operand_count(count)?))
+    }
+
+    /// Returns `true` if another operand of a run of `operation` follows,
+    /// consuming the operator before it. Explains the `-` and implicit
+    /// products that could otherwise only be reported as unexpected.
+    fn continues(&mut self, operation: Operation) -> Result<bool, ExpressionError> {
+        let implicit = operation == Operation::Product;
+        match self.tokens.peek() {
+            Some((Token::Operator(next), _)) if *next == operation => {
+                self.next()?;
+                Ok(true)
+            }
+            Some((Token::LeftParen | Token::Ident(_), position)) if implicit => {
+                if self.options.implicit_multiplication {
+                    Ok(true)
+                } else {
+                    Err(ExpressionError::ImplicitMultiplication(*position))
+                }
+            }
+            Some((Token::Minus, position)) if operation == Operation::Sum => {
+                Err(ExpressionError::Subtraction(*position))
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// `atom := NUMBER | '(' comparison ')' | IDENT '(' arguments ')'`
+    fn atom(&mut self) -> Result<B::Node, ExpressionError> {
+        match self.next()? {
+            (Token::Number(value), _) => Ok(B::literal(value)),
+            (Token::Minus, position) => Err(ExpressionError::UnaryMinus(position)),
+            (Token::LeftParen, _) => {
+                let node = self.comparison()?;
+                self.expect(&Token::RightParen)?;
+                Ok(node)
+            }
+            (Token::Ident(name), position) => {
+                let operation = Operation::from_func_str(name)
+                    .ok_or_else(|| ExpressionError::UnknownFunction(name.to_string(), position))?;
+                self.expect(&Token::LeftParen)?;
+                let mut operands = B::operands(operation);
+                let mut count = 0;
+                if self.peek() == Some(&Token::RightParen) {
+                    self.next()?;
+                } else {
+                    loop {
+                        B::push(&mut operands, self.comparison()?);
+                        count += 1;
+                        match self.next()? {
+                            (Token::Comma, _) => {}
+                            (Token::RightParen, _) => break,
+                            (token, position) => {
+                                return Err(ExpressionError::UnexpectedToken(
+                                    token.to_string(),
+                                    position,
+                                ))
+                            }
+                        }
+                    }
+                }
+                Ok(B::operator(operands, operand_count(count)?))
+            }
+            (token, position) => Err(ExpressionError::UnexpectedToken(
+                token.to_string(),
+                position,
+            )),
+        }
+    }
+}
+
+fn operand_count(count: usize) -> Result<u16, ExpressionError> {
+    u16::try_from(count)
+        .ok()
+        .filter(|&count| count <= Length::MAX_PACKET_COUNT)
+        .ok_or(ExpressionError::TooManyOperands(count))
+}
+
+/// Parses a mathematical expression into a packet with version `0`.
+///
+/// # Errors
+///
+/// Will return `Err` if the expression is malformed.
+pub fn parse(s: &str) -> Result<Packet, ExpressionError> {
+    parse_with(s, &ExpressionOptions::default())
+}
+
+/// Parses a mathematical expression into a packet with version `0`,
+/// according to `options`.
+///
+/// ```
+/// use jacob::expression::{parse_with, ExpressionError, ExpressionOptions};
+///
+/// let options = ExpressionOptions {
+///     implicit_multiplication: true,
+/// };
+/// assert_eq!(parse_with("2(3 + 4)", &options).unwrap().eval().unwrap(), 14);
+/// assert_eq!(
+///     parse_with("2(3 + 4)", &ExpressionOptions::default()),
+///     Err(ExpressionError::ImplicitMultiplication(1))
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if the expression is malformed.
+pub fn parse_with(s: &str, options: &ExpressionOptions) -> Result<Packet, ExpressionError> {
+    Parser::<Tree>::new(s, *options)?.expression()
+}
+
+/// Evaluates a mathematical expression without building its packet.
+///
+/// The result is that of [`Packet::eval_as`] with [`Overflow::Checked`] on
+/// the parsed packet, but operators are folded as their operands are parsed,
+/// which saves allocating the tree and is faster the longer the formula.
+///
+/// ```
+/// assert_eq!(jacob::eval_expression("max(1 + 2, 3 * 4)").unwrap(), 12);
+/// assert!(jacob::eval_expression("min()").is_err());
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if the expression is malformed, an evaluated operator
+/// has an invalid number of operands, or a value overflows.
+///
+/// [`Overflow::Checked`]: crate::width::Overflow::Checked
+pub fn eval_expression(s: &str) -> Result<usize, PacketError> {
+    Parser::<Value>::new(s, ExpressionOptions::default())?.expression()?
+}
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The expression parses and evaluates, but probably not as intended.
+    Warning,
+    /// The expression does not parse or cannot be evaluated.
+    Error,
+}
+
+/// Feedback about part of an expression; see [`diagnostics`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Byte range of the expression the diagnostic is about. Empty for a
+    /// position between two characters.
+    pub span: Range<usize>,
+    pub severity: Severity,
+    pub message: String,
+    /// Text to replace [`span`](Self::span) with to fix the problem, if there
+    /// is an obvious one.
+    pub fix: Option<String>,
+}
+
+/// Returns feedback about an expression as it is being written, for editors
+/// and playgrounds.
+///
+/// An expression that does not [`parse`] gets a single [`Severity::Error`]
+/// at the error. One that does is checked for function calls with the wrong
+/// number of operands, and warned about empty sums and products and about
+/// operations outside the standard profile.
+///
+/// ```
+/// use jacob::expression::{diagnostics, Severity};
+///
+/// let found = diagnostics("1 + mx(2, 3");
+/// assert_eq!(found[0].span, 4..6);
+/// assert_eq!(found[0].severity, Severity::Error);
+/// assert_eq!(found[0].fix.as_deref(), Some("max"));
+///
+/// let found = diagnostics("min() + product()");
+/// assert_eq!(found[0].message, "`min` takes at least 1 operand, found 0");
+/// assert_eq!(found[1].severity, Severity::Warning);
+/// assert!(diagnostics("max(1, 2 * 3)").is_empty());
+/// ```
+#[must_use]
+pub fn diagnostics(src: &str) -> Vec<Diagnostic> {
+    match parse(src) {
+        Ok(_) => call_diagnostics(src),
+        Err(error) => vec![error_diagnostic(src, &error)],
+    }
+}
+
+/// Describes a parse error, suggesting a fix where one is obvious.
+fn error_diagnostic(src: &str, error: &ExpressionError) -> Diagnostic {
+    let mut span = error.position().map_or_else(
+        || match error {
+            ExpressionError::UnexpectedEnd => src.len()..src.len(),
+            _ => 0..src.len(),
+        },
+        |position| position..position + token_len(src, position),
+    );
+    let fix = match error {
+        ExpressionError::UnexpectedEnd => {
+            let unclosed = src
+                .matches('(')
+                .count()
+                .saturating_sub(src.matches(')').count());
+            (unclosed > 0).then(|| ")".repeat(unclosed))
+        }
+        ExpressionError::UnknownFunction(name, _) => closest_function(name).map(str::to_string),
+        // A lone `=` where `==` was meant
+        ExpressionError::UnexpectedChar(_, position)
+            if src[..*position].ends_with('=') && !src[..*position].ends_with("==") =>
+        {
+            span = position - 1..*position;
+            Some("==".to_string())
+        }
+        ExpressionError::ImplicitMultiplication(position) => {
+            span = *position..*position;
+            Some("* ".to_string())
+        }
+        _ => None,
+    };
+    Diagnostic {
+        span,
+        severity: Severity::Error,
+        message: error.to_string(),
+        fix,
+    }
+}
+
+/// Returns the length in bytes of the token starting at `position`.
+fn token_len(src: &str, position: usize) -> usize {
+    let rest = src.get(position..).unwrap_or_default();
+    let word = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.')))
+        .unwrap_or(rest.len());
+    if word > 0 {
+        word
+    } else if rest.starts_with("==") {
+        2
+    } else {
+        rest.chars().next().map_or(0, char::len_utf8)
+    }
+}
+
+/// Returns the function name `name` is most likely a typo of.
+fn closest_function(name: &str) -> Option<&'static str> {
+    Operation::ALL
+        .into_iter()
+        .map(|operation| operation.as_func_str())
+        .map(|func| (edit_distance(name, func), func))
+        .filter(|&(distance, _)| distance <= 2 && distance < name.len())
+        .min()
+        .map(|(_, func)| func)
+}
+
+/// Returns the Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Checks the number of operands of every function call in `src`, which
+/// parses.
+fn call_diagnostics(src: &str) -> Vec<Diagnostic> {
+    let Ok(tokens) = tokenize(src) else {
+        return Vec::new();
+    };
+    let mut diagnostics = Vec::new();
+    // For each open parenthesis, the call it begins, where, and its commas
+    let mut open: Vec<Option<(Operation, usize, usize)>> = Vec::new();
+    let mut previous: Option<&Token<'_>> = None;
+    let mut name_position = 0;
+    for (token, position) in &tokens {
+        match token {
+            Token::LeftParen => open.push(match previous {
+                Some(Token::Ident(name)) => {
+                    Operation::from_func_str(name).map(|operation| (operation, name_position, 0))
+                }
+                _ => None,
+            }),
+            Token::Comma => {
+                if let Some(Some((_, _, commas))) = open.last_mut() {
+                    *commas += 1;
+                }
+            }
+            Token::RightParen => {
+                if let Some(Some((operation, start, commas))) = open.pop() {
+                    let count = if previous == Some(&Token::LeftParen) {
+                        0
+                    } else {
+                        commas + 1
+                    };
+                    diagnostics.extend(call_diagnostic(operation, count, start..position + 1));
+                }
+            }
+            _ => {}
+        }
+        if let Token::Ident(_) = token {
+            name_position = *position;
+        }
+        previous = Some(token);
+    }
+    diagnostics
+}
+
+/// Describes what is wrong with a call of `operation` with `count` operands
+/// at `span`, if anything.
+fn call_diagnostic(operation: Operation, count: usize, span: Range<usize>) -> Option<Diagnostic> {
+    let func = operation.as_func_str();
+    let operands = |n| match n {
+        1 => "1 operand".to_string(),
+        n => format!("{n} operands"),
+    };
+    let (severity, message) = match operation.info().arity {
+        Arity::AtLeast(min) if count < min => (
+            Severity::Error,
+            format!("`{func}` takes at least {}, found {count}", operands(min)),
+        ),
+        Arity::Exactly(n) if count != n => (
+            Severity::Error,
+            format!("`{func}` takes exactly {}, found {count}", operands(n)),
+        ),
+        _ if count == 0 => (
+            Severity::Warning,
+            format!(
+                "`{func}()` has no operands and is always {}",
+                operation.identity().unwrap_or_default()
+            ),
+        ),
+        _ if operation.is_extended() => (
+            Severity::Warning,
+            format!("`{func}` is only available in the extended profile"),
+        ),
+        _ => return None,
+    };
+    Some(Diagnostic {
+        span,
+        severity,
+        message,
+        fix: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        diagnostics, eval_expression, parse, parse_names, parse_with, Diagnostic, ExpressionError,
+        ExpressionOptions, ExpressionStyle, ExpressionSymbols, Radix, Severity,
+    };
+    use crate::diff::{diff_expressions, Markers};
+    use crate::width::Overflow;
+    use crate::ErrorKind;
+    use crate::Operation;
+
+    #[test]
+    fn test_parse_numbers() {
+        for (expr, value) in [
+            ("1_000_000", 1_000_000),
+            ("0x1F", 0x1F),
+            ("0xFF_FF", 0xFFFF),
+            ("0b1010", 0b1010),
+            ("0b1_0000", 0b1_0000),
+            ("0", 0),
+        ] {
+            assert_eq!(parse(expr).unwrap().eval().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_implicit_multiplication_and_minus() {
+        let implicit = ExpressionOptions {
+            implicit_multiplication: true,
+        };
+        for (expr, value) in [
+            ("2(3 + 4)", 14),
+            ("(1 + 2)(3 + 4)", 21),
+            ("2 max(1, 3) + 1", 7),
+            ("2 * 3(4)", 24),
+            ("1 < 2(3)", 1),
+        ] {
+            let packet = parse_with(expr, &implicit).unwrap();
+            assert_eq!(packet.eval().unwrap(), value, "{expr}");
+            assert_eq!(packet, parse(&packet.to_string()).unwrap(), "{expr}");
+        }
+        assert_eq!(
+            parse("1 + 2(3 + 4)"),
+            Err(ExpressionError::ImplicitMultiplication(5))
+        );
+        assert_eq!(
+            parse_with("2 3", &implicit),
+            Err(ExpressionError::UnexpectedToken("3".to_string(), 2))
+        );
+
+        assert_eq!(parse("-1"), Err(ExpressionError::UnaryMinus(0)));
+        assert_eq!(parse("max(1, -2)"), Err(ExpressionError::UnaryMinus(7)));
+        assert_eq!(parse("3 * 2 - 1"), Err(ExpressionError::Subtraction(6)));
+        assert_eq!(parse("1 < 3 - 1"), Err(ExpressionError::Subtraction(6)));
+        assert!(eval_expression("2 - 1")
+            .unwrap_err()
+            .to_string()
+            .contains("no operation subtracts"));
+    }
+
+    #[test]
+    fn test_parse_rejects_ambiguous_numbers() {
+        for expr in [
+            "1__0", "1_", "0x", "0x_1", "010", "1.000", "0o17", "0b102", "0b", "12ab",
+        ] {
+            assert!(
+                matches!(parse(expr), Err(ExpressionError::InvalidNumber { .. })),
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        assert_eq!(parse("1 + 2 * 3").unwrap().eval().unwrap(), 7);
+        assert_eq!(parse("(1 + 2) * 3").unwrap().eval().unwrap(), 9);
+        assert_eq!(parse("1 + 2 < 2 * 2").unwrap().eval().unwrap(), 1);
+        assert!(matches!(
+            parse("1 < 2 < 3"),
+            Err(ExpressionError::ChainedComparison(6))
+        ));
+    }
+
+    #[test]
+    fn test_eval_expression_matches_eval() {
+        for expr in [
+            "7",
+            "1 + 2 * 3 + 4",
+            "max(1 + 2, 3 * 4, min(5, 6))",
+            "(1 < 2) + (3 > 4) + (5 == 5)",
+            "if(1, 10, 20) + if(0, 10, 20)",
+            "if(1, 2, 0xFFFFFFFFFFFFFFFF * 2) + if(0, min(), 3)",
+            "sum() + product() + sum(4)",
+            "min()",
+            "max(1, 2 < 3 < 4)",
+            "lt(1, 2, 3)",
+            "if(1, 2)",
+            "if(min(), 1, 2)",
+            "0xFFFFFFFFFFFFFFFF + 1",
+            "2 * 0xFFFFFFFFFFFFFFFF",
+            "(1 + 2 < 3",
+        ] {
+            let expected = crate::Packet::from_expression(expr)
+                .and_then(|packet| packet.eval_as::<usize>(Overflow::Checked));
+            match (eval_expression(expr), expected) {
+                (Ok(value), Ok(expected)) => assert_eq!(value, expected, "{expr}"),
+                (Err(error), Err(expected)) => {
+                    assert_eq!(error.to_string(), expected.to_string(), "{expr}");
+                }
+                (value, expected) => panic!("{expr}: {value:?} != {expected:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_digit_separators() {
+        let style = ExpressionStyle {
+            digit_separators: true,
+            ..ExpressionStyle::default()
+        };
+        for (value, literal) in [(2021, "2021"), (12345, "12_345"), (1_000_000, "1_000_000")] {
+            let packet = crate::Packet::from(value);
+            assert_eq!(packet.to_expression_styled(&style).unwrap(), literal);
+        }
+        let packet = parse("123456 + 7").unwrap();
+        let styled = packet.to_expression_styled(&style).unwrap();
+        assert_eq!(styled, "123_456 + 7");
+        assert_eq!(parse(&styled).unwrap(), packet);
+    }
+
+    #[test]
+    fn test_radix() {
+        let mut style = ExpressionStyle {
+            radix: Radix::Hexadecimal,
+            radix_threshold: 1 << 32,
+            ..ExpressionStyle::default()
+        };
+        let packet = parse("0x1_0000_0000 + 255").unwrap();
+        assert_eq!(
+            packet.to_expression_styled(&style).unwrap(),
+            "0x100000000 + 255"
+        );
+        style.digit_separators = true;
+        let styled = packet.to_expression_styled(&style).unwrap();
+        assert_eq!(styled, "0x1_0000_0000 + 255");
+        assert_eq!(parse(&styled).unwrap(), packet);
+
+        style.radix = Radix::Binary;
+        style.radix_threshold = 0;
+        let styled = packet.to_expression_styled(&style).unwrap();
+        assert!(styled.ends_with(" + 0b1111_1111"), "{styled}");
+        assert_eq!(parse(&styled).unwrap(), packet);
+        assert_eq!(
+            crate::Packet::from(0).to_expression_styled(&style).unwrap(),
+            "0b0"
+        );
+    }
+
+    #[test]
+    fn test_symbols() {
+        let style = ExpressionStyle {
+            symbols: ExpressionSymbols::unicode()
+                .with(Operation::LessThan, "≺")
+                .with(Operation::If, "when"),
+            ..ExpressionStyle::default()
+        };
+        assert_eq!(style.symbols.get(Operation::Sum), "+");
+        assert_eq!(style.symbols.get(Operation::Minimum), "min");
+        let packet = parse("if(1 < 2 * 3, product(4), 5 == 5)").unwrap();
+        assert_eq!(
+            packet.to_expression_styled(&style).unwrap(),
+            "when((1 ≺ (2 × 3)), (product(4)), (5 = 5))"
+        );
+        assert_eq!(
+            packet.styled(&style).to_string(),
+            packet.to_expression_styled(&style).unwrap()
+        );
+        assert_eq!(
+            packet.styled(&ExpressionStyle::default()).to_string(),
+            packet.to_string()
+        );
+
+        let other = parse("if(1 < 2 * 4, product(4), 5 == 5)").unwrap();
+        let (a, _) = diff_expressions(&packet, &other, &style, &Markers::WORD);
+        assert_eq!(a, "when((1 ≺ (2 × [-3-])), (product(4)), (5 = 5))");
+    }
+
+    #[test]
+    fn test_names() {
+        let names =
+            parse_names("# Deployment constants\nTHRESHOLD = 100\n\nsensor_7 = 0x2A  # probe\n")
+                .unwrap();
+        let style = ExpressionStyle {
+            digit_separators: true,
+            names,
+            ..ExpressionStyle::default()
+        };
+        let packet = parse("100 < 42 + 12345").unwrap();
+        assert_eq!(
+            packet.to_expression_styled(&style).unwrap(),
+            "THRESHOLD < (sensor_7 + 12_345)"
+        );
+
+        for (text, line) in [
+            ("A = 1\nB", 2),
+            ("7up = 1", 1),
+            ("sum = 1", 1),
+            ("A = 1.5", 1),
+            ("A = 1\nA = 2", 2),
+            ("A = 1\n\nB = 1", 3),
+        ] {
+            match parse_names(text) {
+                Err(error @ crate::PacketError::ConfigError { line: l, .. }) => {
+                    assert_eq!(l, line, "{text}");
+                    assert_eq!(error.kind(), ErrorKind::Config);
+                }
+                other => panic!("{text}: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let fix = |src| {
+            let found = diagnostics(src);
+            assert_eq!(found.len(), 1, "{src}");
+            assert_eq!(found[0].severity, Severity::Error, "{src}");
+            (found[0].span.clone(), found[0].fix.clone())
+        };
+        assert_eq!(fix("max(1, min(2"), (12..12, Some("))".to_string())));
+        assert_eq!(fix("1 + summ(2)"), (4..8, Some("sum".to_string())));
+        assert_eq!(fix("frobnicate(2)"), (0..10, None));
+        assert_eq!(fix("1 = 2"), (2..3, Some("==".to_string())));
+        assert_eq!(fix("2(3)"), (1..1, Some("* ".to_string())));
+        assert_eq!(fix("1 + 010"), (4..7, None));
+        assert_eq!(fix("1 + 2 $"), (6..7, None));
+
+        assert_eq!(
+            diagnostics("eq(1) + (2 * if(1, 2, 3))"),
+            [
+                Diagnostic {
+                    span: 0..5,
+                    severity: Severity::Error,
+                    message: "`eq` takes exactly 2 operands, found 1".to_string(),
+                    fix: None,
+                },
+                Diagnostic {
+                    span: 13..24,
+                    severity: Severity::Warning,
+                    message: "`if` is only available in the extended profile".to_string(),
+                    fix: None,
+                }
+            ]
+        );
+        let found = diagnostics("max(product(), (sum()))");
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].span, 4..13);
+        assert_eq!(found[1].message, "`sum()` has no operands and is always 0");
+        assert!(diagnostics("").iter().all(|d| d.span == (0..0)));
+    }
+}
@@ -0,0 +1,133 @@
+//! Reading the individual fields of a packet.
+//!
+//! These are the pieces [`Packet::read_with_profile`](crate::Packet::read_with_profile)
+//! is built from, for decoders that need only part of a transmission. A scan
+//! for packet headers, for instance, can read each header and skip literal
+//! values without building the tree:
+//!
+//! ```
+//! use jacob::bits::{BitCursor, BitReader};
+//! use jacob::fields::{read_header, read_length, read_literal_groups};
+//! use jacob::{bytes_from_hex, Length, Profile};
+//!
+//! let bytes = bytes_from_hex("38006F45291200").unwrap();
+//! let mut reader = BitReader::new(&bytes);
+//! let header = read_header(&mut reader, Profile::Standard).unwrap();
+//! assert_eq!((header.version, header.type_id), (1, 6));
+//! assert_eq!(read_length(&mut reader).unwrap(), Length::TotalBits(27));
+//!
+//! let mut versions = Vec::new();
+//! while reader.position() < 22 + 27 {
+//!     let header = read_header(&mut reader, Profile::Standard).unwrap();
+//!     assert!(header.is_literal());
+//!     read_literal_groups(&mut reader).unwrap();
+//!     versions.push(header.version);
+//! }
+//! assert_eq!(versions, [6, 2]);
+//! ```
+
+use crate::bits::{BitCursor, BitError};
+use crate::{Length, Profile};
+
+/// The version and type ID fields that start every packet, as read and
+/// before either is validated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RawPacketHeader {
+    pub version: u8,
+    pub type_id: u8,
+}
+
+impl RawPacketHeader {
+    /// Type ID of literal packets, in every profile.
+    pub const LITERAL_TYPE_ID: u8 = 4;
+
+    /// Returns `true` if the header starts a literal, so value groups follow
+    /// it rather than a length and operands.
+    #[must_use]
+    pub const fn is_literal(&self) -> bool {
+        self.type_id == Self::LITERAL_TYPE_ID
+    }
+}
+
+/// Reads a packet header, whose type ID is as wide as `profile` specifies.
+///
+/// # Errors
+///
+/// Will return `Err` if too few bits remain.
+pub fn read_header<C: BitCursor>(
+    cursor: &mut C,
+    profile: Profile,
+) -> Result<RawPacketHeader, BitError> {
+    // VVV
+    let version = cursor.read_u8(3)?;
+    // TTT
+    let type_id = cursor.read_u8(u32::from(profile.type_bits()))?;
+    Ok(RawPacketHeader { version, type_id })
+}
+
+/// Reads the value groups of a literal: four bits each, most significant
+/// first, each preceded by a flag that is set if another group follows.
+///
+/// # Errors
+///
+/// Will return `Err` if the input ends before the last group.
+pub fn read_literal_groups<C: BitCursor>(cursor: &mut C) -> Result<usize, BitError> {
+    let mut value: usize = 0;
+    let mut reading = true;
+    // A+, B+, etc...
+    while reading {
+        reading = cursor.read_bool()?;
+        value = value << 4 | usize::from(cursor.read_u8(4)?);
+    }
+    Ok(value)
+}
+
+/// Reads the length type flag and the length of an operator's operands.
+///
+/// # Errors
+///
+/// Will return `Err` if too few bits remain.
+pub fn read_length<C: BitCursor>(cursor: &mut C) -> Result<Length, BitError> {
+    // I
+    Ok(if cursor.read_bool()? {
+        Length::PacketCount(cursor.read_u16(11)?)
+    } else {
+        Length::TotalBits(cursor.read(15)?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_header, read_length, read_literal_groups, RawPacketHeader};
+    use crate::bits::{BitCursor, BitError, BitReader};
+    use crate::{bytes_from_hex, Length, Profile};
+
+    #[test]
+    fn test_fields() {
+        let bytes = bytes_from_hex("D2FE28").unwrap();
+        let mut reader = BitReader::new(&bytes);
+        let header = read_header(&mut reader, Profile::Standard).unwrap();
+        assert_eq!(
+            header,
+            RawPacketHeader {
+                version: 6,
+                type_id: 4
+            }
+        );
+        assert_eq!(read_literal_groups(&mut reader).unwrap(), 2021);
+        assert_eq!(reader.position(), 21);
+
+        let bytes = bytes_from_hex("EE00D40C823060").unwrap();
+        let mut reader = BitReader::new(&bytes);
+        let header = read_header(&mut reader, Profile::Standard).unwrap();
+        assert_eq!((header.version, header.type_id), (7, 3));
+        assert!(!header.is_literal());
+        assert_eq!(read_length(&mut reader).unwrap(), Length::PacketCount(3));
+
+        let mut reader = BitReader::new(&[0x9F]);
+        assert!(matches!(
+            read_literal_groups(&mut reader),
+            Err(BitError::NotEnoughData { .. })
+        ));
+    }
+}
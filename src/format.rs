@@ -0,0 +1,603 @@
+//! Registry of the representations packets are read from and written to.
+//!
+//! Parsers implement [`InputParser`] and renderings implement
+//! [`OutputFormatter`]. Both are looked up by name in a [`Registry`], which
+//! starts out with the built-in formats. Downstream crates add their own
+//! parsers and renderings the same way, without changes to this crate.
+//!
+//! ```
+//! use jacob::format::{InputParser, OutputFormatter, Registry};
+//! use jacob::{Packet, PacketError, ParseOptions};
+//!
+//! /// Literals written as decimal numbers.
+//! struct Decimal;
+//!
+//! impl InputParser for Decimal {
+//!     fn name(&self) -> &str {
+//!         "dec"
+//!     }
+//!
+//!     fn description(&self) -> &str {
+//!         "Literal value in decimal"
+//!     }
+//!
+//!     fn parse(&self, text: &str, _: &ParseOptions) -> Result<Packet, PacketError> {
+//!         Ok(Packet::from(text.trim().parse::<usize>()?))
+//!     }
+//! }
+//!
+//! struct Depth;
+//!
+//! impl OutputFormatter for Depth {
+//!     fn name(&self) -> &str {
+//!         "depth"
+//!     }
+//!
+//!     fn description(&self) -> &str {
+//!         "Number of operator levels"
+//!     }
+//!
+//!     fn render(&self, packet: &Packet) -> Result<String, PacketError> {
+//!         Ok(packet.depth().to_string())
+//!     }
+//! }
+//!
+//! let mut registry = Registry::new();
+//! registry.add_input(Decimal).add_output(Depth);
+//! let packet = Packet::try_from("C200B40A82").unwrap();
+//! assert_eq!(registry.output("depth").unwrap().render(&packet).unwrap(), "1");
+//! assert_eq!(registry.output("hex").unwrap().render(&packet).unwrap(), "C200B40A82");
+//! assert!(registry.find("depth").unwrap().output);
+//!
+//! let packet = registry.input("dec").unwrap().parse("42", &ParseOptions::default()).unwrap();
+//! assert_eq!(registry.output("eval").unwrap().render(&packet).unwrap(), "42");
+//! assert!(!registry.find("dec").unwrap().output);
+//! ```
+//!
+//! Formats are enumerated in order of name, whatever order they were added
+//! in, so listings are the same from run to run however plugins are loaded.
+
+use std::iter::FusedIterator;
+use std::slice;
+
+use crate::encoding::{base64, bin};
+use crate::{report, ExpressionStyle, Packet, PacketError, ParseOptions};
+
+/// A representation of packets, as listed by [`Registry::formats`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Format<'a> {
+    /// Name used to select the format, such as `hex`.
+    pub name: &'a str,
+    pub description: &'a str,
+    /// Packets can be parsed from this format.
+    pub input: bool,
+    /// Packets can be rendered in this format.
+    pub output: bool,
+    /// The format can be processed incrementally, without holding the whole
+    /// text or packet tree in memory.
+    pub streaming: bool,
+}
+
+/// Renders packets in one format.
+pub trait OutputFormatter {
+    /// Returns the name the format is selected by. Names are unique within a
+    /// [`Registry`].
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// Returns `true` if the format can be written incrementally.
+    fn streaming(&self) -> bool {
+        false
+    }
+
+    /// Renders `packet`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the packet cannot be represented in the format.
+    fn render(&self, packet: &Packet) -> Result<String, PacketError>;
+}
+
+/// Parses packets from one format.
+pub trait InputParser {
+    /// Returns the name the format is selected by. Names are unique within a
+    /// [`Registry`].
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// Returns `true` if the format can be read incrementally.
+    fn streaming(&self) -> bool {
+        false
+    }
+
+    /// Parses the packet in `text`. Parsers of encoded packets read them with
+    /// `options`; others may ignore them.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `text` is not a valid packet in the format.
+    fn parse(&self, text: &str, options: &ParseOptions) -> Result<Packet, PacketError>;
+}
+
+/// Formats by name; see the [module documentation](self).
+pub struct Registry {
+    /// Sorted by name.
+    inputs: Vec<Box<dyn InputParser>>,
+    /// Sorted by name.
+    outputs: Vec<Box<dyn OutputFormatter>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Registry {
+    /// Returns a registry of the built-in formats.
+    #[must_use]
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry
+            .add_input(Hex)
+            .add_input(Binary)
+            .add_input(Base64)
+            .add_input(Expression::default())
+            .add_output(Hex)
+            .add_output(Binary)
+            .add_output(Base64)
+            .add_output(Expression::default())
+            .add_output(Eval)
+            .add_output(Mermaid)
+            .add_output(Html);
+        registry
+    }
+
+    /// Returns a registry without any formats.
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Adds an input format, replacing any with the same name.
+    pub fn add_input(&mut self, parser: impl InputParser + 'static) -> &mut Self {
+        self.add_input_boxed(Box::new(parser))
+    }
+
+    /// Adds a boxed input format, replacing any with the same name.
+    pub fn add_input_boxed(&mut self, parser: Box<dyn InputParser>) -> &mut Self {
+        match self
+            .inputs
+            .binary_search_by(|p| p.name().cmp(parser.name()))
+        {
+            Ok(i) => self.inputs[i] = parser,
+            Err(i) => self.inputs.insert(i, parser),
+        }
+        self
+    }
+
+    /// Returns the input format named `name`.
+    #[must_use]
+    pub fn input(&self, name: &str) -> Option<&dyn InputParser> {
+        self.inputs
+            .binary_search_by(|parser| parser.name().cmp(name))
+            .ok()
+            .map(|i| &*self.inputs[i])
+    }
+
+    /// Returns the input formats in order of name.
+    #[must_use]
+    pub fn inputs(&self) -> Inputs<'_> {
+        Inputs(self.inputs.iter())
+    }
+
+    /// Adds an output format, replacing any with the same name.
+    pub fn add_output(&mut self, formatter: impl OutputFormatter + 'static) -> &mut Self {
+        self.add_output_boxed(Box::new(formatter))
+    }
+
+    /// Adds a boxed output format, replacing any with the same name.
+    pub fn add_output_boxed(&mut self, formatter: Box<dyn OutputFormatter>) -> &mut Self {
+        match self.position(formatter.name()) {
+            Ok(i) => self.outputs[i] = formatter,
+            Err(i) => self.outputs.insert(i, formatter),
+        }
+        self
+    }
+
+    fn position(&self, name: &str) -> Result<usize, usize> {
+        self.outputs
+            .binary_search_by(|formatter| formatter.name().cmp(name))
+    }
+
+    /// Returns the output format named `name`.
+    #[must_use]
+    pub fn output(&self, name: &str) -> Option<&dyn OutputFormatter> {
+        self.position(name).ok().map(|i| &*self.outputs[i])
+    }
+
+    /// Returns the output formats in order of name.
+    #[must_use]
+    pub fn outputs(&self) -> Outputs<'_> {
+        Outputs(self.outputs.iter())
+    }
+
+    /// Returns every format in order of name, each once whether it can be
+    /// parsed, rendered, or both.
+    #[must_use]
+    pub fn formats(&self) -> Vec<Format<'_>> {
+        let mut formats: Vec<Format<'_>> = self
+            .inputs()
+            .map(|parser| {
+                let output = self.output(parser.name());
+                Format {
+                    name: parser.name(),
+                    description: parser.description(),
+                    input: true,
+                    output: output.is_some(),
+                    streaming: parser.streaming() && output.is_none_or(OutputFormatter::streaming),
+                }
+            })
+            .collect();
+        formats.extend(
+            self.outputs()
+                .filter(|formatter| self.input(formatter.name()).is_none())
+                .map(|formatter| Format {
+                    name: formatter.name(),
+                    description: formatter.description(),
+                    input: false,
+                    output: true,
+                    streaming: formatter.streaming(),
+                }),
+        );
+        formats.sort_by(|a, b| a.name.cmp(b.name));
+        formats
+    }
+
+    /// Returns the format named `name`.
+    #[must_use]
+    pub fn find(&self, name: &str) -> Option<Format<'_>> {
+        self.formats()
+            .into_iter()
+            .find(|format| format.name == name)
+    }
+}
+
+/// The input formats of a [`Registry`], in order of name.
+#[derive(Clone)]
+pub struct Inputs<'a>(slice::Iter<'a, Box<dyn InputParser>>);
+
+impl<'a> Iterator for Inputs<'a> {
+    type Item = &'a dyn InputParser;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|parser| &**parser)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Inputs<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|parser| &**parser)
+    }
+}
+
+impl ExactSizeIterator for Inputs<'_> {}
+
+impl FusedIterator for Inputs<'_> {}
+
+/// The output formats of a [`Registry`], in order of name.
+#[derive(Clone)]
+pub struct Outputs<'a>(slice::Iter<'a, Box<dyn OutputFormatter>>);
+
+impl<'a> Iterator for Outputs<'a> {
+    type Item = &'a dyn OutputFormatter;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|formatter| &**formatter)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Outputs<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|formatter| &**formatter)
+    }
+}
+
+impl ExactSizeIterator for Outputs<'_> {}
+
+impl FusedIterator for Outputs<'_> {}
+
+/// Hexadecimal digits, two per byte.
+pub struct Hex;
+
+impl InputParser for Hex {
+    fn name(&self) -> &'static str {
+        "hex"
+    }
+
+    fn description(&self) -> &'static str {
+        "Hexadecimal digits, two per byte"
+    }
+
+    fn streaming(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, text: &str, options: &ParseOptions) -> Result<Packet, PacketError> {
+        Packet::from_hex_with(text, options)
+    }
+}
+
+impl OutputFormatter for Hex {
+    fn name(&self) -> &'static str {
+        "hex"
+    }
+
+    fn description(&self) -> &'static str {
+        "Hexadecimal digits, two per byte"
+    }
+
+    fn streaming(&self) -> bool {
+        true
+    }
+
+    fn render(&self, packet: &Packet) -> Result<String, PacketError> {
+        packet.to_hex()
+    }
+}
+
+/// Binary digits, eight per byte.
+pub struct Binary;
+
+impl InputParser for Binary {
+    fn name(&self) -> &'static str {
+        "bin"
+    }
+
+    fn description(&self) -> &'static str {
+        "Binary digits, eight per byte"
+    }
+
+    fn streaming(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, text: &str, options: &ParseOptions) -> Result<Packet, PacketError> {
+        Packet::from_bytes_with(&bin::decode(text)?, options)
+    }
+}
+
+impl OutputFormatter for Binary {
+    fn name(&self) -> &'static str {
+        "bin"
+    }
+
+    fn description(&self) -> &'static str {
+        "Binary digits, eight per byte"
+    }
+
+    fn streaming(&self) -> bool {
+        true
+    }
+
+    fn render(&self, packet: &Packet) -> Result<String, PacketError> {
+        packet.to_bytes().map(|bytes| bin::encode(&bytes))
+    }
+}
+
+/// Standard padded base64.
+pub struct Base64;
+
+impl InputParser for Base64 {
+    fn name(&self) -> &'static str {
+        "base64"
+    }
+
+    fn description(&self) -> &'static str {
+        "Standard padded base64"
+    }
+
+    fn streaming(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, text: &str, options: &ParseOptions) -> Result<Packet, PacketError> {
+        Packet::from_bytes_with(&base64::decode(text)?, options)
+    }
+}
+
+impl OutputFormatter for Base64 {
+    fn name(&self) -> &'static str {
+        "base64"
+    }
+
+    fn description(&self) -> &'static str {
+        "Standard padded base64"
+    }
+
+    fn streaming(&self) -> bool {
+        true
+    }
+
+    fn render(&self, packet: &Packet) -> Result<String, PacketError> {
+        packet.to_bytes().map(|bytes| base64::encode(&bytes))
+    }
+}
+
+/// Mathematical expressions, rendered in a style.
+#[derive(Default)]
+pub struct Expression(pub ExpressionStyle);
+
+impl InputParser for Expression {
+    fn name(&self) -> &'static str {
+        "expr"
+    }
+
+    fn description(&self) -> &'static str {
+        "Mathematical expression, such as `(1 + 3) == (2 * 2)`"
+    }
+
+    fn parse(&self, text: &str, options: &ParseOptions) -> Result<Packet, PacketError> {
+        options.check_input_size(text.len())?;
+        let packet = Packet::from_expression(text)?;
+        options.check_depth(&packet)?;
+        Ok(packet)
+    }
+}
+
+impl OutputFormatter for Expression {
+    fn name(&self) -> &'static str {
+        "expr"
+    }
+
+    fn description(&self) -> &'static str {
+        "Mathematical expression, such as `(1 + 3) == (2 * 2)`"
+    }
+
+    fn render(&self, packet: &Packet) -> Result<String, PacketError> {
+        packet.to_expression_styled(&self.0)
+    }
+}
+
+/// The value a packet evaluates to.
+pub struct Eval;
+
+impl OutputFormatter for Eval {
+    fn name(&self) -> &'static str {
+        "eval"
+    }
+
+    fn description(&self) -> &'static str {
+        "Value the packet evaluates to"
+    }
+
+    fn streaming(&self) -> bool {
+        true
+    }
+
+    fn render(&self, packet: &Packet) -> Result<String, PacketError> {
+        packet.eval().map(|value| value.to_string())
+    }
+}
+
+/// Mermaid flowcharts; see [`Packet::to_mermaid`].
+pub struct Mermaid;
+
+impl OutputFormatter for Mermaid {
+    fn name(&self) -> &'static str {
+        "mermaid"
+    }
+
+    fn description(&self) -> &'static str {
+        "Mermaid flowchart of the packet tree"
+    }
+
+    fn render(&self, packet: &Packet) -> Result<String, PacketError> {
+        Ok(packet.to_mermaid())
+    }
+}
+
+/// HTML reports; see [`report::html`].
+pub struct Html;
+
+impl OutputFormatter for Html {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn description(&self) -> &'static str {
+        "Self-contained HTML page exploring the tree, bits, and values"
+    }
+
+    fn render(&self, packet: &Packet) -> Result<String, PacketError> {
+        report::html(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Base64, Eval, Expression, Hex, OutputFormatter, Registry};
+    use crate::{ExpressionStyle, Packet, ParseOptions};
+
+    #[test]
+    fn test_registry() {
+        let registry = Registry::new();
+        let formats = registry.formats();
+        for (i, format) in formats.iter().enumerate() {
+            assert!(format.input || format.output, "{}", format.name);
+            assert!(
+                formats[..i].iter().all(|other| other.name != format.name),
+                "{}",
+                format.name
+            );
+        }
+        let hex = registry.find("hex").unwrap();
+        assert!(hex.input && hex.output && hex.streaming);
+        let mermaid = registry.find("mermaid").unwrap();
+        assert!(!mermaid.input && mermaid.output);
+        assert!(Registry::empty().output("hex").is_none());
+        assert!(Registry::empty().find("hex").is_none());
+
+        let options = ParseOptions::default();
+        for parser in registry.inputs() {
+            let formatter = registry.output(parser.name()).unwrap();
+            let text = formatter.render(&Packet::from(12345)).unwrap();
+            let packet = parser.parse(&text, &options).unwrap();
+            assert_eq!(packet.eval().unwrap(), 12345, "{}", parser.name());
+        }
+
+        let mut registry = Registry::new();
+        let style = ExpressionStyle {
+            digit_separators: true,
+            ..ExpressionStyle::default()
+        };
+        registry.add_output(Expression(style));
+        assert_eq!(
+            registry.outputs().count(),
+            Registry::new().outputs().count()
+        );
+        let expr = registry.output("expr").unwrap();
+        assert_eq!(expr.render(&Packet::from(12345)).unwrap(), "12_345");
+    }
+
+    #[test]
+    fn test_registry_order() {
+        let mut forward = Registry::empty();
+        forward
+            .add_input(Hex)
+            .add_input(Base64)
+            .add_output(Eval)
+            .add_output(Hex);
+        let mut backward = Registry::empty();
+        backward
+            .add_output(Hex)
+            .add_output(Eval)
+            .add_input(Base64)
+            .add_input(Hex);
+        assert_eq!(forward.formats(), backward.formats());
+        let names: Vec<&str> = forward.formats().iter().map(|format| format.name).collect();
+        assert_eq!(names, ["base64", "eval", "hex"]);
+
+        let registry = Registry::new();
+        let names: Vec<&str> = registry
+            .outputs()
+            .rev()
+            .map(OutputFormatter::name)
+            .collect();
+        assert!(names.windows(2).all(|pair| pair[0] > pair[1]));
+        assert_eq!(registry.inputs().len(), 4);
+    }
+}
@@ -0,0 +1,134 @@
+//! Hashing packets for deduplication.
+//!
+//! [`Packet::content_hash`] feeds a [`Hasher`] the fields of a packet in the
+//! order they are encoded, each as fixed-width little-endian bytes, so the
+//! result does not depend on the platform and nothing is serialized first.
+//! [`Packet::fingerprint`] does the same with a built-in 64-bit FNV-1a hasher,
+//! whose output is stable across runs and releases and so can be stored:
+//!
+//! ```
+//! use std::collections::HashSet;
+//!
+//! use jacob::Packet;
+//!
+//! let mut seen = HashSet::new();
+//! let fresh: Vec<&str> = ["C200B40A82", "D2FE28", "c200b40a82"]
+//!     .into_iter()
+//!     .filter(|hex| seen.insert(hex.parse::<Packet>().unwrap().fingerprint()))
+//!     .collect();
+//! assert_eq!(fresh, ["C200B40A82", "D2FE28"]);
+//! ```
+//!
+//! Like equality, the hash covers versions and how operand lengths are
+//! encoded. Hash the [canonical form](Packet::canonicalize) to treat packets
+//! differing only in those as duplicates.
+
+use std::hash::Hasher;
+
+use crate::fields::RawPacketHeader;
+use crate::{Length, Packet, PacketKind};
+
+impl Packet {
+    /// Writes the fields of the packet and its operands, in encoding order,
+    /// to `hasher`.
+    pub fn content_hash<H: Hasher>(&self, hasher: &mut H) {
+        hasher.write_u8(u8::from(self.version));
+        match &self.kind {
+            PacketKind::Literal(value) => {
+                hasher.write_u8(RawPacketHeader::LITERAL_TYPE_ID);
+                hasher.write(&(*value as u64).to_le_bytes());
+            }
+            PacketKind::Operator {
+                length,
+                operation,
+                packets,
+            } => {
+                hasher.write_u8(u8::from(operation));
+                match length {
+                    Length::TotalBits(bits) => {
+                        hasher.write_u8(0);
+                        hasher.write(&bits.to_le_bytes());
+                    }
+                    Length::PacketCount(count) => {
+                        hasher.write_u8(1);
+                        hasher.write(&count.to_le_bytes());
+                    }
+                }
+                hasher.write(&(packets.len() as u64).to_le_bytes());
+                for packet in packets {
+                    packet.content_hash(hasher);
+                }
+            }
+        }
+    }
+
+    /// Returns a 64-bit hash of the packet that is the same on every
+    /// platform and in every release; see the [module documentation](crate::hash).
+    #[must_use]
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv1a::default();
+        self.content_hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// 64-bit FNV-1a.
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xCBF2_9CE4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+
+    use super::Fnv1a;
+    use crate::Packet;
+
+    #[test]
+    fn test_fingerprint() {
+        let mut hasher = Fnv1a::default();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xAF63_DC4C_8601_EC8C);
+
+        let packets: Vec<Packet> = ["1 + 2", "1 + 2", "2 + 1", "1 + 2 + 0", "3"]
+            .into_iter()
+            .map(|expr| Packet::from_expression(expr).unwrap())
+            .collect();
+        let fingerprints: Vec<u64> = packets.iter().map(Packet::fingerprint).collect();
+        assert_eq!(fingerprints[0], fingerprints[1]);
+        for (i, a) in fingerprints.iter().enumerate().skip(1) {
+            assert!(!fingerprints[i + 1..].contains(a));
+        }
+
+        let hash = |packet: &Packet| {
+            let mut hasher = DefaultHasher::new();
+            packet.content_hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&packets[0]), hash(&packets[1]));
+        assert_ne!(hash(&packets[0]), hash(&packets[2]));
+
+        let versioned: Packet = "38006F45291200".parse().unwrap();
+        assert_ne!(
+            versioned.fingerprint(),
+            versioned.canonicalize().fingerprint()
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Reading files of packets and expressions.
+//!
+//! Input files hold one entry, such as a hex packet or an expression, per
+//! line. `#` starts a comment running to the end of the line, and lines left
+//! blank are skipped, so [`read_entries`] yields only the entries themselves:
+//!
+//! ```
+//! use jacob::input::read_entries;
+//!
+//! let text = "# Samples\n\nD2FE28\n1 + 2  # a sum\n   \n";
+//! let entries: Vec<_> = read_entries(text).map(|entry| entry.text).collect();
+//! assert_eq!(entries, ["D2FE28", "1 + 2"]);
+//! ```
+
+/// A line of an input file holding an entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry<'a> {
+    /// The 1-based number of the line, for error messages.
+    pub line: usize,
+    /// The line without its comment or surrounding whitespace. Never empty.
+    pub text: &'a str,
+}
+
+/// Returns the entries of `text`, skipping comments and blank lines; see the
+/// [module documentation](self).
+pub fn read_entries(text: &str) -> impl Iterator<Item = Entry<'_>> {
+    text.lines().enumerate().filter_map(|(i, line)| {
+        let text = line.split_once('#').map_or(line, |(line, _)| line).trim();
+        (!text.is_empty()).then_some(Entry { line: i + 1, text })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_entries, Entry};
+
+    #[test]
+    fn test_read_entries() {
+        let text = "#!comment\r\n  C200B40A82\t# sum\n\n#\nmax(1,\t2)\r\n# 3\n4";
+        let entries: Vec<Entry> = read_entries(text).collect();
+        assert_eq!(
+            entries,
+            [
+                Entry {
+                    line: 2,
+                    text: "C200B40A82"
+                },
+                Entry {
+                    line: 5,
+                    text: "max(1,\t2)"
+                },
+                Entry { line: 7, text: "4" }
+            ]
+        );
+        assert_eq!(read_entries("").count(), 0);
+        assert_eq!(read_entries("\n  # only comments\n").count(), 0);
+    }
+}
@@ -0,0 +1,511 @@
+//! Structural queries over packet trees.
+//!
+//! Paths are the child indices leading from the root to a packet; the root's
+//! path is empty.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use crate::{Length, Operation, Packet, PacketError, PacketKind};
+
+/// Summary of the literal values in a packet tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiteralStats {
+    pub count: usize,
+    pub min: usize,
+    pub max: usize,
+    /// Sum of every literal, which cannot overflow unlike evaluation.
+    pub sum: u128,
+}
+
+impl LiteralStats {
+    /// Returns the arithmetic mean of the literals.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn mean(&self) -> f64 {
+        self.sum as f64 / self.count as f64
+    }
+}
+
+/// Structurally identical subtrees found by [`Packet::find_duplicates`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    /// Paths of every occurrence, in pre-order.
+    pub paths: Vec<Vec<usize>>,
+    /// Serialized size of one occurrence.
+    pub size_bits: u64,
+}
+
+impl DuplicateGroup {
+    /// Returns the bits saved if every occurrence but one were shared.
+    #[must_use]
+    pub const fn bits_saved(&self) -> u64 {
+        self.size_bits * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// A literal found by [`Packet::literals_with_paths`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LiteralRecord {
+    pub path: Vec<usize>,
+    pub value: usize,
+    /// Operation of the operator directly containing the literal, or `None`
+    /// for a literal root.
+    pub operation: Option<Operation>,
+}
+
+impl Packet {
+    /// Returns every packet in the tree with its path, in pre-order.
+    #[must_use]
+    pub fn packets_with_paths(&self) -> Vec<(Vec<usize>, &Self)> {
+        let mut packets = Vec::new();
+        let mut stack = vec![(Vec::new(), self)];
+        while let Some((path, packet)) = stack.pop() {
+            if let PacketKind::Operator { packets, .. } = &packet.kind {
+                for (i, child) in packets.iter().enumerate().rev() {
+                    let mut child_path = path.clone();
+                    child_path.push(i);
+                    stack.push((child_path, child));
+                }
+            }
+            packets.push((path, packet));
+        }
+        packets
+    }
+
+    /// Returns every literal in the tree with its path and enclosing
+    /// operation, in pre-order.
+    #[must_use]
+    pub fn literals_with_paths(&self) -> Vec<LiteralRecord> {
+        self.packets_with_paths()
+            .into_iter()
+            .filter_map(|(path, packet)| match packet.kind {
+                PacketKind::Literal(value) => {
+                    let operation = path.split_last().and_then(|(_, parent)| {
+                        match self.get(parent).map(|parent| &parent.kind) {
+                            Some(PacketKind::Operator { operation, .. }) => Some(*operation),
+                            _ => None,
+                        }
+                    });
+                    Some(LiteralRecord {
+                        path,
+                        value,
+                        operation,
+                    })
+                }
+                PacketKind::Operator { .. } => None,
+            })
+            .collect()
+    }
+
+    /// Returns the bits every packet in the tree occupies in the standard
+    /// encoding, counted from the start of `self`, in pre-order like
+    /// [`Packet::packets_with_paths`].
+    #[must_use]
+    pub fn bit_spans(&self) -> Vec<Range<u64>> {
+        let mut spans = Vec::new();
+        let mut stack = vec![(0, self)];
+        while let Some((start, packet)) = stack.pop() {
+            spans.push(start..start + packet.bit_len());
+            if let PacketKind::Operator {
+                length, packets, ..
+            } = &packet.kind
+            {
+                let header = match length {
+                    Length::TotalBits(_) => 22,
+                    Length::PacketCount(_) => 18,
+                };
+                let mut offset = start + header;
+                let mut children = Vec::with_capacity(packets.len());
+                for child in packets {
+                    children.push((offset, child));
+                    offset += child.bit_len();
+                }
+                stack.extend(children.into_iter().rev());
+            }
+        }
+        spans
+    }
+
+    /// Returns the packet at `path`, if it exists.
+    #[must_use]
+    pub fn get(&self, path: &[usize]) -> Option<&Self> {
+        path.iter().try_fold(self, |packet, &i| match &packet.kind {
+            PacketKind::Operator { packets, .. } => packets.get(i),
+            PacketKind::Literal(_) => None,
+        })
+    }
+
+    /// Returns how many times each operation occurs in the tree, ordered by type ID.
+    /// Operations that do not occur are omitted.
+    #[must_use]
+    pub fn op_histogram(&self) -> Vec<(Operation, usize)> {
+        let mut histogram: Vec<(Operation, usize)> = Vec::new();
+        for packet in self.flat_packets() {
+            if let PacketKind::Operator { operation, .. } = &packet.kind {
+                match histogram.iter_mut().find(|(op, _)| op == operation) {
+                    Some((_, count)) => *count += 1,
+                    None => histogram.push((*operation, 1)),
+                }
+            }
+        }
+        histogram.sort_by_key(|(operation, _)| u8::from(operation));
+        histogram
+    }
+
+    /// Returns statistics over every literal in the tree, or `None` if it has
+    /// no literals.
+    #[must_use]
+    pub fn literal_stats(&self) -> Option<LiteralStats> {
+        self.flat_packets()
+            .into_iter()
+            .filter_map(|packet| match packet.kind {
+                PacketKind::Literal(value) => Some(value),
+                PacketKind::Operator { .. } => None,
+            })
+            .fold(None, |stats, value| {
+                let stats = stats.unwrap_or(LiteralStats {
+                    count: 0,
+                    min: value,
+                    max: value,
+                    sum: 0,
+                });
+                Some(LiteralStats {
+                    count: stats.count + 1,
+                    min: stats.min.min(value),
+                    max: stats.max.max(value),
+                    sum: stats.sum + value as u128,
+                })
+            })
+    }
+
+    /// Evaluates each direct child of the root, in order. A literal root has
+    /// no children.
+    #[must_use]
+    pub fn eval_per_child(&self) -> Vec<Result<usize, PacketError>> {
+        match &self.kind {
+            PacketKind::Operator { packets, .. } => packets.iter().map(Self::eval).collect(),
+            PacketKind::Literal(_) => Vec::new(),
+        }
+    }
+
+    /// Returns the path of the first packet, in pre-order, at which `self` and
+    /// `other` differ in version, kind, operation, length, literal value, or
+    /// number of operands, or `None` if they are equal.
+    #[must_use]
+    pub fn first_difference(&self, other: &Self) -> Option<Vec<usize>> {
+        let Some((packets, other_packets)) = self.matching_operands(other) else {
+            return Some(Vec::new());
+        };
+        packets
+            .iter()
+            .zip(other_packets)
+            .enumerate()
+            .find_map(|(i, (a, b))| {
+                a.first_difference(b).map(|mut path| {
+                    path.insert(0, i);
+                    path
+                })
+            })
+    }
+
+    /// Returns the paths, in pre-order, of every packet at which `self` and
+    /// `other` differ as in [`first_difference`](Self::first_difference).
+    /// Packets within a differing packet are not compared, so no path is a
+    /// prefix of another.
+    ///
+    /// ```
+    /// use jacob::Packet;
+    ///
+    /// let a = Packet::from_expression("1 + (2 * 3) + 4").unwrap();
+    /// let b = Packet::from_expression("1 + (5 * 3) + max(4)").unwrap();
+    /// assert_eq!(a.differences(&b), [vec![1, 0], vec![2]]);
+    /// ```
+    #[must_use]
+    pub fn differences(&self, other: &Self) -> Vec<Vec<usize>> {
+        let mut paths = Vec::new();
+        self.push_differences(other, &mut Vec::new(), &mut paths);
+        paths
+    }
+
+    fn push_differences(&self, other: &Self, path: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+        let Some((packets, other_packets)) = self.matching_operands(other) else {
+            paths.push(path.clone());
+            return;
+        };
+        for (i, (a, b)) in packets.iter().zip(other_packets).enumerate() {
+            path.push(i);
+            a.push_differences(b, path, paths);
+            path.pop();
+        }
+    }
+
+    /// Returns the operands of `self` and `other`, or `None` if the packets
+    /// differ other than in their operands. Equal literals have no operands.
+    fn matching_operands<'a>(&'a self, other: &'a Self) -> Option<(&'a [Self], &'a [Self])> {
+        if self.version != other.version {
+            return None;
+        }
+        match (&self.kind, &other.kind) {
+            (PacketKind::Literal(a), PacketKind::Literal(b)) => (a == b).then_some((&[], &[])),
+            (
+                PacketKind::Operator {
+                    length,
+                    operation,
+                    packets,
+                },
+                PacketKind::Operator {
+                    length: other_length,
+                    operation: other_operation,
+                    packets: other_packets,
+                },
+            ) => (length == other_length
+                && operation == other_operation
+                && packets.len() == other_packets.len())
+            .then_some((packets, other_packets)),
+            _ => None,
+        }
+    }
+
+    /// Returns groups of identical subtrees of at least `min_size_bits` bits,
+    /// most bits saved first. Subtrees are identical if they serialize to the
+    /// same bits, including versions and length encodings. Groups whose every
+    /// occurrence lies within an occurrence of another group are omitted.
+    #[must_use]
+    pub fn find_duplicates(&self, min_size_bits: u64) -> Vec<DuplicateGroup> {
+        let mut occurrences: HashMap<(Vec<u8>, u64), Vec<Vec<usize>>> = HashMap::new();
+        for (path, packet) in self.packets_with_paths() {
+            if packet.bit_len() < min_size_bits {
+                continue;
+            }
+            if let Ok(bits) = packet.to_bits() {
+                occurrences.entry(bits).or_default().push(path);
+            }
+        }
+        let mut groups: Vec<DuplicateGroup> = occurrences
+            .into_iter()
+            .filter(|(_, paths)| paths.len() > 1)
+            .map(|((_, size_bits), paths)| DuplicateGroup { paths, size_bits })
+            .collect();
+        let duplicated: HashSet<Vec<usize>> = groups
+            .iter()
+            .flat_map(|group| group.paths.iter().cloned())
+            .collect();
+        groups.retain(|group| {
+            !group
+                .paths
+                .iter()
+                .all(|path| (0..path.len()).any(|len| duplicated.contains(&path[..len])))
+        });
+        groups.sort_by(|a, b| {
+            b.bits_saved()
+                .cmp(&a.bits_saved())
+                .then_with(|| a.paths.cmp(&b.paths))
+        });
+        groups
+    }
+
+    /// Returns the path of every operator performing `operation`, in pre-order.
+    #[must_use]
+    pub fn paths_of(&self, operation: Operation) -> Vec<Vec<usize>> {
+        self.packets_with_paths()
+            .into_iter()
+            .filter(|(_, packet)| {
+                matches!(&packet.kind, PacketKind::Operator { operation: op, .. } if *op == operation)
+            })
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    /// Returns the path of every operator without operands, in pre-order.
+    ///
+    /// Such packets parse, but [`Packet::eval`] gives sums and products the
+    /// identity value and fails on everything else; see
+    /// [`EmptyOperator`](crate::width::EmptyOperator) to choose otherwise.
+    #[must_use]
+    pub fn empty_operators(&self) -> Vec<Vec<usize>> {
+        self.packets_with_paths()
+            .into_iter()
+            .filter(|(_, packet)| {
+                matches!(&packet.kind, PacketKind::Operator { packets, .. } if packets.is_empty())
+            })
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    /// Returns an estimate of the bytes the tree allocates on the heap: the
+    /// full capacity of every operand vector, but not the root packet itself,
+    /// which takes `size_of::<Packet>()` wherever it is stored.
+    ///
+    /// The estimate ignores allocator overhead, so it is a lower bound on
+    /// what the tree costs, but comparable between trees and representations.
+    #[must_use]
+    pub fn heap_size(&self) -> usize {
+        match &self.kind {
+            PacketKind::Literal(_) => 0,
+            PacketKind::Operator { packets, .. } => {
+                packets.capacity() * size_of::<Self>()
+                    + packets.iter().map(Self::heap_size).sum::<usize>()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DuplicateGroup, LiteralRecord};
+    use crate::{Operation, Packet, PacketKind};
+
+    #[test]
+    fn test_op_histogram_and_paths() {
+        let packet = Packet::from_expression("(1 * 2) + max(3, 4 * 5) + (6 < 7)").unwrap();
+        assert_eq!(
+            packet.op_histogram(),
+            [
+                (Operation::Sum, 1),
+                (Operation::Product, 2),
+                (Operation::Maximum, 1),
+                (Operation::LessThan, 1)
+            ]
+        );
+        let paths = packet.paths_of(Operation::Product);
+        assert_eq!(paths, [vec![0], vec![1, 1]]);
+        assert_eq!(packet.get(&paths[1]).unwrap().eval().unwrap(), 20);
+        assert!(packet.empty_operators().is_empty());
+        let packet = Packet::from_expression("sum() + max(1, min(), product())").unwrap();
+        assert_eq!(packet.empty_operators(), [vec![0], vec![1, 1], vec![1, 2]]);
+        assert!(packet.get(&[0, 0, 0]).is_none());
+        assert!(packet.paths_of(Operation::EqualTo).is_empty());
+        assert_eq!(
+            packet.packets_with_paths().len(),
+            packet.flat_packets().len()
+        );
+    }
+
+    #[test]
+    fn test_literals_with_paths() {
+        let packet = Packet::from_expression("1 + max(2, 3 * 4)").unwrap();
+        let record = |path: &[usize], value, operation| LiteralRecord {
+            path: path.to_vec(),
+            value,
+            operation: Some(operation),
+        };
+        assert_eq!(
+            packet.literals_with_paths(),
+            [
+                record(&[0], 1, Operation::Sum),
+                record(&[1, 0], 2, Operation::Maximum),
+                record(&[1, 1, 0], 3, Operation::Product),
+                record(&[1, 1, 1], 4, Operation::Product),
+            ]
+        );
+        assert_eq!(
+            Packet::from(7).literals_with_paths(),
+            [LiteralRecord {
+                path: vec![],
+                value: 7,
+                operation: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_heap_size() {
+        let packet_size = size_of::<Packet>();
+        assert_eq!(Packet::from(7).heap_size(), 0);
+        let mut packet = Packet::sum_of([Packet::from(1), Packet::max_of([2, 3])]);
+        assert_eq!(packet.heap_size(), 4 * packet_size);
+        if let PacketKind::Operator { packets, .. } = &mut packet.kind {
+            packets.reserve_exact(8);
+        }
+        assert_eq!(packet.heap_size(), 12 * packet_size);
+    }
+
+    #[test]
+    fn test_bit_spans() {
+        // Total-length (22-bit header) less-than of 10 and 20
+        let packet = Packet::try_from("38006F45291200").unwrap();
+        assert_eq!(packet.bit_spans(), [0..49, 22..33, 33..49]);
+        // Count-length (18-bit header) operators
+        let packet = Packet::from_expression("1 + max(2, 3)").unwrap();
+        assert_eq!(packet.bit_spans(), [0..69, 18..29, 29..69, 47..58, 58..69]);
+    }
+
+    #[test]
+    fn test_first_difference() {
+        let a = Packet::from_expression("1 + max(2, 3 * 4)").unwrap();
+        assert_eq!(a.first_difference(&a), None);
+        let b = Packet::from_expression("1 + max(2, 3 * 5)").unwrap();
+        assert_eq!(a.first_difference(&b), Some(vec![1, 1, 1]));
+        let c = Packet::from_expression("1 + min(2, 3 * 4)").unwrap();
+        assert_eq!(a.first_difference(&c), Some(vec![1]));
+        // The root's length switches from total bits to a packet count
+        let d = Packet::try_from("38006F45291200").unwrap();
+        assert_eq!(d.first_difference(&d.canonicalize()), Some(vec![]));
+    }
+
+    #[test]
+    fn test_differences() {
+        let a = Packet::from_expression("1 + max(2, 3 * 4)").unwrap();
+        assert!(a.differences(&a).is_empty());
+        let b = Packet::from_expression("7 + max(2, 3 * 5)").unwrap();
+        assert_eq!(a.differences(&b), [vec![0], vec![1, 1, 1]]);
+        let c = Packet::from_expression("1 + max(2, 3)").unwrap();
+        assert_eq!(a.differences(&c), [vec![1, 1]]);
+        let d = Packet::from_expression("1 * 2").unwrap();
+        assert_eq!(a.differences(&d), [Vec::<usize>::new()]);
+        for other in [&b, &c, &d] {
+            assert_eq!(
+                a.differences(other).first(),
+                a.first_difference(other).as_ref()
+            );
+        }
+    }
+
+    #[test]
+    fn test_literal_stats_and_eval_per_child() {
+        let packet = Packet::from_expression("(1 * 2) + max(3, 4 * 5) + min()").unwrap();
+        let stats = packet.literal_stats().unwrap();
+        assert_eq!(
+            (stats.count, stats.min, stats.max, stats.sum),
+            (5, 1, 5, 15)
+        );
+        assert!((stats.mean() - 3.0).abs() < f64::EPSILON);
+        assert!(Packet::from_expression("sum()")
+            .unwrap()
+            .literal_stats()
+            .is_none());
+
+        let values = packet.eval_per_child();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values[0].as_ref().unwrap(), &2);
+        assert_eq!(values[1].as_ref().unwrap(), &20);
+        assert!(values[2].is_err());
+        assert!(Packet::from(7).eval_per_child().is_empty());
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let packet = Packet::from_expression("max(1 + 2, 7) + (1 + 2) + max(1 + 2, 7)").unwrap();
+        let sum = packet.get(&[1]).unwrap().bit_len();
+        let max = packet.get(&[0]).unwrap().bit_len();
+        // `1`, `2`, and `7` only occur within larger duplicates
+        assert_eq!(
+            packet.find_duplicates(0),
+            [
+                DuplicateGroup {
+                    paths: vec![vec![0, 0], vec![1], vec![2, 0]],
+                    size_bits: sum,
+                },
+                DuplicateGroup {
+                    paths: vec![vec![0], vec![2]],
+                    size_bits: max,
+                },
+            ]
+        );
+        assert_eq!(packet.find_duplicates(0)[0].bits_saved(), sum * 2);
+        assert_eq!(packet.find_duplicates(sum + 1).len(), 1);
+        assert!(packet.find_duplicates(max + 1).is_empty());
+        assert!(Packet::from(7).find_duplicates(0).is_empty());
+    }
+}
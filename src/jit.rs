@@ -0,0 +1,375 @@
+//! Compilation of packets to native code with cranelift.
+//!
+//! [`Packet::jit_with_variables`] compiles a packet, with the same variables
+//! as [`Packet::compile_with_variables`], to a native function of the host
+//! machine. Compiling takes far longer than a [`Vm`](crate::vm::Vm) run, so
+//! it pays off when one packet is evaluated many times with different
+//! values bound to its variables.
+//!
+//! ```
+//! use jacob::Packet;
+//!
+//! let packet = Packet::from_expression("max(1 + 2, 3 * 4)").unwrap();
+//! assert_eq!(packet.jit().unwrap().run().unwrap(), 12);
+//!
+//! // Replace the literal `4` with a variable
+//! let function = packet.jit_with_variables(&[&[1, 1]]).unwrap();
+//! assert_eq!(function.run_with(&[0]).unwrap(), 3);
+//! assert_eq!(function.run_with(&[10]).unwrap(), 30);
+//! ```
+//!
+//! Only the branch of an `if` that is taken is evaluated, and operators
+//! overflow exactly where [`Packet::eval`] does.
+
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{
+    types, AbiParam, Block, BlockArg, InstBuilder, MemFlagsData, Type, UserFuncName, Value,
+};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Linkage, Module};
+
+use crate::{Operation, Packet, PacketError, PacketKind};
+
+/// Native code taking a pointer to the bound values and a pointer to write
+/// the result to, and returning [`OK`] or the status of an overflow.
+type Code = extern "C" fn(*const usize, *mut usize) -> u32;
+
+/// Status of a run that did not overflow. Overflows return one more than
+/// the type ID of the operation that overflowed.
+const OK: u32 = 0;
+
+/// A packet compiled to native code; see the [module documentation](self).
+pub struct JitFunction {
+    /// Owns the memory `code` lives in. Only `None` while dropping.
+    module: Option<JITModule>,
+    code: Code,
+    variables: usize,
+}
+
+impl JitFunction {
+    /// Returns the number of values [`JitFunction::run_with`] expects.
+    #[must_use]
+    pub const fn variables(&self) -> usize {
+        self.variables
+    }
+
+    /// Runs the function without variables.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the function has variables or a value overflows.
+    pub fn run(&self) -> Result<usize, PacketError> {
+        self.run_with(&[])
+    }
+
+    /// Runs the function with `values` bound to its variables, in order.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the number of values differs from the number of
+    /// variables or a value overflows.
+    pub fn run_with(&self, values: &[usize]) -> Result<usize, PacketError> {
+        if values.len() != self.variables {
+            return Err(PacketError::BindingError {
+                expected: self.variables,
+                found: values.len(),
+            });
+        }
+        let mut result = 0;
+        match (self.code)(values.as_ptr(), &raw mut result) {
+            OK => Ok(result),
+            status => {
+                let operation = u8::try_from(status - 1)
+                    .ok()
+                    .and_then(|id| Operation::try_from(id).ok());
+                Err(PacketError::Overflow(operation))
+            }
+        }
+    }
+}
+
+impl Drop for JitFunction {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // SAFETY: `code` points into the module's memory and is only
+            // reachable through `self`, which is being dropped.
+            unsafe { module.free_memory() };
+        }
+    }
+}
+
+impl std::fmt::Debug for JitFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JitFunction")
+            .field("variables", &self.variables)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Packet {
+    /// Compiles the packet to native code.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any operator has an invalid number of operands,
+    /// or the host machine is not supported.
+    pub fn jit(&self) -> Result<JitFunction, PacketError> {
+        self.jit_with_variables(&[])
+    }
+
+    /// Compiles the packet to native code, replacing the packet at each of
+    /// `paths` with a variable as [`Packet::compile_with_variables`] does.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any compiled operator has an invalid number of
+    /// operands, or the host machine is not supported.
+    pub fn jit_with_variables(&self, paths: &[&[usize]]) -> Result<JitFunction, PacketError> {
+        let error = |e: &dyn std::fmt::Display| PacketError::JitError(e.to_string());
+        let mut flags = settings::builder();
+        flags.set("opt_level", "speed").map_err(|e| error(&e))?;
+        let isa = cranelift_native::builder()
+            .map_err(|e| error(&e))?
+            .finish(settings::Flags::new(flags))
+            .map_err(|e| error(&e))?;
+        let mut module = JITModule::new(JITBuilder::with_isa(isa, default_libcall_names()));
+        let pointer = module.target_config().pointer_type();
+
+        let mut context = module.make_context();
+        context.func.signature.params.push(AbiParam::new(pointer));
+        context.func.signature.params.push(AbiParam::new(pointer));
+        context
+            .func
+            .signature
+            .returns
+            .push(AbiParam::new(types::I32));
+        let id = module
+            .declare_function("packet", Linkage::Local, &context.func.signature)
+            .map_err(|e| error(&e))?;
+        context.func.name = UserFuncName::user(0, id.as_u32());
+
+        let mut function_context = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut context.func, &mut function_context);
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        let overflow = builder.create_block();
+        builder.append_block_param(overflow, types::I32);
+        builder.switch_to_block(entry);
+        let (values, result) = (
+            builder.block_params(entry)[0],
+            builder.block_params(entry)[1],
+        );
+
+        let mut translator = Translator {
+            builder,
+            pointer,
+            values,
+            overflow,
+            paths,
+        };
+        let value = translator.translate(self, &mut Vec::new())?;
+        let mut builder = translator.builder;
+        builder
+            .ins()
+            .store(MemFlagsData::trusted(), value, result, 0);
+        let ok = builder.ins().iconst(types::I32, i64::from(OK));
+        builder.ins().return_(&[ok]);
+        builder.switch_to_block(overflow);
+        let status = builder.block_params(overflow)[0];
+        builder.ins().return_(&[status]);
+        builder.seal_all_blocks();
+        builder.finalize(module.target_config());
+
+        module
+            .define_function(id, &mut context)
+            .map_err(|e| error(&e))?;
+        module.finalize_definitions().map_err(|e| error(&e))?;
+        // SAFETY: the function was declared with the signature of `Code`.
+        let code =
+            unsafe { std::mem::transmute::<*const u8, Code>(module.get_finalized_function(id)) };
+        Ok(JitFunction {
+            module: Some(module),
+            code,
+            variables: paths.len(),
+        })
+    }
+}
+
+/// Lowers packets into the function being built.
+struct Translator<'a, 'p> {
+    builder: FunctionBuilder<'a>,
+    /// Type of `usize` values.
+    pointer: Type,
+    /// Pointer to the values bound to the variables.
+    values: Value,
+    /// Block returning its argument as the status of an overflow.
+    overflow: Block,
+    paths: &'p [&'p [usize]],
+}
+
+impl Translator<'_, '_> {
+    /// Emits code evaluating `packet`, found at `path`, and returns its value.
+    fn translate(&mut self, packet: &Packet, path: &mut Vec<usize>) -> Result<Value, PacketError> {
+        if let Some(variable) = self.paths.iter().position(|p| *p == &path[..]) {
+            let offset = i32::try_from(variable * self.pointer.bytes() as usize)
+                .map_err(|_| PacketError::JitError("too many variables".to_string()))?;
+            let flags = MemFlagsData::trusted();
+            return Ok(self
+                .builder
+                .ins()
+                .load(self.pointer, flags, self.values, offset));
+        }
+        let (operation, packets) = match &packet.kind {
+            PacketKind::Literal(value) => return Ok(self.constant(*value)),
+            PacketKind::Operator {
+                operation, packets, ..
+            } => (*operation, packets),
+        };
+        if !operation.info().arity.accepts(packets.len()) {
+            return Err(PacketError::ArgumentError {
+                count: packets.len(),
+                operation,
+                path: path.clone().into(),
+            });
+        }
+        let mut operand = |translator: &mut Self, i: usize| {
+            path.push(i);
+            let value = translator.translate(&packets[i], path);
+            path.pop();
+            value
+        };
+        if operation == Operation::If {
+            let condition = operand(self, 0)?;
+            let (nonzero, zero) = (self.builder.create_block(), self.builder.create_block());
+            let end = self.builder.create_block();
+            self.builder.append_block_param(end, self.pointer);
+            self.builder.ins().brif(condition, nonzero, &[], zero, &[]);
+            for (block, i) in [(nonzero, 1), (zero, 2)] {
+                self.builder.switch_to_block(block);
+                let value = operand(self, i)?;
+                self.builder.ins().jump(end, &[BlockArg::Value(value)]);
+            }
+            self.builder.switch_to_block(end);
+            return Ok(self.builder.block_params(end)[0]);
+        }
+        // Every operand is evaluated before any is folded, as in a `Vm`
+        let operands = (0..packets.len())
+            .map(|i| operand(self, i))
+            .collect::<Result<Vec<_>, _>>()?;
+        let identity = operation.identity().map(|identity| self.constant(identity));
+        let mut operands = identity.into_iter().chain(operands);
+        let mut acc = operands
+            .next()
+            .expect("operations without an identity take an operand");
+        for x in operands {
+            acc = self.fold(operation, acc, x);
+        }
+        Ok(acc)
+    }
+
+    fn constant(&mut self, value: usize) -> Value {
+        // The immediate holds the bits of the value
+        #[allow(clippy::cast_possible_wrap)]
+        self.builder.ins().iconst(self.pointer, value as i64)
+    }
+
+    /// Emits [`Operation::fold`] of `acc` and `x`, branching to the overflow
+    /// block if it overflows.
+    fn fold(&mut self, operation: Operation, acc: Value, x: Value) -> Value {
+        let ins = self.builder.ins();
+        let (value, overflowed) = match operation {
+            Operation::Sum => ins.uadd_overflow(acc, x),
+            Operation::Product => ins.umul_overflow(acc, x),
+            Operation::Minimum => return ins.umin(acc, x),
+            Operation::Maximum => return ins.umax(acc, x),
+            Operation::GreaterThan | Operation::LessThan | Operation::EqualTo => {
+                let condition = match operation {
+                    Operation::GreaterThan => IntCC::UnsignedGreaterThan,
+                    Operation::LessThan => IntCC::UnsignedLessThan,
+                    _ => IntCC::Equal,
+                };
+                let flag = ins.icmp(condition, acc, x);
+                return self.builder.ins().uextend(self.pointer, flag);
+            }
+            Operation::If => unreachable!("`if` is lowered to branches"),
+        };
+        let status = self
+            .builder
+            .ins()
+            .iconst(types::I32, i64::from(u8::from(operation)) + 1);
+        let next = self.builder.create_block();
+        self.builder.ins().brif(
+            overflowed,
+            self.overflow,
+            &[BlockArg::Value(status)],
+            next,
+            &[],
+        );
+        self.builder.switch_to_block(next);
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ErrorKind, Operation, Packet, PacketError};
+
+    #[test]
+    fn test_run_matches_eval() {
+        for expr in [
+            "7",
+            "1 + 2 + 3",
+            "max(1 + 2, 3 * 4, min(5, 6))",
+            "if((1 < 2), 10, (3 * 4))",
+            "if(0, 10, if((5 == 5), (2 > 1), 8))",
+            "sum() + product()",
+            "(3 > 2) + (2 > 3) + (4 == 4) + (1 < 0)",
+        ] {
+            let packet = Packet::from_expression(expr).unwrap();
+            assert_eq!(
+                packet.jit().unwrap().run().unwrap(),
+                packet.eval().unwrap(),
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_variables() {
+        let packet = Packet::from_expression("if(1, 2 * 3, 4) + 5").unwrap();
+        let function = packet.jit_with_variables(&[&[0, 1, 0], &[1]]).unwrap();
+        assert_eq!(function.variables(), 2);
+        assert_eq!(function.run_with(&[10, 1]).unwrap(), 31);
+        assert_eq!(function.run_with(&[0, 0]).unwrap(), 0);
+        assert!(matches!(
+            function.run(),
+            Err(PacketError::BindingError {
+                expected: 2,
+                found: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn test_errors() {
+        let max = usize::MAX;
+        let packet = Packet::from_expression(&format!("{max} + 1")).unwrap();
+        assert!(matches!(
+            packet.jit().unwrap().run(),
+            Err(PacketError::Overflow(Some(Operation::Sum)))
+        ));
+        let packet = Packet::from_expression(&format!("1 + ({max} * 2)")).unwrap();
+        assert!(matches!(
+            packet.jit().unwrap().run(),
+            Err(PacketError::Overflow(Some(Operation::Product)))
+        ));
+        // The branch not taken would overflow
+        let packet = Packet::from_expression(&format!("if(1, 2, {max} + 1)")).unwrap();
+        assert_eq!(packet.jit().unwrap().run().unwrap(), 2);
+
+        let packet = Packet::from_expression("1 + gt(1, 2, 3)").unwrap();
+        assert_eq!(packet.jit().unwrap_err().kind(), ErrorKind::Arity);
+    }
+}
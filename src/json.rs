@@ -0,0 +1,555 @@
+//! A JSON bridge without dependencies.
+//!
+//! [`Value`] is a small JSON document model, printed with [`Display`] and
+//! parsed with [`FromStr`]. [`Packet::to_json_value`] and
+//! [`Packet::from_json_value`] map packets to and from it by hand, so builds
+//! that avoid serde still get structured JSON. Literals map to
+//! `{"version": 6, "literal": 2021}`, and operators to
+//! `{"version": 1, "operation": "lt", "length": {"bits": 27}, "packets": [...]}`,
+//! with `{"packets": 3}` for lengths counted in packets.
+//!
+//! ```
+//! use jacob::json::Value;
+//! use jacob::Packet;
+//!
+//! let packet: Packet = "D2FE28".parse().unwrap();
+//! let json = packet.to_json_value().to_string();
+//! assert_eq!(json, r#"{"version":6,"literal":2021}"#);
+//!
+//! let value: Value = json.parse().unwrap();
+//! assert_eq!(Packet::from_json_value(&value).unwrap(), packet);
+//! ```
+//!
+//! Parsing follows RFC 8259, except that numbers must be unsigned integers,
+//! which is all a packet holds; documents with negative or fractional numbers
+//! are rejected. Arrays and objects may nest at most [`MAX_DEPTH`] levels
+//! deep, both when parsing and when building a packet, so hostile documents
+//! cannot exhaust the stack.
+//!
+//! [`Display`]: fmt::Display
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Length, Operation, Packet, PacketError, PacketKind, Version};
+
+/// How deeply arrays and objects may nest in a document.
+pub const MAX_DEPTH: usize = 256;
+
+/// A JSON value. Object members keep their order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(u64),
+    String(String),
+    Array(Vec<Self>),
+    Object(Vec<(String, Self)>),
+}
+
+impl Value {
+    /// Returns the member `key` of an object, or `None` if `self` is not an
+    /// object or has no such member.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Object(members) => members
+                .iter()
+                .find_map(|(name, value)| (name == key).then_some(value)),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_array(&self) -> Option<&[Self]> {
+        match self {
+            Self::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("null"),
+            Self::Bool(bool) => write!(f, "{bool}"),
+            Self::Number(number) => write!(f, "{number}"),
+            Self::String(string) => write_string(f, string),
+            Self::Array(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_str("]")
+            }
+            Self::Object(members) => {
+                f.write_str("{")?;
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, name)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in string.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", u32::from(c))?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl FromStr for Value {
+    type Err = PacketError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            text: s,
+            position: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position < s.len() {
+            return Err(PacketError::EncodingError("trailing characters after JSON"));
+        }
+        Ok(value)
+    }
+}
+
+/// Recursive descent over a JSON document.
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+    /// How many arrays and objects enclose the current position.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// Skips the whitespace RFC 8259 allows: spaces, tabs, and line breaks.
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.position..];
+        self.position += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), PacketError> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(PacketError::EncodingError("malformed JSON")),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Value) -> Result<Value, PacketError> {
+        if self.text[self.position..].starts_with(keyword) {
+            self.position += keyword.len();
+            Ok(value)
+        } else {
+            Err(PacketError::EncodingError("malformed JSON"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, PacketError> {
+        self.skip_whitespace();
+        if matches!(self.peek(), Some('[' | '{')) {
+            if self.depth >= MAX_DEPTH {
+                return Err(PacketError::EncodingError("JSON nested too deeply"));
+            }
+            self.depth += 1;
+            let value = self.container();
+            self.depth -= 1;
+            return value;
+        }
+        match self.peek() {
+            Some('n') => self.keyword("null", Value::Null),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('0'..='9') => {
+                let rest = &self.text[self.position..];
+                let digits =
+                    rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+                if matches!(rest[digits..].chars().next(), Some('.' | 'e' | 'E')) {
+                    return Err(PacketError::EncodingError("JSON numbers must be integers"));
+                }
+                if digits > 1 && rest.starts_with('0') {
+                    return Err(PacketError::EncodingError("leading zero in JSON number"));
+                }
+                self.position += digits;
+                Ok(Value::Number(rest[..digits].parse()?))
+            }
+            Some('-') => Err(PacketError::EncodingError("JSON numbers must be unsigned")),
+            _ => Err(PacketError::EncodingError("malformed JSON")),
+        }
+    }
+
+    /// Parses the array or object starting at the current position.
+    fn container(&mut self) -> Result<Value, PacketError> {
+        match self.peek() {
+            Some('[') => {
+                self.next();
+                let mut values = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.next();
+                    return Ok(Value::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.next() {
+                        Some(',') => {}
+                        Some(']') => return Ok(Value::Array(values)),
+                        _ => return Err(PacketError::EncodingError("malformed JSON array")),
+                    }
+                }
+            }
+            Some('{') => {
+                self.next();
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.next();
+                    return Ok(Value::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let name = self.string()?;
+                    self.expect(':')?;
+                    members.push((name, self.value()?));
+                    self.skip_whitespace();
+                    match self.next() {
+                        Some(',') => {}
+                        Some('}') => return Ok(Value::Object(members)),
+                        _ => return Err(PacketError::EncodingError("malformed JSON object")),
+                    }
+                }
+            }
+            _ => Err(PacketError::EncodingError("malformed JSON")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, PacketError> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => string.push(match self.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('/') => '/',
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('u') => {
+                        let mut code = self.code_unit()?;
+                        // Characters outside the BMP are escaped as a surrogate pair
+                        if (0xD800..0xDC00).contains(&code) {
+                            if !self.text[self.position..].starts_with("\\u") {
+                                return Err(PacketError::EncodingError("unpaired JSON surrogate"));
+                            }
+                            self.position += 2;
+                            let low = self.code_unit()?;
+                            if !(0xDC00..0xE000).contains(&low) {
+                                return Err(PacketError::EncodingError("unpaired JSON surrogate"));
+                            }
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                        }
+                        char::from_u32(code)
+                            .ok_or(PacketError::EncodingError("unpaired JSON surrogate"))?
+                    }
+                    _ => return Err(PacketError::EncodingError("malformed JSON escape")),
+                }),
+                Some('\0'..='\u{1f}') => {
+                    return Err(PacketError::EncodingError(
+                        "unescaped control character in JSON",
+                    ))
+                }
+                Some(c) => string.push(c),
+                None => return Err(PacketError::EncodingError("unterminated JSON string")),
+            }
+        }
+    }
+
+    /// Reads the four hex digits of a `\u` escape.
+    fn code_unit(&mut self) -> Result<u32, PacketError> {
+        let hex = self
+            .text
+            .get(self.position..self.position + 4)
+            .filter(|hex| hex.bytes().all(|byte| byte.is_ascii_hexdigit()))
+            .ok_or(PacketError::EncodingError("malformed JSON escape"))?;
+        self.position += 4;
+        Ok(u32::from_str_radix(hex, 16)?)
+    }
+}
+
+impl Packet {
+    /// Returns the packet as a JSON value; see the [module documentation](crate::json).
+    #[must_use]
+    pub fn to_json_value(&self) -> Value {
+        let version = (
+            "version".to_string(),
+            Value::Number(u64::from(self.version.get())),
+        );
+        match &self.kind {
+            PacketKind::Literal(value) => Value::Object(vec![
+                version,
+                ("literal".to_string(), Value::Number(*value as u64)),
+            ]),
+            PacketKind::Operator {
+                length,
+                operation,
+                packets,
+            } => {
+                let length = match length {
+                    Length::TotalBits(bits) => ("bits".to_string(), Value::Number(*bits)),
+                    Length::PacketCount(count) => {
+                        ("packets".to_string(), Value::Number(u64::from(*count)))
+                    }
+                };
+                Value::Object(vec![
+                    version,
+                    (
+                        "operation".to_string(),
+                        Value::String(operation.as_func_str().to_string()),
+                    ),
+                    ("length".to_string(), Value::Object(vec![length])),
+                    (
+                        "packets".to_string(),
+                        Value::Array(packets.iter().map(Self::to_json_value).collect()),
+                    ),
+                ])
+            }
+        }
+    }
+
+    /// Builds a packet from a JSON value in the layout written by
+    /// [`to_json_value`](Self::to_json_value).
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a member is missing or out of range, an
+    /// operation is unknown, or operators nest more than [`MAX_DEPTH`]
+    /// levels deep.
+    pub fn from_json_value(value: &Value) -> Result<Self, PacketError> {
+        Self::from_json_value_at(value, 0)
+    }
+
+    /// Builds a packet nested under `depth` operators from a JSON value.
+    fn from_json_value_at(value: &Value, depth: usize) -> Result<Self, PacketError> {
+        let field = |value: &Value, key| {
+            value
+                .get(key)
+                .and_then(Value::as_u64)
+                .ok_or(PacketError::EncodingError("missing or invalid JSON member"))
+        };
+        let version = u8::try_from(field(value, "version")?)
+            .ok()
+            .and_then(Version::new)
+            .ok_or(PacketError::EncodingError("version out of range"))?;
+        if let Some(literal) = value.get("literal") {
+            let literal = literal
+                .as_u64()
+                .and_then(|literal| usize::try_from(literal).ok())
+                .ok_or(PacketError::EncodingError("literal out of range"))?;
+            return Ok(Self {
+                version,
+                kind: PacketKind::Literal(literal),
+            });
+        }
+        let operation = value
+            .get("operation")
+            .and_then(Value::as_str)
+            .and_then(Operation::from_func_str)
+            .ok_or(PacketError::EncodingError("unknown operation"))?;
+        if depth >= MAX_DEPTH {
+            return Err(PacketError::EncodingError("JSON nested too deeply"));
+        }
+        let length = value
+            .get("length")
+            .ok_or(PacketError::EncodingError("missing or invalid JSON member"))?;
+        let length = if length.get("bits").is_some() {
+            Length::TotalBits(field(length, "bits")?)
+        } else {
+            Length::PacketCount(
+                u16::try_from(field(length, "packets")?)
+                    .map_err(|_| PacketError::EncodingError("length out of range"))?,
+            )
+        };
+        let packets = value
+            .get("packets")
+            .and_then(Value::as_array)
+            .ok_or(PacketError::EncodingError("missing or invalid JSON member"))?
+            .iter()
+            .map(|packet| Self::from_json_value_at(packet, depth + 1))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            version,
+            kind: PacketKind::Operator {
+                length,
+                operation,
+                packets,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Value, MAX_DEPTH};
+    use crate::Packet;
+
+    #[test]
+    fn test_json_value() {
+        for hex in [
+            "38006F45291200",
+            "EE00D40C823060",
+            "9C0141080250320F1802104A08",
+        ] {
+            let packet: Packet = hex.parse().unwrap();
+            let json = packet.to_json_value().to_string();
+            let value: Value = json.parse().unwrap();
+            assert_eq!(Packet::from_json_value(&value).unwrap(), packet);
+        }
+        assert_eq!(
+            "38006F45291200"
+                .parse::<Packet>()
+                .unwrap()
+                .to_json_value()
+                .to_string(),
+            r#"{"version":1,"operation":"lt","length":{"bits":27},"packets":[{"version":6,"literal":10},{"version":2,"literal":20}]}"#
+        );
+
+        let value: Value = r#" { "a" : [ null, true, false, "x\"é\n" ], "b": {} } "#
+            .parse()
+            .unwrap();
+        assert_eq!(
+            value.get("a").and_then(Value::as_array).unwrap()[3],
+            Value::String("x\"\u{e9}\n".to_string())
+        );
+        assert_eq!(
+            value.to_string(),
+            r#"{"a":[null,true,false,"x\"é\n"],"b":{}}"#
+        );
+
+        for invalid in ["", "[1,]", "{\"a\" 1}", "-1", "1.5", "\"abc", "nul", "[] x"] {
+            assert!(invalid.parse::<Value>().is_err(), "{invalid}");
+        }
+        for invalid in [
+            r#"{"version":8,"literal":1}"#,
+            r#"{"version":0,"operation":"pow","length":{"bits":0},"packets":[]}"#,
+            r#"{"version":0,"operation":"sum","length":{"packets":70000},"packets":[]}"#,
+            r#"{"version":0}"#,
+        ] {
+            let value: Value = invalid.parse().unwrap();
+            assert!(Packet::from_json_value(&value).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_json_grammar() {
+        assert_eq!(
+            " \t\r\n[0, 10]\n".parse::<Value>().unwrap().to_string(),
+            "[0,10]"
+        );
+        for invalid in [
+            "01",
+            "[1, 007]",
+            "\u{a0}1",
+            "[1,\u{2003}2]",
+            "1\u{feff}",
+            "\"a\u{1}b\"",
+            "\"tab\there\"",
+        ] {
+            assert!(invalid.parse::<Value>().is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_json_surrogate_pairs() {
+        let value: Value = r#""\uD83D\uDE00 \u00e9""#.parse().unwrap();
+        assert_eq!(value, Value::String("\u{1F600} \u{e9}".to_string()));
+        for invalid in [
+            r#""\uD83D""#,
+            r#""\uD83Dx""#,
+            r#""\uDE00""#,
+            r#""\uD83D\u0041""#,
+        ] {
+            assert!(invalid.parse::<Value>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_json_depth() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(nested(MAX_DEPTH).parse::<Value>().is_ok());
+        assert!(nested(MAX_DEPTH + 1).parse::<Value>().is_err());
+        assert!(nested(100_000).parse::<Value>().is_err());
+
+        let sum = |value| {
+            Value::Object(vec![
+                ("version".to_string(), Value::Number(0)),
+                ("operation".to_string(), Value::String("sum".to_string())),
+                (
+                    "length".to_string(),
+                    Value::Object(vec![("packets".to_string(), Value::Number(1))]),
+                ),
+                ("packets".to_string(), Value::Array(vec![value])),
+            ])
+        };
+        let mut value: Value = r#"{"version":0,"literal":1}"#.parse().unwrap();
+        for _ in 0..MAX_DEPTH {
+            value = sum(value);
+        }
+        assert!(Packet::from_json_value(&value).is_ok());
+        let value = sum(value);
+        assert!(Packet::from_json_value(&value).is_err());
+    }
+}
@@ -1,48 +1,349 @@
 #![warn(clippy::all, clippy::pedantic, clippy::nursery)]
 
+use std::cmp::Ordering;
 use std::str::FromStr;
 
-use bitreader::BitReader;
-use bitstream_io::{BigEndian, BitWrite, BitWriter};
-use itertools::Itertools;
 use thiserror::Error;
 
-const SUM_FUNC: &str = "sum";
-const SUM_SYMBOL: &str = "+";
-const PRODUCT_FUNC: &str = "product";
-const PRODUCT_SYMBOL: &str = "*";
-const MINIMUM_FUNC: &str = "min";
-const MAXIMUM_FUNC: &str = "max";
-const GREATER_THAN_FUNC: &str = "gt";
-const GREATER_THAN_SYMBOL: &str = ">";
-const LESS_THAN_FUNC: &str = "lt";
-const LESS_THAN_SYMBOL: &str = "<";
-const EQUAL_TO_FUNC: &str = "eq";
-const EQUAL_TO_SYMBOL: &str = "==";
+use bits::{BitCursor, BitError, BitReader, BitWriter};
+use diff::Marks;
+use fields::RawPacketHeader;
 
+pub mod batch;
+pub mod bits;
+pub mod builder;
+#[cfg(feature = "capture")]
+pub mod capture;
+pub mod classify;
+pub mod const_eval;
+pub mod corpus;
+#[cfg(feature = "crypto")]
+pub mod crypto;
+pub mod cst;
+pub mod delta;
+pub mod diff;
+pub mod encoding;
+pub mod envelope;
+pub mod evaluator;
+pub mod examples;
+pub mod expression;
+pub mod fields;
+pub mod format;
+pub mod hash;
+pub mod input;
+pub mod inspect;
+#[cfg(feature = "jit")]
+pub mod jit;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod mermaid;
+pub mod minimize;
+pub mod options;
+pub mod partial;
+pub mod pass;
+pub mod path;
+pub mod prelude;
+pub mod profile;
+pub mod report;
+pub mod rle;
+mod rng;
+pub mod scan;
+pub mod schema;
+pub mod shared;
+pub mod stream;
+pub mod stress;
+pub mod template;
+pub mod transform;
+pub mod transmission;
+pub mod version;
+pub mod vm;
+pub mod warnings;
+pub mod width;
+
+pub use batch::eval_batch;
+pub use encoding::hex::{decode as bytes_from_hex, encode as hex_from_bytes};
+pub use evaluator::Evaluator;
+pub use expression::{
+    eval_expression, ExpressionError, ExpressionOptions, ExpressionStyle, ExpressionSymbols, Radix,
+};
+pub use minimize::minimize;
+pub use options::{ParseOptions, TrailingPadding};
+pub use pass::{Pass, PassManager};
+pub use path::PacketPath;
+pub use profile::Profile;
+pub use version::Version;
+pub use warnings::{Warning, Warnings};
+pub use width::EvalOptions;
+
+/// Broad category of a [`PacketError`], stable across additions of new
+/// variants and payload fields.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The input ended before the packet did.
+    Incomplete,
+    /// An operator has the wrong number of operands.
+    Arity,
+    /// A type ID does not name an operation.
+    InvalidOperator,
+    /// An operation is not available in the chosen profile.
+    Unsupported,
+    /// A literal could not be decoded.
+    InvalidLiteral,
+    /// A length field is inconsistent with the packet or input.
+    Length,
+    /// Bits after the packet violate the padding policy.
+    Padding,
+    /// A value does not fit in the chosen integer type.
+    Overflow,
+    /// An envelope, signature, capture, or patch around the packet is invalid.
+    Container,
+    /// Hexadecimal, binary, or base64 input is malformed.
+    Encoding,
+    /// Reading or writing bytes failed.
+    Io,
+    /// A mathematical expression is malformed.
+    Expression,
+    /// A user-supplied table or configuration file is malformed.
+    Config,
+    /// A back-reference does not point to an earlier packet.
+    Reference,
+    /// A version does not fit in its 3-bit field.
+    Version,
+    /// The values bound to compiled bytecode do not match its variables.
+    Binding,
+    /// The input exceeds a limit set in [`ParseOptions`].
+    Limit,
+    /// Compiling a packet to native code failed.
+    Jit,
+}
+
+/// Errors produced while decoding, encoding, transforming, or evaluating
+/// packets.
+///
+/// New variants and fields may be added in minor releases; match on
+/// [`PacketError::kind`] for stable handling.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum PacketError {
-    #[error("incomplete packet bits")]
-    BitsError(#[from] bitreader::BitReaderError),
-    #[error("invalid number of arguments `{0}` for operation `{1:?}`")]
-    ArgumentError(usize, Operation),
-    #[error("invalid operator ID `{0}`")]
-    OperatorError(u8),
+    #[error("{0}")]
+    BitsError(#[from] BitError),
+    #[error("invalid number of arguments `{count}` for operation `{operation:?}` at path {path}")]
+    ArgumentError {
+        count: usize,
+        operation: Operation,
+        /// Path of the offending operator.
+        path: PacketPath,
+    },
+    #[error("invalid operator ID `{id}`{}", .bit_offset.map_or(String::new(), |offset| format!(" at bit {offset}, path {path}")))]
+    OperatorError {
+        id: u8,
+        /// Path of the offending packet, if raised while parsing.
+        path: PacketPath,
+        /// Offset of the offending packet from the start of the input, if
+        /// raised while parsing.
+        bit_offset: Option<u64>,
+    },
+    #[error("length {length:?} of operator at path {path} does not fit in its field")]
+    LengthOverflow { length: Length, path: PacketPath },
+    #[error("invalid configuration at line {line}: {reason}")]
+    ConfigError { line: usize, reason: &'static str },
+    #[error("reference at bit {bit_offset} points {distance} bits back, where no packet ends")]
+    DanglingReference { distance: u64, bit_offset: u64 },
+    #[error("version {0} does not fit in 3 bits")]
+    VersionError(u8),
+    #[error("expected {expected} bound values, found {found}")]
+    BindingError { expected: usize, found: usize },
+    #[error("authentication failed: {0}")]
+    AuthenticationError(&'static str),
+    #[error("native compilation failed: {0}")]
+    JitError(String),
+    #[error("invalid capture: {0}")]
+    CaptureError(&'static str),
+    #[error("invalid encoding: {0}")]
+    EncodingError(&'static str),
+    #[error("invalid envelope: {0}")]
+    EnvelopeError(&'static str),
+    #[error("invalid patch: {0}")]
+    PatchError(&'static str),
+    #[error("operation `{0:?}` cannot be encoded in the `{1:?}` profile")]
+    UnsupportedOperation(Operation, Profile),
+    #[error("value overflowed while evaluating {}", .0.map_or("a literal".to_string(), |op| format!("operation `{op:?}`")))]
+    Overflow(Option<Operation>),
+    #[error("non-zero padding bit at offset {offset}")]
+    NonZeroPadding { offset: u64 },
+    #[error("invalid template: {0}")]
+    TemplateError(&'static str),
+    #[error("{count} bits remain after the packet")]
+    TrailingBits { count: u64 },
     #[error("malformed literal value")]
     ValueError,
+    #[error("input of {size} bytes exceeds the limit of {limit}")]
+    InputTooLarge { size: usize, limit: usize },
+    #[error("operator at path {path} is nested deeper than the limit of {limit}")]
+    TooDeep { limit: usize, path: PacketPath },
+    #[error("shared transmission expands to more than {limit} packets at bit {bit_offset}")]
+    ExpansionTooLarge {
+        limit: usize,
+        /// Offset of the packet that exceeded the limit from the start of
+        /// the input.
+        bit_offset: u64,
+    },
+    #[error("operator at bit {bit_offset}, path {path} declares {declared:?} but only {available} bits remain")]
+    TruncatedOperator {
+        declared: Length,
+        available: u64,
+        path: PacketPath,
+        /// Offset of the operator from the start of the input.
+        bit_offset: u64,
+    },
     #[error(transparent)]
     HexError(#[from] std::num::ParseIntError),
+    #[error("failed to read bytes")]
+    ReadError(std::io::Error),
     #[error("failed to write bytes")]
     WriteError(#[from] std::io::Error),
+    #[error(transparent)]
+    ExpressionError(#[from] ExpressionError),
+}
+
+impl PacketError {
+    /// Returns the category of the error.
+    #[must_use]
+    pub const fn kind(&self) -> ErrorKind {
+        match self {
+            Self::BitsError(BitError::NotEnoughData { .. }) => ErrorKind::Incomplete,
+            Self::ArgumentError { .. } => ErrorKind::Arity,
+            Self::OperatorError { .. } => ErrorKind::InvalidOperator,
+            Self::UnsupportedOperation(..) => ErrorKind::Unsupported,
+            Self::ValueError => ErrorKind::InvalidLiteral,
+            Self::LengthOverflow { .. }
+            | Self::TruncatedOperator { .. }
+            | Self::TrailingBits { .. } => ErrorKind::Length,
+            Self::NonZeroPadding { .. } => ErrorKind::Padding,
+            Self::InputTooLarge { .. } | Self::TooDeep { .. } | Self::ExpansionTooLarge { .. } => {
+                ErrorKind::Limit
+            }
+            Self::Overflow(_) | Self::BitsError(BitError::ValueTooWide { .. }) => {
+                ErrorKind::Overflow
+            }
+            Self::AuthenticationError(_)
+            | Self::CaptureError(_)
+            | Self::EnvelopeError(_)
+            | Self::PatchError(_) => ErrorKind::Container,
+            Self::HexError(_) | Self::EncodingError(_) | Self::TemplateError(_) => {
+                ErrorKind::Encoding
+            }
+            Self::ReadError(_) | Self::WriteError(_) => ErrorKind::Io,
+            Self::ExpressionError(_) => ErrorKind::Expression,
+            Self::ConfigError { .. } => ErrorKind::Config,
+            Self::DanglingReference { .. } => ErrorKind::Reference,
+            Self::VersionError(_) => ErrorKind::Version,
+            Self::BindingError { .. } => ErrorKind::Binding,
+            Self::JitError(_) => ErrorKind::Jit,
+        }
+    }
+
+    /// Returns the path of the packet the error refers to, if known.
+    #[must_use]
+    pub fn path(&self) -> Option<&[usize]> {
+        match self {
+            Self::OperatorError {
+                path,
+                bit_offset: Some(_),
+                ..
+            }
+            | Self::LengthOverflow { path, .. }
+            | Self::TruncatedOperator { path, .. }
+            | Self::TooDeep { path, .. }
+            | Self::ArgumentError { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns an [`ArgumentError`](Self::ArgumentError) for an operator at
+    /// the root with `count` operands.
+    pub(crate) const fn arity(count: usize, operation: Operation) -> Self {
+        Self::ArgumentError {
+            count,
+            operation,
+            path: PacketPath::root(),
+        }
+    }
+
+    /// Makes the path of the error relative to the parent of the packet it
+    /// was raised for, where that packet is the `index`th operand.
+    pub(crate) fn within(mut self, index: usize) -> Self {
+        if let Self::ArgumentError { path, .. } = &mut self {
+            path.prepend(index);
+        }
+        self
+    }
+
+    /// Returns the offset in bits from the start of the input of the packet
+    /// the error refers to, if known.
+    #[must_use]
+    pub const fn bit_offset(&self) -> Option<u64> {
+        match self {
+            Self::NonZeroPadding { offset } => Some(*offset),
+            Self::OperatorError { bit_offset, .. } => *bit_offset,
+            Self::TruncatedOperator { bit_offset, .. }
+            | Self::DanglingReference { bit_offset, .. }
+            | Self::ExpansionTooLarge { bit_offset, .. } => Some(*bit_offset),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Ordered with every [`TotalBits`](Length::TotalBits) length before every
+/// [`PacketCount`](Length::PacketCount) length, then by value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Length {
     TotalBits(u64),
     PacketCount(u16),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+impl Length {
+    /// Largest value the 15-bit total length field can hold.
+    pub const MAX_TOTAL_BITS: u64 = (1 << 15) - 1;
+    /// Largest value the 11-bit packet count field can hold.
+    pub const MAX_PACKET_COUNT: u16 = (1 << 11) - 1;
+
+    /// Returns a length of the same kind as `self` describing `packets`.
+    ///
+    /// Counts too large for the field saturate rather than wrap, so they are
+    /// still reported as overflowing when serialized.
+    #[must_use]
+    pub fn recomputed(self, packets: &[Packet]) -> Self {
+        match self {
+            Self::TotalBits(_) => Self::TotalBits(packets.iter().map(Packet::bit_len).sum()),
+            Self::PacketCount(_) => {
+                Self::PacketCount(u16::try_from(packets.len()).unwrap_or(u16::MAX))
+            }
+        }
+    }
+
+    /// Returns the smallest length encoding able to describe `packets`.
+    #[must_use]
+    pub fn minimal(packets: &[Packet]) -> Self {
+        match u16::try_from(packets.len()) {
+            Ok(count) if count <= Self::MAX_PACKET_COUNT => Self::PacketCount(count),
+            _ => Self::TotalBits(0).recomputed(packets),
+        }
+    }
+
+    /// Returns `true` if the value fits in its field.
+    #[must_use]
+    pub const fn fits(self) -> bool {
+        match self {
+            Self::TotalBits(bits) => bits <= Self::MAX_TOTAL_BITS,
+            Self::PacketCount(count) => count <= Self::MAX_PACKET_COUNT,
+        }
+    }
+}
+
+/// Ordered by type ID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[non_exhaustive]
 pub enum Operation {
     Sum,
@@ -52,50 +353,282 @@ pub enum Operation {
     GreaterThan,
     LessThan,
     EqualTo,
+    /// Extended: evaluates to its second operand if the first is non-zero,
+    /// otherwise to its third. Only the chosen branch is evaluated.
+    If,
 }
 
-impl Operation {
+/// Number of operands an operation accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arity {
+    AtLeast(usize),
+    Exactly(usize),
+}
+
+impl Arity {
+    /// Returns `true` if an operator with `count` operands can be evaluated.
     #[must_use]
-    pub const fn as_func_str(&self) -> &'static str {
+    pub const fn accepts(self, count: usize) -> bool {
         match self {
-            Operation::Sum => SUM_FUNC,
-            Operation::Product => PRODUCT_FUNC,
-            Operation::Minimum => MINIMUM_FUNC,
-            Operation::Maximum => MAXIMUM_FUNC,
-            Operation::GreaterThan => GREATER_THAN_FUNC,
-            Operation::LessThan => LESS_THAN_FUNC,
-            Operation::EqualTo => EQUAL_TO_FUNC,
+            Self::AtLeast(min) => count >= min,
+            Self::Exactly(n) => count == n,
         }
     }
+}
+
+/// Static description of an [`Operation`]; see [`Operation::info`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OperationInfo {
+    pub operation: Operation,
+    /// Type ID in the packet header.
+    pub type_id: u8,
+    /// Infix symbol in expressions, or `None` if the operation is only
+    /// written as a function.
+    pub symbol: Option<&'static str>,
+    /// Function name in expressions.
+    pub func: &'static str,
+    pub arity: Arity,
+    /// The order of operands does not affect the result.
+    pub commutative: bool,
+    /// Nested operators can be merged into one, as in
+    /// `(a + b) + c == a + b + c`.
+    pub associative: bool,
+    /// The operation is only available in the [`Profile::Extended`] profile.
+    pub extended: bool,
+    /// Operand value that leaves the result unchanged, as `0` does for sums.
+    pub identity: Option<usize>,
+    /// Operand value that decides the result regardless of the other
+    /// operands, as `0` does for products.
+    pub absorbing: Option<usize>,
+}
+
+const fn info(
+    operation: Operation,
+    type_id: u8,
+    symbol: Option<&'static str>,
+    func: &'static str,
+    arity: Arity,
+    identity: Option<usize>,
+    absorbing: Option<usize>,
+) -> OperationInfo {
+    OperationInfo {
+        operation,
+        type_id,
+        symbol,
+        func,
+        arity,
+        commutative: !matches!(
+            operation,
+            Operation::GreaterThan | Operation::LessThan | Operation::If
+        ),
+        associative: matches!(
+            operation,
+            Operation::Sum | Operation::Product | Operation::Minimum | Operation::Maximum
+        ),
+        extended: matches!(operation, Operation::If),
+        identity,
+        absorbing,
+    }
+}
+
+/// Metadata of every operation, in the order of [`Operation::ALL`].
+const OPERATION_INFO: [OperationInfo; 8] = [
+    info(
+        Operation::Sum,
+        0,
+        Some("+"),
+        "sum",
+        Arity::AtLeast(0),
+        Some(0),
+        None,
+    ),
+    info(
+        Operation::Product,
+        1,
+        Some("*"),
+        "product",
+        Arity::AtLeast(0),
+        Some(1),
+        Some(0),
+    ),
+    info(
+        Operation::Minimum,
+        2,
+        None,
+        "min",
+        Arity::AtLeast(1),
+        Some(usize::MAX),
+        Some(0),
+    ),
+    info(
+        Operation::Maximum,
+        3,
+        None,
+        "max",
+        Arity::AtLeast(1),
+        Some(0),
+        Some(usize::MAX),
+    ),
+    // ID 4 is a literal
+    info(
+        Operation::GreaterThan,
+        5,
+        Some(">"),
+        "gt",
+        Arity::Exactly(2),
+        None,
+        None,
+    ),
+    info(
+        Operation::LessThan,
+        6,
+        Some("<"),
+        "lt",
+        Arity::Exactly(2),
+        None,
+        None,
+    ),
+    info(
+        Operation::EqualTo,
+        7,
+        Some("=="),
+        "eq",
+        Arity::Exactly(2),
+        None,
+        None,
+    ),
+    info(Operation::If, 8, None, "if", Arity::Exactly(3), None, None),
+];
+
+impl Operation {
+    /// Every operation, ordered by type ID.
+    pub const ALL: [Self; 8] = [
+        Self::Sum,
+        Self::Product,
+        Self::Minimum,
+        Self::Maximum,
+        Self::GreaterThan,
+        Self::LessThan,
+        Self::EqualTo,
+        Self::If,
+    ];
+
+    /// Returns the metadata describing the operation.
+    ///
+    /// ```
+    /// use jacob::{Arity, Operation};
+    ///
+    /// let info = Operation::Product.info();
+    /// assert_eq!((info.type_id, info.symbol, info.func), (1, Some("*"), "product"));
+    /// assert_eq!(info.identity, Some(1));
+    /// assert_eq!(Operation::If.info().arity, Arity::Exactly(3));
+    /// ```
+    #[must_use]
+    pub const fn info(&self) -> &'static OperationInfo {
+        &OPERATION_INFO[*self as usize]
+    }
+
+    #[must_use]
+    pub const fn as_func_str(&self) -> &'static str {
+        self.info().func
+    }
+
+    /// Returns the operation named by `func`, the inverse of [`Operation::as_func_str`].
+    #[must_use]
+    pub fn from_func_str(func: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|operation| operation.info().func == func)
+    }
+
+    /// Returns `true` if the order of operands does not affect the result.
+    #[must_use]
+    pub const fn is_commutative(&self) -> bool {
+        self.info().commutative
+    }
+
+    /// Returns `true` if nested operators of this operation can be merged into
+    /// one, as in `(a + b) + c == a + b + c`.
+    #[must_use]
+    pub const fn is_associative(&self) -> bool {
+        self.info().associative
+    }
+
+    /// Returns `true` if the operation is only available in the [`Profile::Extended`] profile.
+    #[must_use]
+    pub const fn is_extended(&self) -> bool {
+        self.info().extended
+    }
 
     #[must_use]
     pub const fn is_function(&self) -> bool {
+        self.info().symbol.is_none()
+    }
+
+    /// Returns the value [`Operation::fold`] starts from, or `None` if it
+    /// starts from the first operand.
+    ///
+    /// This is [`OperationInfo::identity`]; it is the value of an operator
+    /// without operands only if the [arity](OperationInfo::arity) allows that.
+    #[must_use]
+    pub const fn identity(&self) -> Option<usize> {
+        self.info().identity
+    }
+
+    /// Combines `acc`, the result over the operands before `x`, with `x`.
+    ///
+    /// Every evaluator of `usize` values folds operands this way, so an
+    /// external one agrees with [`Packet::eval`] by doing the same:
+    ///
+    /// ```
+    /// use jacob::Operation;
+    ///
+    /// let fold = |operation: Operation, operands: &[usize]| {
+    ///     let (first, rest) = match operation.identity() {
+    ///         Some(identity) => (identity, operands),
+    ///         None => (operands[0], &operands[1..]),
+    ///     };
+    ///     rest.iter().try_fold(first, |acc, &x| operation.fold(acc, x))
+    /// };
+    /// assert_eq!(fold(Operation::Product, &[2, 3, 7]), Some(42));
+    /// assert_eq!(fold(Operation::Minimum, &[5, 3, 4]), Some(3));
+    /// assert_eq!(fold(Operation::LessThan, &[1, 2]), Some(1));
+    /// assert_eq!(fold(Operation::Sum, &[usize::MAX, 1]), None);
+    /// ```
+    ///
+    /// Comparisons compare `acc` with `x`. Returns `None` if the result
+    /// overflows, or for [`Operation::If`], which picks an operand instead.
+    #[must_use]
+    pub const fn fold(&self, acc: usize, x: usize) -> Option<usize> {
         match self {
-            Operation::Sum
-            | Operation::Product
-            | Operation::GreaterThan
-            | Operation::LessThan
-            | Operation::EqualTo => false,
-            Operation::Minimum | Operation::Maximum => true,
+            Self::Sum => acc.checked_add(x),
+            Self::Product => acc.checked_mul(x),
+            Self::Minimum => Some(if x < acc { x } else { acc }),
+            Self::Maximum => Some(if x > acc { x } else { acc }),
+            Self::GreaterThan => Some((acc > x) as usize),
+            Self::LessThan => Some((acc < x) as usize),
+            Self::EqualTo => Some((acc == x) as usize),
+            Self::If => None,
         }
     }
+
+    /// Folds `operands`, whose number the arity accepts, with
+    /// [`Operation::fold`], returning `None` if the result overflows or the
+    /// operation is [`Operation::If`].
+    pub(crate) fn fold_all(self, operands: &[usize]) -> Option<usize> {
+        let (first, rest) = match self.identity() {
+            Some(identity) => (identity, operands),
+            None => operands.split_first().map(|(&first, rest)| (first, rest))?,
+        };
+        rest.iter().try_fold(first, |acc, &x| self.fold(acc, x))
+    }
 }
 
 impl std::fmt::Display for Operation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Operation::Sum => SUM_SYMBOL,
-                Operation::Product => PRODUCT_SYMBOL,
-                Operation::Minimum => MINIMUM_FUNC,
-                Operation::Maximum => MAXIMUM_FUNC,
-                Operation::GreaterThan => GREATER_THAN_SYMBOL,
-                Operation::LessThan => LESS_THAN_SYMBOL,
-                Operation::EqualTo => EQUAL_TO_SYMBOL,
-            }
-        )
+        let info = self.info();
+        write!(f, "{}", info.symbol.unwrap_or(info.func))
     }
 }
 
@@ -103,32 +636,20 @@ impl TryFrom<u8> for Operation {
     type Error = PacketError;
 
     fn try_from(op_id: u8) -> Result<Self, Self::Error> {
-        match op_id {
-            0 => Ok(Self::Sum),
-            1 => Ok(Self::Product),
-            2 => Ok(Self::Minimum),
-            3 => Ok(Self::Maximum),
-            // ID 4 is a literal
-            5 => Ok(Self::GreaterThan),
-            6 => Ok(Self::LessThan),
-            7 => Ok(Self::EqualTo),
-            _ => Err(PacketError::OperatorError(op_id)),
-        }
+        Self::ALL
+            .into_iter()
+            .find(|operation| operation.info().type_id == op_id)
+            .ok_or(PacketError::OperatorError {
+                id: op_id,
+                path: PacketPath::root(),
+                bit_offset: None,
+            })
     }
 }
 
 impl From<&Operation> for u8 {
     fn from(op: &Operation) -> Self {
-        match op {
-            Operation::Sum => 0,
-            Operation::Product => 1,
-            Operation::Minimum => 2,
-            Operation::Maximum => 3,
-            // ID 4 is a literal
-            Operation::GreaterThan => 5,
-            Operation::LessThan => 6,
-            Operation::EqualTo => 7,
-        }
+        op.info().type_id
     }
 }
 
@@ -138,7 +659,10 @@ impl From<Operation> for u8 {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Ordered with every literal before every operator. Literals are ordered by
+/// value, and operators by operation, then number of operands, then operands
+/// in order, then length.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum PacketKind {
     Literal(usize),
     Operator {
@@ -181,52 +705,119 @@ impl From<PacketKind> for u8 {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+impl Ord for PacketKind {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Literal(a), Self::Literal(b)) => a.cmp(b),
+            (Self::Literal(_), Self::Operator { .. }) => Ordering::Less,
+            (Self::Operator { .. }, Self::Literal(_)) => Ordering::Greater,
+            (
+                Self::Operator {
+                    length,
+                    operation,
+                    packets,
+                },
+                Self::Operator {
+                    length: other_length,
+                    operation: other_operation,
+                    packets: other_packets,
+                },
+            ) => operation
+                .cmp(other_operation)
+                .then(packets.len().cmp(&other_packets.len()))
+                .then_with(|| packets.cmp(other_packets))
+                .then(length.cmp(other_length)),
+        }
+    }
+}
+
+impl PartialOrd for PacketKind {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Ordered by kind, then version; see [`PacketKind`]. This is the order
+/// [`Packet::sort_commutative`] sorts operands in.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Packet {
-    pub version: u8,
+    pub version: Version,
     pub kind: PacketKind,
 }
 
+impl Ord for Packet {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.kind
+            .cmp(&other.kind)
+            .then(self.version.cmp(&other.version))
+    }
+}
+
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Packet {
     /// Evaluates operator packets recursively.
     ///
     /// # Errors
     ///
-    /// Will return `Err` if any operators have an invalid number of arguments.
+    /// Will return `Err` if any operators have an invalid number of arguments,
+    /// or a sum or product overflows.
     pub fn eval(&self) -> Result<usize, PacketError> {
-        Ok(match &self.kind {
-            PacketKind::Literal(value) => *value,
+        self.eval_inner(false)
+    }
+
+    /// Evaluates like [`Packet::eval`], but stops evaluating the operands of a
+    /// product as soon as one of them is zero.
+    ///
+    /// Operands after the zero are never visited, so errors in them are not
+    /// reported.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any evaluated operators have an invalid number of
+    /// arguments, or a sum or product overflows.
+    pub fn eval_short_circuit(&self) -> Result<usize, PacketError> {
+        self.eval_inner(true)
+    }
+
+    fn eval_inner(&self, short_circuit: bool) -> Result<usize, PacketError> {
+        let (operation, packets) = match &self.kind {
+            PacketKind::Literal(value) => return Ok(*value),
             PacketKind::Operator {
                 operation, packets, ..
-            } => {
-                let packets: Vec<usize> =
-                    packets.iter().map(Self::eval).collect::<Result<_, _>>()?;
-                match operation {
-                    Operation::Sum => packets.iter().sum(),
-                    Operation::Product => packets.iter().product(),
-                    Operation::Minimum | Operation::Maximum => *{
-                        match operation {
-                            Operation::Minimum => packets.iter().min(),
-                            Operation::Maximum => packets.iter().max(),
-                            _ => unreachable!(),
-                        }
-                    }
-                    .ok_or_else(|| PacketError::ArgumentError(packets.len(), *operation))?,
-                    Operation::LessThan | Operation::GreaterThan | Operation::EqualTo => {
-                        if let [a, b] = &packets[..] {
-                            Ok(match operation {
-                                Operation::LessThan => a < b,
-                                Operation::GreaterThan => a > b,
-                                Operation::EqualTo => a == b,
-                                _ => unreachable!(),
-                            } as usize)
-                        } else {
-                            Err(PacketError::ArgumentError(packets.len(), *operation))
-                        }?
-                    }
-                }
+            } => (*operation, packets),
+        };
+        let arity_error = || PacketError::arity(packets.len(), operation);
+        let operand = |i: usize| {
+            packets[i]
+                .eval_inner(short_circuit)
+                .map_err(|error| error.within(i))
+        };
+        if !operation.info().arity.accepts(packets.len()) {
+            return Err(arity_error());
+        }
+        if operation == Operation::If {
+            return if operand(0)? == 0 {
+                operand(2)
+            } else {
+                operand(1)
+            };
+        }
+        let mut acc = operation.identity();
+        for i in 0..packets.len() {
+            let value = operand(i)?;
+            acc = acc.map_or(Some(value), |acc| operation.fold(acc, value));
+            match acc {
+                None => return Err(PacketError::Overflow(Some(operation))),
+                Some(0) if short_circuit && operation == Operation::Product => return Ok(0),
+                Some(_) => {}
             }
-        })
+        }
+        Ok(acc.unwrap_or_default())
     }
 
     /// Returns a literal packet with the evaluated value of `self`.
@@ -247,16 +838,35 @@ impl Packet {
     ///
     /// Will return `Err` if serialization fails.
     pub fn to_bytes(&self) -> Result<Vec<u8>, PacketError> {
+        self.to_bytes_with_profile(Profile::Standard)
+    }
+
+    /// Returns packet as byte array encoded in `profile`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if serialization fails or an operation is not supported by `profile`.
+    pub fn to_bytes_with_profile(&self, profile: Profile) -> Result<Vec<u8>, PacketError> {
         let mut bytes: Vec<u8> = Vec::new();
-        let mut writer = BitWriter::endian(&mut bytes, bitstream_io::BigEndian);
-        self.write_bits(&mut writer)?;
+        let mut writer = BitWriter::new(&mut bytes);
+        self.write_bits_with_profile(&mut writer, profile)?;
 
         // Add packing zeros
-        writer.byte_align()?;
+        writer.byte_align();
 
         Ok(bytes)
     }
 
+    /// Returns packet as byte array together with the number of meaningful
+    /// bits, so the zero padding in the last byte can be told apart from data.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if serialization fails.
+    pub fn to_bits(&self) -> Result<(Vec<u8>, u64), PacketError> {
+        Ok((self.to_bytes()?, self.bit_len()))
+    }
+
     /// Returns hexadecimal representation of packet.
     ///
     /// # Errors
@@ -266,69 +876,142 @@ impl Packet {
         Ok(hex_from_bytes(&self.to_bytes()?))
     }
 
+    /// Returns hexadecimal representation of packet encoded in `profile`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if serialization fails or an operation is not supported by `profile`.
+    pub fn to_hex_with_profile(&self, profile: Profile) -> Result<String, PacketError> {
+        Ok(hex_from_bytes(&self.to_bytes_with_profile(profile)?))
+    }
+
     /// Serializes packet as bits into writer.
     ///
     /// # Errors
     ///
     /// Will return `Err` if write fails.
-    pub fn write_bits(
+    pub fn write_bits(&self, writer: &mut BitWriter<'_>) -> Result<(), PacketError> {
+        self.write_bits_with_profile(writer, Profile::Standard)
+    }
+
+    /// Serializes packet as bits into writer, encoded in `profile`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if write fails, an operation is not supported by
+    /// `profile`, or a length does not fit in its field. See
+    /// [`Packet::fit_lengths`] for restructuring packets with oversized lengths.
+    pub fn write_bits_with_profile(
         &self,
-        writer: &mut BitWriter<&mut Vec<u8>, BigEndian>,
+        writer: &mut BitWriter<'_>,
+        profile: Profile,
     ) -> Result<(), PacketError> {
+        if profile == Profile::Shared {
+            return shared::write(self, writer);
+        }
+        self.write_at(writer, profile, &mut Vec::new())
+    }
+
+    fn write_at(
+        &self,
+        writer: &mut BitWriter<'_>,
+        profile: Profile,
+        path: &mut Vec<usize>,
+    ) -> Result<(), PacketError> {
+        if let PacketKind::Operator { operation, .. } = &self.kind {
+            if !profile.supports(*operation) {
+                return Err(PacketError::UnsupportedOperation(*operation, profile));
+            }
+        }
+
+        let run_length = matches!(self.kind, PacketKind::Literal(value)
+            if profile == Profile::RunLength && rle::runs_bit_len(value) < groups_bit_len(value));
+
         // VVV
-        writer.write(3, self.version)?;
+        writer.write(3, self.version.get())?;
         // TTT
-        writer.write::<u8>(3, u8::from(&self.kind))?;
+        let type_id = if run_length {
+            rle::RUN_LENGTH_TYPE_ID
+        } else {
+            u8::from(&self.kind)
+        };
+        writer.write(u32::from(profile.type_bits()), type_id)?;
 
         match &self.kind {
-            PacketKind::Literal(value) => {
-                // Convert value to binbary string representation
-                let value = format!("{:b}", value);
-                // Zero-pad bits to multiple of 3
-                let mut value = std::iter::repeat('0')
-                    .take((4 - value.len() % 4) % 4)
-                    .chain(value.chars())
-                    .map(|c| if c == '0' { 0 } else { 1 })
-                    .collect_vec();
-                value.reverse();
-
-                while !value.is_empty() {
-                    writer.write(1, if value.len() > 4 { 1 } else { 0 })?;
-                    let bits: Vec<u8> = std::iter::repeat_with(|| value.pop())
-                        .take(4)
-                        .collect::<Option<Vec<_>>>()
-                        .expect("value is a multiple of 4");
-                    let n: u8 = bits[0] << 3 | bits[1] << 2 | bits[2] << 1 | bits[3];
-                    writer.write(4, n)?;
-                }
-            }
+            PacketKind::Literal(value) if run_length => rle::write_runs(writer, *value)?,
+            PacketKind::Literal(value) => write_groups(writer, *value)?,
             PacketKind::Operator {
                 packets, length, ..
             } => {
+                let length = match (length, profile) {
+                    (Length::TotalBits(_), profile) if profile != Profile::Standard => {
+                        Length::TotalBits(packets.iter().map(|p| p.bit_len_in(profile)).sum())
+                    }
+                    (length, _) => *length,
+                };
+                if !length.fits() {
+                    return Err(PacketError::LengthOverflow {
+                        length,
+                        path: path.clone().into(),
+                    });
+                }
                 match length {
                     Length::TotalBits(bits) => {
                         // I
-                        writer.write(1, 0)?;
+                        writer.write_bit(false)?;
                         // L
-                        writer.write(15, *bits)?;
+                        writer.write(15, bits)?;
                     }
                     Length::PacketCount(packets) => {
                         // I
-                        writer.write(1, 1)?;
+                        writer.write_bit(true)?;
                         // L
-                        writer.write(11, *packets as u16)?;
+                        writer.write(11, packets)?;
                     }
                 }
 
-                for packet in packets {
-                    packet.write_bits(writer)?;
+                for (i, packet) in packets.iter().enumerate() {
+                    path.push(i);
+                    packet.write_at(writer, profile, path)?;
+                    path.pop();
                 }
             }
-        };
+        }
 
         Ok(())
     }
 
+    /// Returns the number of bits this packet occupies when serialized, excluding padding.
+    #[must_use]
+    pub fn bit_len(&self) -> u64 {
+        self.bit_len_in(Profile::Standard)
+    }
+
+    /// Returns the number of bits this packet occupies when serialized in `profile`, excluding padding.
+    #[must_use]
+    pub fn bit_len_in(&self, profile: Profile) -> u64 {
+        3 + u64::from(profile.type_bits())
+            + match &self.kind {
+                PacketKind::Literal(value) if profile == Profile::RunLength => {
+                    groups_bit_len(*value).min(rle::runs_bit_len(*value))
+                }
+                PacketKind::Literal(value) => groups_bit_len(*value),
+                PacketKind::Operator {
+                    length, packets, ..
+                } => {
+                    let header = match length {
+                        Length::TotalBits(_) => 16,
+                        Length::PacketCount(_) => 12,
+                    };
+                    header
+                        + packets
+                            .iter()
+                            .map(|packet| packet.bit_len_in(profile))
+                            .sum::<u64>()
+                }
+            }
+    }
+
     /// Returns number of sub-packets contained within this packet, and its packets, recursively.
     #[must_use]
     pub fn packet_count(&self) -> usize {
@@ -348,100 +1031,335 @@ impl Packet {
         }
     }
 
+    /// Parses a mathematical expression, such as `(1 + 3) == (2 * 2)`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the expression is malformed.
+    pub fn from_expression(expression: &str) -> Result<Self, PacketError> {
+        Ok(expression::parse(expression)?)
+    }
+
+    /// Parses a mathematical expression according to `options`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the expression is malformed.
+    pub fn from_expression_with(
+        expression: &str,
+        options: &ExpressionOptions,
+    ) -> Result<Self, PacketError> {
+        Ok(expression::parse_with(expression, options)?)
+    }
+
+    /// Returns the number of operator levels above the deepest literal; `0` for a literal.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        match &self.kind {
+            PacketKind::Literal(_) => 0,
+            PacketKind::Operator { packets, .. } => {
+                1 + packets.iter().map(Self::depth).max().unwrap_or(0)
+            }
+        }
+    }
+
     /// Renders to mathematical expression representation.
     ///
     /// # Errors
     ///
-    /// Will return `Err` if packet does not evaluate properly.
+    /// Never returns `Err`: every packet has an expression, whether or not
+    /// it evaluates. The `Result` is kept for compatibility.
     pub fn to_expression(&self) -> Result<String, PacketError> {
+        self.to_expression_styled(&ExpressionStyle::default())
+    }
+
+    /// Renders to mathematical expression representation using `style`.
+    ///
+    /// # Errors
+    ///
+    /// Never returns `Err`: every packet has an expression, whether or not
+    /// it evaluates. The `Result` is kept for compatibility.
+    pub fn to_expression_styled(&self, style: &ExpressionStyle) -> Result<String, PacketError> {
+        let mut expression = String::new();
+        // Writing to a `String` cannot fail
+        let _ = self.write_expression_styled(&mut expression, style);
+        Ok(expression)
+    }
+
+    /// Writes the mathematical expression representation to `w` without
+    /// building intermediate strings.
+    ///
+    /// ```
+    /// let packet = jacob::Packet::from_expression("1 + (2 * 3)").unwrap();
+    /// let mut expression = String::new();
+    /// packet.write_expression(&mut expression).unwrap();
+    /// assert_eq!(expression, "1 + (2 * 3)");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `w` does.
+    pub fn write_expression<W: std::fmt::Write>(&self, w: &mut W) -> std::fmt::Result {
+        self.write_expression_styled(w, &ExpressionStyle::default())
+    }
+
+    /// Writes the mathematical expression representation to `w` using `style`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `w` does.
+    pub fn write_expression_styled<W: std::fmt::Write>(
+        &self,
+        w: &mut W,
+        style: &ExpressionStyle,
+    ) -> std::fmt::Result {
+        self.write_term(w, style, &(), false)
+    }
+
+    /// Writes the expression of this packet, wrapped in parentheses if it is
+    /// an infix operation `nested` in another, and in the delimiters of
+    /// `marks` if it is one of the marked packets.
+    pub(crate) fn write_term<W: std::fmt::Write, M: Marks>(
+        &self,
+        w: &mut W,
+        style: &ExpressionStyle,
+        marks: &M,
+        nested: bool,
+    ) -> std::fmt::Result {
+        let delimiters = marks.delimiters(self);
+        if let Some((open, _)) = delimiters {
+            w.write_str(open)?;
+        }
         match &self.kind {
-            PacketKind::Literal(value) => Ok(value.to_string()),
+            PacketKind::Literal(value) => style.write_literal(w, *value)?,
             PacketKind::Operator {
                 operation, packets, ..
             } => {
-                let mut packet_expressions = packets
-                    .iter()
-                    .map(Self::to_expression)
-                    .collect::<Result<Vec<_>, _>>()?
-                    .into_iter()
-                    .zip(packets.iter())
-                    .map(|(expr, packet)| match packet.kind {
-                        PacketKind::Operator { operation, .. } if !operation.is_function() => {
-                            format!("({})", expr)
-                        }
-                        _ => expr,
-                    });
+                let function_form = operation.is_function() || packets.len() < 2;
+                let parens = nested && !operation.is_function();
+                if parens {
+                    w.write_char('(')?;
+                }
                 if operation.is_function() {
-                    Ok(format!(
-                        "{func}({args})",
-                        func = operation.to_string(),
-                        args = packet_expressions.join(", ")
-                    ))
-                } else {
-                    let args = packet_expressions.collect_vec();
-                    Ok(match args.len() {
-                        1 => {
-                            format!(
-                                "{func}({args})",
-                                func = operation.as_func_str(),
-                                args = args.join(", ")
-                            )
+                    write!(w, "{}(", style.symbols.get(*operation))?;
+                } else if function_form {
+                    write!(w, "{}(", operation.as_func_str())?;
+                }
+                for (i, packet) in packets.iter().enumerate() {
+                    if i > 0 {
+                        if function_form {
+                            w.write_str(", ")?;
+                        } else {
+                            write!(w, " {} ", style.symbols.get(*operation))?;
                         }
-                        _ => args.join(&format!(" {} ", operation.to_string())),
-                    })
+                    }
+                    packet.write_term(w, style, marks, true)?;
+                }
+                if function_form {
+                    w.write_char(')')?;
+                }
+                if parens {
+                    w.write_char(')')?;
                 }
             }
         }
+        if let Some((_, close)) = delimiters {
+            w.write_str(close)?;
+        }
+        Ok(())
     }
 }
 
-impl<'a> TryFrom<&mut BitReader<'a>> for Packet {
+/// Formats the packet as a mathematical expression.
+impl std::fmt::Display for Packet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_expression(f)
+    }
+}
+
+impl TryFrom<&mut BitReader<'_>> for Packet {
     type Error = PacketError;
 
     fn try_from(bit_reader: &mut BitReader) -> Result<Self, Self::Error> {
-        // VVV
-        let version = bit_reader.read_u8(3)?;
-        // TTT
-        let type_id = bit_reader.read_u8(3)?;
-        let kind = match type_id {
-            4 => {
-                let mut bits = Vec::new();
-                let mut reading = true;
-                // A+, B+, etc...
-                while reading {
-                    reading = bit_reader.read_bool()?;
-                    bits.push(bit_reader.read_u8(4)?);
+        Self::read_with_profile(bit_reader, Profile::Standard)
+    }
+}
+
+impl Packet {
+    /// Parses a hexadecimal packet encoded in `profile`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the string is not valid hexadecimal or does not form a valid packet.
+    pub fn from_hex_with_profile(hex: &str, profile: Profile) -> Result<Self, PacketError> {
+        let bytes = bytes_from_hex(hex)?;
+        Self::read_with_profile(&mut BitReader::new(&bytes), profile)
+    }
+
+    /// Parses a packet occupying exactly the first `bit_len` bits of `bytes`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the bits do not form a valid packet, the packet
+    /// needs more than `bit_len` bits, or bits are left over after it.
+    pub fn from_bits(bytes: &[u8], bit_len: u64) -> Result<Self, PacketError> {
+        let not_enough_data = |position, requested| {
+            PacketError::BitsError(BitError::NotEnoughData {
+                position,
+                length: bit_len,
+                requested,
+            })
+        };
+        let available = bytes.len() as u64 * 8;
+        if bit_len > available {
+            return Err(not_enough_data(available, bit_len - available));
+        }
+        let mut reader = BitReader::new(bytes);
+        let packet = Self::read_with_profile(&mut reader, Profile::Standard)?;
+        match reader.position() {
+            position if position > bit_len => Err(not_enough_data(bit_len, position - bit_len)),
+            position if position < bit_len => Err(PacketError::TrailingBits {
+                count: bit_len - position,
+            }),
+            _ => Ok(packet),
+        }
+    }
+
+    /// Parses a packet encoded in `profile` from a bit cursor, leaving it
+    /// after the packet.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the bits do not form a valid packet.
+    pub fn read_with_profile<C: BitCursor>(
+        bit_reader: &mut C,
+        profile: Profile,
+    ) -> Result<Self, PacketError> {
+        Self::read_with_limits(
+            bit_reader,
+            profile,
+            usize::MAX,
+            shared::MAX_EXPANDED_PACKETS,
+            &mut Warnings::new(),
+        )
+    }
+
+    /// Parses a packet like [`Packet::read_with_profile`], failing as soon as
+    /// an operator is nested more than `max_depth` levels deep or a shared
+    /// transmission expands to more than `max_expanded` packets, and adding
+    /// warnings about its encoding to `warnings`.
+    pub(crate) fn read_with_limits<C: BitCursor>(
+        bit_reader: &mut C,
+        profile: Profile,
+        max_depth: usize,
+        max_expanded: usize,
+        warnings: &mut Warnings,
+    ) -> Result<Self, PacketError> {
+        Self::read(
+            bit_reader,
+            profile,
+            &mut Vec::new(),
+            0,
+            &mut shared::References::new(max_expanded),
+            max_depth,
+            warnings,
+        )
+    }
+
+    /// `origin` is the offset of `bit_reader`'s position zero from the start of
+    /// the input, used to report where errors occur. In the
+    /// [`Shared`](Profile::Shared) profile, `references` holds every packet
+    /// read so far by offset.
+    fn read<C: BitCursor>(
+        bit_reader: &mut C,
+        profile: Profile,
+        path: &mut Vec<usize>,
+        origin: u64,
+        references: &mut shared::References,
+        max_depth: usize,
+        warnings: &mut Warnings,
+    ) -> Result<Self, PacketError> {
+        let bit_offset = origin + bit_reader.position();
+        let expanded = references.expanded();
+        let header = fields::read_header(bit_reader, profile)?;
+        let version = Version::try_from(header.version)?;
+        let kind = match header.type_id {
+            RawPacketHeader::LITERAL_TYPE_ID => {
+                let start = bit_reader.position();
+                let value = fields::read_literal_groups(bit_reader)?;
+                let extra_bits = bit_reader.position() - start - groups_bit_len(value);
+                if extra_bits > 0 {
+                    warnings.push(Warning::LiteralPadding {
+                        extra_groups: extra_bits / 5,
+                        path: path.as_slice().into(),
+                    });
                 }
-                let value = bits
-                    .into_iter()
-                    .map(usize::from)
-                    .reduce(|a, b| a << 4 | b)
-                    .ok_or(PacketError::ValueError);
-                PacketKind::Literal(value?)
+                PacketKind::Literal(value)
+            }
+            rle::RUN_LENGTH_TYPE_ID if profile == Profile::RunLength => {
+                PacketKind::Literal(rle::read_runs(bit_reader)?)
+            }
+            shared::REFERENCE_TYPE_ID if profile == Profile::Shared => {
+                let distance = fields::read_literal_groups(bit_reader)? as u64;
+                return references.copy(bit_offset, distance);
             }
             operation => {
-                // I
-                let length = if bit_reader.read_bool()? {
-                    Length::PacketCount(bit_reader.read_u16(11)?)
-                } else {
-                    Length::TotalBits(bit_reader.read_u64(15)?)
+                let operation =
+                    Operation::try_from(operation).map_err(|_| PacketError::OperatorError {
+                        id: operation,
+                        path: path.clone().into(),
+                        bit_offset: Some(bit_offset),
+                    })?;
+                if path.len() >= max_depth {
+                    return Err(PacketError::TooDeep {
+                        limit: max_depth,
+                        path: path.clone().into(),
+                    });
+                }
+                let length = fields::read_length(bit_reader)?;
+                // Reject lengths the remaining input cannot possibly satisfy
+                let available = bit_reader.remaining();
+                let required = match length {
+                    Length::TotalBits(n_bits) => n_bits,
+                    Length::PacketCount(n_packets) => {
+                        // The smallest packet is a single-group literal
+                        u64::from(n_packets) * (3 + u64::from(profile.type_bits()) + 5)
+                    }
                 };
+                if required > available {
+                    return Err(PacketError::TruncatedOperator {
+                        declared: length,
+                        available,
+                        path: path.clone().into(),
+                        bit_offset,
+                    });
+                }
                 // A*, B*, etc...
+                // Operands are read from the same cursor, up to `end` when the
+                // length is in bits
+                let end = bit_reader.position() + required;
                 let mut packets = Vec::new();
-                let mut sub_packet_reader = bit_reader.relative_reader();
-                while {
-                    match length {
-                        Length::TotalBits(n_bits) => sub_packet_reader.position() < n_bits,
-                        Length::PacketCount(n_packets) => packets.len() < n_packets as usize,
-                    }
+                while match length {
+                    Length::TotalBits(_) => bit_reader.position() < end,
+                    Length::PacketCount(n_packets) => packets.len() < n_packets as usize,
                 } {
-                    let mut reader = sub_packet_reader.relative_reader();
-                    let packet = Self::try_from(&mut reader)?;
-                    sub_packet_reader.skip(reader.position())?;
+                    path.push(packets.len());
+                    let packet = Self::read(
+                        bit_reader, profile, path, origin, references, max_depth, warnings,
+                    )?;
+                    path.pop();
                     packets.push(packet);
                 }
-                bit_reader.skip(sub_packet_reader.position())?;
-                let operation = Operation::try_from(operation)?;
+                let length = match length {
+                    // Describe the expanded operands rather than the shared or
+                    // run-length encoding
+                    Length::TotalBits(_)
+                        if matches!(profile, Profile::Shared | Profile::RunLength) =>
+                    {
+                        Length::TotalBits(packets.iter().map(Self::bit_len).sum())
+                    }
+                    length => length,
+                };
                 PacketKind::Operator {
                     operation,
                     packets,
@@ -450,7 +1368,52 @@ impl<'a> TryFrom<&mut BitReader<'a>> for Packet {
             }
         };
 
-        Ok(Self { version, kind })
+        let packet = Self { version, kind };
+        if profile == Profile::Shared {
+            references.insert(bit_offset, &packet, expanded)?;
+        }
+        Ok(packet)
+    }
+}
+
+/// Writes `value` as literal value groups: four bits each, most significant
+/// first, each preceded by a flag that is set if another group follows.
+pub(crate) fn write_groups(writer: &mut BitWriter<'_>, value: usize) -> Result<(), PacketError> {
+    let groups = (usize::BITS - value.leading_zeros()).div_ceil(4).max(1);
+    for group in (0..groups).rev() {
+        writer.write_bit(group > 0)?;
+        writer.write(4, (value >> (group * 4)) as u64 & 0xF)?;
+    }
+    Ok(())
+}
+
+/// Returns the number of bits [`write_groups`] uses for `value`.
+pub(crate) fn groups_bit_len(value: usize) -> u64 {
+    let significant = usize::BITS - value.leading_zeros();
+    5 * u64::from(significant.div_ceil(4).max(1))
+}
+
+impl Packet {
+    /// Parses a hexadecimal packet, unwrapping an envelope if present. Same as
+    /// [`str::parse`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the string is not valid hexadecimal or does not
+    /// form a valid packet.
+    pub fn parse_hex(hex: &str) -> Result<Self, PacketError> {
+        hex.parse()
+    }
+
+    /// Parses a packet from bytes, unwrapping an envelope if present. Same as
+    /// [`Packet::from_bytes`] and `Packet::try_from` on any byte container.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the envelope is invalid or the bytes do not form a
+    /// valid packet.
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Self, PacketError> {
+        Self::from_bytes(bytes)
     }
 }
 
@@ -458,9 +1421,7 @@ impl FromStr for Packet {
     type Err = PacketError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let bytes = bytes_from_hex(s)?;
-        let mut bit_reader = BitReader::new(&bytes);
-        Self::try_from(&mut bit_reader)
+        Self::from_hex_with(s, &ParseOptions::default())
     }
 }
 
@@ -480,32 +1441,38 @@ impl TryFrom<String> for Packet {
     }
 }
 
-/// Converts a hexadecimal string into a byte array.
-///
-/// # Errors
-///
-/// Will return `Err` if string is not valid hexadecimal.
-pub fn bytes_from_hex(hex: &str) -> Result<Vec<u8>, PacketError> {
-    Ok(hex
-        .chars()
-        .chunks(2)
-        .into_iter()
-        .map(|mut chunk| u8::from_str_radix(&chunk.join(""), 16))
-        .collect::<Result<Vec<_>, _>>()?)
+impl TryFrom<&[u8]> for Packet {
+    type Error = PacketError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl TryFrom<Vec<u8>> for Packet {
+    type Error = PacketError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        Self::from_bytes(&bytes)
+    }
 }
 
-/// Converts a byte array into a hexadecimal string.
-#[must_use]
-pub fn hex_from_bytes(bytes: &[u8]) -> String {
-    bytes
-        .iter()
-        .map(|byte| format!("{:02X}", byte))
-        .collect::<String>()
+impl TryFrom<Box<[u8]>> for Packet {
+    type Error = PacketError;
+
+    fn try_from(bytes: Box<[u8]>) -> Result<Self, Self::Error> {
+        Self::from_bytes(&bytes)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{bytes_from_hex, hex_from_bytes, Packet, PacketKind};
+    use crate::bits::BitReader;
+    use crate::bits::BitWriter;
+    use crate::{
+        bytes_from_hex, hex_from_bytes, ErrorKind, Length, Operation, Packet, PacketError,
+        PacketKind, Profile, Version,
+    };
     static TEST_CASES: &[TestCase] = &[
         TestCase {
             hex: "D2FE28",
@@ -581,6 +1548,8 @@ mod tests {
             let packet = Packet::try_from(case.hex).unwrap();
             let encoded_hex = packet.to_hex().unwrap();
             assert_eq!(encoded_hex, case.hex);
+            let bytes = packet.to_bytes().unwrap();
+            assert_eq!(bytes.len() as u64, packet.bit_len().div_ceil(8));
         }
     }
 
@@ -592,6 +1561,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_eval_short_circuit() {
+        for case in TEST_CASES {
+            let packet = Packet::try_from(case.hex).unwrap();
+            assert_eq!(packet.eval_short_circuit().unwrap(), case.eval);
+        }
+        // The malformed `min()` after the zero is never evaluated
+        let packet = Packet::from_expression("0 * min()").unwrap();
+        assert!(packet.eval().is_err());
+        assert_eq!(packet.eval_short_circuit().unwrap(), 0);
+    }
+
     #[test]
     fn test_to_literal() {
         for case in TEST_CASES {
@@ -604,11 +1585,276 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_operation_info() {
+        for operation in Operation::ALL {
+            let info = operation.info();
+            assert_eq!(info.operation, operation);
+            assert_eq!(Operation::try_from(info.type_id).unwrap(), operation);
+            assert_eq!(Operation::from_func_str(info.func), Some(operation));
+            if let Some(identity) = info.identity {
+                for x in [0, 1, 7] {
+                    let packet = Packet::operator(operation, [x, identity]);
+                    assert_eq!(packet.eval().unwrap(), x, "{operation:?}");
+                }
+            }
+            if let Some(absorbing) = info.absorbing {
+                for x in [0, 1, 7] {
+                    let packet = Packet::operator(operation, [x, absorbing]);
+                    assert_eq!(packet.eval().unwrap(), absorbing, "{operation:?}");
+                }
+            }
+            for count in 0..4 {
+                let packet = Packet::operator(operation, vec![1; count]);
+                assert_eq!(info.arity.accepts(count), packet.eval().is_ok());
+            }
+        }
+        assert!(Operation::try_from(4).is_err());
+    }
+
+    #[test]
+    fn test_operation_fold() {
+        struct NoHooks;
+        impl crate::evaluator::EvalHooks for NoHooks {}
+
+        assert_eq!(Operation::Sum.identity(), Some(0));
+        assert_eq!(Operation::EqualTo.identity(), None);
+        assert_eq!(Operation::Maximum.fold(3, 8), Some(8));
+        assert_eq!(Operation::GreaterThan.fold(3, 8), Some(0));
+        assert_eq!(Operation::Product.fold(usize::MAX, 2), None);
+        assert_eq!(Operation::If.fold(1, 2), None);
+
+        // Every evaluator folds alike, overflow included
+        let packet = Packet::operator(Operation::Sum, [usize::MAX, 1]);
+        let hex = &packet.to_hex().unwrap();
+        let overflow = |result: Result<usize, PacketError>| {
+            matches!(result, Err(PacketError::Overflow(Some(Operation::Sum))))
+        };
+        assert!(overflow(packet.eval()));
+        assert!(overflow(packet.eval_with_hooks(&mut NoHooks)));
+        assert!(overflow(
+            crate::vm::Vm::new().run(&packet.compile().unwrap())
+        ));
+        assert!(overflow(crate::stream::eval_of_hex(hex)));
+        assert!(overflow(
+            crate::corpus::CorpusEvaluator::new().eval(&packet)
+        ));
+        assert_eq!(crate::const_eval::eval_hex(hex), None);
+    }
+
     #[test]
     fn test_to_expression() {
         for case in TEST_CASES {
             let packet = Packet::try_from(case.hex).unwrap();
             assert_eq!(packet.to_expression().unwrap(), case.expr);
+            assert_eq!(packet.to_string(), case.expr);
+        }
+        let empty = Packet::sum_of(Vec::<Packet>::new());
+        assert_eq!(empty.to_string(), "sum()");
+        assert_eq!(Packet::from_expression("sum()").unwrap(), empty);
+    }
+
+    #[test]
+    fn test_transforms_preserve_eval() {
+        for case in TEST_CASES {
+            let packet = Packet::try_from(case.hex).unwrap();
+            for transformed in [
+                packet.simplify(),
+                packet.canonicalize(),
+                packet.sort_commutative(),
+                packet.minify(),
+            ] {
+                assert_eq!(transformed.eval().unwrap(), case.eval);
+                let reparsed = Packet::try_from(transformed.to_hex().unwrap()).unwrap();
+                assert_eq!(reparsed, transformed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_extended_profile() {
+        let packet = Packet::from_expression("if(1 < 2, 10, max())").unwrap();
+        assert_eq!(packet.eval().unwrap(), 10);
+        assert_eq!(packet.to_expression().unwrap(), "if((1 < 2), 10, max())");
+        assert_eq!(
+            Packet::from_expression("if(0, max(), 5)")
+                .unwrap()
+                .eval()
+                .unwrap(),
+            5
+        );
+        assert!(matches!(
+            packet.to_hex(),
+            Err(PacketError::UnsupportedOperation(
+                Operation::If,
+                Profile::Standard
+            ))
+        ));
+        let hex = packet.to_hex_with_profile(Profile::Extended).unwrap();
+        assert_eq!(
+            Packet::from_hex_with_profile(&hex, Profile::Extended).unwrap(),
+            packet
+        );
+
+        for case in TEST_CASES {
+            let packet = Packet::try_from(case.hex).unwrap();
+            let hex = packet.to_hex_with_profile(Profile::Extended).unwrap();
+            let reparsed = Packet::from_hex_with_profile(&hex, Profile::Extended).unwrap();
+            assert_eq!(reparsed.eval().unwrap(), case.eval);
+            assert_eq!(
+                reparsed.bit_len_in(Profile::Extended),
+                packet.bit_len_in(Profile::Extended)
+            );
+        }
+    }
+
+    #[test]
+    fn test_ord_and_hash() {
+        use std::cmp::Ordering;
+        use std::collections::{BTreeSet, HashSet};
+
+        let packets: Vec<Packet> = ["max(1, 2)", "3", "1 + 2", "1 + 2", "2", "1 + 2 + 0"]
+            .into_iter()
+            .map(|expr| Packet::from_expression(expr).unwrap())
+            .collect();
+        let sorted: Vec<String> = packets
+            .iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(sorted, ["2", "3", "1 + 2", "1 + 2 + 0", "max(1, 2)"]);
+        assert_eq!(packets.iter().collect::<HashSet<_>>().len(), 5);
+
+        // Equal but for the length encoding or version
+        let packet = Packet::try_from("38006F45291200").unwrap();
+        let canonical = packet.canonicalize();
+        assert_ne!(packet.cmp(&canonical), Ordering::Equal);
+        let mut versioned = canonical.clone();
+        versioned.version = Version::MAX;
+        assert!(canonical < versioned);
+        assert!(Operation::Sum < Operation::If);
+        assert!(Length::TotalBits(5) < Length::PacketCount(0));
+    }
+
+    #[test]
+    fn test_entry_points() {
+        let hex = "C200B40A82";
+        let bytes = bytes_from_hex(hex).unwrap();
+        let packet = Packet::parse_hex(hex).unwrap();
+        assert_eq!(Packet::parse_bytes(&bytes).unwrap(), packet);
+        assert_eq!(Packet::try_from(&bytes[..]).unwrap(), packet);
+        assert_eq!(Packet::try_from(bytes.clone()).unwrap(), packet);
+        assert_eq!(Packet::try_from(bytes.into_boxed_slice()).unwrap(), packet);
+        assert_eq!(Packet::try_from(hex.to_string()).unwrap(), packet);
+
+        let enveloped = packet.to_bytes_enveloped().unwrap();
+        assert_eq!(Packet::try_from(enveloped).unwrap(), packet);
+        assert!(Packet::try_from(vec![0xC2]).is_err());
+        assert!(Packet::parse_hex("C2G0").is_err());
+    }
+
+    #[test]
+    fn test_wide_profile() {
+        let profile = Profile::wide(5).unwrap();
+        for case in TEST_CASES {
+            let packet = Packet::try_from(case.hex).unwrap();
+            let hex = packet.to_hex_with_profile(profile).unwrap();
+            let reparsed = Packet::from_hex_with_profile(&hex, profile).unwrap();
+            assert_eq!(reparsed.eval().unwrap(), case.eval);
+            assert_eq!(
+                reparsed.bit_len_in(profile),
+                packet.bit_len_in(Profile::Extended) + packet.flat_packets().len() as u64
+            );
+        }
+
+        // Version 0, type ID 20
+        let error = Packet::from_hex_with_profile("1400", profile).unwrap_err();
+        assert!(matches!(error, PacketError::OperatorError { id: 20, .. }));
+        assert_eq!(Profile::Wide { type_bits: 20 }.type_bits(), 8);
+    }
+
+    #[test]
+    fn test_truncated_operator() {
+        // Sum claiming 2047 sub-packets with 6 bits left
+        assert!(matches!(
+            Packet::try_from("03FFC0"),
+            Err(PacketError::TruncatedOperator {
+                declared: Length::PacketCount(2047),
+                available: 6,
+                path,
+                bit_offset: 0,
+            }) if path.is_empty()
+        ));
+
+        // Sum of one sum claiming 32767 bits of sub-packets
+        let mut bytes = Vec::new();
+        let mut writer = BitWriter::new(&mut bytes);
+        for (bits, value) in [(3, 0), (3, 0), (1, 1), (11, 1), (3, 0), (3, 0), (1, 0)] {
+            writer.write::<u16>(bits, value).unwrap();
+        }
+        writer.write::<u16>(15, 32767).unwrap();
+        writer.byte_align();
+        let error = Packet::try_from(hex_from_bytes(&bytes)).unwrap_err();
+        assert!(matches!(
+            error,
+            PacketError::TruncatedOperator {
+                declared: Length::TotalBits(32767),
+                ref path,
+                bit_offset: 18,
+                ..
+            } if path[..] == [0]
+        ));
+        assert!(error.to_string().contains("at bit 18, path 0 declares"));
+    }
+
+    #[test]
+    fn test_bits() {
+        for case in TEST_CASES {
+            let packet = Packet::try_from(case.hex).unwrap();
+            let (bytes, bit_len) = packet.to_bits().unwrap();
+            assert_eq!(Packet::from_bits(&bytes, bit_len).unwrap(), packet);
+            assert!(matches!(
+                Packet::from_bits(&bytes, bit_len - 1),
+                Err(PacketError::BitsError(_))
+            ));
+        }
+        // 2021 occupies 21 bits, so 3 padding bits are left over at 24
+        let bytes = bytes_from_hex("D2FE28").unwrap();
+        assert!(matches!(
+            Packet::from_bits(&bytes, 24),
+            Err(PacketError::TrailingBits { count: 3 })
+        ));
+        assert!(Packet::from_bits(&bytes, 25).is_err());
+    }
+
+    #[test]
+    fn test_error_location() {
+        // Extended sum of one operator with the unassigned type ID 9
+        let mut bytes = Vec::new();
+        let mut writer = BitWriter::new(&mut bytes);
+        for (bits, value) in [(3, 0), (4, 0), (1, 1), (11, 1), (3, 0), (4, 9)] {
+            writer.write::<u16>(bits, value).unwrap();
+        }
+        writer.write::<u32>(24, 0).unwrap();
+        writer.byte_align();
+        let error =
+            Packet::read_with_profile(&mut BitReader::new(&bytes), Profile::Extended).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidOperator);
+        assert_eq!(error.path(), Some(&[0][..]));
+        assert_eq!(error.bit_offset(), Some(19));
+
+        let error = Packet::try_from("0").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Incomplete);
+        assert_eq!(error.path(), None);
+    }
+
+    #[test]
+    fn test_from_expression() {
+        for case in TEST_CASES {
+            let packet = Packet::from_expression(case.expr).unwrap();
+            assert_eq!(packet.eval().unwrap(), case.eval);
+            assert_eq!(packet.to_expression().unwrap(), case.expr);
         }
     }
 }
@@ -0,0 +1,80 @@
+//! Mermaid flowcharts of packet trees.
+//!
+//! The output can be pasted into a fenced `mermaid` block in Markdown, which
+//! GitHub and many documentation tools render as a diagram.
+//!
+//! ```
+//! let packet = jacob::Packet::from_expression("1 + max(2, 3)").unwrap();
+//! assert_eq!(
+//!     packet.to_mermaid(),
+//!     "graph TD\n\
+//!      \x20   n0[\"sum\"]\n\
+//!      \x20   n0 --> n1[\"1\"]\n\
+//!      \x20   n0 --> n2[\"max\"]\n\
+//!      \x20   n2 --> n3[\"2\"]\n\
+//!      \x20   n2 --> n4[\"3\"]\n"
+//! );
+//! ```
+
+use std::fmt::Write;
+
+use crate::{Packet, PacketKind};
+
+impl Packet {
+    /// Returns the tree as a top-down Mermaid flowchart. Nodes are numbered
+    /// in pre-order and labelled with their operation or literal value.
+    #[must_use]
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("graph TD\n");
+        let _ = writeln!(mermaid, "    n0[\"{}\"]", label(self));
+        write_operands(&mut mermaid, self, 0, &mut 1);
+        mermaid
+    }
+}
+
+/// Writes an edge to each operand of the node numbered `id`, numbering them
+/// from `next`.
+fn write_operands(mermaid: &mut String, packet: &Packet, id: usize, next: &mut usize) {
+    if let PacketKind::Operator { packets, .. } = &packet.kind {
+        for operand in packets {
+            let child = *next;
+            *next += 1;
+            let _ = writeln!(mermaid, "    n{id} --> n{child}[\"{}\"]", label(operand));
+            write_operands(mermaid, operand, child, next);
+        }
+    }
+}
+
+fn label(packet: &Packet) -> String {
+    match &packet.kind {
+        PacketKind::Literal(value) => value.to_string(),
+        PacketKind::Operator { operation, .. } => operation.as_func_str().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Packet;
+
+    #[test]
+    fn test_to_mermaid() {
+        assert_eq!(Packet::from(7).to_mermaid(), "graph TD\n    n0[\"7\"]\n");
+
+        let packet = Packet::from_expression("(1 + 2) * (3 < 4)").unwrap();
+        let mermaid = packet.to_mermaid();
+        let lines: Vec<&str> = mermaid.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "graph TD",
+                "    n0[\"product\"]",
+                "    n0 --> n1[\"sum\"]",
+                "    n1 --> n2[\"1\"]",
+                "    n1 --> n3[\"2\"]",
+                "    n0 --> n4[\"lt\"]",
+                "    n4 --> n5[\"3\"]",
+                "    n4 --> n6[\"4\"]",
+            ]
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Shrinking failing packets into small reproducers.
+//!
+//! ```
+//! use jacob::{minimize, Packet};
+//!
+//! let packet = Packet::from_expression("1 + max(2, 3 * 40) + 5").unwrap();
+//! // Pretend products are mishandled
+//! let failing = |packet: &Packet| packet.to_expression().unwrap().contains('*');
+//! let minimal = minimize(&packet, failing);
+//! assert_eq!(minimal.to_expression().unwrap(), "0 * 0");
+//! ```
+
+use crate::{Packet, PacketKind, Version};
+
+/// Returns a packet no larger than `packet` for which `predicate` still
+/// holds, or a clone of `packet` if it does not hold to begin with.
+///
+/// Shrinking is greedy and deterministic: it repeatedly takes the first of
+/// these steps the predicate accepts, until none is accepted:
+///
+/// - replacing an operator by one of its operands
+/// - dropping an operand
+/// - replacing a literal by `0`, or truncating its highest or lowest bit
+/// - resetting a version to `0`
+///
+/// Length fields are recomputed after every step, keeping their encoding.
+pub fn minimize(packet: &Packet, predicate: impl Fn(&Packet) -> bool) -> Packet {
+    let mut packet = packet.clone();
+    if !predicate(&packet) {
+        return packet;
+    }
+    'shrink: loop {
+        for candidate in steps(&packet) {
+            if predicate(&candidate) {
+                packet = candidate;
+                continue 'shrink;
+            }
+        }
+        return packet;
+    }
+}
+
+/// Returns every packet one step smaller than `packet`, largest reductions
+/// closest to the root first.
+fn steps(packet: &Packet) -> Vec<Packet> {
+    let mut candidates = Vec::new();
+    match &packet.kind {
+        PacketKind::Literal(0) => {}
+        &PacketKind::Literal(value) => {
+            let top_bit = 1 << (usize::BITS - 1 - value.leading_zeros());
+            for smaller in [0, value & !top_bit, value >> 1] {
+                candidates.push(Packet {
+                    version: packet.version,
+                    kind: PacketKind::Literal(smaller),
+                });
+            }
+        }
+        PacketKind::Operator {
+            length,
+            operation,
+            packets,
+        } => {
+            candidates.extend(packets.iter().cloned());
+            let rebuild = |packets: Vec<Packet>| Packet {
+                version: packet.version,
+                kind: PacketKind::Operator {
+                    length: length.recomputed(&packets),
+                    operation: *operation,
+                    packets,
+                },
+            };
+            for i in 0..packets.len() {
+                let mut fewer = packets.clone();
+                fewer.remove(i);
+                candidates.push(rebuild(fewer));
+            }
+            for (i, child) in packets.iter().enumerate() {
+                for smaller in steps(child) {
+                    let mut replaced = packets.clone();
+                    replaced[i] = smaller;
+                    candidates.push(rebuild(replaced));
+                }
+            }
+        }
+    }
+    if packet.version != Version::ZERO {
+        candidates.push(Packet {
+            version: Version::ZERO,
+            kind: packet.kind.clone(),
+        });
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::minimize;
+    use crate::{Packet, PacketKind};
+
+    #[test]
+    fn test_minimize() {
+        let packet = Packet::try_from("9C0141080250320F1802104A08").unwrap();
+        // Pretend any literal above 100 triggers a bug
+        let large_literal = |packet: &Packet| {
+            packet
+                .flat_packets()
+                .iter()
+                .any(|p| matches!(p.kind, PacketKind::Literal(value) if value > 100))
+        };
+        assert_eq!(minimize(&packet, large_literal), packet);
+
+        let packet = Packet::from_expression("max(1 + 2, 3 * (4 + 500))").unwrap();
+        let minimal = minimize(&packet, large_literal);
+        assert_eq!(minimal, Packet::from(116));
+
+        // Every packet shrinks to a literal `0` with a trivial predicate
+        assert_eq!(minimize(&packet, |_| true), Packet::from(0));
+    }
+}
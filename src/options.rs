@@ -0,0 +1,279 @@
+//! Configurable parsing.
+//!
+//! ```
+//! use jacob::{Packet, PacketError, ParseOptions, TrailingPadding};
+//!
+//! let options = ParseOptions {
+//!     trailing_padding: TrailingPadding::MustBeZero,
+//!     ..ParseOptions::default()
+//! };
+//! assert!(Packet::from_hex_with("D2FE28", &options).is_ok());
+//! assert!(matches!(
+//!     Packet::from_hex_with("D2FE2F", &options),
+//!     Err(PacketError::NonZeroPadding { offset: 21 })
+//! ));
+//! ```
+
+use crate::bits::{BitCursor, BitReader, HexBitReader};
+use crate::{
+    bytes_from_hex, envelope, shared, Packet, PacketError, PacketKind, Profile, Warning, Warnings,
+};
+
+/// How bits after the top-level packet are treated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrailingPadding {
+    /// Accept anything after the packet.
+    #[default]
+    Ignore,
+    /// Every bit after the packet must be zero.
+    MustBeZero,
+    /// Only the zero bits completing the packet's final byte may follow it.
+    Forbid,
+}
+
+/// Options for [`Packet::from_bytes_with`] and [`Packet::from_hex_with`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Profile of bare transmissions. Enveloped transmissions name their own.
+    pub profile: Profile,
+    pub trailing_padding: TrailingPadding,
+    /// Largest input accepted, in bytes once decoded, or in characters for
+    /// expressions.
+    pub max_input_bytes: Option<usize>,
+    /// Deepest nesting of operators accepted, as counted by
+    /// [`Packet::depth`]. Parsing stops at the first operator too deep.
+    pub max_depth: Option<usize>,
+    /// Most packets a transmission in the [`Shared`](Profile::Shared)
+    /// profile may expand to, counting each reference as the packets it
+    /// copies. [`shared::MAX_EXPANDED_PACKETS`] if unset.
+    pub max_expanded_packets: Option<usize>,
+}
+
+impl ParseOptions {
+    /// Returns `Err` if an input of `size` bytes exceeds
+    /// [`max_input_bytes`](Self::max_input_bytes).
+    ///
+    /// # Errors
+    ///
+    /// Will return [`PacketError::InputTooLarge`] if it does.
+    pub const fn check_input_size(&self, size: usize) -> Result<(), PacketError> {
+        match self.max_input_bytes {
+            Some(limit) if size > limit => Err(PacketError::InputTooLarge { size, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns [`max_expanded_packets`](Self::max_expanded_packets), or its
+    /// default if unset.
+    pub(crate) fn max_expanded(&self) -> usize {
+        self.max_expanded_packets
+            .unwrap_or(shared::MAX_EXPANDED_PACKETS)
+    }
+
+    /// Returns `Err` if `packet` is nested deeper than
+    /// [`max_depth`](Self::max_depth), for packets not parsed from bits.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`PacketError::TooDeep`] if it is.
+    pub fn check_depth(&self, packet: &Packet) -> Result<(), PacketError> {
+        let Some(limit) = self.max_depth else {
+            return Ok(());
+        };
+        let mut stack = vec![(Vec::new(), packet)];
+        while let Some((path, packet)) = stack.pop() {
+            if let PacketKind::Operator { packets, .. } = &packet.kind {
+                if path.len() >= limit {
+                    return Err(PacketError::TooDeep {
+                        limit,
+                        path: path.into(),
+                    });
+                }
+                for (i, child) in packets.iter().enumerate() {
+                    let mut path = path.clone();
+                    path.push(i);
+                    stack.push((path, child));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Packet {
+    /// Parses a packet from bytes according to `options`, unwrapping an
+    /// envelope if present.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the bytes do not form a valid packet, exceed a
+    /// limit, or the padding after the packet violates
+    /// [`ParseOptions::trailing_padding`].
+    pub fn from_bytes_with(bytes: &[u8], options: &ParseOptions) -> Result<Self, PacketError> {
+        Self::from_bytes_into(bytes, options, &mut Warnings::new())
+    }
+
+    /// Parses like [`Packet::from_bytes_with`], adding warnings about the
+    /// encoding to `warnings`.
+    fn from_bytes_into(
+        bytes: &[u8],
+        options: &ParseOptions,
+        warnings: &mut Warnings,
+    ) -> Result<Self, PacketError> {
+        options.check_input_size(bytes.len())?;
+        let (profile, payload) = if envelope::is_enveloped(bytes) {
+            envelope::open(bytes)?
+        } else {
+            (options.profile, bytes)
+        };
+        Self::read_with(&mut BitReader::new(payload), profile, options, warnings)
+    }
+
+    /// Parses a hexadecimal packet according to `options`.
+    ///
+    /// Bare transmissions are decoded a digit at a time by a
+    /// [`HexBitReader`] as they are parsed, so no copy of the input as bytes
+    /// is made.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the string is not valid hexadecimal or
+    /// [`Packet::from_bytes_with`] fails.
+    pub fn from_hex_with(hex: &str, options: &ParseOptions) -> Result<Self, PacketError> {
+        Self::from_hex_into(hex, options, &mut Warnings::new())
+    }
+
+    /// Parses like [`Packet::from_hex_with`], adding warnings about the
+    /// encoding to `warnings`.
+    pub(crate) fn from_hex_into(
+        hex: &str,
+        options: &ParseOptions,
+        warnings: &mut Warnings,
+    ) -> Result<Self, PacketError> {
+        let mut reader = HexBitReader::new(hex)?;
+        options.check_input_size(reader.byte_len())?;
+        let magic = u32::from_be_bytes(envelope::MAGIC);
+        if reader.clone().read(u32::BITS) == Ok(u64::from(magic)) {
+            return Self::from_bytes_into(&bytes_from_hex(hex)?, options, warnings);
+        }
+        Self::read_with(&mut reader, options.profile, options, warnings)
+    }
+
+    /// Parses the packet at the start of `reader`, then checks whatever
+    /// follows it against [`ParseOptions::trailing_padding`].
+    fn read_with<C: BitCursor>(
+        reader: &mut C,
+        profile: Profile,
+        options: &ParseOptions,
+        warnings: &mut Warnings,
+    ) -> Result<Self, PacketError> {
+        let total = reader.position() + reader.remaining();
+        let max_depth = options.max_depth.unwrap_or(usize::MAX);
+        let packet =
+            Self::read_with_limits(reader, profile, max_depth, options.max_expanded(), warnings)?;
+
+        let end = reader.position();
+        let aligned_end = end.div_ceil(8) * 8;
+        if total > aligned_end {
+            warnings.push(Warning::UnusedBits {
+                count: total - aligned_end,
+            });
+        }
+        let checked_end = match options.trailing_padding {
+            TrailingPadding::Ignore => return Ok(packet),
+            TrailingPadding::MustBeZero => total,
+            TrailingPadding::Forbid if total > aligned_end => {
+                return Err(PacketError::TrailingBits {
+                    count: total - aligned_end,
+                })
+            }
+            TrailingPadding::Forbid => aligned_end,
+        };
+        for offset in end..checked_end {
+            if reader.read_bool()? {
+                return Err(PacketError::NonZeroPadding { offset });
+            }
+        }
+        Ok(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParseOptions, TrailingPadding};
+    use crate::{Packet, PacketError};
+
+    fn parse(hex: &str, trailing_padding: TrailingPadding) -> Result<Packet, PacketError> {
+        Packet::from_hex_with(
+            hex,
+            &ParseOptions {
+                trailing_padding,
+                ..ParseOptions::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_trailing_padding() {
+        for hex in ["D2FE28", "D2FE2800", "D2FE2F01"] {
+            assert!(parse(hex, TrailingPadding::Ignore).is_ok());
+        }
+        assert!(parse("D2FE2800", TrailingPadding::MustBeZero).is_ok());
+        assert!(matches!(
+            parse("D2FE2801", TrailingPadding::MustBeZero),
+            Err(PacketError::NonZeroPadding { offset: 31 })
+        ));
+        assert!(parse("D2FE28", TrailingPadding::Forbid).is_ok());
+        assert!(matches!(
+            parse("D2FE2800", TrailingPadding::Forbid),
+            Err(PacketError::TrailingBits { count: 8 })
+        ));
+        assert!(matches!(
+            parse("D2FE2C", TrailingPadding::Forbid),
+            Err(PacketError::NonZeroPadding { offset: 21 })
+        ));
+    }
+
+    #[test]
+    fn test_limits() {
+        let options = ParseOptions {
+            max_input_bytes: Some(4),
+            max_depth: Some(1),
+            ..ParseOptions::default()
+        };
+        assert!(Packet::from_hex_with("C200B40A82", &options).is_err());
+        assert!(matches!(
+            Packet::from_hex_with(
+                "C200B40A82",
+                &ParseOptions {
+                    max_input_bytes: Some(4),
+                    ..ParseOptions::default()
+                }
+            ),
+            Err(PacketError::InputTooLarge { size: 5, limit: 4 })
+        ));
+        // `1 + (2 * 3)` has depth 2
+        let hex = Packet::from_expression("1 + (2 * 3)")
+            .unwrap()
+            .to_hex()
+            .unwrap();
+        assert!(Packet::from_hex_with(
+            &hex,
+            &ParseOptions {
+                max_depth: Some(2),
+                ..ParseOptions::default()
+            }
+        )
+        .is_ok());
+        assert!(matches!(
+            Packet::from_hex_with(&hex, &ParseOptions {
+                max_depth: Some(1),
+                ..ParseOptions::default()
+            }),
+            Err(PacketError::TooDeep { limit: 1, ref path }) if path[..] == [1]
+        ));
+        let packet = Packet::from_expression("1 + (2 * 3)").unwrap();
+        assert!(options.check_depth(&packet).is_err());
+        assert!(ParseOptions::default().check_depth(&packet).is_ok());
+    }
+}
@@ -0,0 +1,126 @@
+//! Incremental parsing of transmissions that arrive in chunks.
+//!
+//! ```
+//! use jacob::partial::{PartialParser, Progress};
+//!
+//! let bytes = jacob::bytes_from_hex("9C0141080250320F1802104A08").unwrap();
+//! let mut parser = PartialParser::new();
+//! let (head, tail) = bytes.split_at(5);
+//! assert!(matches!(parser.feed(head).unwrap(), Progress::NeedMoreData));
+//! match parser.feed(tail).unwrap() {
+//!     Progress::Complete(packet) => assert_eq!(packet.eval().unwrap(), 1),
+//!     Progress::NeedMoreData => unreachable!(),
+//! }
+//! ```
+
+use crate::bits::{BitCursor, BitError, BitReader};
+use crate::{Packet, PacketError, Profile};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Progress {
+    /// The buffered bytes are a valid prefix of a packet.
+    NeedMoreData,
+    /// A full packet was parsed from the buffered bytes.
+    Complete(Packet),
+}
+
+/// Buffers bytes until they form a complete packet.
+///
+/// Each call to [`feed`](Self::feed) re-parses the buffered bytes, so feeding
+/// many tiny chunks of a large transmission costs more than a few large ones.
+#[derive(Clone, Debug, Default)]
+pub struct PartialParser {
+    buffer: Vec<u8>,
+    profile: Profile,
+}
+
+impl PartialParser {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub const fn with_profile(profile: Profile) -> Self {
+        Self {
+            buffer: Vec::new(),
+            profile,
+        }
+    }
+
+    /// Appends `bytes` and attempts to parse a packet from everything buffered.
+    ///
+    /// Once a packet is complete, the bytes it occupied (including padding up
+    /// to the next byte boundary) are dropped from the buffer, and any bytes
+    /// after them are kept as the start of the next packet.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the buffered bytes can never form a valid packet,
+    /// no matter what is fed next. The buffer is left untouched.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<Progress, PacketError> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut reader = BitReader::new(&self.buffer);
+        match Packet::read_with_profile(&mut reader, self.profile) {
+            Ok(packet) => {
+                let consumed =
+                    usize::try_from(reader.position().div_ceil(8)).unwrap_or(self.buffer.len());
+                self.buffer.drain(..consumed);
+                Ok(Progress::Complete(packet))
+            }
+            Err(
+                PacketError::BitsError(BitError::NotEnoughData { .. })
+                | PacketError::TruncatedOperator { .. },
+            ) => Ok(Progress::NeedMoreData),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the number of buffered bytes not yet part of a complete packet.
+    #[must_use]
+    pub const fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Discards all buffered bytes.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PartialParser, Progress};
+    use crate::{bytes_from_hex, Packet};
+
+    #[test]
+    fn test_feed_byte_by_byte() {
+        let hex = "880086C3E88112";
+        let bytes = bytes_from_hex(hex).unwrap();
+        let mut parser = PartialParser::new();
+        for byte in &bytes[..bytes.len() - 1] {
+            assert_eq!(parser.feed(&[*byte]).unwrap(), Progress::NeedMoreData);
+        }
+        assert_eq!(
+            parser.feed(&bytes[bytes.len() - 1..]).unwrap(),
+            Progress::Complete(Packet::try_from(hex).unwrap())
+        );
+        assert_eq!(parser.buffered(), 0);
+    }
+
+    #[test]
+    fn test_consecutive_packets() {
+        let mut bytes = bytes_from_hex("D2FE28").unwrap();
+        bytes.extend(bytes_from_hex("C200B40A82").unwrap());
+        let mut parser = PartialParser::new();
+        let Progress::Complete(first) = parser.feed(&bytes).unwrap() else {
+            panic!("first packet is complete");
+        };
+        assert_eq!(first.eval().unwrap(), 2021);
+        let Progress::Complete(second) = parser.feed(&[]).unwrap() else {
+            panic!("second packet is complete");
+        };
+        assert_eq!(second.eval().unwrap(), 3);
+    }
+}
@@ -0,0 +1,289 @@
+//! Ordered pipelines of packet transformations.
+//!
+//! A [`PassManager`] runs registered [`Pass`]es in order and records a
+//! [`PassStats`] entry for each one.
+//!
+//! ```
+//! use jacob::pass::{PassManager, Simplify, SortCommutative};
+//! use jacob::{Packet, Version};
+//!
+//! let mut manager = PassManager::new();
+//! manager
+//!     .add(Simplify)
+//!     .add(SortCommutative)
+//!     .add_fn("zero-versions", |packet| Packet {
+//!         version: Version::ZERO,
+//!         ..packet.clone()
+//!     });
+//!
+//! let packet = Packet::from_expression("(3 + 2) + 1").unwrap();
+//! let (packet, stats) = manager.run(&packet);
+//! assert_eq!(packet.to_expression().unwrap(), "1 + 2 + 3");
+//! assert_eq!(stats.len(), 3);
+//! ```
+
+use std::time::{Duration, Instant};
+
+use crate::transform::SimplifyStats;
+use crate::Packet;
+
+/// A value-preserving transformation of a packet tree.
+pub trait Pass {
+    /// Returns the name reported in [`PassStats`].
+    fn name(&self) -> &str;
+
+    /// Returns the transformed packet.
+    fn run(&self, packet: &Packet) -> Packet;
+
+    /// Returns the transformed packet and how often each of the pass's rules
+    /// fired, by name. Passes without rules report none.
+    fn run_with_rules(&self, packet: &Packet) -> (Packet, Vec<(String, usize)>) {
+        (self.run(packet), Vec::new())
+    }
+}
+
+/// Names the rules of [`Packet::simplify_with_stats`] that fired.
+fn fired(stats: SimplifyStats) -> Vec<(String, usize)> {
+    stats
+        .rules()
+        .into_iter()
+        .filter(|&(_, count)| count > 0)
+        .map(|(rule, count)| (rule.to_string(), count))
+        .collect()
+}
+
+/// Pass wrapping [`Packet::simplify`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Simplify;
+
+/// Pass wrapping [`Packet::canonicalize`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Canonicalize;
+
+/// Pass wrapping [`Packet::sort_commutative`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SortCommutative;
+
+/// Pass wrapping [`Packet::minify`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Minify;
+
+impl Pass for Simplify {
+    fn name(&self) -> &'static str {
+        "simplify"
+    }
+
+    fn run(&self, packet: &Packet) -> Packet {
+        packet.simplify()
+    }
+
+    fn run_with_rules(&self, packet: &Packet) -> (Packet, Vec<(String, usize)>) {
+        let (packet, stats) = packet.simplify_with_stats();
+        (packet, fired(stats))
+    }
+}
+
+impl Pass for Canonicalize {
+    fn name(&self) -> &'static str {
+        "canonicalize"
+    }
+
+    fn run(&self, packet: &Packet) -> Packet {
+        packet.canonicalize()
+    }
+}
+
+impl Pass for SortCommutative {
+    fn name(&self) -> &'static str {
+        "sort-commutative"
+    }
+
+    fn run(&self, packet: &Packet) -> Packet {
+        packet.sort_commutative()
+    }
+}
+
+impl Pass for Minify {
+    fn name(&self) -> &'static str {
+        "minify"
+    }
+
+    fn run(&self, packet: &Packet) -> Packet {
+        packet.minify()
+    }
+
+    fn run_with_rules(&self, packet: &Packet) -> (Packet, Vec<(String, usize)>) {
+        // Simplifying again inside `minify` finds nothing more to do
+        let (packet, stats) = packet.simplify_with_stats();
+        (packet.minify(), fired(stats))
+    }
+}
+
+/// Pass built from a name and a closure.
+pub struct FnPass<F> {
+    name: String,
+    f: F,
+}
+
+impl<F: Fn(&Packet) -> Packet> FnPass<F> {
+    pub fn new(name: impl Into<String>, f: F) -> Self {
+        Self {
+            name: name.into(),
+            f,
+        }
+    }
+}
+
+impl<F: Fn(&Packet) -> Packet> Pass for FnPass<F> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&self, packet: &Packet) -> Packet {
+        (self.f)(packet)
+    }
+}
+
+/// Measurements taken around a single pass.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PassStats {
+    pub name: String,
+    pub duration: Duration,
+    /// Number of packets in the tree, including the root, before the pass.
+    pub packets_before: usize,
+    pub packets_after: usize,
+    /// Serialized size of the tree in bits, excluding padding, before the pass.
+    pub bits_before: u64,
+    pub bits_after: u64,
+    /// Rules of the pass that fired, with how often; see [`Pass::run_with_rules`].
+    pub rules: Vec<(String, usize)>,
+}
+
+impl PassStats {
+    /// Returns `true` if the pass altered the size of the tree.
+    #[must_use]
+    pub const fn changed_size(&self) -> bool {
+        self.packets_before != self.packets_after || self.bits_before != self.bits_after
+    }
+}
+
+/// Ordered collection of passes.
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl PassManager {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `pass` to the end of the pipeline.
+    pub fn add(&mut self, pass: impl Pass + 'static) -> &mut Self {
+        self.add_boxed(Box::new(pass))
+    }
+
+    /// Appends an already boxed `pass` to the end of the pipeline.
+    pub fn add_boxed(&mut self, pass: Box<dyn Pass>) -> &mut Self {
+        self.passes.push(pass);
+        self
+    }
+
+    /// Appends a closure to the end of the pipeline.
+    pub fn add_fn(
+        &mut self,
+        name: impl Into<String>,
+        f: impl Fn(&Packet) -> Packet + 'static,
+    ) -> &mut Self {
+        self.add(FnPass::new(name, f))
+    }
+
+    /// Inserts `pass` at position `index`, shifting later passes back.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `index` is greater than the number of passes.
+    pub fn insert(&mut self, index: usize, pass: impl Pass + 'static) -> &mut Self {
+        self.passes.insert(index, Box::new(pass));
+        self
+    }
+
+    /// Removes every pass called `name`, returning `true` if any were removed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.passes.len();
+        self.passes.retain(|pass| pass.name() != name);
+        self.passes.len() != len
+    }
+
+    /// Returns the names of the registered passes in run order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.passes.iter().map(|pass| pass.name())
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    /// Runs every pass in order, returning the final packet and per-pass statistics.
+    #[must_use]
+    pub fn run(&self, packet: &Packet) -> (Packet, Vec<PassStats>) {
+        let mut packet = packet.clone();
+        let mut stats = Vec::with_capacity(self.passes.len());
+        for pass in &self.passes {
+            let packets_before = packet.flat_packets().len();
+            let bits_before = packet.bit_len();
+            let start = Instant::now();
+            let rules;
+            (packet, rules) = pass.run_with_rules(&packet);
+            stats.push(PassStats {
+                name: pass.name().to_string(),
+                duration: start.elapsed(),
+                packets_before,
+                packets_after: packet.flat_packets().len(),
+                bits_before,
+                bits_after: packet.bit_len(),
+                rules,
+            });
+        }
+        (packet, stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Canonicalize, Minify, PassManager, Simplify, SortCommutative};
+    use crate::Packet;
+
+    #[test]
+    fn test_pass_manager_order_and_stats() {
+        let mut manager = PassManager::new();
+        manager.add(Simplify).add(Minify).insert(0, SortCommutative);
+        manager.add(Canonicalize);
+        assert_eq!(
+            manager.names().collect::<Vec<_>>(),
+            ["sort-commutative", "simplify", "minify", "canonicalize"]
+        );
+        assert!(manager.remove("minify"));
+        assert!(!manager.remove("minify"));
+
+        let packet = Packet::from_expression("sum(5) + (2 + 1)").unwrap();
+        let (packet, stats) = manager.run(&packet);
+        assert_eq!(packet.to_expression().unwrap(), "5 + 1 + 2");
+        assert_eq!(stats.len(), 3);
+        assert!(!stats[0].changed_size());
+        assert_eq!((stats[1].packets_before, stats[1].packets_after), (6, 4));
+        assert!(stats[1].bits_after < stats[1].bits_before);
+        assert_eq!(
+            stats[1].rules,
+            [("flatten".to_string(), 1), ("unwrap".to_string(), 1)]
+        );
+        assert!(stats[0].rules.is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+//! Locations of packets within a tree.
+//!
+//! A [`PacketPath`] lists the operand index taken at each level from the root
+//! down, and is written as the indices separated by dots, or `root` for the
+//! root itself. Errors about one packet of a tree, such as
+//! [`PacketError::ArgumentError`](crate::PacketError::ArgumentError), carry
+//! its path so it can be found again:
+//!
+//! ```
+//! use jacob::{Packet, PacketPath};
+//!
+//! let packet = Packet::from_expression("1 + max(2, 3 * min())").unwrap();
+//! let error = packet.eval().unwrap_err();
+//! let path: PacketPath = "1.1.1".parse().unwrap();
+//! assert_eq!(error.path(), Some(&path[..]));
+//! assert_eq!(packet[&path].to_string(), "min()");
+//! assert_eq!(PacketPath::root().to_string(), "root");
+//! ```
+
+use std::fmt;
+use std::num::ParseIntError;
+use std::ops::{Deref, Index};
+use std::str::FromStr;
+
+use crate::Packet;
+
+/// Path of a packet from the root of its tree; see the
+/// [module documentation](self).
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PacketPath(Vec<usize>);
+
+impl PacketPath {
+    /// Returns the path of the root.
+    #[must_use]
+    pub const fn root() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Returns the path of the `index`th operand of the packet at `self`.
+    #[must_use]
+    pub fn child(&self, index: usize) -> Self {
+        let mut path = self.0.clone();
+        path.push(index);
+        Self(path)
+    }
+
+    /// Returns the path of the packet containing the one at `self`, or `None`
+    /// for the root.
+    #[must_use]
+    pub fn parent(&self) -> Option<Self> {
+        self.0.split_last().map(|(_, parent)| Self(parent.to_vec()))
+    }
+
+    /// Makes the path relative to the parent of its root, whose `index`th
+    /// operand that root is.
+    pub(crate) fn prepend(&mut self, index: usize) {
+        self.0.insert(0, index);
+    }
+}
+
+impl Deref for PacketPath {
+    type Target = [usize];
+
+    fn deref(&self) -> &[usize] {
+        &self.0
+    }
+}
+
+impl From<Vec<usize>> for PacketPath {
+    fn from(path: Vec<usize>) -> Self {
+        Self(path)
+    }
+}
+
+impl From<&[usize]> for PacketPath {
+    fn from(path: &[usize]) -> Self {
+        Self(path.to_vec())
+    }
+}
+
+impl From<PacketPath> for Vec<usize> {
+    fn from(path: PacketPath) -> Self {
+        path.0
+    }
+}
+
+impl fmt::Display for PacketPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((first, rest)) = self.0.split_first() else {
+            return f.write_str("root");
+        };
+        write!(f, "{first}")?;
+        for i in rest {
+            write!(f, ".{i}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PacketPath {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "root" => Ok(Self::root()),
+            dotted => dotted
+                .split('.')
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map(Self),
+        }
+    }
+}
+
+impl Index<&PacketPath> for Packet {
+    type Output = Self;
+
+    /// Returns the packet at `path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no packet at `path`; see [`Packet::get`].
+    fn index(&self, path: &PacketPath) -> &Self {
+        self.get(path).expect("no packet at path")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PacketPath;
+    use crate::width::Overflow;
+    use crate::{eval_expression, Packet};
+
+    #[test]
+    fn test_packet_path() {
+        let path: PacketPath = "0.3.1".parse().unwrap();
+        assert_eq!(&path[..], [0, 3, 1]);
+        assert_eq!(path.to_string(), "0.3.1");
+        assert_eq!(path.parent().unwrap(), PacketPath::from(vec![0, 3]));
+        assert_eq!(PacketPath::root().child(2).to_string(), "2");
+        assert_eq!("root".parse(), Ok(PacketPath::root()));
+        assert_eq!(PacketPath::root().parent(), None);
+        assert!("".parse::<PacketPath>().is_err());
+        assert!("0..1".parse::<PacketPath>().is_err());
+        assert!("-1".parse::<PacketPath>().is_err());
+
+        let packet = Packet::from_expression("max(1, 2 + 3)").unwrap();
+        assert_eq!(packet[&"1.0".parse().unwrap()], Packet::from(2));
+        assert_eq!(packet[&PacketPath::root()], packet);
+        assert!(packet.get(&"0.0".parse::<PacketPath>().unwrap()).is_none());
+
+        // Every evaluator reports where arity is wrong
+        let packet = Packet::from_expression("if(1, max(), 2) + 3").unwrap();
+        let expected = Some(&[0, 1][..]);
+        assert_eq!(packet.eval().unwrap_err().path(), expected);
+        assert_eq!(
+            packet.eval_as::<u8>(Overflow::Checked).unwrap_err().path(),
+            expected
+        );
+        assert_eq!(packet.compile().unwrap_err().path(), expected);
+        assert_eq!(
+            eval_expression("if(1, max(), 2) + 3").unwrap_err().path(),
+            expected
+        );
+        assert_eq!(
+            Packet::from_expression("eq(1)")
+                .unwrap()
+                .eval()
+                .unwrap_err()
+                .to_string(),
+            "invalid number of arguments `1` for operation `EqualTo` at path root"
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! Common types and one-call conversions.
+//!
+//! ```
+//! use jacob::prelude::*;
+//!
+//! assert_eq!(eval_hex("C200B40A82").unwrap(), 3);
+//! assert_eq!(hex_to_expression("C200B40A82").unwrap(), "1 + 2");
+//! assert_eq!(eval_expression("(1 + 3) == (2 * 2)").unwrap(), 1);
+//! let hex = expression_to_hex("6 * 9").unwrap();
+//! assert_eq!(eval_hex(&hex).unwrap(), 54);
+//! ```
+
+pub use crate::{
+    ErrorKind, Evaluator, ExpressionStyle, Length, Operation, Packet, PacketError, PacketKind,
+    Profile, Version,
+};
+
+/// Evaluates a hexadecimal packet.
+///
+/// ```
+/// assert_eq!(jacob::prelude::eval_hex("D2FE28").unwrap(), 2021);
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if the packet fails to parse or evaluate.
+pub fn eval_hex(hex: &str) -> Result<usize, PacketError> {
+    hex.parse::<Packet>()?.eval()
+}
+
+/// Evaluates a mathematical expression.
+///
+/// ```
+/// assert_eq!(jacob::prelude::eval_expression("min(7, 8, 9)").unwrap(), 7);
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if the expression fails to parse or evaluate.
+pub fn eval_expression(expression: &str) -> Result<usize, PacketError> {
+    Packet::from_expression(expression)?.eval()
+}
+
+/// Decompiles a hexadecimal packet into a mathematical expression.
+///
+/// ```
+/// assert_eq!(
+///     jacob::prelude::hex_to_expression("9C0141080250320F1802104A08").unwrap(),
+///     "(1 + 3) == (2 * 2)"
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if the packet fails to parse or render.
+pub fn hex_to_expression(hex: &str) -> Result<String, PacketError> {
+    hex.parse::<Packet>()?.to_expression()
+}
+
+/// Compiles a mathematical expression into a hexadecimal packet with version `0`.
+///
+/// ```
+/// assert_eq!(jacob::prelude::expression_to_hex("2021").unwrap(), "12FE28");
+/// ```
+///
+/// # Errors
+///
+/// Will return `Err` if the expression fails to parse or serialize.
+pub fn expression_to_hex(expression: &str) -> Result<String, PacketError> {
+    Packet::from_expression(expression)?.to_hex()
+}
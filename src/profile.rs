@@ -0,0 +1,88 @@
+//! Encoding dialects.
+//!
+//! The [`Standard`](Profile::Standard) profile is the format from Advent of
+//! Code: a 3-bit type ID and the seven standard operations. The
+//! [`Extended`](Profile::Extended) profile widens the type ID to 4 bits, making
+//! room for operations such as [`Operation::If`]. Lengths counted in bits are
+//! recomputed for the wider headers when serializing with it. The experimental
+//! [`Shared`](Profile::Shared) profile adds back-references to the extended
+//! profile; see [`crate::shared`]. The experimental
+//! [`RunLength`](Profile::RunLength) profile adds run-length encoded literals
+//! to it instead; see [`crate::rle`].
+//!
+//! The [`Wide`](Profile::Wide) profile is the extended profile with a type
+//! field of any width from 4 to 8 bits, for experimenting with richer
+//! instruction sets. Type IDs without an operation are rejected when parsing.
+//!
+//! ```
+//! use jacob::{Packet, Profile};
+//!
+//! let profile = Profile::wide(6).unwrap();
+//! let packet = Packet::from_expression("if(1 < 2, 3, 4)").unwrap();
+//! let hex = packet.to_hex_with_profile(profile).unwrap();
+//! assert_eq!(Packet::from_hex_with_profile(&hex, profile).unwrap(), packet);
+//! assert!(Profile::wide(9).is_none());
+//! ```
+
+use crate::Operation;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Profile {
+    #[default]
+    Standard,
+    Extended,
+    Shared,
+    RunLength,
+    /// The extended profile with a `type_bits`-wide type field. Prefer
+    /// [`Profile::wide`], which checks the width; widths outside 4 to 8 bits
+    /// are clamped.
+    Wide {
+        type_bits: u8,
+    },
+}
+
+impl Profile {
+    /// Narrowest type field of the [`Wide`](Self::Wide) profile.
+    pub const MIN_WIDE_TYPE_BITS: u8 = 4;
+    /// Widest type field of the [`Wide`](Self::Wide) profile.
+    pub const MAX_WIDE_TYPE_BITS: u8 = 8;
+
+    /// Returns the [`Wide`](Self::Wide) profile with a `type_bits`-wide type
+    /// field, or `None` if the width is outside 4 to 8 bits.
+    #[must_use]
+    pub const fn wide(type_bits: u8) -> Option<Self> {
+        if type_bits >= Self::MIN_WIDE_TYPE_BITS && type_bits <= Self::MAX_WIDE_TYPE_BITS {
+            Some(Self::Wide { type_bits })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the width in bits of the type ID field.
+    #[must_use]
+    pub const fn type_bits(self) -> u8 {
+        match self {
+            Self::Standard => 3,
+            Self::Extended | Self::Shared | Self::RunLength => 4,
+            Self::Wide { type_bits } => {
+                if type_bits < Self::MIN_WIDE_TYPE_BITS {
+                    Self::MIN_WIDE_TYPE_BITS
+                } else if type_bits > Self::MAX_WIDE_TYPE_BITS {
+                    Self::MAX_WIDE_TYPE_BITS
+                } else {
+                    type_bits
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if `operation` can be encoded in this profile.
+    #[must_use]
+    pub const fn supports(self, operation: Operation) -> bool {
+        match self {
+            Self::Standard => !operation.is_extended(),
+            Self::Extended | Self::Shared | Self::RunLength | Self::Wide { .. } => true,
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Self-contained HTML reports of a packet.
+//!
+//! The report shows the hexadecimal transmission above a collapsible tree of
+//! its packets. Each node lists its version, the bits it spans, and its value
+//! or the error evaluating it; hovering a node highlights the hexadecimal
+//! digits holding its bits. The page has no external resources.
+//!
+//! ```
+//! let packet = jacob::Packet::try_from("C200B40A82").unwrap();
+//! let html = jacob::report::html(&packet).unwrap();
+//! assert!(html.starts_with("<!DOCTYPE html>"));
+//! assert!(html.contains("bits 0..40"));
+//! ```
+
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::width::Overflow;
+use crate::{Packet, PacketError, PacketKind};
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+.hex { font-family: monospace; font-size: 1.2em; word-break: break-all; }
+.hex span.on { background: #fd5; }
+details { margin-left: 1.5em; }
+summary { cursor: pointer; }
+code { font-weight: bold; }
+.meta { color: #666; }
+.error { color: #b00; }
+";
+
+const SCRIPT: &str = "\
+const digits = document.querySelectorAll('.hex span');
+document.querySelectorAll('summary').forEach(node => {
+  node.addEventListener('mouseenter', () => {
+    const [start, end] = node.dataset.bits.split(',').map(Number);
+    digits.forEach((digit, i) => digit.classList.toggle('on', i * 4 < end && i * 4 + 4 > start));
+  });
+  node.addEventListener('mouseleave', () => digits.forEach(digit => digit.classList.remove('on')));
+});
+";
+
+/// Returns the HTML report of `packet` as encoded in the standard profile.
+///
+/// # Errors
+///
+/// Will return `Err` if the packet cannot be serialized.
+pub fn html(packet: &Packet) -> Result<String, PacketError> {
+    let hex = packet.to_hex()?;
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Packet {}</title>\n<style>\n{STYLE}</style>\n</head>\n<body>\n",
+        escape(&truncated(&hex))
+    );
+    html.push_str("<p class=\"hex\">");
+    for digit in hex.chars() {
+        let _ = write!(html, "<span>{digit}</span>");
+    }
+    html.push_str("</p>\n");
+    write_node(&mut html, packet, &mut packet.bit_spans().into_iter());
+    let _ = write!(html, "<script>\n{SCRIPT}</script>\n</body>\n</html>\n");
+    Ok(html)
+}
+
+/// Writes `packet` and its operands, taking their bit spans in pre-order
+/// from `spans`.
+fn write_node(html: &mut String, packet: &Packet, spans: &mut impl Iterator<Item = Range<u64>>) {
+    let Range { start, end } = spans.next().unwrap_or_default();
+    let value = match packet.eval_as::<usize>(Overflow::Checked) {
+        Ok(value) => format!("= {value}"),
+        Err(e) => format!("<span class=\"error\">{}</span>", escape(&e.to_string())),
+    };
+    let (label, packets) = match &packet.kind {
+        PacketKind::Literal(value) => (value.to_string(), &[][..]),
+        PacketKind::Operator {
+            operation, packets, ..
+        } => (operation.as_func_str().to_string(), &packets[..]),
+    };
+    let _ = write!(
+        html,
+        "<details open>\n<summary data-bits=\"{start},{end}\"><code>{}</code> {value} \
+         <span class=\"meta\">v{}, bits {start}..{end}</span></summary>\n",
+        escape(&label),
+        packet.version
+    );
+    for child in packets {
+        write_node(html, child, spans);
+    }
+    html.push_str("</details>\n");
+}
+
+fn truncated(hex: &str) -> String {
+    if hex.len() > 16 {
+        format!("{}…", &hex[..16])
+    } else {
+        hex.to_string()
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::html;
+    use crate::Packet;
+
+    #[test]
+    fn test_html() {
+        // Sum of literals 1 and 2; operands start after the 18-bit header
+        let packet = Packet::try_from("C200B40A82").unwrap();
+        let html = html(&packet).unwrap();
+        assert!(html.contains("data-bits=\"0,40\"><code>sum</code> = 3"));
+        assert!(html.contains("data-bits=\"18,29\"><code>1</code> = 1"));
+        assert!(html.contains("data-bits=\"29,40\"><code>2</code> = 2"));
+        assert_eq!(html.matches("<details").count(), 3);
+        assert_eq!(html.matches("<span>").count(), 10);
+
+        let html = super::html(&Packet::from_expression("min() < 1").unwrap()).unwrap();
+        assert_eq!(html.matches("class=\"error\"").count(), 2);
+    }
+}
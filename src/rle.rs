@@ -0,0 +1,119 @@
+//! Experimental run-length encoding of literals.
+//!
+//! The [`RunLength`](crate::Profile::RunLength) profile is the
+//! [`Extended`](crate::Profile::Extended) profile plus one packet type: a literal,
+//! with type ID [`RUN_LENGTH_TYPE_ID`], whose value is written as runs of a
+//! repeated four-bit nibble rather than as value groups. Each run is a flag
+//! that is set if another run follows, four bits holding the run's length
+//! minus one, and the nibble. A literal is written this way wherever it is
+//! smaller, which takes at least two repeated nibbles.
+//!
+//! Decoding turns every run-length literal back into an ordinary literal, so
+//! the result's bit lengths describe its standard encoding.
+//!
+//! ```
+//! use jacob::bits::BitReader;
+//! use jacob::{Packet, Profile};
+//!
+//! let packet = Packet::from_expression("4294967295 + 286331153").unwrap();
+//! let bytes = packet.to_bytes_with_profile(Profile::RunLength).unwrap();
+//! assert!(bytes.len() < packet.to_bytes().unwrap().len());
+//!
+//! let decoded = Packet::read_with_profile(&mut BitReader::new(&bytes), Profile::RunLength);
+//! assert_eq!(decoded.unwrap(), packet);
+//! ```
+
+use crate::bits::{BitCursor, BitWriter};
+use crate::PacketError;
+
+/// Type ID of run-length literals in the [`RunLength`](crate::Profile::RunLength)
+/// profile.
+pub const RUN_LENGTH_TYPE_ID: u8 = 14;
+
+/// Longest run one group can describe.
+const MAX_RUN: usize = 16;
+
+/// Returns the runs of `value`'s nibbles, most significant first, as
+/// `(nibble, length)` pairs. Zero is one run of one nibble.
+fn runs(value: usize) -> Vec<(usize, usize)> {
+    let nibbles = (usize::BITS - value.leading_zeros()).div_ceil(4).max(1);
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for i in (0..nibbles).rev() {
+        let nibble = value >> (i * 4) & 0xF;
+        match runs.last_mut() {
+            Some((last, length)) if *last == nibble && *length < MAX_RUN => *length += 1,
+            _ => runs.push((nibble, 1)),
+        }
+    }
+    runs
+}
+
+/// Returns the number of bits [`write_runs`] uses for `value`.
+pub(crate) fn runs_bit_len(value: usize) -> u64 {
+    9 * runs(value).len() as u64
+}
+
+/// Writes `value` as runs of nibbles.
+pub(crate) fn write_runs(writer: &mut BitWriter<'_>, value: usize) -> Result<(), PacketError> {
+    let runs = runs(value);
+    for (i, (nibble, length)) in runs.iter().enumerate() {
+        writer.write_bit(i + 1 < runs.len())?;
+        writer.write(4, (length - 1) as u64)?;
+        writer.write(4, *nibble as u64)?;
+    }
+    Ok(())
+}
+
+/// Reads runs of nibbles written by [`write_runs`].
+pub(crate) fn read_runs<C: BitCursor>(cursor: &mut C) -> Result<usize, PacketError> {
+    let mut value: usize = 0;
+    let mut reading = true;
+    while reading {
+        reading = cursor.read_bool()?;
+        let length = cursor.read_u8(4)? + 1;
+        let nibble = usize::from(cursor.read_u8(4)?);
+        for _ in 0..length {
+            value = value << 4 | nibble;
+        }
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{runs, runs_bit_len, RUN_LENGTH_TYPE_ID};
+    use crate::bits::BitReader;
+    use crate::{Packet, Profile};
+
+    #[test]
+    fn test_run_length() {
+        assert_eq!(runs(0), [(0, 1)]);
+        assert_eq!(runs(0xAAAB), [(0xA, 3), (0xB, 1)]);
+        assert_eq!(
+            runs(usize::MAX).len(),
+            (usize::BITS as usize / 4).div_ceil(16)
+        );
+        assert_eq!(runs_bit_len(0x7000_0000), 18);
+
+        let packet =
+            Packet::from_expression("max(4294967295, 17, 0, if(1 < 2, 3, 536870912))").unwrap();
+        let bytes = packet.to_bytes_with_profile(Profile::RunLength).unwrap();
+        assert!(
+            bytes.len()
+                < packet
+                    .to_bytes_with_profile(Profile::Extended)
+                    .unwrap()
+                    .len()
+        );
+        let decoded =
+            Packet::read_with_profile(&mut BitReader::new(&bytes), Profile::RunLength).unwrap();
+        assert_eq!(decoded, packet);
+        assert_eq!(decoded.bit_len(), packet.bit_len());
+
+        // Run-length literals are only understood in their profile
+        let literal = Packet::from(0xFFFF_usize);
+        let bytes = literal.to_bytes_with_profile(Profile::RunLength).unwrap();
+        assert_eq!(bytes[0] & 0x1E, RUN_LENGTH_TYPE_ID << 1);
+        assert!(Packet::read_with_profile(&mut BitReader::new(&bytes), Profile::Extended).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+//! Small deterministic pseudo-random number generator.
+//!
+//! Reproducibility from a seed matters more here than statistical quality, so
+//! this is a plain `SplitMix64` rather than a dependency.
+
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub const fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub const fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value in `0..bound`, or `0` if `bound` is `0`.
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn below(&mut self, bound: usize) -> usize {
+        if bound == 0 {
+            0
+        } else {
+            (self.next_u64() % bound as u64) as usize
+        }
+    }
+
+    /// Returns `k` distinct indices from `0..n` in ascending order.
+    pub fn sample_indices(&mut self, n: usize, k: usize) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..n).collect();
+        let k = k.min(n);
+        for i in 0..k {
+            let j = i + self.below(n - i);
+            indices.swap(i, j);
+        }
+        indices.truncate(k);
+        indices.sort_unstable();
+        indices
+    }
+}
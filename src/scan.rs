@@ -0,0 +1,123 @@
+//! Carving packets out of data that is not only packets.
+//!
+//! [`scan`] tries to parse a packet at every bit offset of its input, so
+//! transmissions can be recovered from memory dumps and captures without
+//! knowing where they start.
+//!
+//! ```
+//! use jacob::scan::{scan, ScanOptions};
+//!
+//! // `1 + 2` after a stray nibble
+//! let bytes = jacob::bytes_from_hex("FC200B40A820").unwrap();
+//! let options = ScanOptions {
+//!     min_bits: 20,
+//!     ..ScanOptions::default()
+//! };
+//! let found = scan(&bytes, &options);
+//! assert_eq!(found[0].0, 4);
+//! assert_eq!(found[0].1.to_string(), "1 + 2");
+//! ```
+
+use crate::bits::{BitCursor, BitReader};
+use crate::{Packet, Profile};
+
+/// Options for [`scan`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanOptions {
+    pub profile: Profile,
+    /// Bits between the offsets tried: 1 tries every bit, 8 only byte
+    /// boundaries. 0 is treated as 1.
+    pub step: u64,
+    /// Ignore packets shorter than this many bits. Almost any 11 bits of
+    /// noise whose type ID happens to be 4 parse as a literal.
+    pub min_bits: u64,
+    /// Also report packets starting inside earlier candidates, such as their
+    /// operands. Otherwise scanning resumes after each candidate.
+    pub overlapping: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            profile: Profile::default(),
+            step: 1,
+            min_bits: 0,
+            overlapping: false,
+        }
+    }
+}
+
+/// Returns the bit offset and packet of every candidate found in `bytes`, in
+/// order of offset.
+///
+/// Each offset is parsed independently, so scanning noise costs up to the
+/// square of its length in the worst case; a larger
+/// [`step`](ScanOptions::step) trades missed offsets for speed.
+#[must_use]
+pub fn scan(bytes: &[u8], options: &ScanOptions) -> Vec<(u64, Packet)> {
+    let total = bytes.len() as u64 * 8;
+    let step = options.step.max(1);
+    let mut candidates = Vec::new();
+    let mut offset = 0;
+    while offset < total {
+        if let Some((packet, bit_len)) = parse_at(bytes, offset, options.profile) {
+            if bit_len >= options.min_bits {
+                candidates.push((offset, packet));
+                if !options.overlapping {
+                    offset = (offset + bit_len).div_ceil(step) * step;
+                    continue;
+                }
+            }
+        }
+        offset += step;
+    }
+    candidates
+}
+
+/// Parses a packet starting `offset` bits into `bytes`, returning it with the
+/// number of bits it occupies.
+fn parse_at(bytes: &[u8], offset: u64, profile: Profile) -> Option<(Packet, u64)> {
+    let start = usize::try_from(offset / 8).ok()?;
+    let skip = offset % 8;
+    let mut reader = BitReader::new(bytes.get(start..)?);
+    reader.read(u32::try_from(skip).ok()?).ok()?;
+    let packet = Packet::read_with_profile(&mut reader, profile).ok()?;
+    Some((packet, reader.position() - skip))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scan, ScanOptions};
+    use crate::bytes_from_hex;
+
+    #[test]
+    fn test_scan() {
+        // `1 + 2` at bit 4, then `2021` at bit 52
+        let bytes = bytes_from_hex("FC200B40A8206D2FE280").unwrap();
+        let options = ScanOptions {
+            min_bits: 20,
+            ..ScanOptions::default()
+        };
+        let found: Vec<_> = scan(&bytes, &options)
+            .into_iter()
+            .map(|(offset, packet)| (offset, packet.to_string()))
+            .collect();
+        assert!(found.contains(&(4, "1 + 2".to_string())));
+        assert!(found.contains(&(52, "2021".to_string())));
+        assert!(!found.iter().any(|(offset, _)| (5..40).contains(offset)));
+
+        let overlapping = scan(
+            &bytes,
+            &ScanOptions {
+                overlapping: true,
+                min_bits: 0,
+                ..options
+            },
+        );
+        assert!(overlapping.iter().any(|(offset, _)| *offset == 4 + 18));
+
+        let aligned = scan(&bytes, &ScanOptions { step: 8, ..options });
+        assert!(aligned.iter().all(|(offset, _)| offset % 8 == 0));
+        assert!(!aligned.iter().any(|(offset, _)| *offset == 4));
+    }
+}
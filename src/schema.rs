@@ -0,0 +1,294 @@
+//! Structural schemas inferred from a corpus of packets.
+//!
+//! A [`Schema`] records, for every position in the trees it has seen, which
+//! operations appeared there, how many operands they had, and the range of
+//! literal values, along with how often each overall tree shape occurred.
+//! New packets can then be validated against it, reporting every position
+//! where they do something the corpus never did.
+//!
+//! ```
+//! use jacob::schema::Schema;
+//! use jacob::Packet;
+//!
+//! let corpus: Vec<Packet> = ["1 + 20", "3 + 4", "max(5, 6)"]
+//!     .into_iter()
+//!     .map(|expr| Packet::from_expression(expr).unwrap())
+//!     .collect();
+//! let schema = Schema::infer(&corpus);
+//! assert_eq!(schema.shapes()[0], ("sum(_, _)".to_string(), 2));
+//!
+//! assert!(schema.validate(&Packet::from_expression("2 + 19").unwrap()).is_empty());
+//! let violations = schema.validate(&Packet::from_expression("1 * 30").unwrap());
+//! assert_eq!(violations.len(), 2);
+//! assert_eq!(violations[0].to_string(), "root: unexpected operation `product`");
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::{Operation, Packet, PacketKind};
+
+/// What the corpus did at one position in the tree.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Node {
+    /// Number of packets seen at this position.
+    pub count: usize,
+    /// Range of the literals seen here, if any.
+    pub literals: Option<RangeInclusive<usize>>,
+    pub operations: BTreeSet<Operation>,
+    /// Range of the operand counts of the operators seen here, if any.
+    pub operand_counts: Option<RangeInclusive<usize>>,
+    /// What the corpus did at each operand position below this one.
+    pub operands: Vec<Self>,
+}
+
+fn widen(range: &mut Option<RangeInclusive<usize>>, value: usize) {
+    *range = Some(range.take().map_or(value..=value, |range| {
+        (*range.start()).min(value)..=(*range.end()).max(value)
+    }));
+}
+
+impl Node {
+    fn observe(&mut self, packet: &Packet) {
+        self.count += 1;
+        match &packet.kind {
+            PacketKind::Literal(value) => widen(&mut self.literals, *value),
+            PacketKind::Operator {
+                operation, packets, ..
+            } => {
+                self.operations.insert(*operation);
+                widen(&mut self.operand_counts, packets.len());
+                if self.operands.len() < packets.len() {
+                    self.operands.resize_with(packets.len(), Self::default);
+                }
+                for (node, packet) in self.operands.iter_mut().zip(packets) {
+                    node.observe(packet);
+                }
+            }
+        }
+    }
+
+    fn validate(&self, packet: &Packet, path: &mut Vec<usize>, violations: &mut Vec<Violation>) {
+        let mut violation = |kind| {
+            violations.push(Violation {
+                path: path.clone(),
+                kind,
+            });
+        };
+        match &packet.kind {
+            PacketKind::Literal(value) => match &self.literals {
+                Some(range) if range.contains(value) => {}
+                Some(range) => violation(ViolationKind::LiteralOutOfRange {
+                    value: *value,
+                    expected: range.clone(),
+                }),
+                None => violation(ViolationKind::UnexpectedLiteral),
+            },
+            PacketKind::Operator {
+                operation, packets, ..
+            } => {
+                match &self.operand_counts {
+                    None => violation(ViolationKind::UnexpectedOperator),
+                    Some(range) => {
+                        if !self.operations.contains(operation) {
+                            violation(ViolationKind::UnexpectedOperation(*operation));
+                        }
+                        if !range.contains(&packets.len()) {
+                            violation(ViolationKind::OperandCount {
+                                found: packets.len(),
+                                expected: range.clone(),
+                            });
+                        }
+                    }
+                }
+                for (i, (node, packet)) in self.operands.iter().zip(packets).enumerate() {
+                    path.push(i);
+                    node.validate(packet, path, violations);
+                    path.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Schema inferred from a corpus; see the [module documentation](self).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Schema {
+    pub root: Node,
+    shapes: BTreeMap<String, usize>,
+}
+
+impl Schema {
+    /// Infers a schema from every packet in `packets`.
+    pub fn infer<'a>(packets: impl IntoIterator<Item = &'a Packet>) -> Self {
+        let mut schema = Self::default();
+        for packet in packets {
+            schema.observe(packet);
+        }
+        schema
+    }
+
+    /// Widens the schema to accept `packet`.
+    pub fn observe(&mut self, packet: &Packet) {
+        self.root.observe(packet);
+        *self.shapes.entry(shape(packet)).or_default() += 1;
+    }
+
+    /// Returns every tree shape seen with how often it occurred, most common
+    /// first. A shape is the expression with every literal written as `_`,
+    /// in function form.
+    #[must_use]
+    pub fn shapes(&self) -> Vec<(String, usize)> {
+        let mut shapes: Vec<(String, usize)> = self
+            .shapes
+            .iter()
+            .map(|(shape, count)| (shape.clone(), *count))
+            .collect();
+        shapes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        shapes
+    }
+
+    /// Returns every way `packet` departs from the schema, in pre-order.
+    /// Operands beyond any seen at their position are not checked further,
+    /// since the operand count is already reported.
+    #[must_use]
+    pub fn validate(&self, packet: &Packet) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.root.validate(packet, &mut Vec::new(), &mut violations);
+        violations
+    }
+}
+
+/// Returns the expression of `packet` in function form with literals as `_`.
+#[must_use]
+pub fn shape(packet: &Packet) -> String {
+    match &packet.kind {
+        PacketKind::Literal(_) => "_".to_string(),
+        PacketKind::Operator {
+            operation, packets, ..
+        } => format!(
+            "{}({})",
+            operation.as_func_str(),
+            packets.iter().map(shape).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// A departure from a [`Schema`] found by [`Schema::validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub path: Vec<usize>,
+    pub kind: ViolationKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// A literal where the corpus only had operators.
+    UnexpectedLiteral,
+    /// An operator where the corpus only had literals.
+    UnexpectedOperator,
+    LiteralOutOfRange {
+        value: usize,
+        expected: RangeInclusive<usize>,
+    },
+    UnexpectedOperation(Operation),
+    OperandCount {
+        found: usize,
+        expected: RangeInclusive<usize>,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str("root: ")?;
+        } else {
+            let path: Vec<String> = self.path.iter().map(ToString::to_string).collect();
+            write!(f, "{}: ", path.join("."))?;
+        }
+        match &self.kind {
+            ViolationKind::UnexpectedLiteral => f.write_str("unexpected literal"),
+            ViolationKind::UnexpectedOperator => f.write_str("unexpected operator"),
+            ViolationKind::LiteralOutOfRange { value, expected } => write!(
+                f,
+                "literal {value} outside {}..={}",
+                expected.start(),
+                expected.end()
+            ),
+            ViolationKind::UnexpectedOperation(operation) => {
+                write!(f, "unexpected operation `{}`", operation.as_func_str())
+            }
+            ViolationKind::OperandCount { found, expected } => write!(
+                f,
+                "{found} operands outside {}..={}",
+                expected.start(),
+                expected.end()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Schema, Violation, ViolationKind};
+    use crate::{Operation, Packet};
+
+    fn corpus(exprs: &[&str]) -> Vec<Packet> {
+        exprs
+            .iter()
+            .map(|expr| Packet::from_expression(expr).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_infer() {
+        let schema = Schema::infer(&corpus(&["1 + 2", "10 + max(3, 4, 5)", "7"]));
+        let root = &schema.root;
+        assert_eq!(root.count, 3);
+        assert_eq!(root.literals, Some(7..=7));
+        assert_eq!(
+            root.operations.iter().copied().collect::<Vec<_>>(),
+            [Operation::Sum]
+        );
+        assert_eq!(root.operand_counts, Some(2..=2));
+        assert_eq!(root.operands[0].literals, Some(1..=10));
+        assert_eq!(root.operands[1].literals, Some(2..=2));
+        assert_eq!(root.operands[1].operand_counts, Some(3..=3));
+        assert_eq!(root.operands[1].operands.len(), 3);
+        assert_eq!(schema.shapes().len(), 3);
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = Schema::infer(&corpus(&["1 + 2", "3 + 4"]));
+        for packet in corpus(&["2 + 3", "1 + 4"]) {
+            assert!(schema.validate(&packet).is_empty());
+        }
+        assert_eq!(
+            schema.validate(&Packet::from_expression("1 + min(2) + 5").unwrap()),
+            [
+                Violation {
+                    path: vec![],
+                    kind: ViolationKind::OperandCount {
+                        found: 3,
+                        expected: 2..=2
+                    }
+                },
+                Violation {
+                    path: vec![1],
+                    kind: ViolationKind::UnexpectedOperator
+                },
+            ]
+        );
+        assert_eq!(
+            schema.validate(&Packet::from(9)),
+            [Violation {
+                path: vec![],
+                kind: ViolationKind::UnexpectedLiteral
+            }]
+        );
+        let violations = schema.validate(&Packet::from_expression("0 + 2").unwrap());
+        assert_eq!(violations[0].to_string(), "0: literal 0 outside 1..=3");
+    }
+}
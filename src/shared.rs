@@ -0,0 +1,405 @@
+//! Experimental encoding of repeated subtrees as back-references.
+//!
+//! The [`Shared`](Profile::Shared) profile is the
+//! [`Extended`](Profile::Extended) profile plus one packet type: a
+//! back-reference, with type ID [`REFERENCE_TYPE_ID`], whose value groups
+//! hold the distance in bits back to the start of an earlier, complete packet
+//! it stands for. A subtree is written as a reference wherever an identical
+//! one was already written and the reference is smaller.
+//!
+//! Decoding expands every reference, so the result is an ordinary tree whose
+//! bit lengths describe its expanded standard encoding; [`expand`] re-encodes
+//! a shared transmission for consumers that do not understand references.
+//! References to subtrees that are themselves made of references let a short
+//! input expand exponentially, so decoding stops with
+//! [`PacketError::ExpansionTooLarge`] once the tree would exceed
+//! [`ParseOptions::max_expanded_packets`](crate::ParseOptions::max_expanded_packets)
+//! packets, or [`MAX_EXPANDED_PACKETS`] if unset.
+//!
+//! ```
+//! use jacob::{Packet, Profile};
+//!
+//! let packet = Packet::from_expression("max(1 + 2, 3 * 4) + max(1 + 2, 3 * 4)").unwrap();
+//! let shared = packet.to_bytes_with_profile(Profile::Shared).unwrap();
+//! assert!(shared.len() < packet.to_bytes().unwrap().len());
+//!
+//! let expanded = jacob::shared::expand(&shared).unwrap();
+//! assert_eq!(Packet::from_bytes(&expanded).unwrap(), packet);
+//! ```
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::bits::{BitReader, BitWriter};
+use crate::{
+    groups_bit_len, write_groups, Length, Operation, Packet, PacketError, PacketKind, Profile,
+    Version,
+};
+
+/// Type ID of back-reference packets in the [`Shared`](Profile::Shared) profile.
+pub const REFERENCE_TYPE_ID: u8 = 15;
+
+/// Most packets a shared transmission may expand to unless
+/// [`ParseOptions::max_expanded_packets`](crate::ParseOptions::max_expanded_packets)
+/// says otherwise.
+pub const MAX_EXPANDED_PACKETS: usize = 1 << 20;
+
+/// Packets read so far from a shared transmission, for references to copy.
+pub(crate) struct References {
+    /// Each packet by the offset it starts at, with the size of its subtree.
+    packets: HashMap<u64, (Rc<Packet>, usize)>,
+    /// Packets in the tree read so far, counting references as the packets
+    /// they copy.
+    expanded: usize,
+    limit: usize,
+}
+
+impl References {
+    /// Returns an empty table allowing at most `limit` expanded packets.
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            packets: HashMap::new(),
+            expanded: 0,
+            limit,
+        }
+    }
+
+    /// Returns the number of packets read so far.
+    pub(crate) const fn expanded(&self) -> usize {
+        self.expanded
+    }
+
+    /// Records `packet`, read from `offset`, and its operands. `expanded`
+    /// is the number of packets read before it.
+    pub(crate) fn insert(
+        &mut self,
+        offset: u64,
+        packet: &Packet,
+        expanded: usize,
+    ) -> Result<(), PacketError> {
+        self.expanded += 1;
+        if self.expanded > self.limit {
+            return Err(PacketError::ExpansionTooLarge {
+                limit: self.limit,
+                bit_offset: offset,
+            });
+        }
+        let size = self.expanded - expanded;
+        self.packets.insert(offset, (Rc::new(packet.clone()), size));
+        Ok(())
+    }
+
+    /// Returns a copy of the packet a reference read from `offset` points
+    /// `distance` bits back to.
+    pub(crate) fn copy(&mut self, offset: u64, distance: u64) -> Result<Packet, PacketError> {
+        let (packet, size) = offset
+            .checked_sub(distance)
+            .and_then(|target| self.packets.get(&target))
+            .cloned()
+            .ok_or(PacketError::DanglingReference {
+                distance,
+                bit_offset: offset,
+            })?;
+        if size > self.limit - self.expanded {
+            return Err(PacketError::ExpansionTooLarge {
+                limit: self.limit,
+                bit_offset: offset,
+            });
+        }
+        self.expanded += size;
+        let copy = Packet::clone(&packet);
+        self.packets.insert(offset, (packet, size));
+        Ok(copy)
+    }
+}
+
+/// Packet as it will be written, with repeated subtrees replaced.
+enum Encoded {
+    Reference {
+        distance: usize,
+    },
+    Literal {
+        version: Version,
+        value: usize,
+    },
+    Operator {
+        version: Version,
+        operation: Operation,
+        length: Length,
+        packets: Vec<Self>,
+    },
+}
+
+/// Serialized extended encoding of a subtree, identifying it exactly.
+type Key = (Vec<u8>, u64);
+
+/// Decides how `packet`, starting at `offset`, is written and returns its
+/// size. `written` maps every complete subtree written so far to its offset.
+fn plan(
+    packet: &Packet,
+    offset: u64,
+    written: &mut HashMap<Key, u64>,
+    path: &mut Vec<usize>,
+) -> Result<(Encoded, u64), PacketError> {
+    let inline_len = packet.bit_len_in(Profile::Extended);
+    let key = packet
+        .to_bytes_with_profile(Profile::Extended)
+        .ok()
+        .map(|bytes| (bytes, inline_len));
+    let distance = key
+        .as_ref()
+        .and_then(|key| written.get(key))
+        .and_then(|&target| usize::try_from(offset - target).ok());
+    if let Some(distance) = distance {
+        let reference_len = 7 + groups_bit_len(distance);
+        if reference_len < inline_len {
+            return Ok((Encoded::Reference { distance }, reference_len));
+        }
+    }
+
+    let (encoded, len) = match &packet.kind {
+        PacketKind::Literal(value) => (
+            Encoded::Literal {
+                version: packet.version,
+                value: *value,
+            },
+            inline_len,
+        ),
+        PacketKind::Operator {
+            operation,
+            length,
+            packets,
+        } => {
+            let header = 8 + match length {
+                Length::TotalBits(_) => 15,
+                Length::PacketCount(_) => 11,
+            };
+            let mut operands_len = 0;
+            let mut encoded = Vec::with_capacity(packets.len());
+            for (i, operand) in packets.iter().enumerate() {
+                path.push(i);
+                let (operand, len) = plan(operand, offset + header + operands_len, written, path)?;
+                path.pop();
+                operands_len += len;
+                encoded.push(operand);
+            }
+            let length = match length {
+                Length::TotalBits(_) => Length::TotalBits(operands_len),
+                length @ Length::PacketCount(_) => *length,
+            };
+            if !length.fits() {
+                return Err(PacketError::LengthOverflow {
+                    length,
+                    path: path.clone().into(),
+                });
+            }
+            (
+                Encoded::Operator {
+                    version: packet.version,
+                    operation: *operation,
+                    length,
+                    packets: encoded,
+                },
+                header + operands_len,
+            )
+        }
+    };
+    if let Some(key) = key {
+        written.entry(key).or_insert(offset);
+    }
+    Ok((encoded, len))
+}
+
+fn write_encoded(encoded: &Encoded, writer: &mut BitWriter<'_>) -> Result<(), PacketError> {
+    let type_bits = u32::from(Profile::Shared.type_bits());
+    match encoded {
+        Encoded::Reference { distance } => {
+            writer.write(3, 0_u8)?;
+            writer.write(type_bits, REFERENCE_TYPE_ID)?;
+            write_groups(writer, *distance)?;
+        }
+        Encoded::Literal { version, value } => {
+            writer.write(3, version.get())?;
+            writer.write(type_bits, 4_u8)?;
+            write_groups(writer, *value)?;
+        }
+        Encoded::Operator {
+            version,
+            operation,
+            length,
+            packets,
+        } => {
+            writer.write(3, version.get())?;
+            writer.write(type_bits, u8::from(operation))?;
+            match *length {
+                Length::TotalBits(bits) => {
+                    writer.write_bit(false)?;
+                    writer.write(15, bits)?;
+                }
+                Length::PacketCount(count) => {
+                    writer.write_bit(true)?;
+                    writer.write(11, count)?;
+                }
+            }
+            for packet in packets {
+                write_encoded(packet, writer)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Serializes `packet` in the [`Shared`](Profile::Shared) profile.
+pub(crate) fn write(packet: &Packet, writer: &mut BitWriter<'_>) -> Result<(), PacketError> {
+    let (encoded, _) = plan(packet, 0, &mut HashMap::new(), &mut Vec::new())?;
+    write_encoded(&encoded, writer)
+}
+
+/// Re-encodes a transmission in the [`Shared`](Profile::Shared) profile with
+/// every reference expanded, in the most restrictive profile able to
+/// represent it.
+///
+/// # Errors
+///
+/// Will return `Err` if `bytes` do not form a valid shared packet or the
+/// expanded packet cannot be serialized.
+pub fn expand(bytes: &[u8]) -> Result<Vec<u8>, PacketError> {
+    let packet = Packet::read_with_profile(&mut BitReader::new(bytes), Profile::Shared)?;
+    packet.to_bytes_with_profile(packet.required_profile())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand, write_encoded, Encoded, REFERENCE_TYPE_ID};
+    use crate::bits::{BitReader, BitWriter};
+    use crate::{
+        groups_bit_len, write_groups, ErrorKind, Length, Operation, Packet, PacketError,
+        ParseOptions, Profile, Version,
+    };
+
+    fn decode(bytes: &[u8], profile: Profile) -> Result<Packet, PacketError> {
+        Packet::read_with_profile(&mut BitReader::new(bytes), profile)
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for expr in [
+            "2021",
+            "1 + 2 + 3",
+            "max(1 + 2, 3 * 4) + max(1 + 2, 3 * 4) + (1 + 2)",
+            "if(7 < 8, min(5, 5, 5), min(5, 5, 5))",
+        ] {
+            let packet = Packet::from_expression(expr).unwrap();
+            for packet in [packet.clone(), packet.canonicalize()] {
+                let bytes = packet.to_bytes_with_profile(Profile::Shared).unwrap();
+                let decoded = decode(&bytes, Profile::Shared).unwrap();
+                assert_eq!(decoded.eval().unwrap(), packet.eval().unwrap(), "{expr}");
+                assert_eq!(
+                    decoded.to_bytes_with_profile(Profile::Extended).unwrap(),
+                    packet.to_bytes_with_profile(Profile::Extended).unwrap(),
+                    "{expr}"
+                );
+                let expanded = expand(&bytes).unwrap();
+                assert_eq!(
+                    decode(&expanded, packet.required_profile())
+                        .unwrap()
+                        .eval()
+                        .unwrap(),
+                    packet.eval().unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_savings() {
+        let subtree = "max(1 + 2, 3 * 4, 5 + 6)";
+        let packet =
+            Packet::from_expression(&format!("{subtree} + {subtree} + {subtree}")).unwrap();
+        let shared = packet.to_bytes_with_profile(Profile::Shared).unwrap();
+        let extended = packet.to_bytes_with_profile(Profile::Extended).unwrap();
+        assert!(shared.len() * 2 < extended.len());
+    }
+
+    #[test]
+    fn test_dangling_reference() {
+        // Reference to 5 bits before the start of the input
+        let mut bytes = Vec::new();
+        let mut writer = BitWriter::new(&mut bytes);
+        writer.write(7, REFERENCE_TYPE_ID).unwrap();
+        write_groups(&mut writer, 5).unwrap();
+        writer.byte_align();
+        let error = decode(&bytes, Profile::Shared).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Reference);
+        assert_eq!(error.bit_offset(), Some(0));
+        // Type 15 is not an operation in other profiles
+        assert!(decode(&bytes, Profile::Extended).is_err());
+    }
+
+    /// Encodes a sum of `1` and `levels` operators, each the maximum of two
+    /// references to the one before, so the tree doubles with every level.
+    fn doubling_chain(levels: usize) -> Vec<u8> {
+        let version = Version::new(0).unwrap();
+        let type_bits = u64::from(Profile::Shared.type_bits());
+        let operator_bits = 3 + type_bits + 12;
+        let mut packets = vec![Encoded::Literal { version, value: 1 }];
+        let mut previous = operator_bits;
+        let mut offset = previous + 3 + type_bits + 5;
+        for _ in 0..levels {
+            let start = offset;
+            offset += operator_bits;
+            let mut references = Vec::new();
+            for _ in 0..2 {
+                let distance = usize::try_from(offset - previous).unwrap();
+                references.push(Encoded::Reference { distance });
+                offset += 3 + type_bits + groups_bit_len(distance);
+            }
+            previous = start;
+            packets.push(Encoded::Operator {
+                version,
+                operation: Operation::Maximum,
+                length: Length::PacketCount(2),
+                packets: references,
+            });
+        }
+        let sum = Encoded::Operator {
+            version,
+            operation: Operation::Sum,
+            length: Length::PacketCount(u16::try_from(packets.len()).unwrap()),
+            packets,
+        };
+        let mut bytes = Vec::new();
+        let mut writer = BitWriter::new(&mut bytes);
+        write_encoded(&sum, &mut writer).unwrap();
+        writer.byte_align();
+        bytes
+    }
+
+    #[test]
+    fn test_expansion_limit() {
+        let bytes = doubling_chain(8);
+        let packet = decode(&bytes, Profile::Shared).unwrap();
+        assert_eq!(
+            packet.flat_packets().len(),
+            2 + (2..=9).map(|k| (1 << k) - 1).sum::<usize>()
+        );
+        assert_eq!(packet.eval().unwrap(), 9);
+        let options = ParseOptions {
+            profile: Profile::Shared,
+            max_expanded_packets: Some(500),
+            ..ParseOptions::default()
+        };
+        let error = Packet::from_bytes_with(&bytes, &options).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Limit);
+
+        // A few hundred bytes would otherwise expand to 2^64 packets
+        let bytes = doubling_chain(64);
+        assert!(bytes.len() < 1024);
+        let error = decode(&bytes, Profile::Shared).unwrap_err();
+        assert!(
+            matches!(error, PacketError::ExpansionTooLarge { .. }),
+            "{error}"
+        );
+        assert_eq!(expand(&bytes).unwrap_err().kind(), ErrorKind::Limit);
+    }
+}
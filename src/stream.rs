@@ -0,0 +1,203 @@
+//! Answers to common queries on hexadecimal packets without building trees.
+//!
+//! [`version_sum_of_hex`] and [`eval_of_hex`] decode the digits as they are
+//! needed and keep only a stack of the operators still being read, so their
+//! memory grows with how deeply the packet nests rather than its size. Both
+//! read the standard profile, without recognizing envelopes, and report the
+//! same errors as parsing the packet with [`Packet::from_str`].
+//!
+//! [`Packet::from_str`]: std::str::FromStr::from_str
+//!
+//! ```
+//! use jacob::stream::{eval_of_hex, version_sum_of_hex};
+//!
+//! assert_eq!(version_sum_of_hex("A0016C880162017C3686B18A3D4780").unwrap(), 31);
+//! assert_eq!(eval_of_hex("9C0141080250320F1802104A08").unwrap(), 1);
+//! ```
+
+use crate::bits::{BitCursor, HexBitReader};
+use crate::expression::{Build, Value};
+use crate::fields::{self, RawPacketHeader};
+use crate::{Length, Operation, PacketError, Profile, Version};
+
+/// Returns the sum of the versions of every packet in the tree.
+///
+/// # Errors
+///
+/// Will return `Err` if the string is not valid hexadecimal or does not form
+/// a valid packet.
+pub fn version_sum_of_hex(hex: &str) -> Result<usize, PacketError> {
+    let mut version_sum = 0;
+    decode::<Skip>(hex, &mut version_sum)?;
+    Ok(version_sum)
+}
+
+/// Evaluates the packet like [`Packet::eval_as`](crate::Packet::eval_as)
+/// with [`Overflow::Checked`](crate::width::Overflow::Checked).
+///
+/// # Errors
+///
+/// Will return `Err` if the string is not valid hexadecimal or does not form
+/// a valid packet, an evaluated operator has an invalid number of operands,
+/// or a value overflows.
+pub fn eval_of_hex(hex: &str) -> Result<usize, PacketError> {
+    decode::<Value>(hex, &mut 0)?
+}
+
+/// Builds nothing, for queries that only need the headers.
+struct Skip;
+
+impl Build for Skip {
+    type Node = ();
+    type Operands = ();
+
+    fn literal(_: usize) {}
+    fn operands(_: Operation) {}
+    fn push((): &mut (), (): ()) {}
+    fn operator((): (), _: u16) {}
+}
+
+/// An operator whose operands are still being read.
+struct Open<T> {
+    length: Length,
+    /// Position after the operands when the length is in bits.
+    end: u64,
+    count: usize,
+    operands: T,
+}
+
+impl<T> Open<T> {
+    fn is_complete(&self, position: u64) -> bool {
+        match self.length {
+            Length::TotalBits(_) => position >= self.end,
+            Length::PacketCount(n_packets) => self.count >= usize::from(n_packets),
+        }
+    }
+}
+
+/// Decodes the packet in `hex`, building it with `B` and adding every
+/// version to `version_sum`. Mirrors the recursive parser, with an explicit
+/// stack of open operators in place of recursion.
+fn decode<B: Build>(hex: &str, version_sum: &mut usize) -> Result<B::Node, PacketError> {
+    let mut bits = HexBitReader::new(hex)?;
+    let mut open: Vec<Open<B::Operands>> = Vec::new();
+    loop {
+        let bit_offset = bits.position();
+        let header = fields::read_header(&mut bits, Profile::Standard)?;
+        *version_sum += Version::try_from(header.version)?;
+        let mut node = match header.type_id {
+            RawPacketHeader::LITERAL_TYPE_ID => {
+                Some(B::literal(fields::read_literal_groups(&mut bits)?))
+            }
+            type_id => {
+                let operation =
+                    Operation::try_from(type_id).map_err(|_| PacketError::OperatorError {
+                        id: type_id,
+                        path: open
+                            .iter()
+                            .map(|operator| operator.count)
+                            .collect::<Vec<_>>()
+                            .into(),
+                        bit_offset: Some(bit_offset),
+                    })?;
+                let length = fields::read_length(&mut bits)?;
+                // Reject lengths the remaining input cannot possibly satisfy
+                let available = bits.remaining();
+                let required = match length {
+                    Length::TotalBits(n_bits) => n_bits,
+                    Length::PacketCount(n_packets) => {
+                        u64::from(n_packets) * (3 + u64::from(Profile::Standard.type_bits()) + 5)
+                    }
+                };
+                if required > available {
+                    return Err(PacketError::TruncatedOperator {
+                        declared: length,
+                        available,
+                        path: open
+                            .iter()
+                            .map(|operator| operator.count)
+                            .collect::<Vec<_>>()
+                            .into(),
+                        bit_offset,
+                    });
+                }
+                open.push(Open {
+                    length,
+                    end: bits.position() + required,
+                    count: 0,
+                    operands: B::operands(operation),
+                });
+                None
+            }
+        };
+        // Hand the finished packet to its operator, closing every operator
+        // that is now complete
+        while let Some(operator) = open.last_mut() {
+            if let Some(node) = node.take() {
+                B::push(&mut operator.operands, node);
+                operator.count += 1;
+            }
+            if !operator.is_complete(bits.position()) {
+                break;
+            }
+            let count = u16::try_from(operator.count).unwrap_or(u16::MAX);
+            node = open
+                .pop()
+                .map(|operator| B::operator(operator.operands, count));
+        }
+        if let (true, Some(node)) = (open.is_empty(), node) {
+            return Ok(node);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{eval_of_hex, version_sum_of_hex};
+    use crate::stress::Generator;
+    use crate::width::Overflow;
+    use crate::Packet;
+
+    fn check(hex: &str) {
+        let packet = Packet::from_str(hex);
+        let version_sum = packet.as_ref().map(Packet::version_sum);
+        assert_eq!(
+            version_sum_of_hex(hex).map_err(|e| e.to_string()),
+            version_sum.map_err(ToString::to_string),
+            "{hex}"
+        );
+        let value = packet.and_then(|packet| packet.eval_as(Overflow::Checked));
+        assert_eq!(
+            eval_of_hex(hex).map_err(|e| e.to_string()),
+            value.map_err(|e| e.to_string()),
+            "{hex}"
+        );
+    }
+
+    #[test]
+    fn test_matches_parsed_packet() {
+        let mut generator = Generator::new(7);
+        for _ in 0..200 {
+            let hex = generator.packet().to_hex().unwrap();
+            check(&hex);
+            check(&hex[..hex.len() / 2]);
+            check(&hex[..hex.len() - 1]);
+        }
+        for hex in [
+            "8A004A801A8002F478",
+            "9C0141080250320F1802104A08",
+            "38006F45291200",
+            "D2FE28",
+            "E",
+            "3A",
+            "",
+            "C200B40A8",
+            "C200B40A82ZZ",
+            "+1",
+        ] {
+            check(hex);
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Randomized consistency checks.
+//!
+//! [`Generator`] produces reproducible random packets from a seed, and
+//! [`check`] runs one through every serialization that can represent it and
+//! every evaluator, reporting the first disagreement as a [`Mismatch`].
+//! [`minimize`](crate::minimize()) reduces a failing packet to a smaller one
+//! failing the same check, which makes a far better bug report.
+//!
+//! ```
+//! use jacob::stress::{check, Generator};
+//!
+//! let mut generator = Generator::new(7);
+//! for _ in 0..100 {
+//!     assert!(check(&generator.packet()).is_ok());
+//! }
+//! ```
+
+use crate::bits::BitReader;
+use crate::encoding::{base64, bin};
+use crate::evaluator::{EvalHooks, Interval, IntervalEvaluator};
+use crate::profile::Profile;
+use crate::rng::Rng;
+use crate::vm::Vm;
+use crate::width::Overflow;
+use crate::{Arity, Length, Operation, Packet, PacketKind, Version};
+
+/// Produces random packets; the same seed always yields the same packets.
+#[derive(Clone, Debug)]
+pub struct Generator {
+    rng: Rng,
+    /// Operators are not nested more than this deep.
+    pub max_depth: usize,
+    /// Most operands of a variadic operator.
+    pub max_operands: usize,
+    /// Literals hold at most this many bits.
+    pub literal_bits: u32,
+    /// Only operations this profile supports are generated.
+    pub profile: Profile,
+}
+
+impl Generator {
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            max_depth: 4,
+            max_operands: 4,
+            literal_bits: 16,
+            profile: Profile::Standard,
+        }
+    }
+
+    /// Returns the next random packet. Versions and length encodings are
+    /// random too, but lengths always describe the operands.
+    pub fn packet(&mut self) -> Packet {
+        self.packet_at(0)
+    }
+
+    fn packet_at(&mut self, depth: usize) -> Packet {
+        let version = u8::try_from(self.rng.below(8))
+            .ok()
+            .and_then(Version::new)
+            .unwrap_or_default();
+        if depth >= self.max_depth || self.rng.below(3) == 0 {
+            let bits = self.literal_bits.clamp(1, usize::BITS);
+            let value = usize::try_from(self.rng.next_u64() >> (u64::BITS - bits)).unwrap_or(0);
+            return Packet {
+                version,
+                kind: PacketKind::Literal(value),
+            };
+        }
+        let operations: Vec<Operation> = Operation::ALL
+            .into_iter()
+            .filter(|&operation| self.profile.supports(operation))
+            .collect();
+        let operation = operations[self.rng.below(operations.len())];
+        let count = match operation.info().arity {
+            Arity::Exactly(count) => count,
+            Arity::AtLeast(min) => min + self.rng.below(self.max_operands.saturating_sub(min) + 1),
+        };
+        let packets: Vec<Packet> = (0..count).map(|_| self.packet_at(depth + 1)).collect();
+        let length = if self.rng.below(2) == 0 {
+            Length::TotalBits(0)
+        } else {
+            Length::PacketCount(0)
+        };
+        Packet {
+            version,
+            kind: PacketKind::Operator {
+                length: length.recomputed(&packets),
+                operation,
+                packets,
+            },
+        }
+    }
+}
+
+/// A disagreement found by [`check`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Name of the failing check, such as `hex` or `eval`.
+    pub check: &'static str,
+    pub detail: String,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.check, self.detail)
+    }
+}
+
+struct NoHooks;
+
+impl EvalHooks for NoHooks {}
+
+fn mismatch(check: &'static str, detail: impl Into<String>) -> Mismatch {
+    Mismatch {
+        check,
+        detail: detail.into(),
+    }
+}
+
+fn expect_same(
+    check: &'static str,
+    packet: &Packet,
+    other: Option<Packet>,
+) -> Result<(), Mismatch> {
+    let other = other.ok_or_else(|| mismatch(check, "failed to round-trip"))?;
+    packet.first_difference(&other).map_or(Ok(()), |path| {
+        Err(mismatch(check, format!("differs at path {path:?}")))
+    })
+}
+
+fn profile_bytes(packet: &Packet, profile: Profile) -> Option<Vec<u8>> {
+    packet.to_bytes_with_profile(profile).ok()
+}
+
+fn read_profile(bytes: &[u8], profile: Profile) -> Option<Packet> {
+    Packet::read_with_profile(&mut BitReader::new(bytes), profile).ok()
+}
+
+/// Checks that `packet` survives every round trip and evaluates consistently.
+///
+/// The round trips are hexadecimal, binary, base64, expression, extended, and
+/// shared; packets using extended operations skip the standard encodings.
+///
+/// # Errors
+///
+/// Will return `Err` describing the first check that fails.
+pub fn check(packet: &Packet) -> Result<(), Mismatch> {
+    if let Ok(bytes) = packet.to_bytes() {
+        let hex = packet.to_hex().ok();
+        expect_same(
+            "hex",
+            packet,
+            hex.and_then(|hex| Packet::try_from(hex.as_str()).ok()),
+        )?;
+        expect_same(
+            "bin",
+            packet,
+            bin::decode(&bin::encode(&bytes))
+                .ok()
+                .and_then(|bytes| Packet::from_bytes(&bytes).ok()),
+        )?;
+        expect_same(
+            "base64",
+            packet,
+            base64::decode(&base64::encode(&bytes))
+                .ok()
+                .and_then(|bytes| Packet::from_bytes(&bytes).ok()),
+        )?;
+    }
+
+    // Lengths are recomputed for the wider headers, so compare encodings
+    let extended = profile_bytes(packet, Profile::Extended)
+        .ok_or_else(|| mismatch("extended", "failed to serialize"))?;
+    let reread = read_profile(&extended, Profile::Extended)
+        .and_then(|packet| profile_bytes(&packet, Profile::Extended));
+    if reread.as_ref() != Some(&extended) {
+        return Err(mismatch("extended", "re-encoding differs"));
+    }
+    let shared = read_profile(
+        &profile_bytes(packet, Profile::Shared)
+            .ok_or_else(|| mismatch("shared", "failed to serialize"))?,
+        Profile::Shared,
+    )
+    .and_then(|packet| profile_bytes(&packet, Profile::Extended));
+    if shared.as_ref() != Some(&extended) {
+        return Err(mismatch("shared", "expansion differs"));
+    }
+    let run_length = read_profile(
+        &profile_bytes(packet, Profile::RunLength)
+            .ok_or_else(|| mismatch("run-length", "failed to serialize"))?,
+        Profile::RunLength,
+    )
+    .and_then(|packet| profile_bytes(&packet, Profile::Extended));
+    if run_length.as_ref() != Some(&extended) {
+        return Err(mismatch("run-length", "decoding differs"));
+    }
+
+    // Expressions carry neither versions nor length encodings
+    let expression = packet.to_expression().unwrap_or_default();
+    expect_same(
+        "expr",
+        &packet.canonicalize(),
+        Packet::from_expression(&expression).ok(),
+    )
+    .map_err(|e| mismatch(e.check, format!("{}: `{expression}`", e.detail)))?;
+
+    check_eval(packet)
+}
+
+fn check_eval(packet: &Packet) -> Result<(), Mismatch> {
+    let reference = packet.eval_as::<usize>(Overflow::Checked);
+    let hooked = packet.eval_with_hooks(&mut NoHooks);
+    let value = match (&reference, &hooked) {
+        (Ok(a), Ok(b)) if a == b => *a,
+        (Err(_), Err(_)) => return Ok(()),
+        _ => {
+            return Err(mismatch(
+                "eval",
+                format!("checked {reference:?}, hooked {hooked:?}"),
+            ))
+        }
+    };
+    // Only safe once checked evaluation has ruled out overflow
+    for (name, result) in [
+        ("eval", packet.eval()),
+        ("short-circuit", packet.eval_short_circuit()),
+        (
+            "vm",
+            packet
+                .compile()
+                .and_then(|bytecode| Vm::new().run(&bytecode)),
+        ),
+    ] {
+        if result.as_ref().ok() != Some(&value) {
+            return Err(mismatch(
+                "eval",
+                format!("checked {value}, {name} {result:?}"),
+            ));
+        }
+    }
+    let interval = packet.eval_with(&mut IntervalEvaluator::new());
+    if interval.as_ref().ok() != Some(&Interval::exact(value as u128)) {
+        return Err(mismatch(
+            "eval",
+            format!("checked {value}, interval {interval:?}"),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, Generator};
+    use crate::profile::Profile;
+
+    #[test]
+    fn test_generated_packets_pass() {
+        for profile in [Profile::Standard, Profile::Extended] {
+            let mut generator = Generator::new(42);
+            generator.profile = profile;
+            for _ in 0..500 {
+                let packet = generator.packet();
+                assert_eq!(check(&packet), Ok(()), "{packet}");
+            }
+        }
+        let mut a = Generator::new(1);
+        let mut b = Generator::new(1);
+        assert_eq!(a.packet(), b.packet());
+    }
+}
@@ -0,0 +1,213 @@
+//! Transmissions with holes to fill in.
+//!
+//! A template is hexadecimal text with placeholders, parsed once and then
+//! instantiated with many sets of values:
+//!
+//! - `0`-`9`, `A`-`F`: four fixed bits.
+//! - `?`: four bits taken from a value; a run of `?` is one placeholder, so
+//!   `??` holds a byte.
+//! - `{lit:N}`: the value groups of a literal holding `N` bits, `N` being a
+//!   multiple of 4, with continuation flags set. The encoding always spans
+//!   `N / 4` groups so enclosing length fields stay valid.
+//! - `{b:0101}`: fixed bits written in binary, for fields that are not
+//!   aligned to hexadecimal digits.
+//!
+//! Whitespace is ignored. Placeholders are filled in order of appearance.
+//!
+//! ```
+//! use jacob::template::Template;
+//!
+//! let template = Template::parse("D2??28").unwrap();
+//! assert_eq!(template.instantiate_hex(&[0xFE]).unwrap(), "D2FE28");
+//!
+//! // Version 6 literal packet holding any 12-bit value
+//! let template = Template::parse("{b:110100} {lit:12}").unwrap();
+//! let hex = template.instantiate_hex(&[2021]).unwrap();
+//! assert_eq!(hex, "D2FE28");
+//! assert_eq!(hex.parse::<jacob::Packet>().unwrap().eval().unwrap(), 2021);
+//! ```
+
+use crate::bits::BitWriter;
+use crate::{hex_from_bytes, PacketError};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    /// Fixed bits, most significant first.
+    Fixed { bits: u32, value: u64 },
+    /// Raw value of `bits` bits.
+    Raw { bits: u32 },
+    /// Literal value groups covering `bits` bits.
+    Literal { bits: u32 },
+}
+
+/// Parsed template; see the [module documentation](self) for the syntax.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    /// Parses template text.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the text contains unknown characters or malformed
+    /// braces, or a placeholder is wider than 64 bits.
+    pub fn parse(text: &str) -> Result<Self, PacketError> {
+        let invalid = PacketError::TemplateError;
+        let mut segments = Vec::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            let segment = match c {
+                c if c.is_whitespace() => continue,
+                '?' => {
+                    let mut bits = 4;
+                    while chars.next_if_eq(&'?').is_some() {
+                        bits += 4;
+                    }
+                    Segment::Raw { bits }
+                }
+                '{' => {
+                    let mut body = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => body.push(c),
+                            None => return Err(invalid("unclosed `{`")),
+                        }
+                    }
+                    match body.split_once(':') {
+                        Some(("lit", bits)) => {
+                            let bits: u32 =
+                                bits.parse().map_err(|_| invalid("invalid literal width"))?;
+                            if bits == 0 || !bits.is_multiple_of(4) {
+                                return Err(invalid("literal width must be a multiple of 4"));
+                            }
+                            Segment::Literal { bits }
+                        }
+                        Some(("b", digits)) => {
+                            let value = u64::from_str_radix(digits, 2)
+                                .map_err(|_| invalid("invalid binary digits"))?;
+                            let bits = u32::try_from(digits.len()).unwrap_or(u32::MAX);
+                            Segment::Fixed { bits, value }
+                        }
+                        _ => return Err(invalid("unknown placeholder")),
+                    }
+                }
+                c => {
+                    let value = c.to_digit(16).ok_or_else(|| invalid("invalid character"))?;
+                    Segment::Fixed {
+                        bits: 4,
+                        value: u64::from(value),
+                    }
+                }
+            };
+            let bits = match segment {
+                Segment::Fixed { bits, .. } | Segment::Raw { bits } | Segment::Literal { bits } => {
+                    bits
+                }
+            };
+            if bits > u64::BITS {
+                return Err(invalid("placeholder is wider than 64 bits"));
+            }
+            segments.push(segment);
+        }
+        Ok(Self { segments })
+    }
+
+    /// Returns the number of values [`Template::instantiate`] expects.
+    #[must_use]
+    pub fn placeholders(&self) -> usize {
+        self.segments
+            .iter()
+            .filter(|segment| !matches!(segment, Segment::Fixed { .. }))
+            .count()
+    }
+
+    /// Fills the placeholders with `values` and returns the bytes, padded
+    /// with zeros to a whole byte.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the number of values does not match the number of
+    /// placeholders or a value does not fit in its placeholder.
+    pub fn instantiate(&self, values: &[usize]) -> Result<Vec<u8>, PacketError> {
+        if values.len() != self.placeholders() {
+            return Err(PacketError::TemplateError("wrong number of values"));
+        }
+        let mut values = values.iter().map(|&value| value as u64);
+        let mut bytes = Vec::new();
+        let mut writer = BitWriter::new(&mut bytes);
+        for segment in &self.segments {
+            let (bits, value) = match *segment {
+                Segment::Fixed { bits, value } => (bits, value),
+                Segment::Raw { bits } | Segment::Literal { bits } => {
+                    let value = values.next().unwrap_or_default();
+                    if bits < u64::BITS && value >> bits != 0 {
+                        return Err(PacketError::TemplateError(
+                            "value does not fit its placeholder",
+                        ));
+                    }
+                    (bits, value)
+                }
+            };
+            match segment {
+                Segment::Literal { .. } => {
+                    for group in (0..bits / 4).rev() {
+                        writer.write_bit(group != 0)?;
+                        writer.write(4, (value >> (group * 4)) & 0xF)?;
+                    }
+                }
+                _ => writer.write(bits, value)?,
+            }
+        }
+        writer.byte_align();
+        Ok(bytes)
+    }
+
+    /// Returns [`Template::instantiate`] as hexadecimal.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if [`Template::instantiate`] fails.
+    pub fn instantiate_hex(&self, values: &[usize]) -> Result<String, PacketError> {
+        Ok(hex_from_bytes(&self.instantiate(values)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Template;
+    use crate::Packet;
+
+    #[test]
+    fn test_instantiate() {
+        // Sum of two 8-bit literals, with lengths independent of the values
+        let template =
+            Template::parse("{b:000000100000000010} {b:000100} {lit:8} {b:000100} {lit:8}")
+                .unwrap();
+        assert_eq!(template.placeholders(), 2);
+        for (a, b) in [(0, 0), (1, 2), (255, 17)] {
+            let packet = Packet::from_bytes(&template.instantiate(&[a, b]).unwrap()).unwrap();
+            assert_eq!(packet.eval().unwrap(), a + b);
+        }
+        assert!(template.instantiate(&[256, 0]).is_err());
+        assert!(template.instantiate(&[1]).is_err());
+    }
+
+    #[test]
+    #[allow(clippy::literal_string_with_formatting_args)]
+    fn test_parse_errors() {
+        for text in [
+            "D2G8",
+            "{lit:5}",
+            "{lit:x}",
+            "{b:102}",
+            "{foo:4}",
+            "{lit:8",
+            "?????????????????",
+        ] {
+            assert!(Template::parse(text).is_err(), "{text}");
+        }
+    }
+}
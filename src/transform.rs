@@ -0,0 +1,511 @@
+//! Value-preserving transformations of packet trees.
+//!
+//! Each transformation returns a new packet that evaluates to the same value as
+//! the original, with length fields recomputed to match the new sub-packets.
+
+use crate::rng::Rng;
+use crate::width::Overflow;
+use crate::{Length, Packet, PacketError, PacketKind, Version};
+use std::cell::RefCell;
+
+/// Number of times each rule of [`Packet::simplify`] fired.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SimplifyStats {
+    /// Nested operators merged into their parent.
+    pub flattened: usize,
+    /// Identity operands dropped.
+    pub identities: usize,
+    /// Operators replaced by their absorbing operand.
+    pub absorbed: usize,
+    /// Single-operand operators replaced by their operand.
+    pub unwrapped: usize,
+}
+
+impl SimplifyStats {
+    /// Returns the count of each rule by name.
+    #[must_use]
+    pub const fn rules(&self) -> [(&'static str, usize); 4] {
+        [
+            ("flatten", self.flattened),
+            ("identity", self.identities),
+            ("absorb", self.absorbed),
+            ("unwrap", self.unwrapped),
+        ]
+    }
+}
+
+impl Packet {
+    /// Rebuilds the tree bottom-up, passing every packet through `f` once its
+    /// sub-packets have been rebuilt.
+    fn rewrite(&self, f: &impl Fn(Self) -> Self) -> Self {
+        let packet = match &self.kind {
+            PacketKind::Literal(_) => self.clone(),
+            PacketKind::Operator {
+                length,
+                operation,
+                packets,
+            } => {
+                let packets: Vec<Self> = packets.iter().map(|packet| packet.rewrite(f)).collect();
+                Self {
+                    version: self.version,
+                    kind: PacketKind::Operator {
+                        length: length.recomputed(&packets),
+                        operation: *operation,
+                        packets,
+                    },
+                }
+            }
+        };
+        f(packet)
+    }
+
+    /// Flattens nested associative operators (`(1 + 2) + 3` becomes `1 + 2 + 3`),
+    /// drops identity operands (`x + 0`, `x * 1`), replaces operators with an
+    /// absorbing operand by it (`x * 0` becomes `0`) when the other operands
+    /// evaluate, and replaces single-operand sums, products, minimums, and
+    /// maximums with their operand.
+    #[must_use]
+    pub fn simplify(&self) -> Self {
+        self.simplify_with_stats().0
+    }
+
+    /// Returns [`Packet::simplify`] together with how often each rule fired.
+    #[must_use]
+    pub fn simplify_with_stats(&self) -> (Self, SimplifyStats) {
+        let stats = RefCell::new(SimplifyStats::default());
+        let packet = self.rewrite(&|packet| match packet.kind {
+            PacketKind::Operator {
+                length,
+                operation,
+                packets,
+            } if operation.is_associative() => {
+                let mut stats = stats.borrow_mut();
+                let info = operation.info();
+                let mut flattened = Vec::with_capacity(packets.len());
+                for child in packets {
+                    match child.kind {
+                        PacketKind::Operator {
+                            operation: child_operation,
+                            packets: grandchildren,
+                            ..
+                        } if child_operation == operation && !grandchildren.is_empty() => {
+                            stats.flattened += 1;
+                            flattened.extend(grandchildren);
+                        }
+                        _ => flattened.push(child),
+                    }
+                }
+                let is_literal =
+                    |packet: &Self, value| matches!(packet.kind, PacketKind::Literal(v) if Some(v) == value);
+                if let Some(absorbing) = info.absorbing {
+                    if flattened.iter().any(|child| is_literal(child, info.absorbing))
+                        && flattened
+                            .iter()
+                            .all(|child| child.eval_as::<usize>(Overflow::Checked).is_ok())
+                    {
+                        stats.absorbed += 1;
+                        return Self {
+                            version: packet.version,
+                            kind: PacketKind::Literal(absorbing),
+                        };
+                    }
+                }
+                if let Some(identity) = info.identity {
+                    let len = flattened.len();
+                    flattened.retain(|child| !is_literal(child, info.identity));
+                    stats.identities += len - flattened.len();
+                    if flattened.is_empty() && len > 0 {
+                        return Self {
+                            version: packet.version,
+                            kind: PacketKind::Literal(identity),
+                        };
+                    }
+                }
+                if flattened.len() == 1 {
+                    stats.unwrapped += 1;
+                    flattened.remove(0)
+                } else {
+                    Self {
+                        version: packet.version,
+                        kind: PacketKind::Operator {
+                            length: length.recomputed(&flattened),
+                            operation,
+                            packets: flattened,
+                        },
+                    }
+                }
+            }
+            kind => Self {
+                version: packet.version,
+                kind,
+            },
+        });
+        (packet, stats.into_inner())
+    }
+
+    /// Sets every version to `0` and every length to its smallest encoding, so
+    /// that packets describing the same expression serialize identically.
+    #[must_use]
+    pub fn canonicalize(&self) -> Self {
+        self.rewrite(&|mut packet| {
+            packet.version = Version::ZERO;
+            if let PacketKind::Operator {
+                length, packets, ..
+            } = &mut packet.kind
+            {
+                *length = Length::minimal(packets);
+            }
+            packet
+        })
+    }
+
+    /// Sorts the operands of commutative operators in the order of [`Ord`] for
+    /// [`Packet`]: literals first in ascending order, then operators by type
+    /// ID and structure.
+    #[must_use]
+    pub fn sort_commutative(&self) -> Self {
+        self.rewrite(&|mut packet| {
+            if let PacketKind::Operator {
+                operation, packets, ..
+            } = &mut packet.kind
+            {
+                if operation.is_commutative() {
+                    packets.sort();
+                }
+            }
+            packet
+        })
+    }
+
+    /// Simplifies the packet and encodes every length in its smallest form,
+    /// keeping versions intact.
+    #[must_use]
+    pub fn minify(&self) -> Self {
+        self.simplify().rewrite(&|mut packet| {
+            if let PacketKind::Operator {
+                length, packets, ..
+            } = &mut packet.kind
+            {
+                *length = Length::minimal(packets);
+            }
+            packet
+        })
+    }
+}
+
+impl Packet {
+    /// Replaces every operator nested `max_depth` levels below the root with a
+    /// literal holding its evaluated value.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an elided operator fails to evaluate.
+    pub fn truncate_depth(&self, max_depth: usize) -> Result<Self, PacketError> {
+        match &self.kind {
+            PacketKind::Operator { .. } if max_depth == 0 => self.to_literal(),
+            PacketKind::Literal(_) => Ok(self.clone()),
+            PacketKind::Operator {
+                length,
+                operation,
+                packets,
+            } => {
+                let packets = packets
+                    .iter()
+                    .map(|packet| packet.truncate_depth(max_depth - 1))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Self {
+                    version: self.version,
+                    kind: PacketKind::Operator {
+                        length: length.recomputed(&packets),
+                        operation: *operation,
+                        packets,
+                    },
+                })
+            }
+        }
+    }
+
+    /// Keeps at most `k` randomly chosen operands of every sum, product,
+    /// minimum, and maximum, folding the dropped operands into one trailing
+    /// literal so the packet still evaluates to the same value.
+    ///
+    /// Comparisons are left untouched. The same `seed` always picks the same
+    /// operands.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if dropped operands fail to evaluate.
+    pub fn sample_children(&self, k: usize, seed: u64) -> Result<Self, PacketError> {
+        self.sample_children_with(k, &mut Rng::new(seed))
+    }
+
+    fn sample_children_with(&self, k: usize, rng: &mut Rng) -> Result<Self, PacketError> {
+        let PacketKind::Operator {
+            length,
+            operation,
+            packets,
+        } = &self.kind
+        else {
+            return Ok(self.clone());
+        };
+
+        let packets = if packets.len() > k && operation.is_associative() {
+            let kept = rng.sample_indices(packets.len(), k);
+            let (kept, dropped): (Vec<_>, Vec<_>) = packets
+                .iter()
+                .enumerate()
+                .partition(|(i, _)| kept.binary_search(i).is_ok());
+            let dropped: Vec<Self> = dropped.into_iter().map(|(_, p)| p.clone()).collect();
+            let folded = Self {
+                version: Version::ZERO,
+                kind: PacketKind::Operator {
+                    length: Length::minimal(&dropped),
+                    operation: *operation,
+                    packets: dropped,
+                },
+            }
+            .to_literal()?;
+            kept.into_iter()
+                .map(|(_, p)| p)
+                .chain(std::iter::once(&folded))
+                .map(|packet| packet.sample_children_with(k, rng))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            packets
+                .iter()
+                .map(|packet| packet.sample_children_with(k, rng))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        Ok(Self {
+            version: self.version,
+            kind: PacketKind::Operator {
+                length: length.recomputed(&packets),
+                operation: *operation,
+                packets,
+            },
+        })
+    }
+}
+
+impl Packet {
+    /// Restructures the tree so every length fits in its field.
+    ///
+    /// Total bit lengths over [`Length::MAX_TOTAL_BITS`] switch to packet
+    /// counts, and associative operators with more than
+    /// [`Length::MAX_PACKET_COUNT`] operands are split into nested operators of
+    /// the same operation with version `0`. Lengths that already fit are kept.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a non-associative operator has too many operands.
+    pub fn fit_lengths(&self) -> Result<Self, PacketError> {
+        self.fit_lengths_at(&mut Vec::new())
+    }
+
+    fn fit_lengths_at(&self, path: &mut Vec<usize>) -> Result<Self, PacketError> {
+        let PacketKind::Operator {
+            length,
+            operation,
+            packets,
+        } = &self.kind
+        else {
+            return Ok(self.clone());
+        };
+
+        let mut packets = packets
+            .iter()
+            .enumerate()
+            .map(|(i, packet)| {
+                path.push(i);
+                let packet = packet.fit_lengths_at(path);
+                path.pop();
+                packet
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let max_count = usize::from(Length::MAX_PACKET_COUNT);
+        if packets.len() > max_count && !operation.is_associative() {
+            return Err(PacketError::LengthOverflow {
+                length: Length::PacketCount(0).recomputed(&packets),
+                path: path.clone().into(),
+            });
+        }
+        while packets.len() > max_count {
+            packets = packets
+                .chunks(max_count)
+                .map(|chunk| Self {
+                    version: Version::ZERO,
+                    kind: PacketKind::Operator {
+                        length: Length::minimal(chunk),
+                        operation: *operation,
+                        packets: chunk.to_vec(),
+                    },
+                })
+                .collect();
+        }
+
+        let length = match length.recomputed(&packets) {
+            length if length.fits() => length,
+            _ => Length::minimal(&packets),
+        };
+        Ok(Self {
+            version: self.version,
+            kind: PacketKind::Operator {
+                length,
+                operation: *operation,
+                packets,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Length, Operation, Packet, PacketError, PacketKind, Version};
+
+    fn expr(s: &str) -> Packet {
+        Packet::from_expression(s).unwrap()
+    }
+
+    #[test]
+    fn test_simplify() {
+        let packet = expr("((1 + 2) + sum(3)) * product(max(4, max(5, 6)))");
+        assert_eq!(
+            packet.simplify().to_expression().unwrap(),
+            "(1 + 2 + 3) * max(4, 5, 6)"
+        );
+    }
+
+    #[test]
+    fn test_simplify_identities() {
+        let max = usize::MAX;
+        for (input, simplified) in [
+            ("(7 + 0) * 1", "7"),
+            ("(7 * 0) + 3", "3"),
+            (&format!("min(7, {max}) + max(0, 4, 0)"), "7 + 4"),
+            (&format!("max(7, {max})"), &max.to_string()),
+            ("sum(0, 0) * (1 < 2)", "0"),
+            // The empty `min()` fails to evaluate, so the product must too
+            ("0 * min()", "0 * min()"),
+            ("(0 < 1) * 5", "(0 < 1) * 5"),
+        ] {
+            let packet = expr(input);
+            let result = packet.simplify();
+            assert_eq!(result.to_expression().unwrap(), simplified, "{input}");
+            assert_eq!(result.eval().ok(), packet.eval().ok(), "{input}");
+        }
+
+        let (_, stats) = expr("((7 + 0) * 1) + (2 * 0) + (3 + 4)").simplify_with_stats();
+        assert_eq!(
+            stats.rules(),
+            [
+                ("flatten", 1),
+                ("identity", 3),
+                ("absorb", 1),
+                ("unwrap", 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sort_commutative() {
+        let packet = expr("(9 * 2) + 7 + (3 < 1) + min(8, 2)");
+        assert_eq!(
+            packet.sort_commutative().to_expression().unwrap(),
+            "7 + (2 * 9) + min(2, 8) + (3 < 1)"
+        );
+    }
+
+    #[test]
+    fn test_truncate_depth() {
+        let packet = expr("max(1, 2 * (3 + 4)) + 5");
+        assert_eq!(
+            packet.truncate_depth(0).unwrap().to_expression().unwrap(),
+            "19"
+        );
+        assert_eq!(
+            packet.truncate_depth(1).unwrap().to_expression().unwrap(),
+            "14 + 5"
+        );
+        assert_eq!(
+            packet.truncate_depth(2).unwrap().to_expression().unwrap(),
+            "max(1, 14) + 5"
+        );
+        assert_eq!(packet.truncate_depth(3).unwrap().depth(), 3);
+    }
+
+    #[test]
+    fn test_sample_children() {
+        let packet = expr("1 + 2 + 3 + 4 + 5 + 6 + (7 < 8) + max(9, 10, 11, 12)");
+        let sampled = packet.sample_children(3, 42).unwrap();
+        assert_eq!(sampled, packet.sample_children(3, 42).unwrap());
+        assert_eq!(sampled.eval().unwrap(), packet.eval().unwrap());
+        assert!(sampled.packet_count() < packet.packet_count());
+        assert!(sampled
+            .flat_packets()
+            .iter()
+            .all(|packet| match &packet.kind {
+                crate::PacketKind::Operator { packets, .. } => packets.len() <= 4,
+                crate::PacketKind::Literal(_) => true,
+            }));
+    }
+
+    fn wide(operation: Operation, length: Length, count: usize) -> Packet {
+        let packets = vec![expr("0xFFFF"); count];
+        Packet {
+            version: Version::ZERO,
+            kind: PacketKind::Operator {
+                length: length.recomputed(&packets),
+                operation,
+                packets,
+            },
+        }
+    }
+
+    #[test]
+    fn test_fit_lengths() {
+        // 3000 literals of 26 bits each overflow both length fields
+        for length in [Length::TotalBits(0), Length::PacketCount(0)] {
+            let packet = wide(Operation::Sum, length, 3000);
+            assert!(matches!(
+                packet.to_bytes(),
+                Err(PacketError::LengthOverflow { ref path, .. }) if path.is_empty()
+            ));
+            let fitted = packet.fit_lengths().unwrap();
+            assert_eq!(fitted.eval().unwrap(), 3000 * 0xFFFF);
+            let reparsed = Packet::from_bytes(&fitted.to_bytes().unwrap()).unwrap();
+            assert_eq!(reparsed, fitted);
+        }
+
+        // 2000 literals overflow only the total length
+        let packet = wide(Operation::Maximum, Length::TotalBits(0), 2000);
+        let fitted = packet.fit_lengths().unwrap();
+        assert!(matches!(
+            fitted.kind,
+            PacketKind::Operator {
+                length: Length::PacketCount(2000),
+                ..
+            }
+        ));
+
+        let packet = wide(Operation::LessThan, Length::PacketCount(0), 3000);
+        assert!(matches!(
+            packet.fit_lengths(),
+            Err(PacketError::LengthOverflow { .. })
+        ));
+    }
+
+    #[test]
+    fn test_canonicalize_and_minify() {
+        // (10 < 20) encoded with a total bit length and version 1
+        let packet: Packet = "38006F45291200".parse().unwrap();
+        let canonical = packet.canonicalize();
+        assert_eq!(canonical.version, 0);
+        assert!(canonical.bit_len() < packet.bit_len());
+        let minified = packet.minify();
+        assert_eq!(minified.version, 1);
+        assert_eq!(minified.bit_len(), canonical.bit_len());
+        assert_eq!(minified.eval().unwrap(), packet.eval().unwrap());
+    }
+}
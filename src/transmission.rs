@@ -0,0 +1,196 @@
+//! Transmissions carrying several packets.
+//!
+//! A multi-packet transmission is a sequence of packets, each starting at the
+//! byte after the one before it ends, followed by nothing but zero padding.
+//! [`parse_all`] reads one into a [`Transmission`], which writes it back:
+//!
+//! ```
+//! use jacob::transmission::parse_all;
+//! use jacob::{bytes_from_hex, ParseOptions};
+//!
+//! let bytes = bytes_from_hex("C200B40A82D2FE2800").unwrap();
+//! let transmission = parse_all(&bytes, &ParseOptions::default()).unwrap();
+//! assert_eq!(transmission.packets.len(), 2);
+//! assert_eq!(transmission.packets[1].to_string(), "2021");
+//! assert_eq!(transmission.to_hex().unwrap(), "C200B40A82D2FE28");
+//! ```
+
+use std::str::FromStr;
+
+use crate::bits::{BitCursor, BitReader};
+use crate::{
+    bytes_from_hex, hex_from_bytes, Operation, Packet, PacketError, ParseOptions, Profile, Version,
+    Warnings,
+};
+
+/// The packets of a multi-packet transmission, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Transmission {
+    pub packets: Vec<Packet>,
+}
+
+impl Transmission {
+    /// Returns the packets encoded one after another, each padded to a byte.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if serialization fails.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, PacketError> {
+        self.to_bytes_with_profile(Profile::Standard)
+    }
+
+    /// Returns the packets encoded in `profile` one after another, each padded
+    /// to a byte.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if serialization fails or an operation is not
+    /// supported by `profile`.
+    pub fn to_bytes_with_profile(&self, profile: Profile) -> Result<Vec<u8>, PacketError> {
+        let mut bytes = Vec::new();
+        for packet in &self.packets {
+            bytes.extend(packet.to_bytes_with_profile(profile)?);
+        }
+        Ok(bytes)
+    }
+
+    /// Returns a packet combining the packets of the transmission as the
+    /// operands of a new `operation` with `version`, with the smallest
+    /// length encoding.
+    ///
+    /// ```
+    /// use jacob::transmission::Transmission;
+    /// use jacob::{Operation, Version};
+    ///
+    /// let transmission: Transmission = "C200B40A82D2FE28".parse().unwrap();
+    /// let packet = transmission.wrap(Operation::Maximum, Version::MAX).unwrap();
+    /// assert_eq!(packet.to_string(), "max((1 + 2), 2021)");
+    /// assert_eq!(packet.version, Version::MAX);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return [`PacketError::ArgumentError`] if `operation` cannot take
+    /// that many operands.
+    pub fn wrap(self, operation: Operation, version: Version) -> Result<Packet, PacketError> {
+        if !operation.info().arity.accepts(self.packets.len()) {
+            return Err(PacketError::arity(self.packets.len(), operation));
+        }
+        let mut packet = Packet::operator(operation, self.packets);
+        packet.version = version;
+        Ok(packet)
+    }
+
+    /// Returns hexadecimal representation of [`Transmission::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if serialization fails.
+    pub fn to_hex(&self) -> Result<String, PacketError> {
+        Ok(hex_from_bytes(&self.to_bytes()?))
+    }
+}
+
+impl From<Vec<Packet>> for Transmission {
+    fn from(packets: Vec<Packet>) -> Self {
+        Self { packets }
+    }
+}
+
+impl FromStr for Transmission {
+    type Err = PacketError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(&bytes_from_hex(s)?, &ParseOptions::default())
+    }
+}
+
+/// Parses every packet of a multi-packet transmission.
+///
+/// Packets are read in the profile and within the limits of `options`.
+/// Envelopes are not recognized, and
+/// [`trailing_padding`](ParseOptions::trailing_padding) is ignored: anything
+/// after a packet but zeros is the start of the next one.
+///
+/// # Errors
+///
+/// Will return `Err` if the input exceeds a limit or any packet is invalid.
+pub fn parse_all(bytes: &[u8], options: &ParseOptions) -> Result<Transmission, PacketError> {
+    options.check_input_size(bytes.len())?;
+    let max_depth = options.max_depth.unwrap_or(usize::MAX);
+    let mut packets = Vec::new();
+    let mut rest = bytes;
+    while rest.iter().any(|&byte| byte != 0) {
+        let mut reader = BitReader::new(rest);
+        packets.push(Packet::read_with_limits(
+            &mut reader,
+            options.profile,
+            max_depth,
+            options.max_expanded(),
+            &mut Warnings::new(),
+        )?);
+        let end = usize::try_from(reader.position().div_ceil(8)).unwrap_or(usize::MAX);
+        rest = &rest[end..];
+    }
+    Ok(Transmission { packets })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_all, Transmission};
+    use crate::{bytes_from_hex, Operation, Packet, PacketError, ParseOptions, Profile, Version};
+
+    #[test]
+    fn test_parse_all() {
+        let hexes = ["8A004A801A8002F478", "D2FE28", "9C0141080250320F1802104A08"];
+        let transmission: Transmission = hexes.concat().parse().unwrap();
+        let packets: Vec<Packet> = hexes.iter().map(|hex| hex.parse().unwrap()).collect();
+        assert_eq!(transmission, Transmission::from(packets));
+        assert_eq!(transmission.to_hex().unwrap(), hexes.concat());
+
+        assert_eq!("".parse::<Transmission>().unwrap(), Transmission::default());
+        assert_eq!("0000".parse::<Transmission>().unwrap().packets.len(), 0);
+        assert_eq!(
+            "D2FE280000".parse::<Transmission>().unwrap().packets.len(),
+            1
+        );
+        assert!("D2FE2801".parse::<Transmission>().is_err());
+
+        let extended = Packet::from_expression("if(1, 2, 3)").unwrap();
+        let options = ParseOptions {
+            profile: Profile::Extended,
+            max_input_bytes: Some(32),
+            ..ParseOptions::default()
+        };
+        let transmission = Transmission::from(vec![extended.clone(), extended]);
+        let bytes = transmission
+            .to_bytes_with_profile(Profile::Extended)
+            .unwrap();
+        assert_eq!(parse_all(&bytes, &options).unwrap(), transmission);
+        let too_large = bytes_from_hex(&"D2FE28".repeat(11)).unwrap();
+        assert!(parse_all(&too_large, &options).is_err());
+    }
+
+    #[test]
+    fn test_wrap() {
+        let transmission: Transmission = "C200B40A82D2FE28".parse().unwrap();
+        let packet = transmission
+            .clone()
+            .wrap(Operation::Sum, Version::new(1).unwrap())
+            .unwrap();
+        assert_eq!(packet.eval().unwrap(), 2024);
+        assert_eq!(
+            packet.version_sum(),
+            1 + transmission
+                .packets
+                .iter()
+                .map(Packet::version_sum)
+                .sum::<usize>()
+        );
+        assert_eq!(packet.to_hex().unwrap().parse::<Packet>().unwrap(), packet);
+        assert!(matches!(
+            transmission.wrap(Operation::If, Version::ZERO),
+            Err(PacketError::ArgumentError { count: 2, .. })
+        ));
+    }
+}
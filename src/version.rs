@@ -0,0 +1,135 @@
+//! The 3-bit packet version field.
+//!
+//! ```
+//! use jacob::{Packet, Version};
+//!
+//! assert!(Version::try_from(8).is_err());
+//! let packet = Packet::try_from("8A004A801A8002F478").unwrap();
+//! assert_eq!(packet.version, Version::try_from(4).unwrap());
+//! assert_eq!(packet.version_sum(), 16);
+//! ```
+
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+use crate::{Packet, PacketError};
+
+/// Packet version, always in `0..=7` so it fits its field when serialized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version(u8);
+
+impl Version {
+    pub const ZERO: Self = Self(0);
+    pub const MAX: Self = Self(7);
+
+    /// Returns the version `version`, or `None` if it does not fit in 3 bits.
+    #[must_use]
+    pub const fn new(version: u8) -> Option<Self> {
+        if version <= Self::MAX.0 {
+            Some(Self(version))
+        } else {
+            None
+        }
+    }
+
+    #[must_use]
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for Version {
+    type Error = PacketError;
+
+    fn try_from(version: u8) -> Result<Self, Self::Error> {
+        Self::new(version).ok_or(PacketError::VersionError(version))
+    }
+}
+
+impl From<Version> for u8 {
+    fn from(version: Version) -> Self {
+        version.0
+    }
+}
+
+impl From<Version> for usize {
+    fn from(version: Version) -> Self {
+        Self::from(version.0)
+    }
+}
+
+impl PartialEq<u8> for Version {
+    fn eq(&self, other: &u8) -> bool {
+        self.0 == *other
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Versions add up to plain numbers, for version sums.
+impl Add<Version> for usize {
+    type Output = Self;
+
+    fn add(self, version: Version) -> Self {
+        self + Self::from(version)
+    }
+}
+
+impl AddAssign<Version> for usize {
+    fn add_assign(&mut self, version: Version) {
+        *self += Self::from(version);
+    }
+}
+
+impl Sum<Version> for usize {
+    fn sum<I: Iterator<Item = Version>>(iter: I) -> Self {
+        iter.map(Self::from).sum()
+    }
+}
+
+impl<'a> Sum<&'a Version> for usize {
+    fn sum<I: Iterator<Item = &'a Version>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+impl Packet {
+    /// Returns the sum of the versions of every packet in the tree.
+    #[must_use]
+    pub fn version_sum(&self) -> usize {
+        self.flat_packets()
+            .into_iter()
+            .map(|packet| packet.version)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Version;
+    use crate::{ErrorKind, Packet};
+
+    #[test]
+    fn test_version() {
+        assert_eq!(Version::new(7), Some(Version::MAX));
+        assert_eq!(Version::new(8), None);
+        assert_eq!(Version::try_from(9).unwrap_err().kind(), ErrorKind::Version);
+        assert_eq!(u8::from(Version::try_from(5).unwrap()), 5);
+        let mut sum = 1;
+        sum += Version::MAX;
+        assert_eq!(sum + Version::MAX, 15);
+
+        for (hex, sum) in [
+            ("8A004A801A8002F478", 16),
+            ("620080001611562C8802118E34", 12),
+            ("C0015000016115A2E0802F182340", 23),
+            ("A0016C880162017C3686B18A3D4780", 31),
+        ] {
+            assert_eq!(Packet::try_from(hex).unwrap().version_sum(), sum);
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! Compilation of packets to bytecode for repeated evaluation.
+//!
+//! [`Packet::compile`] lowers a tree to a flat postfix program once, checking
+//! every operator's arity up front. A [`Vm`] then runs the program without
+//! recursion or per-node allocation, reusing its stack across runs. Subtrees
+//! can be compiled as variables and bound to new values on every run, which
+//! suits evaluating one large packet many times with different inputs.
+//!
+//! ```
+//! use jacob::vm::Vm;
+//! use jacob::Packet;
+//!
+//! let packet = Packet::from_expression("max(1 + 2, 3 * 4)").unwrap();
+//! let mut vm = Vm::new();
+//! assert_eq!(vm.run(&packet.compile().unwrap()).unwrap(), 12);
+//!
+//! // Replace the literal `4` with a variable
+//! let bytecode = packet.compile_with_variables(&[&[1, 1]]).unwrap();
+//! assert_eq!(vm.run_with(&bytecode, &[0]).unwrap(), 3);
+//! assert_eq!(vm.run_with(&bytecode, &[10]).unwrap(), 30);
+//! ```
+
+use crate::{Operation, Packet, PacketError, PacketKind};
+
+/// One step of a [`Bytecode`] program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// Pushes a literal value.
+    Push(usize),
+    /// Pushes the value bound to a variable.
+    Load(usize),
+    /// Pops `count` operands, the last pushed being the last operand, and
+    /// pushes the result of `operation` on them. Compiled conditionals use
+    /// jumps instead, so only the taken branch is evaluated.
+    Apply { operation: Operation, count: usize },
+    /// Pops a value and continues at the given instruction if it is zero.
+    JumpIfZero(usize),
+    /// Continues at the given instruction.
+    Jump(usize),
+}
+
+/// A compiled packet; see the [module documentation](self).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bytecode {
+    instructions: Vec<Instruction>,
+    variables: usize,
+}
+
+impl Bytecode {
+    #[must_use]
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// Returns the number of values [`Vm::run_with`] expects.
+    #[must_use]
+    pub const fn variables(&self) -> usize {
+        self.variables
+    }
+}
+
+impl Packet {
+    /// Compiles the packet to bytecode.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any operator has an invalid number of operands,
+    /// including in branches [`Packet::eval`] would not take.
+    pub fn compile(&self) -> Result<Bytecode, PacketError> {
+        self.compile_with_variables(&[])
+    }
+
+    /// Compiles the packet to bytecode, replacing the packet at each of
+    /// `paths` with a variable numbered by its position in `paths`. Paths
+    /// that do not exist are ignored, and paths inside another variable never
+    /// take effect.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any compiled operator has an invalid number of
+    /// operands.
+    pub fn compile_with_variables(&self, paths: &[&[usize]]) -> Result<Bytecode, PacketError> {
+        let mut instructions = Vec::new();
+        self.compile_at(&mut instructions, paths, &mut Vec::new())?;
+        Ok(Bytecode {
+            instructions,
+            variables: paths.len(),
+        })
+    }
+
+    /// Compiles the packet without variables into `bytecode`, reusing its
+    /// allocation.
+    pub(crate) fn compile_into(&self, bytecode: &mut Bytecode) -> Result<(), PacketError> {
+        bytecode.instructions.clear();
+        bytecode.variables = 0;
+        self.compile_at(&mut bytecode.instructions, &[], &mut Vec::new())
+    }
+
+    fn compile_at(
+        &self,
+        instructions: &mut Vec<Instruction>,
+        paths: &[&[usize]],
+        path: &mut Vec<usize>,
+    ) -> Result<(), PacketError> {
+        if let Some(variable) = paths.iter().position(|p| *p == &path[..]) {
+            instructions.push(Instruction::Load(variable));
+            return Ok(());
+        }
+        let (operation, packets) = match &self.kind {
+            PacketKind::Literal(value) => {
+                instructions.push(Instruction::Push(*value));
+                return Ok(());
+            }
+            PacketKind::Operator {
+                operation, packets, ..
+            } => (*operation, packets),
+        };
+        if !operation.info().arity.accepts(packets.len()) {
+            return Err(PacketError::ArgumentError {
+                count: packets.len(),
+                operation,
+                path: path.clone().into(),
+            });
+        }
+        let mut operand = |i: usize, instructions: &mut Vec<Instruction>| {
+            path.push(i);
+            let result = packets[i].compile_at(instructions, paths, path);
+            path.pop();
+            result
+        };
+        if operation == Operation::If {
+            // condition, JumpIfZero(else), then, Jump(end), else
+            operand(0, instructions)?;
+            let jump_to_else = instructions.len();
+            instructions.push(Instruction::JumpIfZero(0));
+            operand(1, instructions)?;
+            let jump_to_end = instructions.len();
+            instructions.push(Instruction::Jump(0));
+            instructions[jump_to_else] = Instruction::JumpIfZero(instructions.len());
+            operand(2, instructions)?;
+            instructions[jump_to_end] = Instruction::Jump(instructions.len());
+        } else {
+            for i in 0..packets.len() {
+                operand(i, instructions)?;
+            }
+            instructions.push(Instruction::Apply {
+                operation,
+                count: packets.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Runs [`Bytecode`], keeping its stack allocated between runs.
+#[derive(Clone, Debug, Default)]
+pub struct Vm {
+    stack: Vec<usize>,
+}
+
+impl Vm {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs bytecode without variables.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the bytecode has variables or a value overflows.
+    pub fn run(&mut self, bytecode: &Bytecode) -> Result<usize, PacketError> {
+        self.run_with(bytecode, &[])
+    }
+
+    /// Runs bytecode with `values` bound to its variables, in order.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the number of values differs from the number of
+    /// variables or a value overflows.
+    pub fn run_with(
+        &mut self,
+        bytecode: &Bytecode,
+        values: &[usize],
+    ) -> Result<usize, PacketError> {
+        if values.len() != bytecode.variables {
+            return Err(PacketError::BindingError {
+                expected: bytecode.variables,
+                found: values.len(),
+            });
+        }
+        let stack = &mut self.stack;
+        stack.clear();
+        let instructions = &bytecode.instructions;
+        let mut next = 0;
+        while let Some(&instruction) = instructions.get(next) {
+            next += 1;
+            match instruction {
+                Instruction::Push(value) => stack.push(value),
+                Instruction::Load(variable) => stack.push(values[variable]),
+                Instruction::JumpIfZero(target) => {
+                    if stack.pop() == Some(0) {
+                        next = target;
+                    }
+                }
+                Instruction::Jump(target) => next = target,
+                Instruction::Apply { operation, count } => {
+                    let operands = &stack[stack.len() - count..];
+                    let overflow = || PacketError::Overflow(Some(operation));
+                    let value = match operation {
+                        Operation::If => unreachable!("`if` compiles to jumps"),
+                        _ => operation.fold_all(operands).ok_or_else(overflow)?,
+                    };
+                    stack.truncate(stack.len() - count);
+                    stack.push(value);
+                }
+            }
+        }
+        Ok(stack.pop().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Instruction, Vm};
+    use crate::{ErrorKind, Operation, Packet};
+
+    #[test]
+    fn test_run_matches_eval() {
+        let mut vm = Vm::new();
+        for expr in [
+            "7",
+            "1 + 2 + 3",
+            "max(1 + 2, 3 * 4, min(5, 6))",
+            "if((1 < 2), 10, (3 * 4))",
+            "if(0, 10, if((5 == 5), (2 > 1), 8))",
+            "sum() + product()",
+        ] {
+            let packet = Packet::from_expression(expr).unwrap();
+            let bytecode = packet.compile().unwrap();
+            assert_eq!(vm.run(&bytecode).unwrap(), packet.eval().unwrap(), "{expr}");
+        }
+    }
+
+    #[test]
+    fn test_compile() {
+        let packet = Packet::from_expression("if(1, 2, 3 + 4)").unwrap();
+        assert_eq!(
+            packet.compile().unwrap().instructions(),
+            [
+                Instruction::Push(1),
+                Instruction::JumpIfZero(4),
+                Instruction::Push(2),
+                Instruction::Jump(7),
+                Instruction::Push(3),
+                Instruction::Push(4),
+                Instruction::Apply {
+                    operation: Operation::Sum,
+                    count: 2
+                },
+            ]
+        );
+        let error = Packet::from_expression("min()")
+            .unwrap()
+            .compile()
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Arity);
+    }
+
+    #[test]
+    fn test_variables() {
+        let packet = Packet::from_expression("(1 + 2) * 3").unwrap();
+        let bytecode = packet.compile_with_variables(&[&[0], &[1], &[9]]).unwrap();
+        assert_eq!(bytecode.variables(), 3);
+        let mut vm = Vm::new();
+        assert_eq!(vm.run_with(&bytecode, &[5, 6, 0]).unwrap(), 30);
+        assert_eq!(vm.run(&bytecode).unwrap_err().kind(), ErrorKind::Binding);
+        assert_eq!(
+            vm.run_with(&bytecode, &[usize::MAX, 2, 0])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Overflow
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Problems that do not stop a packet from parsing or serializing.
+//!
+//! Parsing accepts some encodings a careful encoder would not produce, and
+//! some packets are valid but probably not what their sender meant.
+//! [`Packet::parse_with_warnings`] reports these as [`Warnings`], separately
+//! from the [`PacketError`]s that reject a packet outright:
+//!
+//! ```
+//! use jacob::{Packet, ParseOptions, Warning};
+//!
+//! // `1 + 2` with a literal padded by a leading zero group, followed by a
+//! // byte of zeros
+//! let hex = "C2008480441000";
+//! let (packet, warnings) = Packet::parse_with_warnings(hex, &ParseOptions::default()).unwrap();
+//! assert_eq!(packet.eval().unwrap(), 3);
+//! assert!(matches!(
+//!     warnings[0],
+//!     Warning::LiteralPadding { extra_groups: 1, .. }
+//! ));
+//! for warning in &warnings {
+//!     println!("warning: {warning}");
+//! }
+//! ```
+//!
+//! [`Packet::warnings`] checks a packet built any other way, such as one about
+//! to be serialized, for the warnings that do not depend on its encoding.
+
+use std::fmt;
+use std::ops::Deref;
+
+use crate::{Length, Packet, PacketError, PacketKind, PacketPath, ParseOptions, Version};
+
+/// Something questionable about a packet that does not make it invalid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// A literal is encoded with `extra_groups` more value groups than its
+    /// value needs, as leading zeros.
+    LiteralPadding { extra_groups: u64, path: PacketPath },
+    /// `count` bits follow the byte the packet ends in.
+    UnusedBits { count: u64 },
+    /// Every packet has version 0, as when the sender does not set versions.
+    AllVersionsZero,
+    /// An operator gives the length of its operands in bits where counting
+    /// them would take 4 bits fewer.
+    SuboptimalLength { path: PacketPath },
+    /// An operator has no operands; see
+    /// [`EmptyOperator`](crate::width::EmptyOperator).
+    EmptyOperator { path: PacketPath },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LiteralPadding { extra_groups, path } => write!(
+                f,
+                "literal at path {path} is padded with {extra_groups} leading zero groups"
+            ),
+            Self::UnusedBits { count } => {
+                write!(f, "{count} unused bits follow the packet's final byte")
+            }
+            Self::AllVersionsZero => f.write_str("every packet has version 0"),
+            Self::SuboptimalLength { path } => write!(
+                f,
+                "operator at path {path} gives its length in bits where a count is shorter"
+            ),
+            Self::EmptyOperator { path } => write!(f, "operator at path {path} has no operands"),
+        }
+    }
+}
+
+/// The [`Warning`]s about one packet, in the order they were found.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Warnings(Vec<Warning>);
+
+impl Warnings {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub(crate) fn push(&mut self, warning: Warning) {
+        self.0.push(warning);
+    }
+}
+
+impl Deref for Warnings {
+    type Target = [Warning];
+
+    fn deref(&self) -> &[Warning] {
+        &self.0
+    }
+}
+
+impl IntoIterator for Warnings {
+    type Item = Warning;
+    type IntoIter = std::vec::IntoIter<Warning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Warnings {
+    type Item = &'a Warning;
+    type IntoIter = std::slice::Iter<'a, Warning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl From<Warnings> for Vec<Warning> {
+    fn from(warnings: Warnings) -> Self {
+        warnings.0
+    }
+}
+
+impl Packet {
+    /// Parses a hexadecimal packet like [`Packet::from_hex_with`], also
+    /// returning the warnings about its encoding and about the packet itself.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if [`Packet::from_hex_with`] would.
+    pub fn parse_with_warnings(
+        hex: &str,
+        options: &ParseOptions,
+    ) -> Result<(Self, Warnings), PacketError> {
+        let mut warnings = Warnings::new();
+        let packet = Self::from_hex_into(hex, options, &mut warnings)?;
+        warnings.0.extend(packet.warnings());
+        Ok((packet, warnings))
+    }
+
+    /// Returns the warnings about the packet that do not depend on how it was
+    /// encoded: empty operators, sub-optimal length encodings, and versions
+    /// all 0.
+    #[must_use]
+    pub fn warnings(&self) -> Warnings {
+        let mut warnings = Warnings::new();
+        let mut all_zero = true;
+        for (path, packet) in self.packets_with_paths() {
+            all_zero &= packet.version == Version::ZERO;
+            let PacketKind::Operator {
+                length, packets, ..
+            } = &packet.kind
+            else {
+                continue;
+            };
+            if packets.is_empty() {
+                warnings.push(Warning::EmptyOperator {
+                    path: path.clone().into(),
+                });
+            }
+            if matches!(length, Length::TotalBits(_))
+                && matches!(Length::minimal(packets), Length::PacketCount(_))
+            {
+                warnings.push(Warning::SuboptimalLength { path: path.into() });
+            }
+        }
+        if all_zero {
+            warnings.push(Warning::AllVersionsZero);
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Warning;
+    use crate::{Packet, PacketPath, ParseOptions};
+
+    #[test]
+    fn test_warnings() {
+        let parse = |hex| Packet::parse_with_warnings(hex, &ParseOptions::default()).unwrap();
+        let (packet, warnings) = parse("D2FE28");
+        assert_eq!(packet, "D2FE28".parse().unwrap());
+        assert!(warnings.is_empty());
+
+        // Version 0 throughout, with the operand length in bits
+        let (_, warnings) = parse("000058408820");
+        assert_eq!(
+            Vec::from(warnings),
+            [
+                Warning::SuboptimalLength {
+                    path: PacketPath::root()
+                },
+                Warning::AllVersionsZero
+            ]
+        );
+        let (_, warnings) = parse("D2FE280000");
+        assert_eq!(warnings[..], [Warning::UnusedBits { count: 16 }]);
+        assert!(parse("EE00D40C823060").1.is_empty());
+
+        // A two-group encoding of 1 inside `1 + 2`
+        let (packet, warnings) = parse("C2008480441000");
+        assert_eq!(packet.to_string(), "1 + 2");
+        assert_eq!(
+            warnings[..],
+            [
+                Warning::LiteralPadding {
+                    extra_groups: 1,
+                    path: vec![0].into()
+                },
+                Warning::UnusedBits { count: 8 }
+            ]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "literal at path 0 is padded with 1 leading zero groups"
+        );
+
+        let packet = Packet::from_expression("sum() + 1").unwrap();
+        assert_eq!(
+            packet.warnings()[..],
+            [
+                Warning::EmptyOperator {
+                    path: vec![0].into()
+                },
+                Warning::AllVersionsZero
+            ]
+        );
+    }
+}
@@ -0,0 +1,642 @@
+//! Evaluation with an explicitly chosen integer type.
+//!
+//! [`Packet::eval`] computes in `usize`, whose width depends on the target and
+//! which panics on overflow in debug builds. [`Packet::eval_as`] lets callers
+//! pick any primitive integer and decide what happens when a value does not
+//! fit.
+//!
+//! ```
+//! use jacob::width::Overflow;
+//! use jacob::Packet;
+//!
+//! let packet = Packet::from_expression("200 + 100").unwrap();
+//! assert_eq!(packet.eval_as::<u8>(Overflow::Wrapping).unwrap(), 44);
+//! assert_eq!(packet.eval_as::<u8>(Overflow::Saturating).unwrap(), 255);
+//! assert!(packet.eval_as::<u8>(Overflow::Checked).is_err());
+//! assert_eq!(packet.eval_as::<i128>(Overflow::Checked).unwrap(), 300);
+//! ```
+//!
+//! [`Packet::eval_with_options`] evaluates in `u64` as configured by
+//! [`EvalOptions`], including arithmetic modulo a fixed number:
+//!
+//! ```
+//! use jacob::width::Overflow;
+//! use jacob::{EvalOptions, Packet};
+//!
+//! let packet = Packet::from_expression("(200 + 100) * 3").unwrap();
+//! let options = EvalOptions::new().modulus(Some(256));
+//! assert_eq!(packet.eval_with_options(&options).unwrap(), 900 % 256);
+//!
+//! let packet = Packet::from_expression("18446744073709551615 + 1").unwrap();
+//! let options = EvalOptions::new().overflow(Overflow::Saturating);
+//! assert_eq!(packet.eval_with_options(&options).unwrap(), u64::MAX);
+//! ```
+//!
+//! Comparisons take exactly two operands unless [`ComparisonOrder`] says how
+//! to read more:
+//!
+//! ```
+//! use jacob::width::ComparisonOrder;
+//! use jacob::{EvalOptions, Packet};
+//!
+//! let packet = Packet::from_expression("gt(3, 2, 1)").unwrap();
+//! assert!(packet.eval_with_options(&EvalOptions::new()).is_err());
+//! let chain = EvalOptions::new().comparison_order(ComparisonOrder::Chain);
+//! assert_eq!(packet.eval_with_options(&chain).unwrap(), 1);
+//! let fold = EvalOptions::new().comparison_order(ComparisonOrder::LeftFold);
+//! assert_eq!(packet.eval_with_options(&fold).unwrap(), 0);
+//! ```
+//!
+//! Operators without operands are handled as [`EmptyOperator`] says:
+//!
+//! ```
+//! use jacob::width::EmptyOperator;
+//! use jacob::{EvalOptions, Packet};
+//!
+//! let packet = Packet::from_expression("2 * max(3, sum()) * product()").unwrap();
+//! assert_eq!(packet.eval_with_options(&EvalOptions::new()).unwrap(), 6);
+//! let error = EvalOptions::new().empty_operators(EmptyOperator::Error);
+//! assert!(packet.eval_with_options(&error).is_err());
+//! let skip = EvalOptions::new().empty_operators(EmptyOperator::Skip);
+//! assert_eq!(packet.eval_with_options(&skip).unwrap(), 6);
+//! assert!(Packet::from_expression("max()").unwrap().eval_with_options(&skip).is_err());
+//! ```
+
+use crate::{Operation, Packet, PacketError, PacketKind};
+
+/// What to do when a literal or intermediate result does not fit the chosen type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Wrap around at the boundary of the type.
+    Wrapping,
+    /// Clamp to the type's minimum or maximum.
+    Saturating,
+    /// Fail with [`PacketError::Overflow`].
+    #[default]
+    Checked,
+}
+
+/// How comparisons treat operands beyond the two the format specifies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ComparisonOrder {
+    /// Exactly two operands; any other number fails with
+    /// [`PacketError::ArgumentError`].
+    #[default]
+    StrictTwo,
+    /// Compare the result of each comparison with the next operand, as in C:
+    /// `a > b > c` is `(a > b) > c`.
+    LeftFold,
+    /// Compare each operand with the next, as in Python: `a > b > c` is
+    /// `a > b and b > c`.
+    Chain,
+}
+
+impl ComparisonOrder {
+    /// Applies the comparison `operation` to `operands`, where `truth` is the
+    /// value of a true comparison, or returns `None` if the number of
+    /// operands is not accepted.
+    fn compare<T: PrimInt>(self, operation: Operation, operands: &[T], truth: T) -> Option<T> {
+        let test = |a: T, b: T| match operation {
+            Operation::LessThan => a < b,
+            Operation::GreaterThan => a > b,
+            _ => a == b,
+        };
+        let value = |result| if result { truth } else { T::ZERO };
+        match (self, operands) {
+            (_, [a, b]) => Some(value(test(*a, *b))),
+            (Self::StrictTwo, _) | (_, [] | [_]) => None,
+            (Self::LeftFold, [first, rest @ ..]) => {
+                Some(rest.iter().fold(*first, |acc, &b| value(test(acc, b))))
+            }
+            (Self::Chain, _) => Some(value(
+                operands.windows(2).all(|pair| test(pair[0], pair[1])),
+            )),
+        }
+    }
+}
+
+/// Primitive integer types that packets can be evaluated as.
+///
+/// Implemented for every built-in signed and unsigned integer type.
+pub trait PrimInt: Copy + Ord {
+    const ZERO: Self;
+    const ONE: Self;
+
+    /// Converts a literal value, returning `None` if it does not fit.
+    fn checked_from_usize(value: usize) -> Option<Self>;
+    /// Converts a literal value, keeping only the low bits that fit.
+    fn wrapping_from_usize(value: usize) -> Self;
+    /// Converts a literal value, clamping it to the type's maximum.
+    fn saturating_from_usize(value: usize) -> Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    #[must_use]
+    fn wrapping_add(self, rhs: Self) -> Self;
+    #[must_use]
+    fn wrapping_mul(self, rhs: Self) -> Self;
+    #[must_use]
+    fn saturating_add(self, rhs: Self) -> Self;
+    #[must_use]
+    fn saturating_mul(self, rhs: Self) -> Self;
+}
+
+macro_rules! impl_prim_int {
+    ($($t:ty),*) => {$(
+        impl PrimInt for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            fn checked_from_usize(value: usize) -> Option<Self> {
+                Self::try_from(value).ok()
+            }
+
+            #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+            fn wrapping_from_usize(value: usize) -> Self {
+                value as Self
+            }
+
+            fn saturating_from_usize(value: usize) -> Self {
+                Self::try_from(value).unwrap_or(Self::MAX)
+            }
+
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                self.checked_add(rhs)
+            }
+
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                self.checked_mul(rhs)
+            }
+
+            fn wrapping_add(self, rhs: Self) -> Self {
+                self.wrapping_add(rhs)
+            }
+
+            fn wrapping_mul(self, rhs: Self) -> Self {
+                self.wrapping_mul(rhs)
+            }
+
+            fn saturating_add(self, rhs: Self) -> Self {
+                self.saturating_add(rhs)
+            }
+
+            fn saturating_mul(self, rhs: Self) -> Self {
+                self.saturating_mul(rhs)
+            }
+        }
+    )*};
+}
+
+impl_prim_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl Overflow {
+    fn literal<T: PrimInt>(self, value: usize) -> Result<T, PacketError> {
+        match self {
+            Self::Wrapping => Ok(T::wrapping_from_usize(value)),
+            Self::Saturating => Ok(T::saturating_from_usize(value)),
+            Self::Checked => T::checked_from_usize(value).ok_or(PacketError::Overflow(None)),
+        }
+    }
+
+    fn add<T: PrimInt>(self, a: T, b: T) -> Result<T, PacketError> {
+        match self {
+            Self::Wrapping => Ok(a.wrapping_add(b)),
+            Self::Saturating => Ok(a.saturating_add(b)),
+            Self::Checked => a
+                .checked_add(b)
+                .ok_or(PacketError::Overflow(Some(Operation::Sum))),
+        }
+    }
+
+    fn mul<T: PrimInt>(self, a: T, b: T) -> Result<T, PacketError> {
+        match self {
+            Self::Wrapping => Ok(a.wrapping_mul(b)),
+            Self::Saturating => Ok(a.saturating_mul(b)),
+            Self::Checked => a
+                .checked_mul(b)
+                .ok_or(PacketError::Overflow(Some(Operation::Product))),
+        }
+    }
+}
+
+/// How operators without operands are evaluated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EmptyOperator {
+    /// Sums are 0 and products 1, as in [`Packet::eval`]; other operations
+    /// fail with [`PacketError::ArgumentError`].
+    #[default]
+    Identity,
+    /// Every operation fails with [`PacketError::ArgumentError`].
+    Error,
+    /// Drop them from the operands of the operator containing them, along
+    /// with operators left without operands by doing so. A packet that is
+    /// dropped entirely fails with [`PacketError::ArgumentError`].
+    Skip,
+}
+
+/// Options for [`Packet::eval_with_options`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EvalOptions {
+    overflow: Overflow,
+    modulus: Option<u64>,
+    comparison_order: ComparisonOrder,
+    empty_operators: EmptyOperator,
+}
+
+impl EvalOptions {
+    /// Returns the default options: checked `u64` arithmetic.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            overflow: Overflow::Checked,
+            modulus: None,
+            comparison_order: ComparisonOrder::StrictTwo,
+            empty_operators: EmptyOperator::Identity,
+        }
+    }
+
+    /// Handles values that do not fit in `u64` according to `overflow`,
+    /// emulating consumers that wrap or saturate instead of failing.
+    /// Irrelevant with a [`modulus`](Self::modulus).
+    #[must_use]
+    pub const fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Performs all arithmetic modulo `modulus`, as in checksums or fixed-width
+    /// registers: literals are reduced before use, and sums and products
+    /// after each step, so nothing can overflow. Comparisons, `min`, and `max`
+    /// compare reduced values. `Some(0)` is the same as `None`.
+    #[must_use]
+    pub const fn modulus(mut self, modulus: Option<u64>) -> Self {
+        self.modulus = match modulus {
+            Some(0) => None,
+            modulus => modulus,
+        };
+        self
+    }
+
+    /// Reads comparisons with more than two operands according to
+    /// `comparison_order`, matching dialects that allow them.
+    #[must_use]
+    pub const fn comparison_order(mut self, comparison_order: ComparisonOrder) -> Self {
+        self.comparison_order = comparison_order;
+        self
+    }
+
+    /// Evaluates operators without operands according to `empty_operators`.
+    #[must_use]
+    pub const fn empty_operators(mut self, empty_operators: EmptyOperator) -> Self {
+        self.empty_operators = empty_operators;
+        self
+    }
+
+    /// Returns the indices of the operands of an operator that are evaluated,
+    /// or `Err` if there are none and the policy does not allow that.
+    fn operand_indices<'a>(
+        &self,
+        operation: Operation,
+        packets: &'a [Packet],
+    ) -> Result<impl Iterator<Item = usize> + Clone + 'a, PacketError> {
+        let skip = self.empty_operators == EmptyOperator::Skip;
+        let indices = (0..packets.len()).filter(move |&i| !(skip && packets[i].is_vacant()));
+        if self.empty_operators != EmptyOperator::Identity && indices.clone().next().is_none() {
+            return Err(PacketError::arity(packets.len(), operation));
+        }
+        Ok(indices)
+    }
+}
+
+impl Packet {
+    /// Evaluates the packet in `u64` as configured by `options`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any operators have an invalid number of arguments,
+    /// or if a value overflows `u64` with [`Overflow::Checked`] and no modulus.
+    pub fn eval_with_options(&self, options: &EvalOptions) -> Result<u64, PacketError> {
+        options.modulus.map_or_else(
+            || self.eval_in(options),
+            |modulus| self.eval_modulo(modulus, options),
+        )
+    }
+
+    /// Returns `true` if the packet is an operator whose operands, if any,
+    /// are all vacant too, so that [`EmptyOperator::Skip`] drops it.
+    fn is_vacant(&self) -> bool {
+        matches!(&self.kind, PacketKind::Operator { packets, .. } if packets.iter().all(Self::is_vacant))
+    }
+
+    // Remainders are below `modulus`, so they fit in `u64`, and the sum or
+    // product of two fits in `u128`
+    #[allow(clippy::cast_possible_truncation)]
+    fn eval_modulo(&self, modulus: u64, options: &EvalOptions) -> Result<u64, PacketError> {
+        let reduce = |value: u128| (value % u128::from(modulus)) as u64;
+        let (operation, packets) = match &self.kind {
+            PacketKind::Literal(value) => return Ok(reduce(*value as u128)),
+            PacketKind::Operator {
+                operation, packets, ..
+            } => (*operation, packets),
+        };
+        let arity_error = || PacketError::arity(packets.len(), operation);
+        let order = options.comparison_order;
+        let operand = |i: usize| {
+            packets[i]
+                .eval_modulo(modulus, options)
+                .map_err(|error| error.within(i))
+        };
+        let indices = options.operand_indices(operation, packets)?;
+        let mut operands = indices.clone().map(operand);
+        Ok(match operation {
+            Operation::Sum => operands.try_fold(0, |acc, operand| {
+                Ok::<_, PacketError>(reduce(u128::from(acc) + u128::from(operand?)))
+            })?,
+            Operation::Product => operands.try_fold(reduce(1), |acc, operand| {
+                Ok::<_, PacketError>(reduce(u128::from(acc) * u128::from(operand?)))
+            })?,
+            Operation::Minimum | Operation::Maximum => {
+                let first = operands.next().ok_or_else(arity_error)??;
+                operands.try_fold(first, |acc, operand| {
+                    let value = operand?;
+                    Ok::<_, PacketError>(if operation == Operation::Minimum {
+                        acc.min(value)
+                    } else {
+                        acc.max(value)
+                    })
+                })?
+            }
+            Operation::LessThan | Operation::GreaterThan | Operation::EqualTo => {
+                let operands = operands.collect::<Result<Vec<_>, _>>()?;
+                order
+                    .compare(operation, &operands, reduce(1))
+                    .ok_or_else(arity_error)?
+            }
+            Operation::If => {
+                let Ok([condition, then, otherwise]) =
+                    <[usize; 3]>::try_from(indices.collect::<Vec<_>>())
+                else {
+                    return Err(arity_error());
+                };
+                if operand(condition)? == 0 {
+                    operand(otherwise)?
+                } else {
+                    operand(then)?
+                }
+            }
+        })
+    }
+
+    /// Evaluates the packet using `T` arithmetic, handling values that do not
+    /// fit in `T` according to `overflow`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any operators have an invalid number of arguments,
+    /// or if a value overflows `T` with [`Overflow::Checked`].
+    pub fn eval_as<T: PrimInt>(&self, overflow: Overflow) -> Result<T, PacketError> {
+        self.eval_in(&EvalOptions::new().overflow(overflow))
+    }
+
+    fn eval_in<T: PrimInt>(&self, options: &EvalOptions) -> Result<T, PacketError> {
+        let overflow = options.overflow;
+        let (operation, packets) = match &self.kind {
+            PacketKind::Literal(value) => return overflow.literal(*value),
+            PacketKind::Operator {
+                operation, packets, ..
+            } => (*operation, packets),
+        };
+        let arity_error = || PacketError::arity(packets.len(), operation);
+        let order = options.comparison_order;
+        let operand = |i: usize| {
+            packets[i]
+                .eval_in::<T>(options)
+                .map_err(|error| error.within(i))
+        };
+        let indices = options.operand_indices(operation, packets)?;
+        let mut operands = indices.clone().map(operand);
+        Ok(match operation {
+            Operation::Sum => {
+                operands.try_fold(T::ZERO, |acc, operand| overflow.add(acc, operand?))?
+            }
+            Operation::Product => {
+                operands.try_fold(T::ONE, |acc, operand| overflow.mul(acc, operand?))?
+            }
+            Operation::Minimum | Operation::Maximum => {
+                let first = operands.next().ok_or_else(arity_error)??;
+                operands.try_fold(first, |acc, operand| {
+                    let value = operand?;
+                    Ok::<_, PacketError>(if operation == Operation::Minimum {
+                        acc.min(value)
+                    } else {
+                        acc.max(value)
+                    })
+                })?
+            }
+            Operation::LessThan | Operation::GreaterThan | Operation::EqualTo => {
+                let operands = operands.collect::<Result<Vec<_>, _>>()?;
+                order
+                    .compare(operation, &operands, T::ONE)
+                    .ok_or_else(arity_error)?
+            }
+            Operation::If => {
+                let Ok([condition, then, otherwise]) =
+                    <[usize; 3]>::try_from(indices.collect::<Vec<_>>())
+                else {
+                    return Err(arity_error());
+                };
+                if operand(condition)? == T::ZERO {
+                    operand(otherwise)?
+                } else {
+                    operand(then)?
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ComparisonOrder, EmptyOperator, EvalOptions, Overflow};
+    use crate::{Operation, Packet, PacketError};
+
+    #[test]
+    fn test_eval_as() {
+        let packet = Packet::from_expression("(300 * 2) < 100").unwrap();
+        assert_eq!(packet.eval_as::<u64>(Overflow::Checked).unwrap(), 0);
+        // 300 wraps to 44 and 44 * 2 = 88
+        assert_eq!(packet.eval_as::<u8>(Overflow::Wrapping).unwrap(), 1);
+        assert_eq!(packet.eval_as::<u8>(Overflow::Saturating).unwrap(), 0);
+        assert!(matches!(
+            packet.eval_as::<u8>(Overflow::Checked),
+            Err(PacketError::Overflow(None))
+        ));
+
+        let packet = Packet::from_expression("100 * 100").unwrap();
+        assert_eq!(packet.eval_as::<i8>(Overflow::Saturating).unwrap(), i8::MAX);
+        assert_eq!(packet.eval_as::<i16>(Overflow::Checked).unwrap(), 10_000);
+        assert!(matches!(
+            packet.eval_as::<u8>(Overflow::Checked),
+            Err(PacketError::Overflow(Some(Operation::Product)))
+        ));
+    }
+
+    #[test]
+    fn test_eval_as_matches_eval() {
+        for expr in ["if(1 < 2, 7, 8)", "min(3, 9) + max(4, 1)", "2 * 3 == 6"] {
+            let packet = Packet::from_expression(expr).unwrap();
+            let value = packet.eval().unwrap();
+            assert_eq!(
+                packet.eval_as::<u128>(Overflow::Checked).unwrap(),
+                value as u128
+            );
+            assert_eq!(
+                packet.eval_as::<i32>(Overflow::Wrapping).unwrap(),
+                i32::try_from(value).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_eval_modulo() {
+        let modulo = |expr, modulus| {
+            Packet::from_expression(expr)
+                .unwrap()
+                .eval_with_options(&EvalOptions::new().modulus(modulus))
+        };
+        assert_eq!(modulo("(200 + 100) * 3", Some(256)).unwrap(), 900 % 256);
+        // 300 and 44 are equal modulo 256
+        assert_eq!(modulo("300 == 44", Some(256)).unwrap(), 1);
+        assert_eq!(modulo("300 < 100", Some(256)).unwrap(), 1);
+        assert_eq!(modulo("min(7, 9)", Some(1)).unwrap(), 0);
+        assert_eq!(modulo("2 < 3", Some(1)).unwrap(), 0);
+        assert_eq!(modulo("if(256, 1, 2)", Some(256)).unwrap(), 2);
+        let big = "18446744073709551615 * 18446744073709551615";
+        assert_eq!(modulo(big, Some(u64::MAX)).unwrap(), 0);
+        assert_eq!(modulo(big, Some(10)).unwrap(), 5 * 5 % 10);
+        assert!(matches!(
+            modulo(big, None),
+            Err(PacketError::Overflow(Some(Operation::Product)))
+        ));
+        assert_eq!(modulo("2 * 3", Some(0)).unwrap(), 6);
+        assert!(matches!(
+            modulo("min()", Some(7)),
+            Err(PacketError::ArgumentError {
+                count: 0,
+                operation: Operation::Minimum,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_comparison_order() {
+        let eval = |expr, order, modulus| {
+            Packet::from_expression(expr)
+                .unwrap()
+                .eval_with_options(&EvalOptions::new().comparison_order(order).modulus(modulus))
+        };
+        assert!(matches!(
+            eval("eq(7, 7, 1)", ComparisonOrder::StrictTwo, None),
+            Err(PacketError::ArgumentError {
+                count: 3,
+                operation: Operation::EqualTo,
+                ..
+            })
+        ));
+        assert_eq!(
+            eval("eq(7, 7)", ComparisonOrder::StrictTwo, None).unwrap(),
+            1
+        );
+        // (7 == 7) == 1
+        assert_eq!(
+            eval("eq(7, 7, 1)", ComparisonOrder::LeftFold, None).unwrap(),
+            1
+        );
+        // 7 == 7 and 7 == 1
+        assert_eq!(
+            eval("eq(7, 7, 1)", ComparisonOrder::Chain, None).unwrap(),
+            0
+        );
+        // 7, 7, and 1 are equal modulo 3
+        assert_eq!(
+            eval("eq(7, 7, 1)", ComparisonOrder::Chain, Some(3)).unwrap(),
+            1
+        );
+        assert_eq!(
+            eval("lt(1, 5, 3)", ComparisonOrder::LeftFold, None).unwrap(),
+            1
+        );
+        assert_eq!(
+            eval("lt(1, 5, 3)", ComparisonOrder::Chain, None).unwrap(),
+            0
+        );
+        assert!(eval("gt(1)", ComparisonOrder::Chain, None).is_err());
+    }
+
+    #[test]
+    fn test_eval_with_overflow() {
+        let packet = Packet::from_expression("18446744073709551615 * 2 + 5").unwrap();
+        let eval = |overflow| packet.eval_with_options(&EvalOptions::new().overflow(overflow));
+        assert_eq!(eval(Overflow::Wrapping).unwrap(), 3);
+        assert_eq!(eval(Overflow::Saturating).unwrap(), u64::MAX);
+        assert!(eval(Overflow::Checked).is_err());
+        // A modulus takes precedence
+        let options = EvalOptions::new()
+            .overflow(Overflow::Saturating)
+            .modulus(Some(10));
+        assert_eq!(
+            packet.eval_with_options(&options).unwrap(),
+            (5 * 2 + 5) % 10
+        );
+    }
+
+    #[test]
+    fn test_empty_operators() {
+        let eval = |expr, policy, modulus| {
+            Packet::from_expression(expr)
+                .unwrap()
+                .eval_with_options(&EvalOptions::new().empty_operators(policy).modulus(modulus))
+        };
+        for modulus in [None, Some(1000)] {
+            assert_eq!(
+                eval("sum() + product()", EmptyOperator::Identity, modulus).unwrap(),
+                1
+            );
+            assert!(eval("max()", EmptyOperator::Identity, modulus).is_err());
+            let error = eval("5 + max(2, product())", EmptyOperator::Error, modulus).unwrap_err();
+            assert_eq!(error.path(), Some(&[1, 1][..]));
+            assert!(eval("5 + max(2, sum())", EmptyOperator::Error, modulus).is_err());
+            // Skipped operands count for neither arity nor paths
+            assert_eq!(
+                eval(
+                    "lt(min(), 1, sum(product()), 2)",
+                    EmptyOperator::Skip,
+                    modulus
+                )
+                .unwrap(),
+                1
+            );
+            assert_eq!(
+                eval(
+                    "if(max(), 0, 3, 4) * product()",
+                    EmptyOperator::Skip,
+                    modulus
+                )
+                .unwrap(),
+                4
+            );
+            let error = eval("7 + eq(sum(), 1)", EmptyOperator::Skip, modulus).unwrap_err();
+            assert!(matches!(
+                error,
+                PacketError::ArgumentError {
+                    count: 2,
+                    operation: Operation::EqualTo,
+                    ..
+                }
+            ));
+            assert_eq!(error.path(), Some(&[1][..]));
+            assert!(eval("sum(max())", EmptyOperator::Skip, modulus).is_err());
+        }
+    }
+}